@@ -0,0 +1,213 @@
+//! Veriform decoder benchmark (using criterion)
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use veriform::{
+    decoder::{sequence, Decode, DecodeOptions, DecodeSeq},
+    field::{Header, WireType},
+    vint64, Decoder, Message,
+};
+
+/// Length of the `uint64` sequence benchmark
+const SEQUENCE_LEN: u64 = 1024;
+
+/// Number of fields in the all-`uint64` message benchmark
+const UINT64_FIELDS: u64 = 100;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct SmallMessage {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub a: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub b: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct ManyFieldMessage {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub f0: u64,
+    #[field(tag = 1, wire_type = "uint64")]
+    pub f1: u64,
+    #[field(tag = 2, wire_type = "uint64")]
+    pub f2: u64,
+    #[field(tag = 3, wire_type = "uint64")]
+    pub f3: u64,
+    #[field(tag = 4, wire_type = "uint64")]
+    pub f4: u64,
+    #[field(tag = 5, wire_type = "uint64")]
+    pub f5: u64,
+    #[field(tag = 6, wire_type = "uint64")]
+    pub f6: u64,
+    #[field(tag = 7, wire_type = "uint64")]
+    pub f7: u64,
+    #[field(tag = 8, wire_type = "sint64")]
+    pub f8: i64,
+    #[field(tag = 9, wire_type = "sint64")]
+    pub f9: i64,
+    #[field(tag = 10, wire_type = "sint64")]
+    pub f10: i64,
+    #[field(tag = 11, wire_type = "sint64")]
+    pub f11: i64,
+    #[field(tag = 12, wire_type = "sint64")]
+    pub f12: i64,
+    #[field(tag = 13, wire_type = "sint64")]
+    pub f13: i64,
+    #[field(tag = 14, wire_type = "sint64")]
+    pub f14: i64,
+    #[field(tag = 15, wire_type = "sint64")]
+    pub f15: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Nested1 {
+    #[field(tag = 0, wire_type = "message")]
+    pub inner: SmallMessage,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Nested2 {
+    #[field(tag = 0, wire_type = "message")]
+    pub inner: Nested1,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Nested3 {
+    #[field(tag = 0, wire_type = "message")]
+    pub inner: Nested2,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct DeepMessage {
+    #[field(tag = 0, wire_type = "message")]
+    pub inner: Nested3,
+}
+
+/// Encode a message into a buffer, returning the number of bytes written
+fn encode(msg: &dyn Message, buffer: &mut [u8]) -> usize {
+    msg.encode(buffer).unwrap().len()
+}
+
+/// Encode a `uint64` sequence field with tag `0`
+fn encode_uint64_seq(buffer: &mut [u8]) -> usize {
+    let mut body = [0u8; 16 * SEQUENCE_LEN as usize];
+    let mut body_len = 0;
+
+    for n in 0..SEQUENCE_LEN {
+        let encoded = vint64::encode(n * 1000);
+        body[body_len..body_len + encoded.as_ref().len()].copy_from_slice(encoded.as_ref());
+        body_len += encoded.as_ref().len();
+    }
+
+    let header = Header::new(0, false, WireType::Sequence).encode();
+    let seq_header = vint64::encode((body_len as u64) << 4 | WireType::UInt64 as u64);
+
+    let mut len = 0;
+    for chunk in &[header.as_ref(), seq_header.as_ref(), &body[..body_len]] {
+        buffer[len..len + chunk.len()].copy_from_slice(chunk);
+        len += chunk.len();
+    }
+
+    len
+}
+
+/// Encode a message consisting of [`UINT64_FIELDS`] `uint64` fields
+fn encode_uint64_message(buffer: &mut [u8]) -> usize {
+    let mut len = 0;
+
+    for tag in 0..UINT64_FIELDS {
+        let header = Header::new(tag, false, WireType::UInt64).encode();
+        let value = vint64::encode(tag * 1000);
+
+        for chunk in &[header.as_ref(), value.as_ref()] {
+            buffer[len..len + chunk.len()].copy_from_slice(chunk);
+            len += chunk.len();
+        }
+    }
+
+    len
+}
+
+/// Decode the all-`uint64` message, returning the sum of its fields
+fn decode_uint64_message(options: DecodeOptions, mut input: &[u8]) -> u64 {
+    let mut decoder: Decoder = Decoder::with_options(options);
+    let mut sum = 0u64;
+
+    for tag in 0..UINT64_FIELDS {
+        let value: u64 = decoder.decode(tag, &mut input).unwrap();
+        sum += value;
+    }
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    sum
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decoder");
+    let mut buffer = vec![0u8; 65536];
+
+    let small = SmallMessage { a: 42, b: -42 };
+    let len = encode(&small, &mut buffer);
+    let encoded = buffer[..len].to_vec();
+    group.bench_function("small message", |b| {
+        b.iter(|| SmallMessage::decode(&mut Decoder::new(), &encoded).unwrap())
+    });
+
+    let many = ManyFieldMessage {
+        f0: 1,
+        f1: 1 << 8,
+        f2: 1 << 16,
+        f3: 1 << 24,
+        f4: 1 << 32,
+        f5: 1 << 40,
+        f6: 1 << 48,
+        f7: 1 << 56,
+        f8: -1,
+        f9: -(1 << 8),
+        f10: -(1 << 16),
+        f11: -(1 << 24),
+        f12: -(1 << 32),
+        f13: -(1 << 40),
+        f14: -(1 << 48),
+        f15: -(1 << 56),
+    };
+    let len = encode(&many, &mut buffer);
+    let encoded = buffer[..len].to_vec();
+    group.bench_function("many-field message", |b| {
+        b.iter(|| ManyFieldMessage::decode(&mut Decoder::new(), &encoded).unwrap())
+    });
+
+    let deep = DeepMessage::default();
+    let len = encode(&deep, &mut buffer);
+    let encoded = buffer[..len].to_vec();
+    group.bench_function("deep nesting", |b| {
+        b.iter(|| DeepMessage::decode(&mut Decoder::new(), &encoded).unwrap())
+    });
+
+    let len = encode_uint64_message(&mut buffer);
+    let encoded = buffer[..len].to_vec();
+    group.bench_function("uint64 message (general path)", |b| {
+        let options = DecodeOptions::new().scalar_fast_path(false);
+        b.iter(|| decode_uint64_message(options, &encoded))
+    });
+    group.bench_function("uint64 message (fast path)", |b| {
+        b.iter(|| decode_uint64_message(DecodeOptions::new(), &encoded))
+    });
+
+    let len = encode_uint64_seq(&mut buffer);
+    let encoded = buffer[..len].to_vec();
+    group.bench_function("uint64 sequence", |b| {
+        b.iter(|| {
+            let mut decoder = Decoder::new();
+            let iter: sequence::Iter<'_, '_, u64, _> =
+                decoder.decode_seq(0, &mut encoded.as_slice()).unwrap();
+
+            iter.map(Result::unwrap).sum::<u64>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -108,7 +108,7 @@ impl DeriveEnum {
                     mut input: &[u8]
                 ) -> Result<Self, veriform::Error>
                 where
-                    D: veriform::digest::Digest,
+                    D: veriform::verihash::VerihashDigest,
                 {
                     #[allow(unused_imports)]
                     use core::convert::TryInto;
@@ -153,6 +153,17 @@ struct DeriveStruct {
     /// Body of `Message::decode()` in-progress for a struct
     decode_body: TokenStream,
 
+    /// Computation of the message digest (if any) in `Message::decode()`,
+    /// performed after all fields have been decoded
+    digest_body: TokenStream,
+
+    /// Body of `Message::decode_into()` in-progress for a struct
+    decode_into_body: TokenStream,
+
+    /// Computation of the message digest (if any) in
+    /// `Message::decode_into()`
+    digest_into_body: TokenStream,
+
     /// Instantiation of the struct at the end of `Message::decode()`
     inst_body: TokenStream,
 
@@ -169,6 +180,9 @@ impl DeriveStruct {
 
         let mut state = Self {
             decode_body: TokenStream::new(),
+            digest_body: TokenStream::new(),
+            decode_into_body: TokenStream::new(),
+            digest_into_body: TokenStream::new(),
             inst_body: TokenStream::new(),
             encode_body: TokenStream::new(),
             encoded_len_body: quote!(0),
@@ -260,6 +274,24 @@ impl DeriveStruct {
             }
         }
         .to_tokens(&mut self.decode_body);
+
+        if wire_type.is_ref_type() {
+            quote! { out.#name = decoder.decode_ref(#tag, &mut input)?; }
+        } else if wire_type.is_message() {
+            quote! { decoder.decode_into(#tag, &mut input, &mut out.#name)?; }
+        } else if wire_type.is_sequence() {
+            quote! {
+                veriform::derive_helpers::decode_message_seq_into(
+                    decoder,
+                    #tag,
+                    &mut input,
+                    &mut out.#name
+                )?;
+            }
+        } else {
+            quote! { out.#name = decoder.decode(#tag, &mut input)?; }
+        }
+        .to_tokens(&mut self.decode_into_body);
     }
 
     /// Derive handling for a `#[digest(...)]` member of a struct
@@ -283,7 +315,13 @@ impl DeriveStruct {
             decoder.fill_digest(&mut #name)?;
         };
 
-        fill_digest.to_tokens(&mut self.decode_body);
+        fill_digest.to_tokens(&mut self.digest_body);
+
+        let fill_digest_into = quote! {
+            decoder.fill_digest(out.#name.get_or_insert_with(Default::default))?;
+        };
+
+        fill_digest_into.to_tokens(&mut self.digest_into_body);
 
         let inst_field = quote!(#name: Some(#name),);
         inst_field.to_tokens(&mut self.inst_body);
@@ -292,6 +330,9 @@ impl DeriveStruct {
     /// Finish deriving a struct
     fn finish(self, s: &Structure<'_>, pattern: TokenStream) -> TokenStream {
         let decode_body = self.decode_body;
+        let digest_body = self.digest_body;
+        let decode_into_body = self.decode_into_body;
+        let digest_into_body = self.digest_into_body;
         let inst_body = self.inst_body;
         let encode_body = self.encode_body;
         let encoded_len_body = self.encoded_len_body;
@@ -303,16 +344,36 @@ impl DeriveStruct {
                     mut input: &[u8]
                 ) -> Result<Self, veriform::Error>
                 where
-                    D: veriform::digest::Digest,
+                    D: veriform::verihash::VerihashDigest,
                 {
                     #[allow(unused_imports)]
                     use veriform::decoder::{Decode, DecodeRef};
 
                     #decode_body
+                    decoder.decode_unknown_fields(&mut input)?;
+                    #digest_body
 
                     Ok(Self { #inst_body })
                 }
 
+                fn decode_into<D>(
+                    decoder: &mut veriform::decoder::Decoder<D>,
+                    mut input: &[u8],
+                    out: &mut Self
+                ) -> Result<(), veriform::Error>
+                where
+                    D: veriform::verihash::VerihashDigest,
+                {
+                    #[allow(unused_imports)]
+                    use veriform::decoder::{Decode, DecodeInto, DecodeRef};
+
+                    #decode_into_body
+                    decoder.decode_unknown_fields(&mut input)?;
+                    #digest_into_body
+
+                    Ok(())
+                }
+
                 fn encode<'a>(
                     &self,
                     buffer: &'a mut [u8]
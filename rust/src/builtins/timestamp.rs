@@ -13,16 +13,17 @@ pub use tai64::TAI64N as Timestamp;
 
 use crate::{
     decoder::{Decode, Decoder},
-    digest::Digest,
     error::{self, Error},
-    field, Encoder, Message,
+    field,
+    verihash::VerihashDigest,
+    Encoder, Message,
 };
 use core::convert::TryInto;
 
 impl Message for Timestamp {
     fn decode<D>(decoder: &mut Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
     where
-        D: Digest,
+        D: VerihashDigest,
     {
         let secs: u64 = decoder.decode(0, &mut input)?;
         let nanos: u64 = decoder.decode(1, &mut input)?;
@@ -12,16 +12,17 @@ pub use uuid::Uuid;
 
 use crate::{
     decoder::{DecodeRef, Decoder},
-    digest::Digest,
     error::{self, Error},
-    field, Encoder, Message,
+    field,
+    verihash::VerihashDigest,
+    Encoder, Message,
 };
 use core::convert::TryInto;
 
 impl Message for Uuid {
     fn decode<D>(decoder: &mut Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
     where
-        D: Digest,
+        D: VerihashDigest,
     {
         let bytes: &[u8] = decoder.decode_ref(0, &mut input)?;
 
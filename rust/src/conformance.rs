@@ -0,0 +1,875 @@
+//! Conformance tests against fixtures shared with other implementations.
+//!
+//! Fixtures are [TJSON] documents (see `vectors/README.md` in the Veriform
+//! repository) describing a message as structured data along with its
+//! canonical encoding and SHA-256 Verihash digest, or an encoding which is
+//! malformed along with the [`error::Kind`] decoding it should fail with.
+//!
+//! Each fixture is checked three ways by [`Fixture::check`]:
+//!
+//! - **decode**: the encoded bytes decode to the structured message
+//! - **encode**: the structured message encodes to the encoded bytes
+//! - **hash**: the Verihash digest of the encoded bytes is the given digest
+//!
+//! Malformed fixtures are only decoded, which must fail with an error whose
+//! [`error::Kind::code`] is the given code.
+//!
+//! Messages which are partially corrupt can be inspected using
+//! [`decode_with_recovery`], which skips fields whose values fail to decode.
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let fixtures = veriform::conformance::load(r#"{
+//!     "examples:A<O>": [{
+//!         "name:s": "uint64 field",
+//!         "success:b": true,
+//!         "encoded:d16": "2555",
+//!         "decoded:O": { "1:u": "42" },
+//!         "digest:d16": "d544f6c2340750009fd3e34626cccb10300d7cf944e05e65c19c85f50705a9c5"
+//!     }]
+//! }"#)?;
+//!
+//! for fixture in &fixtures {
+//!     if let Err(mismatch) = fixture.check() {
+//!         println!("{}", mismatch);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [TJSON]: https://tjson.org
+
+use crate::{
+    decoder::{hash_message, Event, Stream},
+    encoder::EventEncoder,
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+    Sha256Digest,
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    convert::{TryFrom, TryInto},
+    fmt::{self, Display},
+};
+use serde_json::{Map, Value as Json};
+use sha2::Sha256;
+
+/// Key of the array of fixtures in a fixture file
+const EXAMPLES_KEY: &str = "examples:A<O>";
+
+/// Load the fixtures from a TJSON fixture file
+pub fn load(tjson: &str) -> Result<Vec<Fixture>, LoadError> {
+    let root: Json = serde_json::from_str(tjson).map_err(|e| LoadError::new(e.to_string()))?;
+
+    root.get(EXAMPLES_KEY)
+        .and_then(Json::as_array)
+        .ok_or_else(|| LoadError::new(format!("missing `{}`", EXAMPLES_KEY)))?
+        .iter()
+        .map(Fixture::from_tjson)
+        .collect()
+}
+
+/// Decode an encoded message into its fields.
+///
+/// The message is decoded using [`Stream`], and must be complete.
+/// Messages which end partway through a field are [`error::Kind::Truncated`].
+pub fn decode(encoded: &[u8]) -> Result<Vec<Field>, Error> {
+    let mut stream = Stream::<Sha256>::new();
+    let mut builder = Builder::new();
+    let mut input = encoded;
+
+    while let Some(event) = stream.decode(&mut input)? {
+        builder.handle_event(&event)?;
+        builder.pop_completed(stream.depth())?;
+    }
+
+    // `Stream` buffers incomplete fields awaiting more input, in which case
+    // the message's digest can't be computed
+    let mut digest = Sha256Digest::default();
+
+    if stream.depth() > 0 || stream.fill_digest(&mut digest).is_err() {
+        return Err(error::Kind::Truncated {
+            remaining: 0,
+            wire_type: WireType::Message,
+        }
+        .into());
+    }
+
+    builder.finish()
+}
+
+/// Decode an encoded message into its fields like [`decode`], but recover
+/// from errors in the values of top-level fields.
+///
+/// This is intended for diagnostic tooling which wants to extract as much
+/// as possible from a partially corrupt message. When the value of a field
+/// fails to decode (e.g. a string containing invalid UTF-8, or a malformed
+/// nested message), its length delimiter is used to skip the remainder of
+/// the field, the error is recorded, and decoding resumes with the next
+/// field. Malformed field headers and lengths which overrun the input
+/// leave no way to find the next field, so they're returned as errors.
+///
+/// No digest is computed. The recovered fields aren't the message which
+/// was encoded, and must never be used to verify a signature or commitment.
+pub fn decode_with_recovery(encoded: &[u8]) -> Result<Recovered, Error> {
+    let mut recovered = Recovered::default();
+    let mut input = encoded;
+    let mut last_tag = None;
+
+    while !input.is_empty() {
+        let position = encoded.len() - input.len();
+        let (header, field_bytes) = split_field(&mut input)?;
+
+        let result = match last_tag {
+            Some(last_tag) if header.tag <= last_tag => {
+                Err(error::Kind::Order { tag: header.tag }.into())
+            }
+            _ => {
+                last_tag = Some(header.tag);
+                decode_field(header, field_bytes)
+            }
+        };
+
+        match result {
+            Ok(field) => recovered.fields.push(field),
+            Err(error) => recovered.errors.push(FieldError {
+                header,
+                position,
+                error,
+            }),
+        }
+    }
+
+    Ok(recovered)
+}
+
+/// Encode the given fields as a message
+pub fn encode(fields: &[Field]) -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![0u8; fields_len(fields)];
+    let mut encoder = EventEncoder::new(&mut buffer);
+    encode_fields(&mut encoder, fields)?;
+
+    let len = encoder.finish()?.len();
+    buffer.truncate(len);
+    Ok(buffer)
+}
+
+/// Conformance test fixture
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fixture {
+    /// Name of the fixture
+    pub name: String,
+
+    /// Description of the fixture (empty if it has none)
+    pub description: String,
+
+    /// Encoded message
+    pub encoded: Vec<u8>,
+
+    /// Expected result of decoding the message
+    pub expected: Expected,
+}
+
+impl Fixture {
+    /// Check that this implementation agrees with the fixture, returning the
+    /// first check which failed
+    pub fn check(&self) -> Result<(), Mismatch> {
+        let (fields, digest) = match &self.expected {
+            Expected::Message { fields, digest } => (fields, digest),
+            Expected::Error { code } => {
+                return match decode(&self.encoded) {
+                    Err(err) if err.kind().code() == *code => Ok(()),
+                    Err(err) => Err(self.mismatch(
+                        Check::Decode,
+                        format!(
+                            "expected error code {}, got {} ({})",
+                            code,
+                            err.kind().code(),
+                            err
+                        ),
+                    )),
+                    Ok(decoded) => Err(self.mismatch(
+                        Check::Decode,
+                        format!("expected error code {}, got {:?}", code, decoded),
+                    )),
+                };
+            }
+        };
+
+        match decode(&self.encoded) {
+            Ok(ref decoded) if decoded == fields => (),
+            Ok(decoded) => {
+                return Err(self.mismatch(
+                    Check::Decode,
+                    format!("expected {:?}, got {:?}", fields, decoded),
+                ))
+            }
+            Err(err) => return Err(self.mismatch(Check::Decode, err.to_string())),
+        }
+
+        match encode(fields) {
+            Ok(ref encoded) if encoded == &self.encoded => (),
+            Ok(encoded) => {
+                return Err(self.mismatch(
+                    Check::Encode,
+                    format!("expected {}, got {}", hex(&self.encoded), hex(&encoded)),
+                ))
+            }
+            Err(err) => return Err(self.mismatch(Check::Encode, err.to_string())),
+        }
+
+        match hash_message::<Sha256>(&self.encoded) {
+            Ok(ref actual) if actual.as_slice() == digest => Ok(()),
+            Ok(actual) => Err(self.mismatch(
+                Check::Hash,
+                format!("expected {}, got {}", hex(digest), hex(&actual)),
+            )),
+            Err(err) => Err(self.mismatch(Check::Hash, err.to_string())),
+        }
+    }
+
+    /// Parse a fixture from a TJSON object
+    fn from_tjson(json: &Json) -> Result<Self, LoadError> {
+        let object = json
+            .as_object()
+            .ok_or_else(|| LoadError::new("fixture is not an object"))?;
+
+        let name = string_member(object, "name:s")?;
+
+        // Prefix errors with the name of the fixture they occurred in
+        let parse = || {
+            let description = match object.get("description:s") {
+                Some(_) => string_member(object, "description:s")?,
+                None => String::new(),
+            };
+
+            let encoded = parse_hex(&string_member(object, "encoded:d16")?)?;
+
+            let success = object
+                .get("success:b")
+                .and_then(Json::as_bool)
+                .ok_or_else(|| LoadError::new("missing `success:b`"))?;
+
+            let expected = if success {
+                let decoded = object
+                    .get("decoded:O")
+                    .ok_or_else(|| LoadError::new("missing `decoded:O`"))?;
+
+                let digest = parse_hex(&string_member(object, "digest:d16")?)?;
+
+                Expected::Message {
+                    fields: parse_fields(decoded)?,
+                    digest: digest
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| LoadError::new("`digest:d16` is not a SHA-256 digest"))?,
+                }
+            } else {
+                let code = string_member(object, "error:u")?;
+
+                Expected::Error {
+                    code: code
+                        .parse()
+                        .map_err(|_| LoadError::new(format!("invalid error code: {}", code)))?,
+                }
+            };
+
+            Ok(Fixture {
+                name: name.clone(),
+                description,
+                encoded,
+                expected,
+            })
+        };
+
+        parse().map_err(|e: LoadError| LoadError::new(format!("{}: {}", name, e.message)))
+    }
+
+    /// Create a [`Mismatch`] for the given check of this fixture
+    fn mismatch(&self, check: Check, details: String) -> Mismatch {
+        Mismatch {
+            fixture: self.name.clone(),
+            check,
+            details,
+        }
+    }
+}
+
+/// Expected result of decoding a fixture's encoded message
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expected {
+    /// Message decodes successfully
+    Message {
+        /// Fields of the message, in ascending tag order
+        fields: Vec<Field>,
+
+        /// SHA-256 Verihash digest of the message
+        digest: Sha256Digest,
+    },
+
+    /// Message is malformed
+    Error {
+        /// Numeric code of the [`error::Kind`] decoding fails with
+        code: u16,
+    },
+}
+
+/// Field of a message described by a fixture
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+    /// Tag which identifies the field
+    pub tag: Tag,
+
+    /// Is this field critical?
+    pub critical: bool,
+
+    /// Value of the field
+    pub value: Value,
+}
+
+impl Field {
+    /// Get the header of this field
+    pub fn header(&self) -> Header {
+        Header::new(self.tag, self.critical, self.value.wire_type())
+    }
+
+    /// Get the length of this field when encoded
+    fn encoded_len(&self) -> usize {
+        // Booleans are encoded entirely in the wire type of the header
+        let value_len = match self.value {
+            Value::Bool(_) => 0,
+            ref value => value.encoded_len(),
+        };
+
+        self.header().encoded_len() + value_len
+    }
+}
+
+/// Fields recovered from a partially corrupt message by
+/// [`decode_with_recovery`]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Recovered {
+    /// Fields which decoded successfully
+    pub fields: Vec<Field>,
+
+    /// Errors in the fields which were skipped
+    pub errors: Vec<FieldError>,
+}
+
+/// Error decoding the value of a field skipped by [`decode_with_recovery`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldError {
+    /// Header of the skipped field
+    pub header: Header,
+
+    /// Position of the skipped field in the message
+    pub position: usize,
+
+    /// Error which occurred, with a position relative to the field
+    pub error: Error,
+}
+
+/// Values of fields (or elements of sequences) described by a fixture
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    /// Boolean
+    Bool(bool),
+
+    /// Unsigned 64-bit integer
+    UInt64(u64),
+
+    /// Signed 64-bit integer
+    SInt64(i64),
+
+    /// Binary data
+    Bytes(Vec<u8>),
+
+    /// Unicode string
+    String(String),
+
+    /// Nested message
+    Message(Vec<Field>),
+
+    /// Sequence whose elements have the given wire type
+    Sequence(WireType, Vec<Value>),
+}
+
+impl Value {
+    /// Get the wire type of this value
+    pub fn wire_type(&self) -> WireType {
+        match self {
+            Value::Bool(false) => WireType::False,
+            Value::Bool(true) => WireType::True,
+            Value::UInt64(_) => WireType::UInt64,
+            Value::SInt64(_) => WireType::SInt64,
+            Value::Bytes(_) => WireType::Bytes,
+            Value::String(_) => WireType::String,
+            Value::Message(_) => WireType::Message,
+            Value::Sequence(..) => WireType::Sequence,
+        }
+    }
+
+    /// Get the length of this value when encoded as an element of a sequence
+    fn encoded_len(&self) -> usize {
+        let delimited_len = |len: usize| vint64::encoded_len(len as u64) + len;
+
+        match self {
+            Value::Bool(_) => 1,
+            Value::UInt64(value) => vint64::encoded_len(*value),
+            Value::SInt64(value) => vint64::signed::encoded_len(*value),
+            Value::Bytes(bytes) => delimited_len(bytes.len()),
+            Value::String(string) => delimited_len(string.len()),
+            Value::Message(fields) => delimited_len(fields_len(fields)),
+            Value::Sequence(wire_type, elements) => {
+                let body_len = elements.iter().map(Value::encoded_len).sum::<usize>();
+                vint64::encoded_len((body_len as u64) << 4 | *wire_type as u64) + body_len
+            }
+        }
+    }
+}
+
+/// Fixture check which failed
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Check {
+    /// Decoding the encoded message
+    Decode,
+
+    /// Encoding the structured message
+    Encode,
+
+    /// Hashing the encoded message
+    Hash,
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Check::Decode => "decode",
+            Check::Encode => "encode",
+            Check::Hash => "hash",
+        })
+    }
+}
+
+/// Disagreement between this implementation and a fixture
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mismatch {
+    /// Name of the fixture
+    pub fixture: String,
+
+    /// Check which failed
+    pub check: Check,
+
+    /// Description of the disagreement
+    pub details: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} check failed: {}",
+            self.fixture, self.check, self.details
+        )
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Errors which occur when loading a fixture file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadError {
+    /// Description of the error
+    message: String,
+}
+
+impl LoadError {
+    /// Create a new load error
+    fn new(message: impl Into<String>) -> Self {
+        LoadError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error loading fixtures: {}", self.message)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Builds the fields of a message from the [`Event`]s emitted while
+/// decoding it
+struct Builder {
+    /// Stack of messages and sequences being decoded
+    stack: Vec<Frame>,
+
+    /// Wire type and bytes of the dynamically sized value being decoded
+    chunks: Option<(WireType, Vec<u8>)>,
+}
+
+/// Message or sequence being decoded by a [`Builder`]
+enum Frame {
+    /// Message along with the header of the field being decoded
+    Message(Vec<Field>, Option<Header>),
+
+    /// Sequence of elements with the given wire type
+    Sequence(WireType, Vec<Value>),
+}
+
+impl Builder {
+    /// Create a new builder for the outermost message
+    fn new() -> Self {
+        Self {
+            stack: vec![Frame::Message(Vec::new(), None)],
+            chunks: None,
+        }
+    }
+
+    /// Handle an event emitted by the decoder
+    fn handle_event(&mut self, event: &Event<'_>) -> Result<(), Error> {
+        match *event {
+            Event::FieldHeader(header) => match self.stack.last_mut() {
+                Some(Frame::Message(_, pending @ None)) => *pending = Some(header),
+                _ => return Err(error::Kind::Failed.into()),
+            },
+            Event::Bool(value) => self.push_value(Value::Bool(value))?,
+            Event::UInt64(value) => self.push_value(Value::UInt64(value))?,
+            Event::SInt64(value) => self.push_value(Value::SInt64(value))?,
+            Event::LengthDelimiter {
+                wire_type: WireType::Message,
+                ..
+            } => self.stack.push(Frame::Message(Vec::new(), None)),
+            Event::LengthDelimiter { wire_type, length } => {
+                self.chunks = Some((wire_type, Vec::with_capacity(length)));
+
+                if length == 0 {
+                    self.push_chunks()?;
+                }
+            }
+            Event::ValueChunk {
+                bytes, remaining, ..
+            } => {
+                self.chunks
+                    .as_mut()
+                    .ok_or(error::Kind::Failed)?
+                    .1
+                    .extend_from_slice(bytes);
+
+                if remaining == 0 {
+                    self.push_chunks()?;
+                }
+            }
+            Event::SequenceHeader { wire_type, .. } => {
+                self.stack.push(Frame::Sequence(wire_type, Vec::new()))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pop messages and sequences until the stack has the given depth
+    fn pop_completed(&mut self, depth: usize) -> Result<(), Error> {
+        while self.stack.len() > depth + 1 {
+            let value = match self.stack.pop() {
+                Some(Frame::Message(fields, None)) => Value::Message(fields),
+                Some(Frame::Sequence(wire_type, elements)) => Value::Sequence(wire_type, elements),
+                _ => return Err(error::Kind::Failed.into()),
+            };
+
+            self.push_value(value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finish building the outermost message, returning its fields
+    fn finish(mut self) -> Result<Vec<Field>, Error> {
+        match self.stack.pop() {
+            Some(Frame::Message(fields, None)) if self.stack.is_empty() => Ok(fields),
+            _ => Err(error::Kind::Failed.into()),
+        }
+    }
+
+    /// Push the dynamically sized value whose chunks have been decoded
+    fn push_chunks(&mut self) -> Result<(), Error> {
+        let value = match self.chunks.take() {
+            Some((WireType::Bytes, bytes)) => Value::Bytes(bytes),
+            Some((WireType::String, bytes)) => {
+                Value::String(String::from_utf8(bytes).map_err(|e| error::Kind::Utf8 {
+                    valid_up_to: e.utf8_error().valid_up_to(),
+                })?)
+            }
+            _ => return Err(error::Kind::Failed.into()),
+        };
+
+        self.push_value(value)
+    }
+
+    /// Push a decoded value into the innermost message or sequence
+    fn push_value(&mut self, value: Value) -> Result<(), Error> {
+        match self.stack.last_mut() {
+            Some(Frame::Message(fields, pending)) => {
+                let header = pending.take().ok_or(error::Kind::Failed)?;
+
+                fields.push(Field {
+                    tag: header.tag,
+                    critical: header.critical,
+                    value,
+                });
+            }
+            Some(Frame::Sequence(_, elements)) => elements.push(value),
+            None => return Err(error::Kind::Failed.into()),
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the next top-level field off the input using its header and
+/// length delimiter, without decoding its value
+fn split_field<'a>(input: &mut &'a [u8]) -> Result<(Header, &'a [u8]), Error> {
+    let field_input = *input;
+    let header = vint64::decode(input)?;
+    let wire_type = WireType::from_unmasked(header);
+
+    match wire_type {
+        // Booleans are encoded entirely in the wire type of the header
+        WireType::False | WireType::True => (),
+        WireType::UInt64 | WireType::SInt64 => {
+            vint64::decode(input)?;
+        }
+        _ => {
+            let mut length = vint64::decode(input)?;
+
+            // Sequence lengths are carried alongside the element type
+            if wire_type == WireType::Sequence {
+                length >>= 4;
+            }
+
+            let length = usize::try_from(length).map_err(|_| error::Kind::Length)?;
+
+            if length > input.len() {
+                return Err(error::Kind::Truncated {
+                    remaining: length - input.len(),
+                    wire_type,
+                }
+                .into());
+            }
+
+            *input = &input[length..];
+        }
+    }
+
+    let header = Header::new(header >> 4, (header >> 3) & 1 == 1, wire_type);
+    let field_len = field_input.len() - input.len();
+    Ok((header, &field_input[..field_len]))
+}
+
+/// Decode a single top-level field split off by [`split_field`]
+fn decode_field(header: Header, field_bytes: &[u8]) -> Result<Field, Error> {
+    match header.wire_type {
+        WireType::False | WireType::True => Ok(Field {
+            tag: header.tag,
+            critical: header.critical,
+            value: Value::Bool(header.wire_type == WireType::True),
+        }),
+        _ => decode(field_bytes)?
+            .pop()
+            .ok_or_else(|| error::Kind::Failed.into()),
+    }
+}
+
+/// Get the length of the given fields when encoded
+fn fields_len(fields: &[Field]) -> usize {
+    fields.iter().map(Field::encoded_len).sum()
+}
+
+/// Encode the given fields as events
+fn encode_fields(encoder: &mut EventEncoder<'_>, fields: &[Field]) -> Result<(), Error> {
+    for field in fields {
+        encoder.encode(&Event::FieldHeader(field.header()))?;
+
+        // Booleans are encoded entirely in the wire type of the header
+        if let Value::Bool(_) = field.value {
+            continue;
+        }
+
+        encode_value(encoder, &field.value)?;
+    }
+
+    Ok(())
+}
+
+/// Encode the given value as events
+fn encode_value(encoder: &mut EventEncoder<'_>, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Bool(value) => encoder.encode(&Event::Bool(*value)),
+        Value::UInt64(value) => encoder.encode(&Event::UInt64(*value)),
+        Value::SInt64(value) => encoder.encode(&Event::SInt64(*value)),
+        Value::Bytes(bytes) => encode_dynamically_sized(encoder, WireType::Bytes, bytes),
+        Value::String(string) => {
+            encode_dynamically_sized(encoder, WireType::String, string.as_bytes())
+        }
+        Value::Message(fields) => {
+            encoder.encode(&Event::LengthDelimiter {
+                wire_type: WireType::Message,
+                length: fields_len(fields),
+            })?;
+
+            encode_fields(encoder, fields)
+        }
+        Value::Sequence(wire_type, elements) => {
+            encoder.encode(&Event::SequenceHeader {
+                wire_type: *wire_type,
+                length: elements.iter().map(Value::encoded_len).sum(),
+            })?;
+
+            for element in elements {
+                encode_value(encoder, element)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Encode a `bytes` or `string` value as events
+fn encode_dynamically_sized(
+    encoder: &mut EventEncoder<'_>,
+    wire_type: WireType,
+    bytes: &[u8],
+) -> Result<(), Error> {
+    encoder.encode(&Event::LengthDelimiter {
+        wire_type,
+        length: bytes.len(),
+    })?;
+
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    encoder.encode(&Event::ValueChunk {
+        wire_type,
+        bytes,
+        remaining: 0,
+    })
+}
+
+/// Parse the fields of a message from a TJSON object whose keys are of
+/// the form `<tag>:<type>`, or `<tag>!:<type>` for critical fields
+fn parse_fields(json: &Json) -> Result<Vec<Field>, LoadError> {
+    let object = json
+        .as_object()
+        .ok_or_else(|| LoadError::new("message is not an object"))?;
+
+    let mut fields = Vec::with_capacity(object.len());
+
+    for (key, value) in object {
+        let mut parts = key.splitn(2, ':');
+        let tag = parts.next().unwrap_or_default();
+        let tag_type = parts
+            .next()
+            .ok_or_else(|| LoadError::new(format!("untagged field: {}", key)))?;
+
+        let (tag, critical) = match tag.strip_suffix('!') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+
+        fields.push(Field {
+            tag: tag
+                .parse()
+                .map_err(|_| LoadError::new(format!("invalid tag: {}", key)))?,
+            critical,
+            value: parse_value(tag_type, value)?,
+        });
+    }
+
+    fields.sort_by_key(|field| field.tag);
+    Ok(fields)
+}
+
+/// Parse a value with the given TJSON type tag
+fn parse_value(tag_type: &str, json: &Json) -> Result<Value, LoadError> {
+    let string = || {
+        json.as_str()
+            .ok_or_else(|| LoadError::new(format!("`{}` value is not a string", tag_type)))
+    };
+
+    let invalid = || LoadError::new(format!("invalid `{}` value: {}", tag_type, json));
+
+    Ok(match tag_type {
+        "b" => Value::Bool(json.as_bool().ok_or_else(invalid)?),
+        "u" => Value::UInt64(string()?.parse().map_err(|_| invalid())?),
+        "i" => Value::SInt64(string()?.parse().map_err(|_| invalid())?),
+        "d16" => Value::Bytes(parse_hex(string()?)?),
+        "s" => Value::String(string()?.into()),
+        "O" => Value::Message(parse_fields(json)?),
+        _ => {
+            let element_type = tag_type
+                .strip_prefix("A<")
+                .and_then(|t| t.strip_suffix('>'))
+                .ok_or_else(|| LoadError::new(format!("unsupported type: {}", tag_type)))?;
+
+            let wire_type = match element_type {
+                "b" => WireType::False,
+                "u" => WireType::UInt64,
+                "i" => WireType::SInt64,
+                "d16" => WireType::Bytes,
+                "s" => WireType::String,
+                "O" => WireType::Message,
+                _ => {
+                    return Err(LoadError::new(format!(
+                        "unsupported sequence element type: {}",
+                        element_type
+                    )))
+                }
+            };
+
+            let elements = json
+                .as_array()
+                .ok_or_else(invalid)?
+                .iter()
+                .map(|element| parse_value(element_type, element))
+                .collect::<Result<_, _>>()?;
+
+            Value::Sequence(wire_type, elements)
+        }
+    })
+}
+
+/// Get a string member of a TJSON object
+fn string_member(object: &Map<String, Json>, key: &str) -> Result<String, LoadError> {
+    object
+        .get(key)
+        .and_then(Json::as_str)
+        .map(String::from)
+        .ok_or_else(|| LoadError::new(format!("missing `{}`", key)))
+}
+
+/// Parse a hex (i.e. base16) string
+fn parse_hex(hex: &str) -> Result<Vec<u8>, LoadError> {
+    let invalid = || LoadError::new(format!("invalid hex: {}", hex));
+
+    let digit = |byte: u8| char::from(byte).to_digit(16).ok_or_else(invalid);
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match *pair {
+            [hi, lo] => Ok((digit(hi)? << 4 | digit(lo)?) as u8),
+            _ => Err(invalid()),
+        })
+        .collect()
+}
+
+/// Format bytes as lower case hex for displaying mismatches
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
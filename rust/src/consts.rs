@@ -0,0 +1,299 @@
+//! `const fn` encoders for building Veriform messages at compile time,
+//! e.g. for test vectors and protocol constants.
+//!
+//! The free functions encode a single `vint64`, field header, or integer
+//! field into a fixed-size array, returning it along with the number of
+//! bytes used. [`Literal`] composes them into a whole message:
+//!
+//! ```
+//! use veriform::consts::Literal;
+//!
+//! /// Field 1 = uint64 42, field 2 = bytes "foo"
+//! const EXAMPLE: Literal = Literal::new().uint64(1, 42).bytes(2, b"foo");
+//!
+//! assert_eq!(EXAMPLE.as_bytes(), &[0x25, 0x55, 0x49, 0x07, b'f', b'o', b'o']);
+//! ```
+//!
+//! Messages are encoded as given: fields must be added in ascending tag
+//! order for the result to be canonical.
+
+use crate::field::{Tag, WireType};
+
+/// Maximum length of a `vint64` in bytes
+const VINT64_MAX: usize = 9;
+
+/// Maximum length of a field header followed by a `vint64` in bytes
+const FIELD_MAX: usize = VINT64_MAX * 2;
+
+/// Maximum length of a [`Literal`] in bytes
+pub const LITERAL_MAX: usize = 128;
+
+/// Encode a `vint64`
+pub const fn vint64(value: u64) -> ([u8; VINT64_MAX], usize) {
+    let mut bytes = [0u8; VINT64_MAX];
+    let length = vint64_len(value);
+
+    if length == VINT64_MAX {
+        // 9-byte special case: the first byte is zero
+        let mut i = 0;
+        while i < 8 {
+            bytes[i + 1] = (value >> (i * 8)) as u8;
+            i += 1;
+        }
+    } else {
+        let encoded = (value << 1 | 1) << (length - 1);
+        let mut i = 0;
+        while i < length {
+            bytes[i] = (encoded >> (i * 8)) as u8;
+            i += 1;
+        }
+    }
+
+    (bytes, length)
+}
+
+/// Encode a field header
+pub const fn header(tag: Tag, critical: bool, wire_type: WireType) -> ([u8; VINT64_MAX], usize) {
+    vint64(tag << 4 | (critical as u64) << 3 | wire_type.to_u8() as u64)
+}
+
+/// Encode a non-critical `uint64` field
+pub const fn uint64_field(tag: Tag, value: u64) -> ([u8; FIELD_MAX], usize) {
+    field(header(tag, false, WireType::UInt64), vint64(value))
+}
+
+/// Encode a non-critical `sint64` field
+pub const fn sint64_field(tag: Tag, value: i64) -> ([u8; FIELD_MAX], usize) {
+    field(header(tag, false, WireType::SInt64), vint64(zigzag(value)))
+}
+
+/// Encoded message built up field-by-field in `const` contexts.
+///
+/// Holds up to [`LITERAL_MAX`] bytes. Exceeding this is an error when
+/// evaluated at compile time, and a panic at runtime.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Literal {
+    /// Buffer containing the encoded message
+    bytes: [u8; LITERAL_MAX],
+
+    /// Length of the encoded message
+    length: usize,
+}
+
+impl Literal {
+    /// Create a new, empty message
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0u8; LITERAL_MAX],
+            length: 0,
+        }
+    }
+
+    /// Append a `vint64`, e.g. a length or value following a header
+    /// added with [`Literal::header`]
+    pub const fn vint64(self, value: u64) -> Self {
+        self.append_vint64(vint64(value))
+    }
+
+    /// Append a field header, e.g. for a critical field or one whose value
+    /// is constructed by hand
+    pub const fn header(self, tag: Tag, critical: bool, wire_type: WireType) -> Self {
+        self.append_vint64(header(tag, critical, wire_type))
+    }
+
+    /// Append a `bool` field, which is encoded entirely in its header
+    pub const fn bool(self, tag: Tag, value: bool) -> Self {
+        let wire_type = if value {
+            WireType::True
+        } else {
+            WireType::False
+        };
+
+        self.header(tag, false, wire_type)
+    }
+
+    /// Append a `uint64` field
+    pub const fn uint64(self, tag: Tag, value: u64) -> Self {
+        self.header(tag, false, WireType::UInt64).vint64(value)
+    }
+
+    /// Append a `sint64` field
+    pub const fn sint64(self, tag: Tag, value: i64) -> Self {
+        self.header(tag, false, WireType::SInt64)
+            .vint64(zigzag(value))
+    }
+
+    /// Append a `bytes` field
+    pub const fn bytes(self, tag: Tag, value: &[u8]) -> Self {
+        self.header(tag, false, WireType::Bytes)
+            .vint64(value.len() as u64)
+            .raw(value)
+    }
+
+    /// Append a `string` field
+    pub const fn string(self, tag: Tag, value: &str) -> Self {
+        self.header(tag, false, WireType::String)
+            .vint64(value.len() as u64)
+            .raw(value.as_bytes())
+    }
+
+    /// Append a nested message field
+    pub const fn message(self, tag: Tag, message: &Literal) -> Self {
+        let mut literal = self
+            .header(tag, false, WireType::Message)
+            .vint64(message.length as u64);
+
+        let mut i = 0;
+
+        while i < message.length {
+            literal.bytes[literal.length] = message.bytes[i];
+            literal.length += 1;
+            i += 1;
+        }
+
+        literal
+    }
+
+    /// Append a sequence field containing the given (already encoded) body
+    pub const fn sequence(self, tag: Tag, wire_type: WireType, body: &[u8]) -> Self {
+        self.header(tag, false, WireType::Sequence)
+            .vint64((body.len() as u64) << 4 | wire_type.to_u8() as u64)
+            .raw(body)
+    }
+
+    /// Get the length of the encoded message
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Is the encoded message empty?
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Get the encoded message
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.length]
+    }
+
+    /// Append raw bytes, e.g. a hand-encoded value or malformed data
+    pub const fn raw(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+
+        while i < bytes.len() {
+            self.bytes[self.length] = bytes[i];
+            self.length += 1;
+            i += 1;
+        }
+
+        self
+    }
+
+    /// Append an encoded `vint64` (or field header)
+    const fn append_vint64(mut self, vint64: ([u8; VINT64_MAX], usize)) -> Self {
+        let mut i = 0;
+
+        while i < vint64.1 {
+            self.bytes[self.length] = vint64.0[i];
+            self.length += 1;
+            i += 1;
+        }
+
+        self
+    }
+}
+
+impl AsRef<[u8]> for Literal {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Default for Literal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the length of a `vint64` in bytes
+const fn vint64_len(value: u64) -> usize {
+    match value.leading_zeros() {
+        0..=7 => 9,
+        8..=14 => 8,
+        15..=21 => 7,
+        22..=28 => 6,
+        29..=35 => 5,
+        36..=42 => 4,
+        43..=49 => 3,
+        50..=56 => 2,
+        _ => 1,
+    }
+}
+
+/// Zigzag encode a signed integer
+const fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Concatenate a field header and value
+const fn field(
+    header: ([u8; VINT64_MAX], usize),
+    value: ([u8; VINT64_MAX], usize),
+) -> ([u8; FIELD_MAX], usize) {
+    let mut bytes = [0u8; FIELD_MAX];
+    let mut i = 0;
+
+    while i < header.1 {
+        bytes[i] = header.0[i];
+        i += 1;
+    }
+
+    let mut j = 0;
+
+    while j < value.1 {
+        bytes[i + j] = value.0[j];
+        j += 1;
+    }
+
+    (bytes, i + j)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{header, sint64_field, uint64_field, vint64, Literal};
+    use crate::field::{Header, WireType};
+
+    #[test]
+    fn vint64_matches_runtime_encoding() {
+        for &value in &[0, 1, 127, 128, 0x3fff, 0x4000, 1 << 56, u64::MAX] {
+            let (bytes, length) = vint64(value);
+            assert_eq!(&bytes[..length], ::vint64::encode(value).as_ref());
+        }
+    }
+
+    #[test]
+    fn header_matches_runtime_encoding() {
+        let (bytes, length) = header(42, true, WireType::Message);
+        assert_eq!(
+            &bytes[..length],
+            Header::new(42, true, WireType::Message).encode().as_ref()
+        );
+    }
+
+    #[test]
+    fn integer_fields() {
+        let (bytes, length) = uint64_field(42, 42);
+        assert_eq!(&bytes[..length], &[138, 10, 85]);
+
+        let (bytes, length) = sint64_field(43, -42);
+        assert_eq!(&bytes[..length], &[206, 10, 167]);
+    }
+
+    #[test]
+    fn nested_message() {
+        const INNER: Literal = Literal::new().uint64(0, 1);
+        const OUTER: Literal = Literal::new().bool(1, true).message(2, &INNER);
+
+        assert_eq!(OUTER.as_bytes(), &[0x23, 0x4d, 0x05, 0x05, 0x03]);
+    }
+}
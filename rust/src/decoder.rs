@@ -1,29 +1,239 @@
 //! Veriform decoder
 
+#[cfg(any(feature = "log", feature = "defmt"))]
+#[macro_use]
+mod trace;
+
 pub(crate) mod message;
 pub mod sequence;
 
+mod collector;
+mod cursor;
 mod decodable;
+mod dedup;
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+mod diff;
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+mod dynamic;
 mod event;
+mod field;
+mod guard;
+mod interner;
+mod options;
+#[cfg(feature = "alloc")]
+mod owned;
+mod repeated;
+mod schema;
+mod stack;
+mod stream;
+mod summary;
+mod tag_set;
 mod traits;
+mod unknown_fields;
+mod validate;
 mod vint64;
 
-#[cfg(feature = "log")]
-#[macro_use]
-mod trace;
+pub use self::{
+    collector::{DigestCallback, PathElement},
+    cursor::Cursor,
+    dedup::{DedupSet, DigestStorage},
+    event::Event,
+    field::Field,
+    guard::MessageGuard,
+    interner::{Interner, Interning},
+    options::DecodeOptions,
+    repeated::Repeated,
+    schema::{FieldSchema, Presence, Schema},
+    stream::Stream,
+    summary::{FieldRecord, FieldSummary},
+    tag_set::TagSet,
+    traits::{Decode, DecodeInto, DecodeRef, DecodeSeq},
+    unknown_fields::UnknownFieldPolicy,
+    validate::{validate, validate_against},
+};
+pub use crate::verihash::DigestOutput;
 
-pub use self::traits::{Decode, DecodeRef, DecodeSeq};
+#[cfg(feature = "alloc")]
+pub use self::{
+    collector::NestedDigest,
+    owned::{OwnedField, OwnedValue},
+    traits::DecodeSeqOwned,
+};
 
-pub(crate) use self::{decodable::Decodable, event::Event};
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+pub use self::{
+    diff::{diff, DiffReport},
+    dynamic::{DynDecoder, HashAlg},
+};
+
+pub(crate) use self::decodable::Decodable;
+
+use self::{collector::Collector, stack::Stack};
 
 use crate::{
     error::{self, Error},
-    field::{Tag, WireType},
-    verihash::DigestOutput,
+    field::{Header, Tag, WireType},
+    verihash::VerihashDigest,
     Message,
 };
-use digest::Digest;
-use heapless::consts::U16;
+use core::{cmp, convert::TryFrom, ops::RangeInclusive};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+/// Compute the Verihash digest of an encoded message.
+///
+/// Integers are hashed as their 64-bit little endian serialization, and not
+/// the `vint64` encoding they're carried in on the wire.
+///
+/// Messages have no framing beyond their fields, so the digest of the empty
+/// message is the digest of no input at all (for SHA-256,
+/// `e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855`).
+/// A nested message with a zero-length body is hashed as this digest.
+///
+/// Messages may be nested arbitrarily deeply, although without the `alloc`
+/// feature nesting is limited to 16 messages and sequences.
+pub fn hash_message<D: VerihashDigest>(message: &[u8]) -> Result<DigestOutput<D>, Error> {
+    hash_message_limited::<D>(message, &mut None, None)
+}
+
+/// Compute the Verihash digest of an encoded message, charging each of its
+/// fields (including those of nested messages) and the elements of any
+/// sequences it contains against a budget of `fields_remaining` (if any),
+/// and nesting messages at most `max_depth` deep (if limited), counting
+/// the message itself
+pub(crate) fn hash_message_limited<D: VerihashDigest>(
+    message: &[u8],
+    fields_remaining: &mut Option<usize>,
+    max_depth: Option<usize>,
+) -> Result<DigestOutput<D>, Error> {
+    if max_depth == Some(0) {
+        return Err(error::Kind::NestingDepth.into());
+    }
+
+    let mut stream = Stream::<D>::new();
+    stream.set_max_total_fields(*fields_remaining);
+    stream.set_max_depth(max_depth);
+
+    let mut input = message;
+    while stream.decode(&mut input)?.is_some() {}
+
+    *fields_remaining = stream.fields_remaining();
+    stream.compute_digest()
+}
+
+/// Compute the Verihash digest of the body of a sequence whose elements are
+/// of the given wire type.
+///
+/// The digest is that of the elements alone: the body of an empty sequence
+/// still hashes its element type, so empty sequences of different types
+/// have different digests.
+pub fn hash_sequence<D: VerihashDigest>(
+    wire_type: WireType,
+    body: &[u8],
+) -> Result<DigestOutput<D>, Error> {
+    hash_sequence_limited::<D>(wire_type, body, &mut None, None)
+}
+
+/// Compute the Verihash digest of the body of a sequence, charging each of
+/// its elements (and the fields of message elements) against a budget of
+/// `fields_remaining` (if any), and nesting message elements at most
+/// `max_depth` deep (if limited), counting the elements themselves
+pub(crate) fn hash_sequence_limited<D: VerihashDigest>(
+    wire_type: WireType,
+    body: &[u8],
+    fields_remaining: &mut Option<usize>,
+    max_depth: Option<usize>,
+) -> Result<DigestOutput<D>, Error> {
+    let mut decoder = sequence::Decoder::<D>::new(wire_type, body.len());
+    let mut input = body;
+
+    while decoder.remaining() > 0 {
+        if wire_type == WireType::Message {
+            charge_field(fields_remaining)?;
+            let msg_bytes = decoder.decode_message(&mut input)?;
+            let digest = hash_message_limited::<D>(msg_bytes, fields_remaining, max_depth)?;
+            decoder.hash_message_digest(&digest)?;
+        } else if let Some(event) = decoder.decode(&mut input)? {
+            if event.begins_element() {
+                charge_field(fields_remaining)?;
+            }
+        } else {
+            return Err(error::Kind::Truncated {
+                remaining: decoder.remaining(),
+                wire_type,
+            }
+            .into());
+        }
+    }
+
+    decoder
+        .compute_digest()?
+        .ok_or_else(|| error::Kind::Hashing.into())
+}
+
+/// Get the maximum depth of the messages nested within a message at the
+/// given depth (where the outermost message is at depth `1`), counting the
+/// nested messages themselves, or `None` if `max_depth` is unlimited
+fn max_nested_depth(max_depth: Option<usize>, depth: usize) -> Option<usize> {
+    max_depth.map(|max_depth| max_depth.saturating_sub(depth))
+}
+
+/// Charge a field against a budget of `fields_remaining` fields (if any),
+/// returning an [`error::Kind::LengthLimit`] error if it's exhausted
+fn charge_field(fields_remaining: &mut Option<usize>) -> Result<(), Error> {
+    if let Some(remaining) = fields_remaining.as_mut() {
+        *remaining = remaining.checked_sub(1).ok_or(error::Kind::LengthLimit)?;
+    }
+
+    Ok(())
+}
+
+/// Find the value of the `bytes` field of a message with the given tag,
+/// decoding it with the given options but without hashing it
+fn find_bytes_field<D: VerihashDigest>(
+    mut input: &[u8],
+    tag: Tag,
+    options: DecodeOptions,
+) -> Result<Option<&[u8]>, Error> {
+    let mut decoder = Decoder::<D>::with_options(options.hashing(false));
+
+    while let Some(field) = decoder.decode_field(&mut input)? {
+        match field.tag() {
+            t if t == tag => return field.bytes(&mut input).map(Some),
+            t if t > tag => break,
+            _ => field.skip(&mut input)?,
+        }
+    }
+
+    Ok(None)
+}
+
+/// Copy a secret into the beginning of `out` (which must be at least as
+/// long) and zero the rest of it, without branching on the contents of the
+/// secret or on which bytes of `out` it covers
+fn copy_padded(secret: &[u8], out: &mut [u8]) {
+    copy_padded_with(secret, out.len(), |i, byte| out[i] = byte);
+}
+
+/// Copy a secret into a buffer of `len` bytes as with [`copy_padded`],
+/// writing each byte of the buffer in turn using `write`
+fn copy_padded_with(secret: &[u8], len: usize, mut write: impl FnMut(usize, u8)) {
+    // Read a zero byte in place of an empty secret, so every byte of the
+    // buffer is copied from the source
+    let source: &[u8] = if secret.is_empty() { &[0] } else { secret };
+    let last = source.len() - 1;
+
+    for i in 0..len {
+        // All ones within the secret, and all zeros past the end of it
+        let mask = ((i < secret.len()) as u8).wrapping_neg();
+        write(i, source[cmp::min(i, last)] & mask);
+    }
+}
+
+/// Function invoked before each field is decoded which returns `true` if
+/// decoding should be cancelled, e.g. because a deadline has passed
+pub type CancelCheck = fn() -> bool;
 
 /// Veriform decoder.
 ///
@@ -31,29 +241,319 @@ use heapless::consts::U16;
 /// computation.
 ///
 /// It's intended to be used in conjunction with the [`Message`] trait.
-pub struct Decoder<D: Digest> {
-    /// Stack of message decoders (max nesting depth 16)
-    stack: heapless::Vec<message::Decoder<D>, U16>,
+///
+/// # Memory usage
+///
+/// The stack of message decoders (one per level of nesting) is stored
+/// inline with room for 16 messages, which is also the default maximum
+/// nesting depth, so decoding doesn't allocate. A `Decoder<Sha256>`
+/// occupies roughly 10 KiB on 64-bit targets as a result, depending on
+/// which features are enabled. When a higher maximum depth is configured,
+/// the stack spills onto the heap once it's exceeded, unless room was
+/// allocated up front using [`Decoder::with_capacity`].
+pub struct Decoder<D: VerihashDigest> {
+    /// Stack of message decoders (max nesting depth 16, unless the `alloc`
+    /// feature is enabled and a different maximum has been configured)
+    stack: Stack<message::Decoder<D>>,
 
     /// Sequence decoder if we're presently decoding a sequence
     // TODO(tarcieri): support nested sequences?
     seq_decoder: Option<sequence::Decoder<D>>,
+
+    /// Path to the nested message presently being decoded
+    path: Stack<PathElement>,
+
+    /// Decoder configuration
+    options: DecodeOptions,
+
+    /// Number of fields which may still be decoded, or `None` for unlimited
+    fields_remaining: Option<usize>,
+
+    /// Collector for the digests of nested messages (if enabled)
+    collector: Option<Collector<D>>,
+
+    /// Check for cancellation invoked before each field (if enabled)
+    cancel_check: Option<CancelCheck>,
+
+    /// Summary of the fields consumed so far (if enabled)
+    summary: Option<FieldSummary>,
+
+    /// Unknown fields collected using [`UnknownFieldPolicy::Collect`]
+    #[cfg(feature = "alloc")]
+    unknown_fields: Vec<(Tag, Vec<u8>)>,
 }
 
 impl<D> Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Initialize decoder
     pub fn new() -> Self {
-        let mut stack = heapless::Vec::new();
-        stack.push(message::Decoder::new()).unwrap();
+        Self::with_options(DecodeOptions::default())
+    }
+
+    /// Initialize decoder with the given options
+    pub fn with_options(options: DecodeOptions) -> Self {
+        let mut stack = Stack::new();
+
+        // Never fails: the stack is empty
+        let mut root = message::Decoder::with_hashing(options.hashing);
+        root.set_max_nested_depth(max_nested_depth(options.max_depth, 1));
+        let _ = stack.push(root);
+
         Decoder {
             stack,
             seq_decoder: None,
+            path: Stack::new(),
+            options,
+            fields_remaining: options.max_total_fields,
+            collector: None,
+            cancel_check: None,
+            summary: None,
+            #[cfg(feature = "alloc")]
+            unknown_fields: Vec::new(),
+        }
+    }
+
+    /// Initialize decoder with room for messages nested up to `depth` deep
+    /// (counting the outermost message) without growing its stack.
+    ///
+    /// Depths of up to 16 are always accommodated inline, so this only
+    /// allocates for greater depths, which also need to be permitted with
+    /// [`Decoder::set_max_depth`].
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn with_capacity(depth: usize) -> Self {
+        let mut decoder = Self::new();
+        decoder.stack.reserve(depth);
+        decoder.path.reserve(depth.saturating_sub(1));
+        decoder
+    }
+
+    /// Get the options this decoder was configured with
+    pub fn options(&self) -> DecodeOptions {
+        self.options
+    }
+
+    /// Set the maximum nesting depth of messages, counting the outermost
+    /// message, or `None` to allow messages to be nested arbitrarily deeply.
+    ///
+    /// The default maximum is 16. Beyond that depth the decoder's stack
+    /// spills onto the heap, so raising the maximum allows an encoded
+    /// message to allocate memory in proportion to its length.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.options.max_depth = max_depth;
+
+        for (i, decoder) in self.stack.iter_mut().enumerate() {
+            decoder.set_max_nested_depth(max_nested_depth(max_depth, i + 1));
+        }
+    }
+
+    /// Set the maximum total number of fields which may be decoded, counting
+    /// the fields of nested messages (including any which are skipped or
+    /// only hashed), the elements of sequences, and any unknown fields, or
+    /// `None` to allow any number of fields (the default).
+    ///
+    /// This bounds the work done decoding a message which is shallow but has
+    /// an enormous number of fields. Once the budget is exhausted, decoding
+    /// another field returns an [`error::Kind::LengthLimit`] error.
+    pub fn set_max_total_fields(&mut self, max_total_fields: Option<usize>) {
+        self.options.max_total_fields = max_total_fields;
+        self.fields_remaining = max_total_fields;
+    }
+
+    /// Invoke the given function before decoding each field, including the
+    /// fields of nested messages and unknown fields, or `None` to disable
+    /// cancellation (the default).
+    ///
+    /// If the function returns `true`, decoding stops with an
+    /// [`error::Kind::Cancelled`] error whose [`Error::position`] is the
+    /// number of bytes of the innermost message being decoded which were
+    /// consumed before the cancelled field (see [`error::Kind`] for the
+    /// offset base). The function is invoked once
+    /// per field, so it should be cheap, e.g. checking an atomic flag set
+    /// when a deadline passes.
+    pub fn set_cancel_check(&mut self, cancel_check: Option<CancelCheck>) {
+        self.cancel_check = cancel_check;
+    }
+
+    /// Set the policy for handling fields whose tags aren't handled by any
+    /// field of the message being decoded
+    pub fn set_unknown_field_policy(&mut self, policy: UnknownFieldPolicy) {
+        self.options.unknown_field_policy = policy;
+    }
+
+    /// Get the unknown fields collected so far using
+    /// [`UnknownFieldPolicy::Collect`], in the order they were encountered.
+    ///
+    /// Each field is returned along with its raw encoded bytes, including
+    /// the field header.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn unknown_fields(&self) -> impl Iterator<Item = (Tag, &[u8])> {
+        self.unknown_fields
+            .iter()
+            .map(|(tag, bytes)| (*tag, bytes.as_slice()))
+    }
+
+    /// Handle any unknown fields remaining in the input after the known
+    /// fields of a message have been decoded, according to the configured
+    /// [`UnknownFieldPolicy`].
+    ///
+    /// This method is invoked from proc macro-generated code before the
+    /// digest of a message is computed.
+    ///
+    /// Under [`UnknownFieldPolicy::Error`], any remaining input is an error:
+    /// an [`error::Kind::UnknownField`] if it begins with a complete field,
+    /// or [`error::Kind::Malformed`] otherwise. The latter catches stray
+    /// data following a value, such as a `uint64` or `sint64` value with a
+    /// spurious length delimiter (which would otherwise be decoded as the
+    /// value itself).
+    ///
+    /// This is a heuristic: a spurious length delimiter is indistinguishable
+    /// from the value on the wire, so it's only detected when it precedes
+    /// the last field of a message and the actual value isn't itself a
+    /// valid field. Otherwise the value is decoded as a field (or as stray
+    /// data), which may be skipped under the other policies, or decoded as
+    /// the next known field.
+    pub fn decode_unknown_fields(&mut self, input: &mut &[u8]) -> Result<(), Error> {
+        if self.options.unknown_field_policy == UnknownFieldPolicy::Error {
+            return if input.is_empty() {
+                Ok(())
+            } else {
+                self.reject_trailing_data(input)
+            };
+        }
+
+        while !input.is_empty() {
+            self.check_cancelled()?;
+            let field = *input;
+            let header = self.peek().decode_header(input)?;
+            self.count_field()?;
+            self.unknown_field(header, field, input)?;
+        }
+
+        Ok(())
+    }
+
+    /// Invoke the given callback with the path and digest of each nested
+    /// message as it's decoded
+    pub fn on_nested_digest(&mut self, callback: DigestCallback<D>) {
+        self.collector = Some(Collector::Callback(callback));
+    }
+
+    /// Collect the paths and digests of nested messages as they're decoded.
+    ///
+    /// Use [`Decoder::take_nested_digests`] to retrieve them.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn collect_nested_digests(&mut self) {
+        self.collector = Some(Collector::Vec(Vec::new()));
+    }
+
+    /// Take the nested message digests collected so far, in the order in
+    /// which the nested messages finished decoding
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn take_nested_digests(&mut self) -> Vec<NestedDigest<D>> {
+        match &mut self.collector {
+            Some(Collector::Vec(digests)) => core::mem::take(digests),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Record the tag, wire type, and encoded length of each field as it's
+    /// consumed in the given [`FieldSummary`], which can be retrieved with
+    /// [`Decoder::take_field_summary`] once decoding is complete.
+    pub fn summarize_fields(&mut self, summary: FieldSummary) {
+        self.summary = Some(summary);
+    }
+
+    /// Take the summary of the fields consumed so far, if one was provided
+    /// with [`Decoder::summarize_fields`]
+    pub fn take_field_summary(&mut self) -> Option<FieldSummary> {
+        self.summary.take()
+    }
+
+    /// Record the digest of each field of the outermost message as it's
+    /// decoded, so they can be obtained with [`Decoder::field_digests`].
+    ///
+    /// This must be called before decoding begins, and returns an
+    /// [`error::Kind::Hashing`] error if hashing is disabled.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn record_field_digests(&mut self) -> Result<(), Error> {
+        self.stack
+            .first_mut()
+            .ok_or(error::Kind::NestingDepth)?
+            .record_field_digests()
+    }
+
+    /// Exclude the fields of the outermost message whose tags are in the
+    /// given set from its digest (by default, every field is hashed).
+    ///
+    /// Excluded fields are still decoded, but the digest is computed as if
+    /// they were absent, e.g. so a signature over a message can be carried
+    /// in a field of the message it signs. Excluded fields aren't recorded
+    /// by [`Decoder::record_field_digests`] either.
+    ///
+    /// This must be called before decoding begins.
+    pub fn exclude_from_digest(&mut self, tags: TagSet) {
+        if let Some(decoder) = self.stack.first_mut() {
+            decoder.exclude_from_digest(tags);
+        }
+    }
+
+    /// Get the tag and digest of each field of the outermost message, in
+    /// the order they were decoded.
+    ///
+    /// A field's digest is its contribution to the Verihash digest of the
+    /// message, hashed on its own (i.e. the digest of a message containing
+    /// only that field), so comparing the field digests of two versions of
+    /// a message identifies which fields differ between them.
+    ///
+    /// Returns an [`error::Kind::Hashing`] error unless
+    /// [`Decoder::record_field_digests`] was called before decoding, or if
+    /// decoding stopped partway through a field.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn field_digests(mut self) -> Result<Vec<(Tag, DigestOutput<D>)>, Error> {
+        self.stack
+            .first_mut()
+            .ok_or(error::Kind::Hashing)?
+            .take_field_digests()
+    }
+
+    /// Hash the raw bytes of the outermost message as they're consumed, in
+    /// addition to computing its Verihash digest, so a plain digest of the
+    /// serialized message can be obtained with [`Decoder::raw_digest`]
+    /// without hashing the input again.
+    ///
+    /// This must be called before decoding begins.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn hash_raw_bytes(&mut self) {
+        if let Some(decoder) = self.stack.first_mut() {
+            decoder.hash_raw_bytes();
         }
     }
 
+    /// Get the digest of the raw bytes of the outermost message consumed so
+    /// far, i.e. `D::digest` of the prefix of the input which was decoded.
+    ///
+    /// Returns an [`error::Kind::Hashing`] error unless
+    /// [`Decoder::hash_raw_bytes`] was called before decoding.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn raw_digest(mut self) -> Result<DigestOutput<D>, Error> {
+        self.stack
+            .first_mut()
+            .ok_or(error::Kind::Hashing)?
+            .take_raw_digest()
+    }
+
     /// Fill the provided slice with the digest of the message if it fits
     // TODO(tarcieri): find a better way to handle generic digest sizes
     pub fn fill_digest(&mut self, output: &mut [u8]) -> Result<(), Error> {
@@ -66,34 +566,1043 @@ where
             return Err(error::Kind::Hashing)?;
         }
 
-        output.copy_from_slice(&digest);
-        Ok(())
+        output.copy_from_slice(&digest);
+        Ok(())
+    }
+
+    /// Decode the header of the next field in the message, returning a
+    /// [`Field`] which can be used to decode its value, or `None` if there
+    /// are no fields remaining in the input.
+    ///
+    /// This allows fields to be dispatched on their tag as they're
+    /// encountered (e.g. to decode messages where any field may be omitted),
+    /// rather than requiring their tags to be known in advance as with
+    /// [`Decode`] and the other decoding traits. Fields must still appear
+    /// in ascending tag order, and are hashed exactly as if they'd been
+    /// decoded using those traits.
+    pub fn decode_field<'a>(
+        &'a mut self,
+        input: &mut &[u8],
+    ) -> Result<Option<Field<'a, D>>, Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let result = self
+            .check_cancelled()
+            .and_then(|()| self.peek().decode_header(input))
+            .and_then(|header| self.count_field().map(|_| header));
+
+        let header = self.field_result(result)?;
+
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: field", header.tag);
+
+        Ok(Some(Field::new(self, header)))
+    }
+
+    /// Decode fields using [`Decoder::decode_field`], invoking the given
+    /// callback with each of them (and the input, for decoding its value),
+    /// until a field whose tag is `sentinel` or higher is reached or there
+    /// are no fields remaining in the input.
+    ///
+    /// The header of the field which ends the iteration isn't consumed, so
+    /// it can be decoded afterward, e.g. as one of a number of fixed fields
+    /// which follow a region of variable fields.
+    pub fn decode_fields_until<'i, F>(
+        &mut self,
+        sentinel: Tag,
+        input: &mut &'i [u8],
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(Field<'_, D>, &mut &'i [u8]) -> Result<(), Error>,
+    {
+        loop {
+            // Malformed headers are reported by `decode_field` below
+            let mut header_bytes = *input;
+            if let Ok(header) = ::vint64::decode(&mut header_bytes).map(Header::from) {
+                if header.tag >= sentinel {
+                    return Ok(());
+                }
+            }
+
+            match self.decode_field(input)? {
+                Some(field) => f(field, input)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Decode the outermost (root) message of a document, where `input` is
+    /// the message body: a bare sequence of fields, without the header and
+    /// length prefix of a [`WireType::Message`] field.
+    ///
+    /// This is equivalent to [`Message::decode`]. By contrast, decoding a
+    /// message using [`Decode::decode`] expects a field of the message being
+    /// decoded which contains it. The digest of the root message can be
+    /// obtained afterward with [`Decoder::fill_digest`].
+    pub fn decode_root<M: Message>(&mut self, input: &[u8]) -> Result<M, Error> {
+        M::decode(self, input)
+    }
+
+    /// Decode a self-certifying root message, which carries its own digest
+    /// in the `bytes` field with the given tag, verifying that the digest
+    /// of the message's other fields matches it.
+    ///
+    /// The field containing the digest is excluded from the digest, along
+    /// with any set with [`Decoder::exclude_from_digest`] (which must have
+    /// room for its tag), so the digest obtained afterward
+    /// with [`Decoder::fill_digest`] is the embedded one. A missing digest
+    /// field is an [`error::Kind::MissingField`] error, and a digest which
+    /// doesn't match is an [`error::Kind::Verification`] error.
+    ///
+    /// This must be called before decoding begins.
+    pub fn decode_self_certifying<M: Message>(
+        &mut self,
+        input: &[u8],
+        digest_tag: Tag,
+    ) -> Result<M, Error> {
+        let embedded = find_bytes_field::<D>(input, digest_tag, self.options)?
+            .ok_or(error::Kind::MissingField { tag: digest_tag })?;
+
+        if let Some(decoder) = self.stack.first_mut() {
+            decoder.exclude_tag_from_digest(digest_tag)?;
+        }
+
+        let msg = self.decode_root(input)?;
+        let digest = self.peek().compute_digest()?.ok_or(error::Kind::Hashing)?;
+
+        if digest[..] == *embedded {
+            Ok(msg)
+        } else {
+            Err(error::Kind::Verification.into())
+        }
+    }
+
+    /// Decode a root message whose body is the next `len` bytes of `input`,
+    /// e.g. when its length is known from a frame header rather than a
+    /// length delimiter, advancing `input` past the body.
+    ///
+    /// As with [`Decoder::decode_root`], no header or length delimiter is
+    /// read. If `input` is shorter than `len`, an [`error::Kind::Truncated`]
+    /// error is returned and `input` is left unchanged.
+    pub fn decode_message_with_len<M: Message>(
+        &mut self,
+        input: &mut &[u8],
+        len: usize,
+    ) -> Result<M, Error> {
+        if len > input.len() {
+            return Err(error::Kind::Truncated {
+                remaining: len - input.len(),
+                wire_type: WireType::Message,
+            }
+            .into());
+        }
+
+        let (body, rest) = input.split_at(len);
+        let msg = self.decode_root(body)?;
+        *input = rest;
+        Ok(msg)
+    }
+
+    /// Enter the nested message field with the given tag, returning a
+    /// [`MessageGuard`] which can be used to decode its fields inline, along
+    /// with the body of the nested message.
+    ///
+    /// This is the imperative alternative to decoding the nested message
+    /// with a [`Message`] type: the fields are decoded from the returned
+    /// body using the guard, after which the remainder of the body must be
+    /// passed to [`MessageGuard::finish`] to pop the nested message's frame.
+    pub fn enter<'a>(
+        &mut self,
+        tag: Tag,
+        input: &mut &'a [u8],
+    ) -> Result<(MessageGuard<'_, D>, &'a [u8]), Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: msg?", tag);
+
+        let msg_bytes =
+            self.decode_field_with(tag, WireType::Message, input, |decoder, input| {
+                decoder.decode_message(input)
+            })?;
+
+        let depth = self.stack.len();
+
+        if let Err(error) = self.push(PathElement::field(tag)) {
+            self.unwind(depth);
+            return Err(error);
+        }
+
+        Ok((MessageGuard::new(self, tag, depth), msg_bytes))
+    }
+
+    /// Borrow this decoder along with the given [`Interner`], returning an
+    /// [`Interning`] guard whose [`Interning::decode_string`] method decodes
+    /// `string` fields into interned handles rather than `&str`.
+    ///
+    /// Equal strings are only validated the first time they're seen, and
+    /// share a single handle thereafter.
+    pub fn with_interner<'a, I: Interner>(
+        &'a mut self,
+        interner: &'a mut I,
+    ) -> Interning<'a, D, I> {
+        Interning::new(self, interner)
+    }
+
+    /// Decode the fields of a message without a schema, returning an
+    /// [`OwnedValue::Message`] which owns all of the decoded data.
+    ///
+    /// The message is hashed exactly as if it had been decoded using a
+    /// [`Message`] type, so its digest can be obtained afterward.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn decode_owned(&mut self, input: &[u8]) -> Result<OwnedValue, Error> {
+        owned::decode_fields(self, input).map(OwnedValue::Message)
+    }
+
+    /// Decode all of the fields remaining in the input of the message
+    /// presently being decoded, returning them as [`OwnedField`]s which own
+    /// all of the decoded data.
+    ///
+    /// When hand-writing [`Message::decode`], this can be used after the
+    /// known fields have been decoded to capture any others for passthrough.
+    /// The fields are hashed exactly as if they'd been decoded individually,
+    /// so the message's digest remains valid.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn decode_remaining(&mut self, input: &mut &[u8]) -> Result<Vec<OwnedField>, Error> {
+        owned::decode_remaining(self, input)
+    }
+
+    /// Has the message presently being decoded been fully consumed, given
+    /// the input remaining in its body?
+    ///
+    /// When hand-writing [`Message::decode`], this can be used to determine
+    /// whether any optional trailing fields are present. It's false partway
+    /// through a field, as well as when fields remain in the input.
+    pub fn at_end(&self, input: &[u8]) -> bool {
+        match self.stack.last() {
+            Some(decoder) => decoder.at_end(input),
+            None => input.is_empty(),
+        }
+    }
+
+    /// Decode the field with the given tag if it's present, or return the
+    /// default value of `T` (e.g. `0`, or a message whose fields all have
+    /// their default values) if it's absent, e.g. when decoding a message
+    /// encoded before the field was added to its schema.
+    ///
+    /// The field is absent if the message has been fully consumed, or if
+    /// the next field has a greater tag (as fields are encoded in ascending
+    /// tag order). Absent fields contribute nothing to the message's digest.
+    ///
+    /// Unknown fields with lesser tags are handled according to the
+    /// [`UnknownFieldPolicy`] before the field is looked for, so under
+    /// [`UnknownFieldPolicy::Skip`] they don't mask its absence.
+    pub fn decode_or_default<T>(&mut self, tag: Tag, input: &mut &[u8]) -> Result<T, Error>
+    where
+        T: Default,
+        Self: Decode<T>,
+    {
+        self.decode_unknown_fields_before(tag, input)?;
+
+        let next_tag = self
+            .stack
+            .last()
+            .and_then(|decoder| decoder.peek_tag(input));
+
+        if self.at_end(input) || matches!(next_tag, Some(next_tag) if next_tag > tag) {
+            Ok(T::default())
+        } else {
+            self.decode(tag, input)
+        }
+    }
+
+    /// Decode a repeated `uint64` field with the given tag, returning an
+    /// iterator over its values.
+    ///
+    /// The field may be encoded either as a single sequence field (i.e. the
+    /// "packed" encoding of protobuf) or, for compatibility, as any number
+    /// of consecutive `uint64` fields with the same tag, which is the only
+    /// case in which a tag may occur more than once in a message. Both are
+    /// hashed as they're encoded.
+    ///
+    /// If the next field has a different tag, the field is absent and the
+    /// iterator is empty. Unknown fields with lesser tags are handled first,
+    /// as with [`Decoder::decode_or_default`].
+    pub fn decode_repeated_u64<'b>(
+        &mut self,
+        tag: Tag,
+        input: &mut &'b [u8],
+    ) -> Result<Repeated<'_, 'b, D>, Error> {
+        self.decode_unknown_fields_before(tag, input)?;
+
+        let header = self
+            .stack
+            .last()
+            .and_then(|decoder| decoder.peek_header(input));
+
+        match header {
+            Some(header) if header.tag == tag && header.wire_type == WireType::Sequence => {
+                DecodeSeq::<u64, D>::decode_seq(self, tag, input).map(Repeated::packed)
+            }
+            Some(header) if header.tag == tag => {
+                let fields = repeated::split_unpacked(tag, input);
+                Ok(Repeated::unpacked(self, tag, fields))
+            }
+            _ => Ok(Repeated::unpacked(self, tag, &[])),
+        }
+    }
+
+    /// Decode the field with the given tag, skipping any fields which
+    /// precede it, then stop without decoding the rest of the message, e.g.
+    /// to extract a routing key from the start of a large message.
+    ///
+    /// The remainder of the input is left untouched, so the digest of the
+    /// message can't be computed: this disables hashing for the rest of the
+    /// decoder's lifetime, and the digests of the messages presently being
+    /// decoded (including any enclosing ones) are discarded, so they can't
+    /// be verified. Obtaining a digest afterward returns an
+    /// [`error::Kind::Hashing`] error rather than the digest of part of a
+    /// message.
+    pub fn decode_field_then_stop<T>(&mut self, tag: Tag, input: &mut &[u8]) -> Result<T, Error>
+    where
+        Self: Decode<T>,
+    {
+        self.options.hashing = false;
+
+        for decoder in self.stack.iter_mut() {
+            decoder.disable_hashing();
+        }
+
+        while matches!(self.peek().peek_tag(input), Some(next_tag) if next_tag < tag) {
+            if let Some(field) = self.decode_field(input)? {
+                field.skip(input)?;
+            }
+        }
+
+        self.decode(tag, input)
+    }
+
+    /// Attempt to decode a message of type `M` from the given input,
+    /// restoring the decoder to the state it was in beforehand if decoding
+    /// fails.
+    ///
+    /// This allows probing which of several message types the input contains
+    /// by trying each in turn with the same decoder.
+    ///
+    /// A successful attempt leaves the decoder as if the message had been
+    /// decoded with [`Message::decode`], so its digest is computed as it's
+    /// decoded and can be obtained with [`Decoder::fill_digest`] without
+    /// hashing the input again.
+    ///
+    /// Digests of nested messages collected by a failed attempt are
+    /// discarded, however any which were passed to a callback registered with
+    /// [`Decoder::on_nested_digest`] can't be retracted.
+    ///
+    /// Restoring the decoder requires cloning its hasher state, so this
+    /// needs a concrete `D: Clone` digest type and can't be used within a
+    /// generic [`Message::decode`]. Decoding a nested message field which
+    /// fails to decode leaves the stack at its prior depth, but the field
+    /// has been consumed and the enclosing message can't be decoded further.
+    pub fn try_decode_message<M: Message>(&mut self, input: &[u8]) -> Result<M, Error>
+    where
+        D: Clone,
+    {
+        let depth = self.stack.len();
+        let decoder = self.peek().clone();
+        let in_sequence = self.seq_decoder.is_some();
+        let fields_remaining = self.fields_remaining;
+        let digests_collected = self.collector.as_ref().map(Collector::len);
+        let fields_summarized = self.summary.as_ref().map(FieldSummary::len);
+
+        #[cfg(feature = "alloc")]
+        let unknown_fields = self.unknown_fields.len();
+
+        let result = M::decode(self, input);
+
+        if result.is_err() {
+            self.unwind(depth);
+            *self.peek() = decoder;
+
+            if !in_sequence {
+                self.seq_decoder = None;
+            }
+
+            self.fields_remaining = fields_remaining;
+
+            if let (Some(collector), Some(len)) = (&mut self.collector, digests_collected) {
+                collector.truncate(len);
+            }
+
+            if let (Some(summary), Some(len)) = (&mut self.summary, fields_summarized) {
+                summary.truncate(len);
+            }
+
+            #[cfg(feature = "alloc")]
+            self.unknown_fields.truncate(unknown_fields);
+        }
+
+        result
+    }
+
+    /// Decode a `uint64` field, returning an [`error::Kind::OutOfRange`]
+    /// error if its value isn't within the given range, e.g. for an enum
+    /// discriminant or a port number.
+    pub fn decode_uint64_range(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+        range: RangeInclusive<u64>,
+    ) -> Result<u64, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: uint64 range?", tag);
+
+        self.decode_field_with(tag, WireType::UInt64, input, |decoder, input| {
+            let value = decoder.decode_uint64(input)?;
+
+            if range.contains(&value) {
+                Ok(value)
+            } else {
+                Err(error::Kind::OutOfRange.into())
+            }
+        })
+    }
+
+    /// Decode a `sint64` field into a narrower integer type such as `i32`,
+    /// `i16`, or `i8`, returning an [`error::Kind::OutOfRange`] error if its
+    /// value doesn't fit.
+    pub fn decode_sint64_as<T>(&mut self, tag: Tag, input: &mut &[u8]) -> Result<T, Error>
+    where
+        T: TryFrom<i64>,
+    {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: sint64 narrowed?", tag);
+
+        self.decode_field_with(tag, WireType::SInt64, input, |decoder, input| {
+            let value = decoder.decode_sint64(input)?;
+            T::try_from(value).map_err(|_| error::Kind::OutOfRange.into())
+        })
+    }
+
+    /// Decode a message field, returning the raw bytes of the message body
+    /// without decoding it.
+    ///
+    /// The header and length of the field are validated, and the message
+    /// body is still hashed if hashing is enabled. The returned bytes can be
+    /// forwarded verbatim or decoded later using [`Message::decode`].
+    pub fn decode_message_bytes<'a>(
+        &mut self,
+        tag: Tag,
+        input: &mut &'a [u8],
+    ) -> Result<&'a [u8], Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: msg bytes?", tag);
+
+        self.decode_field_limited(tag, WireType::Message, input, |decoder, input, budget| {
+            decoder.decode_message_bytes(tag, input, budget)
+        })
+    }
+
+    /// Decode a sequence of messages, returning an iterator over the raw
+    /// bytes of each message body without decoding them.
+    ///
+    /// This allows sequences whose elements are different [`Message`] types
+    /// to be decoded, e.g. by first reading a discriminator field from each
+    /// element to select the type to decode it as. Each message body is
+    /// still hashed if hashing is enabled.
+    pub fn decode_message_seq_bytes<'a, 'b>(
+        &'a mut self,
+        tag: Tag,
+        input: &mut &'b [u8],
+    ) -> Result<sequence::Iter<'a, 'b, &'b [u8], D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<msg bytes>?", tag);
+
+        let seq_bytes = self.decode_seq_field(tag, WireType::Message, input)?;
+
+        Ok(sequence::Iter::new(self, tag, seq_bytes))
+    }
+
+    /// Decode a `bytes` field, writing its value to the given writer rather
+    /// than returning it, e.g. to pass it through to an output sink without
+    /// buffering it. Returns the number of bytes written.
+    ///
+    /// Errors returned by the writer are mapped to [`error::Kind::Io`].
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn decode_bytes_to_writer<W>(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+        writer: &mut W,
+    ) -> Result<usize, Error>
+    where
+        W: std::io::Write + ?Sized,
+    {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: bytes -> writer?", tag);
+
+        self.decode_field_with(tag, WireType::Bytes, input, |decoder, input| {
+            decoder.decode_bytes_to_writer(input, writer)
+        })
+    }
+
+    /// Decode a `bytes` field from a [`bytes::Bytes`] buffer, returning its
+    /// value as a [`bytes::Bytes`] which shares the buffer's storage rather
+    /// than borrowing it, advancing `input` past the field.
+    ///
+    /// This is zero-copy like [`DecodeRef<[u8]>`][DecodeRef], but the value
+    /// can be retained after the input and the decoder have been dropped.
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+    pub fn decode_bytes_shared(
+        &mut self,
+        tag: Tag,
+        input: &mut bytes::Bytes,
+    ) -> Result<bytes::Bytes, Error> {
+        let mut remaining: &[u8] = input;
+        let value: &[u8] = self.decode_ref(tag, &mut remaining)?;
+        let consumed = input.len() - remaining.len();
+        let shared = input.slice_ref(value);
+
+        bytes::Buf::advance(input, consumed);
+        Ok(shared)
+    }
+
+    /// Decode a `bytes` field containing a secret (e.g. a credential) into
+    /// the given buffer, whose length is the maximum length of the secret,
+    /// returning the actual length of the secret.
+    ///
+    /// Every byte of the buffer is written regardless of the length of the
+    /// secret, with the bytes following it set to zero, and the copy doesn't
+    /// branch on the contents of the secret or on which bytes of the buffer
+    /// it fills, to reduce how much its timing reveals about the secret. A
+    /// secret longer than the buffer is an [`error::Kind::Capacity`] error.
+    ///
+    /// This is a mitigation, not a guarantee of constant-time decoding: the
+    /// length delimiter of the field is in the clear and is used to locate
+    /// the secret in the input, which (along with computing the message's
+    /// digest) takes time proportional to its actual length, and nothing
+    /// prevents the compiler from introducing branches of its own.
+    pub fn decode_secret_bytes(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+        out: &mut [u8],
+    ) -> Result<usize, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: secret bytes?", tag);
+
+        let secret = self.decode_field_with(tag, WireType::Bytes, input, |decoder, input| {
+            decoder.decode_bytes(input)
+        })?;
+
+        if secret.len() > out.len() {
+            return Err(error::Kind::Capacity.into());
+        }
+
+        copy_padded(secret, out);
+        Ok(secret.len())
+    }
+
+    /// Decode an expected field header, handling any (in-order) unknown
+    /// fields which precede it, and returning an error if the field is
+    /// missing or unexpected
+    fn expect_header(
+        &mut self,
+        input: &mut &[u8],
+        tag: Tag,
+        wire_type: WireType,
+    ) -> Result<(), Error> {
+        loop {
+            self.check_cancelled()?;
+            let field = *input;
+            let header = self.peek().decode_expected_header(input, tag, wire_type)?;
+            self.count_field()?;
+
+            if header.tag >= tag {
+                return self.peek().check_header(header, tag, wire_type);
+            }
+
+            self.unknown_field(header, field, input)?;
+        }
+    }
+
+    /// Handle any unknown fields preceding the field with the given tag
+    /// according to the configured [`UnknownFieldPolicy`]
+    fn decode_unknown_fields_before(&mut self, tag: Tag, input: &mut &[u8]) -> Result<(), Error> {
+        while let Some(next_tag) = self
+            .stack
+            .last()
+            .and_then(|decoder| decoder.peek_tag(input))
+        {
+            if next_tag >= tag {
+                break;
+            }
+
+            self.check_cancelled()?;
+            let field = *input;
+            let header = self.peek().decode_header(input)?;
+            self.count_field()?;
+            self.unknown_field(header, field, input)?;
+        }
+
+        Ok(())
+    }
+
+    /// Charge a decoded field against the budget set with
+    /// [`Decoder::set_max_total_fields`]
+    fn count_field(&mut self) -> Result<(), Error> {
+        charge_field(&mut self.fields_remaining)
+    }
+
+    /// Invoke the function set with [`Decoder::set_cancel_check`] before
+    /// decoding a field, returning an error if decoding has been cancelled
+    fn check_cancelled(&self) -> Result<(), Error> {
+        match self.cancel_check {
+            Some(cancelled) if cancelled() => {
+                let position = self.stack.last().map(message::Decoder::position);
+                Err(error::Kind::Cancelled.position(position.unwrap_or(0)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Handle an unknown field whose header has just been decoded from
+    /// `field`, according to the configured [`UnknownFieldPolicy`]
+    fn unknown_field(
+        &mut self,
+        header: Header,
+        field: &[u8],
+        input: &mut &[u8],
+    ) -> Result<(), Error> {
+        let skip = match self.options.unknown_field_policy {
+            UnknownFieldPolicy::Error => false,
+            UnknownFieldPolicy::Skip => !header.critical,
+            #[cfg(feature = "alloc")]
+            UnknownFieldPolicy::Collect => true,
+        };
+
+        if !skip {
+            return Err(error::Kind::UnknownField { tag: header.tag }.into());
+        }
+
+        let decoder = self.stack.last_mut().ok_or(error::Kind::NestingDepth)?;
+        decoder.skip_field(header, input, &mut self.fields_remaining)?;
+        self.summarize_field()?;
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.options.unknown_field_policy == UnknownFieldPolicy::Collect {
+                let field_len = field.len() - input.len();
+                self.unknown_fields
+                    .push((header.tag, field[..field_len].to_vec()));
+            }
+        }
+
+        #[cfg(not(feature = "alloc"))]
+        let _ = field;
+
+        Ok(())
+    }
+
+    /// Reject input remaining after the known fields of a message under
+    /// [`UnknownFieldPolicy::Error`]
+    fn reject_trailing_data(&mut self, input: &mut &[u8]) -> Result<(), Error> {
+        let result = self.peek().decode_header(input).and_then(|header| {
+            let kind = match self.peek().skip_field(header, input, &mut None) {
+                Ok(()) => error::Kind::UnknownField { tag: header.tag },
+                Err(_) => error::Kind::Malformed,
+            };
+
+            Err(kind.into())
+        });
+
+        self.field_result(result)
+    }
+
+    /// Get the depth of the pushdown stack
+    #[cfg(any(feature = "log", feature = "defmt"))]
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Push a new message decoder down onto the stack for the nested
+    /// message at the given path element
+    fn push(&mut self, element: PathElement) -> Result<(), Error> {
+        if let Some(max_depth) = self.options.max_depth {
+            if self.stack.len() >= max_depth {
+                return Err(error::Kind::NestingDepth.into());
+            }
+        }
+
+        let mut decoder = message::Decoder::with_hashing(self.options.hashing);
+        decoder.set_max_nested_depth(self.max_nested_depth(1));
+
+        self.stack
+            .push(decoder)
+            .map_err(|_| error::Kind::NestingDepth)?;
+
+        self.path
+            .push(element)
+            .map_err(|_| error::Kind::NestingDepth)?;
+
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        enter!(self, element.tag);
+
+        Ok(())
+    }
+
+    /// Get the maximum depth of the messages nested within a message
+    /// `levels` deeper than the innermost one, counting the nested messages
+    /// themselves, or `None` if unlimited
+    fn max_nested_depth(&self, levels: usize) -> Option<usize> {
+        max_nested_depth(self.options.max_depth, self.stack.len() + levels)
+    }
+
+    /// Decode a field with the given tag and wire type, using the given
+    /// function to decode its value
+    fn decode_field_with<'a, T>(
+        &mut self,
+        tag: Tag,
+        wire_type: WireType,
+        input: &mut &'a [u8],
+        decode_value: impl FnOnce(&mut message::Decoder<D>, &mut &'a [u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.decode_field_limited(tag, wire_type, input, |decoder, input, _| {
+            decode_value(decoder, input)
+        })
+    }
+
+    /// Decode a field with the given tag and wire type, using the given
+    /// function to decode its value, which is also passed the remaining
+    /// field budget to charge the fields of any nested messages it hashes
+    fn decode_field_limited<'a, T>(
+        &mut self,
+        tag: Tag,
+        wire_type: WireType,
+        input: &mut &'a [u8],
+        decode_value: impl FnOnce(
+            &mut message::Decoder<D>,
+            &mut &'a [u8],
+            &mut Option<usize>,
+        ) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let result = self.expect_header(input, tag, wire_type).and_then(|()| {
+            let decoder = self.stack.last_mut().ok_or(error::Kind::NestingDepth)?;
+            decode_value(decoder, input, &mut self.fields_remaining)
+        });
+
+        self.field_result(result)
+    }
+
+    /// Decode a `uint64` or `sint64` field in a single step using the
+    /// message decoder's fast path (if enabled), returning its raw `vint64`
+    /// value, or `None` if the field must be decoded using the general path
+    fn decode_scalar_field(
+        &mut self,
+        tag: Tag,
+        wire_type: WireType,
+        input: &mut &[u8],
+    ) -> Result<Option<u64>, Error> {
+        // Fields which would exhaust the budget take the general path, so
+        // the error occurs at the same point
+        if !self.options.scalar_fast_path || self.fields_remaining == Some(0) {
+            return Ok(None);
+        }
+
+        let (header, value, encoded_len) =
+            match self.peek().peek_scalar_field(input, tag, wire_type) {
+                Some(field) => field,
+                None => return Ok(None),
+            };
+
+        let result = self
+            .check_cancelled()
+            .and_then(|()| {
+                self.peek()
+                    .decode_scalar_field(input, header, value, encoded_len)
+            })
+            .and_then(|()| self.count_field())
+            .map(|()| Some(value));
+
+        self.field_result(result)
+    }
+
+    /// Decode the value of a field whose header has already been decoded
+    /// using the given function, after ensuring it has the given wire type
+    fn decode_value_with<'a, T>(
+        &mut self,
+        header: Header,
+        wire_type: WireType,
+        input: &mut &'a [u8],
+        decode_value: impl FnOnce(&mut message::Decoder<D>, &mut &'a [u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.decode_value_limited(header, wire_type, input, |decoder, input, _| {
+            decode_value(decoder, input)
+        })
+    }
+
+    /// Decode the value of a field whose header has already been decoded
+    /// like [`Decoder::decode_value_with`], also passing the remaining field
+    /// budget to the given function
+    fn decode_value_limited<'a, T>(
+        &mut self,
+        header: Header,
+        wire_type: WireType,
+        input: &mut &'a [u8],
+        decode_value: impl FnOnce(
+            &mut message::Decoder<D>,
+            &mut &'a [u8],
+            &mut Option<usize>,
+        ) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let result = self
+            .peek()
+            .check_header(header, header.tag, wire_type)
+            .and_then(|()| {
+                let decoder = self.stack.last_mut().ok_or(error::Kind::NestingDepth)?;
+                decode_value(decoder, input, &mut self.fields_remaining)
+            });
+
+        self.field_result(result)
+    }
+
+    /// Trace an error which occurred while decoding a field, attaching the
+    /// context in which it occurred, or summarize the field if it was
+    /// decoded successfully
+    fn field_result<T>(&mut self, result: Result<T, Error>) -> Result<T, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        {
+            if let Err(error) = &result {
+                fail!(self, error);
+            }
+        }
+
+        let result = result.and_then(|value| self.summarize_field().map(|()| value));
+        self.with_context(result)
+    }
+
+    /// Record the field most recently completed by the message decoder on
+    /// the top of the stack in the [`FieldSummary`] (if enabled)
+    fn summarize_field(&mut self) -> Result<(), Error> {
+        if let Some(summary) = &mut self.summary {
+            let decoder = self.stack.last_mut().ok_or(error::Kind::NestingDepth)?;
+
+            if let Some((header, encoded_len)) = decoder.take_completed_field() {
+                summary.record(&self.path, header, encoded_len)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode a sequence field with the given element wire type, pushing a
+    /// sequence decoder and returning the body of the sequence
+    fn decode_seq_field<'a>(
+        &mut self,
+        tag: Tag,
+        wire_type: WireType,
+        input: &mut &'a [u8],
+    ) -> Result<&'a [u8], Error> {
+        let seq_bytes =
+            self.decode_field_with(tag, WireType::Sequence, input, |decoder, input| {
+                decoder.decode_sequence(wire_type, input)
+            })?;
+
+        self.push_seq(wire_type, seq_bytes.len())?;
+        Ok(seq_bytes)
+    }
+
+    /// Decode a sequence field with the given element wire type, hashing it
+    /// into the message containing it and returning an owned iterator over
+    /// its elements.
+    ///
+    /// Errors hashing the sequence leave the message's digest incomplete,
+    /// and are otherwise deferred until the offending element is decoded
+    /// by the iterator.
+    #[cfg(feature = "alloc")]
+    fn decode_seq_owned_field<T>(
+        &mut self,
+        tag: Tag,
+        wire_type: WireType,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<T, D>, Error> {
+        let seq_bytes = self.decode_seq_field(tag, wire_type, input)?;
+        let mut cursor = sequence::Cursor::new(&mut *self, tag);
+
+        while let Some(Ok(())) = cursor.next(seq_bytes, sequence::Cursor::skip_element) {}
+
+        drop(cursor);
+        sequence::IntoIter::new(self, tag, wire_type, seq_bytes.to_vec())
+    }
+
+    /// Attach the tags and wire types of the fields presently being decoded
+    /// to an error
+    #[cfg(feature = "trace-context")]
+    pub(crate) fn with_context<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        result.map_err(|error| {
+            error.with_context(
+                self.stack
+                    .iter()
+                    .filter_map(|decoder| decoder.last_header()),
+            )
+        })
+    }
+
+    /// Attach the tags and wire types of the fields presently being decoded
+    /// to an error (no-op unless the `trace-context` feature is enabled)
+    #[cfg(not(feature = "trace-context"))]
+    pub(crate) fn with_context<T>(&self, result: Result<T, Error>) -> Result<T, Error> {
+        result
+    }
+
+    /// Decode a nested message field using the given function to decode the
+    /// message body, hashing its digest into the parent message
+    fn decode_nested_message<T>(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+        decode_body: impl FnOnce(&mut Self, &[u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let msg_bytes =
+            self.decode_field_with(tag, WireType::Message, input, |decoder, input| {
+                decoder.decode_message(input)
+            })?;
+
+        self.decode_nested_body(tag, msg_bytes, decode_body)
+    }
+
+    /// Decode the body of a nested message field using the given function,
+    /// hashing its digest into the parent message
+    fn decode_nested_body<T>(
+        &mut self,
+        tag: Tag,
+        msg_bytes: &[u8],
+        decode_body: impl FnOnce(&mut Self, &[u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let depth = self.stack.len();
+        let result = self
+            .push(PathElement::field(tag))
+            .and_then(|()| decode_body(self, msg_bytes));
+        let result = match self.with_context(result) {
+            Ok(result) => result,
+            Err(error) => {
+                self.unwind(depth);
+                return Err(error);
+            }
+        };
+
+        self.check_depth(depth)?;
+
+        if let Some(digest) = self.pop()? {
+            self.peek().hash_message_digest(tag, &digest)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Ensure the pushdown stack has returned to the expected depth after
+    /// decoding a nested message whose frame was pushed at `depth`, i.e.
+    /// that only the nested message's frame remains above it.
+    ///
+    /// Unbalanced stacks are caused by buggy [`Message::decode`] impls. This
+    /// is a debug assertion, or an [`error::Kind::UnbalancedStack`] error
+    /// when the `stack-checks` feature is enabled, in which case the frames
+    /// above `depth` are discarded and the message containing the nested
+    /// one is marked as failed, so nothing more is decoded into a leaked
+    /// frame.
+    fn check_depth(&mut self, depth: usize) -> Result<(), Error> {
+        let expected = depth + 1;
+
+        #[cfg(feature = "stack-checks")]
+        {
+            if self.stack.len() != expected {
+                self.unwind(depth);
+                self.peek().fail();
+                return Err(error::Kind::UnbalancedStack.into());
+            }
+        }
+
+        #[cfg(not(feature = "stack-checks"))]
+        debug_assert_eq!(
+            self.stack.len(),
+            expected,
+            "unbalanced decoder stack after decoding nested message"
+        );
+
+        Ok(())
+    }
+
+    /// Pop the message decoder from the stack when we've finished a message.
+    ///
+    /// Returns a digest of the nested message if message hashing is enabled,
+    /// or an error if the decoder stack underflows or the nested message was
+    /// only partially decoded.
+    fn pop(&mut self) -> Result<Option<DigestOutput<D>>, Error> {
+        let digest = self
+            .stack
+            .pop()
+            .ok_or(error::Kind::NestingDepth)?
+            .compute_digest()?;
+
+        if let (Some(collector), Some(digest)) = (&mut self.collector, &digest) {
+            collector.record(&self.path, digest);
+        }
+
+        let _element = self.path.pop();
+
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        {
+            if let Some(element) = _element {
+                exit!(self, element.tag);
+            }
+        }
+
+        Ok(digest)
+    }
+
+    /// Discard the decoders of any nested messages beyond the given depth
+    /// after an error, so the stack is left as it was before they were
+    /// pushed. Unlike [`Decoder::pop`], no digests are computed or recorded.
+    fn unwind(&mut self, depth: usize) {
+        while self.stack.len() > depth {
+            self.stack.pop();
+        }
+
+        // The path has one fewer element than the stack (the outermost
+        // message has no path element)
+        while self.path.len() >= depth {
+            self.path.pop();
+        }
     }
 
-    /// Get the depth of the pushdown stack
-    #[cfg(feature = "log")]
-    pub(crate) fn depth(&self) -> usize {
-        self.stack.len()
-    }
+    /// Record the digest of a nested message which was decoded using a
+    /// separate decoder
+    #[cfg(feature = "rayon")]
+    fn record_nested_digest(
+        &mut self,
+        element: PathElement,
+        digest: &DigestOutput<D>,
+    ) -> Result<(), Error> {
+        if let Some(collector) = &mut self.collector {
+            self.path
+                .push(element)
+                .map_err(|_| error::Kind::NestingDepth)?;
 
-    /// Push a new message decoder down onto the stack
-    fn push(&mut self) -> Result<(), Error> {
-        self.stack
-            .push(message::Decoder::new())
-            .map_err(|_| error::Kind::NestingDepth.into())
-    }
+            collector.record(&self.path, digest);
+            self.path.pop();
+        }
 
-    /// Pop the message decoder from the stack when we've finished a message.
-    ///
-    /// Returns a digest of the nested message if message hashing is enabled.
-    ///
-    /// Panics if the decoder stack underflows.
-    // TODO(tarcieri): panic-free higher-level API, possibly RAII-based?
-    fn pop(&mut self) -> Option<DigestOutput<D>> {
-        self.stack.pop().unwrap().compute_digest().unwrap()
+        Ok(())
     }
 
-    /// Peek at the message decoder on the top of the stack
+    /// Peek at the message decoder on the top of the stack.
+    ///
+    /// The root message decoder is pushed by [`Decoder::new`] and is never
+    /// popped, so this can't panic when nested decodes are balanced.
     fn peek(&mut self) -> &mut message::Decoder<D> {
         self.stack.last_mut().unwrap()
     }
@@ -102,7 +1611,11 @@ where
     // TODO(tarcieri): support nested sequences?
     fn push_seq(&mut self, wire_type: WireType, length: usize) -> Result<(), Error> {
         if self.seq_decoder.is_none() {
-            self.seq_decoder = Some(sequence::Decoder::new(wire_type, length));
+            self.seq_decoder = Some(sequence::Decoder::with_hashing(
+                wire_type,
+                length,
+                self.options.hashing,
+            ));
             Ok(())
         } else {
             Err(error::Kind::NestedSequence.into())
@@ -111,13 +1624,19 @@ where
 
     /// Pop the sequence decoder.
     ///
-    /// Panics if the decoder stack underflows.
-    // TODO(tarcieri): panic-free higher-level API, possibly RAII-based?
-    fn pop_seq(&mut self) -> Option<DigestOutput<D>> {
-        self.seq_decoder.take().unwrap().compute_digest().unwrap()
+    /// Returns an error if there is no sequence decoder or the sequence was
+    /// only partially decoded.
+    fn pop_seq(&mut self) -> Result<Option<DigestOutput<D>>, Error> {
+        self.seq_decoder
+            .take()
+            .ok_or(error::Kind::Failed)?
+            .compute_digest()
     }
 
     /// Peek at the sequence decoder.
+    ///
+    /// Only called by [`sequence::Iter`], which is constructed after
+    /// [`Decoder::push_seq`] and pops the sequence decoder on drop.
     fn peek_seq(&mut self) -> &mut sequence::Decoder<D> {
         self.seq_decoder.as_mut().unwrap()
     }
@@ -125,7 +1644,7 @@ where
 
 impl<D> Default for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn default() -> Self {
         Self::new()
@@ -134,82 +1653,99 @@ where
 
 impl<D, M> Decode<M> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
     M: Message,
 {
     fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<M, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: msg?", tag);
 
-        self.peek().expect_header(input, tag, WireType::Message)?;
-        let msg_bytes = self.peek().decode_message(input)?;
-
-        self.push()?;
-        let msg = M::decode(self, msg_bytes)?;
+        self.decode_nested_message(tag, input, M::decode)
+    }
+}
 
-        if let Some(digest) = self.pop() {
-            self.peek().hash_message_digest(tag, &digest)?;
-        }
+impl<D, M> DecodeInto<M> for Decoder<D>
+where
+    D: VerihashDigest,
+    M: Message,
+{
+    fn decode_into(&mut self, tag: Tag, input: &mut &[u8], out: &mut M) -> Result<(), Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: msg?", tag);
 
-        Ok(msg)
+        self.decode_nested_message(tag, input, |decoder, msg_bytes| {
+            M::decode_into(decoder, msg_bytes, out)
+        })
     }
 }
 
 impl<D> Decode<u64> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<u64, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: uint64?", tag);
 
-        self.peek().expect_header(input, tag, WireType::UInt64)?;
-        self.peek().decode_uint64(input)
+        if let Some(value) = self.decode_scalar_field(tag, WireType::UInt64, input)? {
+            return Ok(value);
+        }
+
+        self.decode_field_with(tag, WireType::UInt64, input, |decoder, input| {
+            decoder.decode_uint64(input)
+        })
     }
 }
 
 impl<D> Decode<i64> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<i64, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: sint64?", tag);
 
-        self.peek().expect_header(input, tag, WireType::SInt64)?;
-        self.peek().decode_sint64(input)
+        if let Some(value) = self.decode_scalar_field(tag, WireType::SInt64, input)? {
+            return Ok(vint64::zigzag::decode(value));
+        }
+
+        self.decode_field_with(tag, WireType::SInt64, input, |decoder, input| {
+            decoder.decode_sint64(input)
+        })
     }
 }
 
 impl<D> DecodeRef<[u8]> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode_ref<'a>(&mut self, tag: Tag, input: &mut &'a [u8]) -> Result<&'a [u8], Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: bytes?", tag);
 
-        self.peek().expect_header(input, tag, WireType::Bytes)?;
-        self.peek().decode_bytes(input)
+        self.decode_field_with(tag, WireType::Bytes, input, |decoder, input| {
+            decoder.decode_bytes(input)
+        })
     }
 }
 
 impl<D> DecodeRef<str> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode_ref<'a>(&mut self, tag: Tag, input: &mut &'a [u8]) -> Result<&'a str, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: string?", tag);
 
-        self.peek().expect_header(input, tag, WireType::String)?;
-        self.peek().decode_string(input)
+        self.decode_field_with(tag, WireType::String, input, |decoder, input| {
+            decoder.decode_string(input)
+        })
     }
 }
 
 impl<D, M> DecodeSeq<M, D> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
     M: Message,
 {
     fn decode_seq<'a, 'b>(
@@ -217,12 +1753,28 @@ where
         tag: Tag,
         input: &mut &'b [u8],
     ) -> Result<sequence::Iter<'a, 'b, M, D>, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: seq<msg>?", tag);
 
-        self.peek().expect_header(input, tag, WireType::Sequence)?;
-        let seq_bytes = self.peek().decode_sequence(WireType::Message, input)?;
-        self.push_seq(WireType::Message, seq_bytes.len())?;
+        let seq_bytes = self.decode_seq_field(tag, WireType::Message, input)?;
+
+        Ok(sequence::Iter::new(self, tag, seq_bytes))
+    }
+}
+
+impl<D> DecodeSeq<bool, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq<'a, 'b>(
+        &'a mut self,
+        tag: Tag,
+        input: &mut &'b [u8],
+    ) -> Result<sequence::Iter<'a, 'b, bool, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<bool>?", tag);
+
+        let seq_bytes = self.decode_seq_field(tag, WireType::False, input)?;
 
         Ok(sequence::Iter::new(self, tag, seq_bytes))
     }
@@ -230,19 +1782,17 @@ where
 
 impl<D> DecodeSeq<u64, D> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode_seq<'a, 'b>(
         &'a mut self,
         tag: Tag,
         input: &mut &'b [u8],
     ) -> Result<sequence::Iter<'a, 'b, u64, D>, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: seq<uint64>?", tag);
 
-        self.peek().expect_header(input, tag, WireType::Sequence)?;
-        let seq_bytes = self.peek().decode_sequence(WireType::UInt64, input)?;
-        self.push_seq(WireType::UInt64, seq_bytes.len())?;
+        let seq_bytes = self.decode_seq_field(tag, WireType::UInt64, input)?;
 
         Ok(sequence::Iter::new(self, tag, seq_bytes))
     }
@@ -250,33 +1800,234 @@ where
 
 impl<D> DecodeSeq<i64, D> for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode_seq<'a, 'b>(
         &'a mut self,
         tag: Tag,
         input: &mut &'b [u8],
     ) -> Result<sequence::Iter<'a, 'b, i64, D>, Error> {
-        #[cfg(feature = "log")]
+        #[cfg(any(feature = "log", feature = "defmt"))]
         begin!(self, "[{}]: seq<sint64>?", tag);
 
-        self.peek().expect_header(input, tag, WireType::Sequence)?;
-        let seq_bytes = self.peek().decode_sequence(WireType::SInt64, input)?;
-        self.push_seq(WireType::SInt64, seq_bytes.len())?;
+        let seq_bytes = self.decode_seq_field(tag, WireType::SInt64, input)?;
 
         Ok(sequence::Iter::new(self, tag, seq_bytes))
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<D, M> DecodeSeqOwned<M, D> for Decoder<D>
+where
+    D: VerihashDigest,
+    M: Message,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<M, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<msg>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::Message, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecodeSeqOwned<bool, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<bool, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<bool>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::False, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecodeSeqOwned<u64, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<u64, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<uint64>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::UInt64, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecodeSeqOwned<i64, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<i64, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<sint64>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::SInt64, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecodeSeqOwned<Vec<u8>, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<Vec<u8>, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<bytes>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::Bytes, input)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecodeSeqOwned<String, D> for Decoder<D>
+where
+    D: VerihashDigest,
+{
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<String, D>, Error> {
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        begin!(self, "[{}]: seq<string>?", tag);
+
+        self.decode_seq_owned_field(tag, WireType::String, input)
+    }
+}
+
 #[cfg(all(test, feature = "sha2"))]
 mod tests {
-    use super::{Decode, DecodeRef};
-    use crate::Decoder;
+    use super::{
+        copy_padded_with, hash_message, hash_sequence, sequence, stack::Stack, Decode, DecodeRef,
+        DecodeSeq,
+    };
+    use crate::{
+        consts::Literal, error, field::WireType, verihash::VerihashDigest, Decoder, Error, Message,
+    };
+    use digest::Digest;
+    use sha2::Sha256;
+
+    /// Maximum length of the secrets in the `copy_padded` tests
+    const SECRET_MAX: usize = 16;
+
+    /// Buffer which counts the writes made to it by [`copy_padded_with`]
+    /// and records the order of the bytes written
+    #[derive(Debug, Default)]
+    struct CountingBuffer {
+        bytes: [u8; SECRET_MAX],
+        order: [usize; SECRET_MAX],
+        writes: usize,
+    }
+
+    impl CountingBuffer {
+        fn copy(secret: &[u8]) -> Self {
+            let mut buffer = Self::default();
+
+            copy_padded_with(secret, SECRET_MAX, |i, byte| {
+                buffer.bytes[i] = byte;
+                buffer.order[buffer.writes] = i;
+                buffer.writes += 1;
+            });
+
+            buffer
+        }
+    }
+
+    /// Buggy [`Message`] impl which leaves a frame pushed onto the stack
+    struct UnbalancedMessage;
+
+    impl Message for UnbalancedMessage {
+        fn decode<D>(decoder: &mut super::Decoder<D>, _input: &[u8]) -> Result<Self, Error>
+        where
+            D: VerihashDigest,
+        {
+            decoder.push(super::PathElement::field(0))?;
+            Ok(UnbalancedMessage)
+        }
+
+        fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+            Ok(&buffer[..0])
+        }
+
+        fn encoded_len(&self) -> usize {
+            0
+        }
+    }
+
+    /// [`Message`] which contains itself in field 0, so decoding fails at
+    /// the first nested message whose field 0 isn't a message
+    struct RecursiveMessage;
+
+    impl Message for RecursiveMessage {
+        fn decode<D>(decoder: &mut super::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+        where
+            D: VerihashDigest,
+        {
+            let _: RecursiveMessage = decoder.decode(0, &mut input)?;
+            Ok(RecursiveMessage)
+        }
+
+        fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+            Ok(&buffer[..0])
+        }
+
+        fn encoded_len(&self) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn copy_padded_writes_independent_of_secret() {
+        let a = CountingBuffer::copy(b"hunter2");
+        let b = CountingBuffer::copy(b"letmein");
+
+        // Equal-length secrets are copied using the same writes in the
+        // same order, differing only in the bytes written
+        assert_eq!(a.writes, SECRET_MAX);
+        assert_eq!(b.writes, SECRET_MAX);
+        assert_eq!(a.order, b.order);
+        assert_eq!(&a.bytes[..7], b"hunter2");
+        assert_eq!(&b.bytes[..7], b"letmein");
+        assert_eq!(a.bytes[7..], b.bytes[7..]);
+        assert_eq!(&a.bytes[7..], &[0; SECRET_MAX - 7]);
+
+        // As are secrets of different lengths (including empty ones)
+        for secret in &[&b""[..], b"correct horse", &[0xFF; SECRET_MAX]] {
+            let c = CountingBuffer::copy(secret);
+            assert_eq!(c.writes, SECRET_MAX);
+            assert_eq!(c.order, a.order);
+            assert_eq!(&c.bytes[..secret.len()], *secret);
+        }
+    }
 
     #[test]
     fn decode_uint64() {
-        let input = [138, 10, 85];
-        let mut input_ref = &input[..];
+        let input = Literal::new().uint64(42, 42);
+        let mut input_ref = input.as_bytes();
 
         let value: u64 = Decoder::new().decode(42, &mut input_ref).unwrap();
         assert_eq!(value, 42);
@@ -285,8 +2036,8 @@ mod tests {
 
     #[test]
     fn decode_sint64() {
-        let input = [206, 10, 167];
-        let mut input_ref = &input[..];
+        let input = Literal::new().sint64(43, -42);
+        let mut input_ref = input.as_bytes();
 
         let value: i64 = Decoder::new().decode(43, &mut input_ref).unwrap();
         assert_eq!(value, -42);
@@ -295,8 +2046,8 @@ mod tests {
 
     #[test]
     fn decode_bytes() {
-        let input = [73, 11, 98, 121, 116, 101, 115];
-        let mut input_ref = &input[..];
+        let input = Literal::new().bytes(2, b"bytes");
+        let mut input_ref = input.as_bytes();
 
         let bytes: &[u8] = Decoder::new().decode_ref(2, &mut input_ref).unwrap();
         assert_eq!(bytes, &[98, 121, 116, 101, 115]);
@@ -305,11 +2056,418 @@ mod tests {
 
     #[test]
     fn decode_string() {
-        let input = [139, 7, 98, 97, 122];
-        let mut input_ref = &input[..];
+        let input = Literal::new().string(4, "baz");
+        let mut input_ref = input.as_bytes();
 
         let string: &str = Decoder::new().decode_ref(4, &mut input_ref).unwrap();
         assert_eq!(string, "baz");
         assert!(input_ref.is_empty());
     }
+
+    #[test]
+    fn seq_rolling_digest() {
+        let body = Literal::new().vint64(1).vint64(2).vint64(3).vint64(4);
+        let body = body.as_bytes();
+        let input = Literal::new().sequence(1, WireType::UInt64, body);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let mut seq: sequence::Iter<'_, '_, u64, _> =
+            decoder.decode_seq(1, &mut input_ref).unwrap();
+
+        assert_eq!(seq.next().unwrap().unwrap(), 1);
+        assert_eq!(seq.next().unwrap().unwrap(), 2);
+
+        let prefix_digest = seq.rolling_digest().unwrap();
+        assert_eq!(
+            prefix_digest,
+            hash_sequence::<Sha256>(WireType::UInt64, &body[..2]).unwrap()
+        );
+
+        assert_eq!(seq.next().unwrap().unwrap(), 3);
+        assert_eq!(seq.next().unwrap().unwrap(), 4);
+        assert!(seq.next().is_none());
+
+        let final_digest = seq.rolling_digest().unwrap();
+        assert_ne!(prefix_digest, final_digest);
+        assert_eq!(
+            final_digest,
+            hash_sequence::<Sha256>(WireType::UInt64, body).unwrap()
+        );
+
+        drop(seq);
+        assert!(input_ref.is_empty());
+
+        let mut digest = crate::Sha256Digest::default();
+        decoder.fill_digest(&mut digest).unwrap();
+        assert_eq!(
+            &digest[..],
+            &hash_message::<Sha256>(input.as_bytes()).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn hash_message_seq_containing_uint64s() {
+        // Sequence header claims 3 bytes of messages, body contains `uint64`s
+        let input = [47, 109, 3, 5, 7];
+        assert!(hash_message::<Sha256>(&input).is_err());
+    }
+
+    #[test]
+    fn hash_string_seq_containing_bytes() {
+        let input = [47, 22, 2, 7, 102, 111, 111, 7, 98, 0xff, 114];
+        let err = hash_message::<Sha256>(&input).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Utf8 { valid_up_to: 1 });
+    }
+
+    #[test]
+    fn decode_bool_seq() {
+        let input = Literal::new().sequence(1, WireType::False, &[1, 0, 1]);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let seq: sequence::Iter<'_, '_, bool, _> = decoder.decode_seq(1, &mut input_ref).unwrap();
+
+        let values = seq.collect::<Result<heapless::Vec<_, heapless::consts::U3>, _>>();
+        assert_eq!(values.unwrap(), [true, false, true]);
+        assert!(input_ref.is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extend_into_vec() {
+        let mut values = alloc::vec::Vec::new();
+
+        for input in &[&[47, 97, 1, 0, 1][..], &[47, 65, 0, 0][..]] {
+            let mut input_ref = *input;
+            let mut decoder = Decoder::new();
+
+            let seq: sequence::Iter<'_, '_, bool, _> =
+                decoder.decode_seq(1, &mut input_ref).unwrap();
+
+            seq.extend_into(&mut values).unwrap();
+            assert!(input_ref.is_empty());
+        }
+
+        assert_eq!(values, [true, false, true, false, false]);
+    }
+
+    #[test]
+    fn extend_into_full_heapless_vec() {
+        let input = [47, 97, 1, 0, 1];
+        let mut input_ref = &input[..];
+        let mut decoder = Decoder::new();
+        let mut values = heapless::Vec::<_, heapless::consts::U2>::new();
+
+        let seq: sequence::Iter<'_, '_, bool, _> = decoder.decode_seq(1, &mut input_ref).unwrap();
+
+        assert!(seq.extend_into_heapless(&mut values).is_err());
+        assert_eq!(values, [true, false]);
+    }
+
+    #[test]
+    fn decode_bool_seq_malformed() {
+        let input = [47, 97, 1, 2, 1];
+        let mut input_ref = &input[..];
+        let mut decoder = Decoder::new();
+
+        let mut seq: sequence::Iter<'_, '_, bool, _> =
+            decoder.decode_seq(1, &mut input_ref).unwrap();
+
+        assert_eq!(seq.next().unwrap(), Ok(true));
+        assert_eq!(
+            seq.next().unwrap().err().unwrap().kind(),
+            error::Kind::Malformed
+        );
+    }
+
+    #[test]
+    fn decode_seq_with_reserved_element_type() {
+        // Sequence header with the reserved high bit of the element type set
+        let input = [47, 53, 3];
+        let mut input_ref = &input[..];
+        let mut decoder = Decoder::new();
+
+        let result: Result<sequence::Iter<'_, '_, u64, _>, _> =
+            decoder.decode_seq(1, &mut input_ref);
+
+        assert_eq!(result.err().unwrap().kind(), error::Kind::InvalidWireType);
+    }
+
+    #[test]
+    fn decode_seq_of_seqs() {
+        let input = [47, 47, 1];
+        let mut input_ref = &input[..];
+        let mut decoder = Decoder::new();
+
+        let result: Result<sequence::Iter<'_, '_, u64, _>, _> =
+            decoder.decode_seq(1, &mut input_ref);
+
+        assert_eq!(result.err().unwrap().kind(), error::Kind::NestedSequence);
+    }
+
+    #[test]
+    #[cfg_attr(
+        all(debug_assertions, not(feature = "stack-checks")),
+        should_panic(expected = "unbalanced decoder stack")
+    )]
+    fn decode_unbalanced_message() {
+        let input = Literal::new()
+            .message(1, &Literal::new().uint64(2, 3))
+            .uint64(2, 4);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let result: Result<UnbalancedMessage, Error> = decoder.decode(1, &mut input_ref);
+
+        #[cfg(feature = "stack-checks")]
+        {
+            assert_eq!(result.err().unwrap().kind(), error::Kind::UnbalancedStack);
+
+            // The leaked frame is discarded and the parent message is failed
+            assert_eq!(decoder.stack.len(), 1);
+            let err = Decode::<u64>::decode(&mut decoder, 2, &mut input_ref).unwrap_err();
+            assert_eq!(err.kind(), error::Kind::Failed);
+        }
+
+        #[cfg(not(feature = "stack-checks"))]
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn decode_empty_fields() {
+        let input = Literal::new()
+            .bytes(1, b"")
+            .string(2, "")
+            .message(3, &Literal::new())
+            .uint64(4, 7);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let bytes: &[u8] = decoder.decode_ref(1, &mut input_ref).unwrap();
+        assert!(bytes.is_empty());
+
+        let string: &str = decoder.decode_ref(2, &mut input_ref).unwrap();
+        assert!(string.is_empty());
+
+        let msg_bytes = decoder.decode_message_bytes(3, &mut input_ref).unwrap();
+        assert!(msg_bytes.is_empty());
+
+        let value: u64 = decoder.decode(4, &mut input_ref).unwrap();
+        assert_eq!(value, 7);
+        assert!(input_ref.is_empty());
+
+        let mut digest = [0u8; 32];
+        decoder.fill_digest(&mut digest).unwrap();
+        assert_eq!(
+            &digest[..],
+            hash_message::<Sha256>(input.as_bytes()).unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn hash_empty_bytes_and_string_fields() {
+        let input = Literal::new().bytes(1, b"").string(2, "");
+
+        let mut expected = Sha256::new();
+        for &(tag, wire_type) in &[(1u64, WireType::Bytes), (2, WireType::String)] {
+            expected.update([WireType::UInt64 as u8]);
+            expected.update(tag.to_le_bytes());
+            expected.update([wire_type as u8]);
+            expected.update(0u64.to_le_bytes());
+        }
+
+        assert_eq!(
+            hash_message::<Sha256>(input.as_bytes()).unwrap(),
+            expected.finalize()
+        );
+    }
+
+    #[test]
+    fn hash_sequences_with_empty_elements() {
+        // Elements: "", "ab", "", "c"
+        let body = [1, 5, b'a', b'b', 1, 3, b'c'];
+        let elements: &[&[u8]] = &[b"", b"ab", b"", b"c"];
+
+        for &wire_type in &[WireType::Bytes, WireType::String] {
+            let mut expected = Sha256::new();
+            expected.update([wire_type as u8]);
+
+            for element in elements {
+                expected.update([wire_type as u8]);
+                expected.update((element.len() as u64).to_le_bytes());
+                expected.update(element);
+            }
+
+            assert_eq!(
+                hash_sequence::<Sha256>(wire_type, &body).unwrap(),
+                expected.finalize()
+            );
+        }
+    }
+
+    #[test]
+    fn hash_message_sequence_with_empty_elements() {
+        // Elements: empty message, message containing `uint64` 42 (tag 0),
+        // empty message
+        let body = [1, 5, 5, 85, 1];
+        let elements: &[&[u8]] = &[b"", &[5, 85], b""];
+
+        let mut expected = Sha256::new();
+        expected.update([WireType::Message as u8]);
+
+        for element in elements {
+            expected.update([WireType::Message as u8]);
+            expected.update((element.len() as u64).to_le_bytes());
+            expected.update(hash_message::<Sha256>(element).unwrap());
+        }
+
+        assert_eq!(
+            hash_sequence::<Sha256>(WireType::Message, &body).unwrap(),
+            expected.finalize()
+        );
+        assert_eq!(
+            hash_message::<Sha256>(&[]).unwrap(),
+            Sha256::new().finalize()
+        );
+    }
+
+    /// Integers (`uint64`, then `sint64` in two's complement) and the
+    /// 8-byte little endian serialization they're hashed as, regardless of
+    /// the width of their `vint64` encoding
+    const INTEGER_VECTORS: &[(WireType, u64, [u8; 8])] = &[
+        (WireType::UInt64, 0, [0, 0, 0, 0, 0, 0, 0, 0]),
+        (WireType::UInt64, 127, [0x7f, 0, 0, 0, 0, 0, 0, 0]),
+        (WireType::UInt64, 128, [0x80, 0, 0, 0, 0, 0, 0, 0]),
+        (WireType::UInt64, 0x0102_0304, [4, 3, 2, 1, 0, 0, 0, 0]),
+        (WireType::UInt64, 0xffff_ffff_ffff_ffff, [0xff; 8]),
+        (WireType::SInt64, 1, [1, 0, 0, 0, 0, 0, 0, 0]),
+        (WireType::SInt64, -1i64 as u64, [0xff; 8]),
+        (
+            WireType::SInt64,
+            -256i64 as u64,
+            [0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        ),
+        (
+            WireType::SInt64,
+            0x8000_0000_0000_0000,
+            [0, 0, 0, 0, 0, 0, 0, 0x80],
+        ),
+    ];
+
+    /// Encode an integer from [`INTEGER_VECTORS`] as `vint64`
+    fn encode_integer(wire_type: WireType, value: u64) -> ::vint64::VInt64 {
+        if wire_type == WireType::UInt64 {
+            ::vint64::encode(value)
+        } else {
+            ::vint64::signed::encode(value as i64)
+        }
+    }
+
+    #[test]
+    fn integer_field_hash_vectors() {
+        for &(wire_type, value, le_bytes) in INTEGER_VECTORS {
+            let header = ::vint64::encode(1 << 4 | wire_type as u64);
+            let value_bytes = encode_integer(wire_type, value);
+            let (header, value_bytes) = (header.as_ref(), value_bytes.as_ref());
+
+            let mut message = [0u8; 16];
+            let len = header.len() + value_bytes.len();
+            message[..header.len()].copy_from_slice(header);
+            message[header.len()..len].copy_from_slice(value_bytes);
+
+            // Tag `1`, followed by the value
+            let mut expected = Sha256::new();
+            expected.update([WireType::UInt64 as u8]);
+            expected.update(1u64.to_le_bytes());
+            expected.update([wire_type as u8]);
+            expected.update(le_bytes);
+            let expected = expected.finalize();
+
+            assert_eq!(hash_message::<Sha256>(&message[..len]).unwrap(), expected);
+
+            let mut decoder = Decoder::new();
+            let mut input = &message[..len];
+            if wire_type == WireType::UInt64 {
+                assert_eq!(decoder.decode(1, &mut input), Ok(value));
+            } else {
+                assert_eq!(decoder.decode(1, &mut input), Ok(value as i64));
+            }
+
+            let mut digest = [0u8; 32];
+            decoder.fill_digest(&mut digest).unwrap();
+            assert_eq!(&digest[..], expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn integer_sequence_hash_vectors() {
+        for &(wire_type, value, le_bytes) in INTEGER_VECTORS {
+            // Element wire type (domain separation), followed by the value
+            let mut expected = Sha256::new();
+            expected.update([wire_type as u8]);
+            expected.update([wire_type as u8]);
+            expected.update(le_bytes);
+
+            assert_eq!(
+                hash_sequence::<Sha256>(wire_type, encode_integer(wire_type, value).as_ref())
+                    .unwrap(),
+                expected.finalize()
+            );
+        }
+    }
+
+    #[test]
+    fn inline_stack_holds_default_max_depth() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.stack.len(), 1);
+
+        for tag in 0..15 {
+            decoder.push(super::PathElement::field(tag)).unwrap();
+        }
+
+        assert_eq!(decoder.stack.len(), 16);
+        assert!(matches!(decoder.stack, Stack::Inline(_)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn with_capacity_preallocates_stack() {
+        fn heap_capacity<T>(stack: &Stack<T>) -> usize {
+            match stack {
+                Stack::Heap(heap) => heap.capacity(),
+                Stack::Inline(_) => panic!("stack not preallocated on the heap"),
+            }
+        }
+
+        let mut decoder = Decoder::with_capacity(32);
+        decoder.set_max_depth(Some(32));
+        assert_eq!(decoder.stack.len(), 1);
+
+        let capacity = heap_capacity(&decoder.stack);
+        assert!(capacity >= 32);
+
+        for tag in 0..31 {
+            decoder.push(super::PathElement::field(tag)).unwrap();
+        }
+
+        assert_eq!(heap_capacity(&decoder.stack), capacity);
+    }
+
+    #[test]
+    fn failed_nested_decode_unwinds_stack() {
+        let mut message = Literal::new().uint64(0, 1);
+
+        for _ in 0..4 {
+            message = Literal::new().message(0, &message);
+
+            let mut decoder = Decoder::new();
+            let mut input = message.as_bytes();
+            let result: Result<RecursiveMessage, Error> = decoder.decode(0, &mut input);
+
+            assert!(result.is_err());
+            assert_eq!(decoder.stack.len(), 1);
+            assert!(decoder.path.is_empty());
+        }
+    }
 }
@@ -0,0 +1,98 @@
+//! Collection of the digests of nested messages
+
+use crate::{
+    field::Tag,
+    verihash::{DigestOutput, VerihashDigest},
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Element of the path to a nested message, relative to the outermost
+/// message being decoded
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PathElement {
+    /// Tag of the field containing the nested message
+    pub tag: Tag,
+
+    /// Index of the nested message if the field contains a sequence
+    pub index: Option<usize>,
+}
+
+impl PathElement {
+    /// Path element for a message field
+    pub fn field(tag: Tag) -> Self {
+        Self { tag, index: None }
+    }
+
+    /// Path element for an element of a sequence field
+    pub fn seq_element(tag: Tag, index: usize) -> Self {
+        Self {
+            tag,
+            index: Some(index),
+        }
+    }
+}
+
+/// Digest of a nested message along with its path
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NestedDigest<D: VerihashDigest> {
+    /// Path to the nested message
+    pub path: Vec<PathElement>,
+
+    /// Digest of the nested message
+    pub digest: DigestOutput<D>,
+}
+
+/// Callback invoked with the path and digest of each nested message
+pub type DigestCallback<D> = fn(&[PathElement], &DigestOutput<D>);
+
+/// Collector for the digests of nested messages
+pub(crate) enum Collector<D: VerihashDigest> {
+    /// Invoke a callback for each nested message
+    Callback(DigestCallback<D>),
+
+    /// Store the digests of nested messages
+    #[cfg(feature = "alloc")]
+    Vec(Vec<NestedDigest<D>>),
+}
+
+impl<D> Collector<D>
+where
+    D: VerihashDigest,
+{
+    /// Record the digest of the nested message at the given path
+    pub fn record(&mut self, path: &[PathElement], digest: &DigestOutput<D>) {
+        match self {
+            Collector::Callback(callback) => callback(path, digest),
+            #[cfg(feature = "alloc")]
+            Collector::Vec(digests) => digests.push(NestedDigest {
+                path: path.to_vec(),
+                digest: digest.clone(),
+            }),
+        }
+    }
+
+    /// Get the number of digests which have been stored (always zero when
+    /// invoking a callback)
+    pub fn len(&self) -> usize {
+        match self {
+            Collector::Callback(_) => 0,
+            #[cfg(feature = "alloc")]
+            Collector::Vec(digests) => digests.len(),
+        }
+    }
+
+    /// Discard any stored digests beyond the first `len`. Digests which have
+    /// already been passed to a callback can't be retracted.
+    pub fn truncate(&mut self, _len: usize) {
+        match self {
+            Collector::Callback(_) => (),
+            #[cfg(feature = "alloc")]
+            Collector::Vec(digests) => digests.truncate(_len),
+        }
+    }
+}
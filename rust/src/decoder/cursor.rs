@@ -0,0 +1,75 @@
+//! Cursor over an input buffer
+
+use core::ops::{Deref, DerefMut};
+
+/// Cursor over an input buffer which tracks the position of the data
+/// remaining to be decoded.
+///
+/// Dereferences to the remaining input, so a `&mut Cursor` can be passed to
+/// any decoding method which accepts `&mut &[u8]`, advancing the cursor:
+///
+/// ```
+/// use veriform::{decoder::{Cursor, Decode}, Decoder};
+///
+/// // Field 1 = uint64 42, field 2 = uint64 7
+/// let mut input = Cursor::new(&[0x25, 0x55, 0x45, 0x0f]);
+/// let mut decoder = Decoder::new();
+///
+/// let value: u64 = decoder.decode(1, &mut input).unwrap();
+/// assert_eq!(value, 42);
+/// assert_eq!(input.position(), 2);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Cursor<'a> {
+    /// Length of the entire input buffer
+    length: usize,
+
+    /// Input which remains to be decoded
+    remaining: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    /// Create a new cursor positioned at the start of the given input
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            length: input.len(),
+            remaining: input,
+        }
+    }
+
+    /// Get the position of the cursor, i.e. the number of bytes of the
+    /// input which have been consumed
+    pub fn position(&self) -> usize {
+        self.length - self.remaining.len()
+    }
+
+    /// Get the number of bytes of the input remaining to be decoded
+    pub fn remaining(&self) -> usize {
+        self.remaining.len()
+    }
+
+    /// Has the entire input been consumed?
+    pub fn is_empty(&self) -> bool {
+        self.remaining.is_empty()
+    }
+}
+
+impl<'a> From<&'a [u8]> for Cursor<'a> {
+    fn from(input: &'a [u8]) -> Self {
+        Self::new(input)
+    }
+}
+
+impl<'a> Deref for Cursor<'a> {
+    type Target = &'a [u8];
+
+    fn deref(&self) -> &&'a [u8] {
+        &self.remaining
+    }
+}
+
+impl<'a> DerefMut for Cursor<'a> {
+    fn deref_mut(&mut self) -> &mut &'a [u8] {
+        &mut self.remaining
+    }
+}
@@ -23,8 +23,32 @@ pub(crate) trait Decodable {
         input: &mut &'a [u8],
     ) -> Result<&'a [u8], Error>;
 
+    /// Ensure the next value can have the given wire type.
+    ///
+    /// Used by the `sequence` decoder to reject attempts to decode elements
+    /// of a different type than the sequence contains.
+    fn expect_wire_type(&self, _wire_type: WireType) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Decode an expected boolean, returning an error for anything else
+    fn decode_bool(&mut self, input: &mut &[u8]) -> Result<bool, Error> {
+        self.expect_wire_type(WireType::False)?;
+
+        match self.decode(input)? {
+            Some(Event::Bool(value)) => Ok(value),
+            _ => Err(error::Kind::Decode {
+                element: Element::Value,
+                wire_type: WireType::False,
+            }
+            .into()),
+        }
+    }
+
     /// Decode an expected `uint64`, returning an error for anything else
     fn decode_uint64(&mut self, input: &mut &[u8]) -> Result<u64, Error> {
+        self.expect_wire_type(WireType::UInt64)?;
+
         match self.decode(input)? {
             Some(Event::UInt64(value)) => Ok(value),
             _ => Err(error::Kind::Decode {
@@ -37,6 +61,8 @@ pub(crate) trait Decodable {
 
     /// Decode an expected `sint64`, returning an error for anything else
     fn decode_sint64(&mut self, input: &mut &[u8]) -> Result<i64, Error> {
+        self.expect_wire_type(WireType::SInt64)?;
+
         match self.decode(input)? {
             Some(Event::SInt64(value)) => Ok(value),
             _ => Err(error::Kind::Decode {
@@ -52,7 +78,12 @@ pub(crate) trait Decodable {
         self.decode_dynamically_sized_value(WireType::Bytes, input)
     }
 
-    /// Decode an expected `string` field, returning an error for anything else
+    /// Decode an expected `string` field, returning an error for anything else.
+    ///
+    /// Invalid UTF-8 (including overlong encodings and surrogates) is an
+    /// [`error::Kind::Utf8`] error with the offset of the first invalid byte
+    /// within the string. Valid UTF-8 which isn't canonical is an
+    /// [`error::Kind::UnicodeNormalization`] error.
     fn decode_string<'a>(&mut self, input: &mut &'a [u8]) -> Result<&'a str, Error> {
         let bytes = self.decode_dynamically_sized_value(WireType::String, input)?;
 
@@ -78,6 +109,13 @@ pub(crate) trait Decodable {
             Some(Event::SequenceHeader { wire_type, length }) if wire_type == expected_type => {
                 length
             }
+            Some(Event::SequenceHeader { wire_type, .. }) => {
+                return Err(error::Kind::UnexpectedWireType {
+                    actual: wire_type,
+                    wanted: expected_type,
+                }
+                .into())
+            }
             _ => {
                 return Err(error::Kind::Decode {
                     element: Element::SequenceHeader,
@@ -87,12 +125,22 @@ pub(crate) trait Decodable {
             }
         };
 
+        check_body_length(WireType::Sequence, length, input)?;
+
+        // Empty sequences aren't followed by any value chunks
+        if length == 0 {
+            return Ok(&input[..0]);
+        }
+
         match self.decode(input)? {
             Some(Event::ValueChunk {
                 bytes, remaining, ..
             }) => {
                 if remaining == 0 {
-                    debug_assert_eq!(length, bytes.len());
+                    if length != bytes.len() {
+                        return Err(error::Kind::Length.into());
+                    }
+
                     Ok(bytes)
                 } else {
                     Err(error::Kind::Truncated {
@@ -110,3 +158,20 @@ pub(crate) trait Decodable {
         }
     }
 }
+
+/// Ensure the input contains the entire body of a length delimited value,
+/// returning an [`error::Kind::Truncated`] error if any of it is missing
+pub(crate) fn check_body_length(
+    wire_type: WireType,
+    length: usize,
+    input: &[u8],
+) -> Result<(), Error> {
+    match length.checked_sub(input.len()) {
+        Some(remaining) if remaining > 0 => Err(error::Kind::Truncated {
+            remaining,
+            wire_type,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,127 @@
+//! Detection of duplicate messages by their Verihash digests
+
+use super::{DecodeOptions, Decoder};
+use crate::{
+    error::{self, Error},
+    verihash::{DigestOutput, VerihashDigest},
+    Message,
+};
+use core::marker::PhantomData;
+use heapless::ArrayLength;
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeSet;
+
+/// Set of the digests of the top-level messages decoded so far, used to
+/// detect duplicates, e.g. when the same signed message occurs more than
+/// once in a batch.
+///
+/// Digests are stored in caller-provided [`DigestStorage`], and messages
+/// are compared by their entire digest.
+#[derive(Clone, Debug, Default)]
+pub struct DedupSet<D: VerihashDigest, S> {
+    /// Digests of the messages decoded so far
+    storage: S,
+
+    /// Options used to decode each message
+    options: DecodeOptions,
+
+    /// Digest algorithm
+    digest: PhantomData<D>,
+}
+
+impl<D, S> DedupSet<D, S>
+where
+    D: VerihashDigest,
+    S: DigestStorage<D>,
+{
+    /// Create a new set which records digests in the given storage
+    pub fn new(storage: S) -> Self {
+        Self::with_options(storage, DecodeOptions::default())
+    }
+
+    /// Create a new set which records digests in the given storage, and
+    /// decodes messages with the given options.
+    ///
+    /// Hashing must not be disabled with [`DecodeOptions::hashing`], as
+    /// messages are compared by their digests.
+    pub fn with_options(storage: S, options: DecodeOptions) -> Self {
+        Self {
+            storage,
+            options,
+            digest: PhantomData,
+        }
+    }
+
+    /// Decode a top-level message, returning `Ok(None)` if a message with
+    /// the same digest has already been decoded.
+    ///
+    /// The digest is computed while the message is decoded, so duplicates
+    /// are detected without hashing the message a second time.
+    ///
+    /// If the storage is full, an [`error::Kind::Capacity`] error is
+    /// returned for any message which hasn't already been seen, rather than
+    /// accepting a message which may be a duplicate.
+    pub fn decode<M: Message>(&mut self, input: &[u8]) -> Result<Option<M>, Error> {
+        let mut decoder = Decoder::<D>::with_options(self.options);
+        let msg = M::decode(&mut decoder, input)?;
+        let digest = decoder
+            .peek()
+            .compute_digest()?
+            .ok_or(error::Kind::Hashing)?;
+
+        if self.storage.insert_digest(&digest)? {
+            Ok(Some(msg))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Borrow the storage containing the digests decoded so far
+    pub fn storage(&self) -> &S {
+        &self.storage
+    }
+
+    /// Take the storage containing the digests decoded so far
+    pub fn into_storage(self) -> S {
+        self.storage
+    }
+}
+
+/// Storage for the digests recorded by a [`DedupSet`].
+///
+/// Impl'd for [`heapless::Vec`], which is searched linearly and has a fixed
+/// capacity, and for [`BTreeSet`] when the `alloc` feature is enabled.
+pub trait DigestStorage<D: VerihashDigest> {
+    /// Insert a digest, returning `false` if it was already present, or an
+    /// [`error::Kind::Capacity`] error if there's no room for it.
+    fn insert_digest(&mut self, digest: &DigestOutput<D>) -> Result<bool, Error>;
+}
+
+impl<D, N> DigestStorage<D> for heapless::Vec<DigestOutput<D>, N>
+where
+    D: VerihashDigest,
+    N: ArrayLength<DigestOutput<D>>,
+{
+    fn insert_digest(&mut self, digest: &DigestOutput<D>) -> Result<bool, Error> {
+        if self.contains(digest) {
+            return Ok(false);
+        }
+
+        self.push(digest.clone())
+            .map_err(|_| error::Kind::Capacity)?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+impl<D> DigestStorage<D> for BTreeSet<DigestOutput<D>>
+where
+    D: VerihashDigest,
+{
+    fn insert_digest(&mut self, digest: &DigestOutput<D>) -> Result<bool, Error> {
+        Ok(self.insert(digest.clone()))
+    }
+}
@@ -0,0 +1,141 @@
+//! Structural comparison of messages
+
+use super::{owned, DecodeOptions, Decoder, OwnedField, OwnedValue, PathElement};
+use crate::error::Error;
+use alloc::vec::Vec;
+use core::cmp;
+
+/// First difference between two messages found by [`diff`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiffReport {
+    /// Path to the differing field, or to the differing element if the
+    /// field contains a sequence
+    pub path: Vec<PathElement>,
+
+    /// Value in the first message, or `None` if it isn't present
+    pub left: Option<OwnedValue>,
+
+    /// Value in the second message, or `None` if it isn't present
+    pub right: Option<OwnedValue>,
+
+    /// Whether the differing field is marked critical in the first and
+    /// second message, or `None` if it isn't present in both of them.
+    /// If only these flags differ, `left` and `right` are equal.
+    pub critical: Option<(bool, bool)>,
+}
+
+/// Compare two encoded messages structurally, returning `None` if they're
+/// equal, or a [`DiffReport`] describing the first field (in tag order,
+/// depth first) which differs between them.
+///
+/// Both messages are decoded without a schema as [`OwnedValue`]s, so this
+/// is intended for testing and debugging, e.g. to explain why a round trip
+/// didn't produce the same encoding. A field which is only present in one
+/// of the messages, which has a different wire type in each of them, which
+/// is only marked critical in one of them, or which contains sequences
+/// with different element types (even if they're empty), is reported as a
+/// difference like any other.
+pub fn diff(left: &[u8], right: &[u8]) -> Result<Option<DiffReport>, Error> {
+    let left = decode(left)?;
+    let right = decode(right)?;
+    Ok(diff_fields(&mut Vec::new(), &left, &right))
+}
+
+/// Decode the fields of a message without computing its digest
+fn decode(input: &[u8]) -> Result<Vec<OwnedField>, Error> {
+    let mut decoder = Decoder::<sha2::Sha256>::with_options(DecodeOptions::new().hashing(false));
+    owned::decode_fields(&mut decoder, input)
+}
+
+/// Find the first difference between the fields of two messages
+fn diff_fields(
+    path: &mut Vec<PathElement>,
+    left: &[OwnedField],
+    right: &[OwnedField],
+) -> Option<DiffReport> {
+    let (mut i, mut j) = (0, 0);
+
+    loop {
+        let (tag, l, r, critical) = match (left.get(i), right.get(j)) {
+            (None, None) => return None,
+            (Some(l), Some(r)) if l.tag == r.tag => {
+                i += 1;
+                j += 1;
+                (
+                    l.tag,
+                    Some(&l.value),
+                    Some(&r.value),
+                    Some((l.critical, r.critical)),
+                )
+            }
+            (Some(l), Some(r)) if l.tag < r.tag => {
+                i += 1;
+                (l.tag, Some(&l.value), None, None)
+            }
+            (Some(l), None) => {
+                i += 1;
+                (l.tag, Some(&l.value), None, None)
+            }
+            (_, Some(r)) => {
+                j += 1;
+                (r.tag, None, Some(&r.value), None)
+            }
+        };
+
+        let report = diff_values(path, PathElement::field(tag), l, r, critical);
+
+        if report.is_some() {
+            return report;
+        }
+    }
+}
+
+/// Find the first difference between two values at the given path element,
+/// given whether the field containing them is marked critical in each message
+fn diff_values(
+    path: &mut Vec<PathElement>,
+    element: PathElement,
+    left: Option<&OwnedValue>,
+    right: Option<&OwnedValue>,
+    critical: Option<(bool, bool)>,
+) -> Option<DiffReport> {
+    let headers_differ = matches!(critical, Some((l, r)) if l != r);
+
+    if let (
+        Some(OwnedValue::Sequence {
+            wire_type: l_type,
+            elements: l,
+        }),
+        Some(OwnedValue::Sequence {
+            wire_type: r_type,
+            elements: r,
+        }),
+    ) = (left, right)
+    {
+        // Sequences with different element types are reported as a whole
+        if l_type == r_type && !headers_differ {
+            return (0..cmp::max(l.len(), r.len())).find_map(|index| {
+                let element = PathElement::seq_element(element.tag, index);
+                diff_values(path, element, l.get(index), r.get(index), critical)
+            });
+        }
+    }
+
+    path.push(element);
+
+    let report = match (left, right) {
+        (Some(OwnedValue::Message(l)), Some(OwnedValue::Message(r))) if !headers_differ => {
+            diff_fields(path, l, r)
+        }
+        _ if left == right && !headers_differ => None,
+        _ => Some(DiffReport {
+            path: path.clone(),
+            left: left.cloned(),
+            right: right.cloned(),
+            critical,
+        }),
+    };
+
+    path.pop();
+    report
+}
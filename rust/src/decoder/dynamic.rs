@@ -0,0 +1,188 @@
+//! Decoding with a digest algorithm selected at runtime
+
+use super::{Decode, DecodeInto, DecodeRef, Decoder, UnknownFieldPolicy};
+use crate::{field::Tag, Error, Message};
+use alloc::{boxed::Box, vec::Vec};
+use digest::Digest;
+
+/// Digest algorithms supported by [`DynDecoder`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HashAlg {
+    /// SHA-256
+    Sha256,
+
+    /// SHA-512
+    Sha512,
+
+    /// BLAKE2b-512
+    #[cfg(feature = "blake2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake2")))]
+    Blake2b,
+
+    /// BLAKE2s-256
+    #[cfg(feature = "blake2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake2")))]
+    Blake2s,
+}
+
+impl HashAlg {
+    /// Size of the digests computed by this algorithm in bytes
+    pub fn output_size(self) -> usize {
+        match self {
+            HashAlg::Sha256 => sha2::Sha256::output_size(),
+            HashAlg::Sha512 => sha2::Sha512::output_size(),
+            #[cfg(feature = "blake2")]
+            HashAlg::Blake2b => blake2::Blake2b::output_size(),
+            #[cfg(feature = "blake2")]
+            HashAlg::Blake2s => blake2::Blake2s::output_size(),
+        }
+    }
+}
+
+/// Veriform decoder whose digest algorithm is selected at runtime, e.g.
+/// based on a protocol version.
+///
+/// This wraps a [`Decoder`] for each supported [`HashAlg`], dispatching the
+/// [`Decode`], [`DecodeInto`], and [`DecodeRef`] traits to it dynamically.
+/// Sequences are decoded to an iterator which is generic over the digest
+/// algorithm, so [`DecodeSeq`] isn't supported: decode messages containing
+/// sequences using the [`Message`] trait instead.
+///
+/// [`DecodeSeq`]: super::DecodeSeq
+pub enum DynDecoder {
+    /// SHA-256 decoder
+    Sha256(Box<Decoder<sha2::Sha256>>),
+
+    /// SHA-512 decoder
+    Sha512(Box<Decoder<sha2::Sha512>>),
+
+    /// BLAKE2b-512 decoder
+    #[cfg(feature = "blake2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake2")))]
+    Blake2b(Box<Decoder<blake2::Blake2b>>),
+
+    /// BLAKE2s-256 decoder
+    #[cfg(feature = "blake2")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake2")))]
+    Blake2s(Box<Decoder<blake2::Blake2s>>),
+}
+
+/// Invoke the given expression with the [`Decoder`] wrapped by a
+/// [`DynDecoder`]
+macro_rules! dispatch {
+    ($dyn_decoder:expr, $decoder:ident => $body:expr) => {
+        match $dyn_decoder {
+            DynDecoder::Sha256($decoder) => $body,
+            DynDecoder::Sha512($decoder) => $body,
+            #[cfg(feature = "blake2")]
+            DynDecoder::Blake2b($decoder) => $body,
+            #[cfg(feature = "blake2")]
+            DynDecoder::Blake2s($decoder) => $body,
+        }
+    };
+}
+
+impl DynDecoder {
+    /// Initialize a decoder which computes digests using the given algorithm
+    pub fn new(alg: HashAlg) -> Self {
+        match alg {
+            HashAlg::Sha256 => DynDecoder::Sha256(Box::default()),
+            HashAlg::Sha512 => DynDecoder::Sha512(Box::default()),
+            #[cfg(feature = "blake2")]
+            HashAlg::Blake2b => DynDecoder::Blake2b(Box::default()),
+            #[cfg(feature = "blake2")]
+            HashAlg::Blake2s => DynDecoder::Blake2s(Box::default()),
+        }
+    }
+
+    /// Get the digest algorithm used by this decoder
+    pub fn hash_alg(&self) -> HashAlg {
+        match self {
+            DynDecoder::Sha256(_) => HashAlg::Sha256,
+            DynDecoder::Sha512(_) => HashAlg::Sha512,
+            #[cfg(feature = "blake2")]
+            DynDecoder::Blake2b(_) => HashAlg::Blake2b,
+            #[cfg(feature = "blake2")]
+            DynDecoder::Blake2s(_) => HashAlg::Blake2s,
+        }
+    }
+
+    /// Set the policy for handling fields whose tags aren't handled by any
+    /// field of the message being decoded
+    pub fn set_unknown_field_policy(&mut self, policy: UnknownFieldPolicy) {
+        dispatch!(self, decoder => decoder.set_unknown_field_policy(policy))
+    }
+
+    /// Fill the provided slice with the digest of the message, returning an
+    /// error if its length isn't [`HashAlg::output_size`]
+    pub fn fill_digest(&mut self, output: &mut [u8]) -> Result<(), Error> {
+        dispatch!(self, decoder => decoder.fill_digest(output))
+    }
+
+    /// Compute the digest of the message
+    pub fn digest(&mut self) -> Result<Vec<u8>, Error> {
+        let mut output = vec![0u8; self.hash_alg().output_size()];
+        self.fill_digest(&mut output)?;
+        Ok(output)
+    }
+
+    /// Decode a message field, returning the raw bytes of the message body
+    /// without decoding it.
+    ///
+    /// See [`Decoder::decode_message_bytes`].
+    pub fn decode_message_bytes<'a>(
+        &mut self,
+        tag: Tag,
+        input: &mut &'a [u8],
+    ) -> Result<&'a [u8], Error> {
+        dispatch!(self, decoder => decoder.decode_message_bytes(tag, input))
+    }
+}
+
+impl From<HashAlg> for DynDecoder {
+    fn from(alg: HashAlg) -> DynDecoder {
+        DynDecoder::new(alg)
+    }
+}
+
+impl<M> Decode<M> for DynDecoder
+where
+    M: Message,
+{
+    fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<M, Error> {
+        dispatch!(self, decoder => decoder.decode(tag, input))
+    }
+}
+
+impl<M> DecodeInto<M> for DynDecoder
+where
+    M: Message,
+{
+    fn decode_into(&mut self, tag: Tag, input: &mut &[u8], out: &mut M) -> Result<(), Error> {
+        dispatch!(self, decoder => decoder.decode_into(tag, input, out))
+    }
+}
+
+impl Decode<u64> for DynDecoder {
+    fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<u64, Error> {
+        dispatch!(self, decoder => decoder.decode(tag, input))
+    }
+}
+
+impl Decode<i64> for DynDecoder {
+    fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<i64, Error> {
+        dispatch!(self, decoder => decoder.decode(tag, input))
+    }
+}
+
+impl DecodeRef<[u8]> for DynDecoder {
+    fn decode_ref<'a>(&mut self, tag: Tag, input: &mut &'a [u8]) -> Result<&'a [u8], Error> {
+        dispatch!(self, decoder => decoder.decode_ref(tag, input))
+    }
+}
+
+impl DecodeRef<str> for DynDecoder {
+    fn decode_ref<'a>(&mut self, tag: Tag, input: &mut &'a [u8]) -> Result<&'a str, Error> {
+        dispatch!(self, decoder => decoder.decode_ref(tag, input))
+    }
+}
@@ -1,10 +1,15 @@
 //! Events emitted by Veriform's decoder
 
-use crate::field::{Header, WireType};
+use crate::{
+    error::{self, Error},
+    field::{Header, WireType},
+};
+use core::convert::TryFrom;
 
 /// Events emitted by Veriform's decoder
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) enum Event<'a> {
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event<'a> {
     /// Consumed field header with the given tag and wire type
     FieldHeader(Header),
 
@@ -17,7 +22,10 @@ pub(crate) enum Event<'a> {
     /// Consumed a signed 64-bit integer
     SInt64(i64),
 
-    /// Consumed a length delimiter for the given wire type
+    /// Consumed a length delimiter for the given wire type.
+    ///
+    /// Empty values (i.e. with a `length` of 0) consist of the length
+    /// delimiter alone: no [`Event::ValueChunk`] follows it.
     LengthDelimiter {
         /// Wire type of the value this length delimits
         wire_type: WireType,
@@ -47,3 +55,52 @@ pub(crate) enum Event<'a> {
         length: usize,
     },
 }
+
+impl<'a> Event<'a> {
+    /// Create a [`Event::SequenceHeader`] from a decoded `vint64` containing
+    /// the length of the sequence body and the wire type of its elements.
+    ///
+    /// Returns an error if the element wire type is reserved, or is itself
+    /// a sequence (nested sequences are not presently supported).
+    pub(crate) fn sequence_header(value: u64) -> Result<Self, Error> {
+        // The high bit of the 4-bit element wire type is reserved
+        if value & 0b1000 != 0 {
+            return Err(error::Kind::InvalidWireType.into());
+        }
+
+        let wire_type = WireType::from_unmasked(value);
+
+        if wire_type == WireType::Sequence {
+            return Err(error::Kind::NestedSequence.into());
+        }
+
+        Ok(Event::SequenceHeader {
+            wire_type,
+            length: usize::try_from(value >> 4).map_err(|_| error::Kind::Length)?,
+        })
+    }
+
+    /// Get the wire type of the value this event is part of, if any
+    pub(crate) fn wire_type(&self) -> Option<WireType> {
+        match *self {
+            Event::FieldHeader(_) => None,
+            Event::Bool(false) => Some(WireType::False),
+            Event::Bool(true) => Some(WireType::True),
+            Event::UInt64(_) => Some(WireType::UInt64),
+            Event::SInt64(_) => Some(WireType::SInt64),
+            Event::LengthDelimiter { wire_type, .. } | Event::ValueChunk { wire_type, .. } => {
+                Some(wire_type)
+            }
+            Event::SequenceHeader { .. } => Some(WireType::Sequence),
+        }
+    }
+
+    /// Is this the first event of an element, when emitted by a sequence
+    /// decoder?
+    pub(crate) fn begins_element(&self) -> bool {
+        matches!(
+            self,
+            Event::Bool(_) | Event::UInt64(_) | Event::SInt64(_) | Event::LengthDelimiter { .. }
+        )
+    }
+}
@@ -0,0 +1,179 @@
+//! Fields decoded by dispatching on their tag
+
+use super::{sequence, Decodable, Decoder};
+use crate::{
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+    verihash::VerihashDigest,
+    Message,
+};
+
+/// Field whose header has been decoded by [`Decoder::decode_field`], but
+/// whose value has yet to be decoded.
+///
+/// The value must be decoded (or skipped) by calling the method which
+/// corresponds to the field's wire type before decoding the next field.
+/// Calling a method for a different wire type returns an error.
+pub struct Field<'a, D: VerihashDigest> {
+    /// Decoder the field is being decoded with
+    decoder: &'a mut Decoder<D>,
+
+    /// Header of the field
+    header: Header,
+}
+
+impl<'a, D> Field<'a, D>
+where
+    D: VerihashDigest,
+{
+    /// Create a new field from its decoded header
+    pub(super) fn new(decoder: &'a mut Decoder<D>, header: Header) -> Self {
+        Self { decoder, header }
+    }
+
+    /// Get the header of this field
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Get the tag of this field
+    pub fn tag(&self) -> Tag {
+        self.header.tag
+    }
+
+    /// Get the wire type of this field
+    pub fn wire_type(&self) -> WireType {
+        self.header.wire_type
+    }
+
+    /// Decode a `bool` value.
+    ///
+    /// Booleans are encoded entirely in the field header, so no input is
+    /// consumed.
+    pub fn bool(self) -> Result<bool, Error> {
+        let Field { decoder, header } = self;
+
+        let result = match header.wire_type {
+            WireType::False | WireType::True => decoder.peek().decode_bool_field(header),
+            actual => Err(error::Kind::UnexpectedWireType {
+                actual,
+                wanted: WireType::False,
+            }
+            .into()),
+        };
+
+        decoder.field_result(result)
+    }
+
+    /// Decode a `uint64` value
+    pub fn uint64(self, input: &mut &[u8]) -> Result<u64, Error> {
+        self.decoder
+            .decode_value_with(self.header, WireType::UInt64, input, |decoder, input| {
+                decoder.decode_uint64(input)
+            })
+    }
+
+    /// Decode a `sint64` value
+    pub fn sint64(self, input: &mut &[u8]) -> Result<i64, Error> {
+        self.decoder
+            .decode_value_with(self.header, WireType::SInt64, input, |decoder, input| {
+                decoder.decode_sint64(input)
+            })
+    }
+
+    /// Decode a `bytes` value
+    pub fn bytes<'b>(self, input: &mut &'b [u8]) -> Result<&'b [u8], Error> {
+        self.decoder
+            .decode_value_with(self.header, WireType::Bytes, input, |decoder, input| {
+                decoder.decode_bytes(input)
+            })
+    }
+
+    /// Decode a `string` value
+    pub fn string<'b>(self, input: &mut &'b [u8]) -> Result<&'b str, Error> {
+        self.decoder
+            .decode_value_with(self.header, WireType::String, input, |decoder, input| {
+                decoder.decode_string(input)
+            })
+    }
+
+    /// Decode a nested message
+    pub fn message<M: Message>(self, input: &mut &[u8]) -> Result<M, Error> {
+        self.message_with(input, M::decode)
+    }
+
+    /// Decode a nested message using the given function to decode the
+    /// message body
+    pub(super) fn message_with<T>(
+        self,
+        input: &mut &[u8],
+        decode_body: impl FnOnce(&mut Decoder<D>, &[u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let Field { decoder, header } = self;
+
+        let msg_bytes =
+            decoder.decode_value_with(header, WireType::Message, input, |decoder, input| {
+                decoder.decode_message(input)
+            })?;
+
+        decoder.decode_nested_body(header.tag, msg_bytes, decode_body)
+    }
+
+    /// Decode a nested message, returning the raw bytes of the message body
+    /// without decoding it.
+    ///
+    /// See [`Decoder::decode_message_bytes`].
+    pub fn message_bytes<'b>(self, input: &mut &'b [u8]) -> Result<&'b [u8], Error> {
+        let tag = self.header.tag;
+
+        self.decoder.decode_value_limited(
+            self.header,
+            WireType::Message,
+            input,
+            |decoder, input, budget| decoder.decode_message_bytes(tag, input, budget),
+        )
+    }
+
+    /// Decode a sequence, returning an iterator over its elements.
+    ///
+    /// The elements must have the wire type corresponding to `T`, which is
+    /// checked as each of them is decoded.
+    pub fn sequence<'b, T>(
+        self,
+        input: &mut &'b [u8],
+    ) -> Result<sequence::Iter<'a, 'b, T, D>, Error> {
+        let tag = self.header.tag;
+        let (_, decoder, seq_bytes) = self.any_sequence(input)?;
+        Ok(sequence::Iter::new(decoder, tag, seq_bytes))
+    }
+
+    /// Decode the header of a sequence with any element type, pushing a
+    /// sequence decoder and returning the element type, the decoder, and the
+    /// body of the sequence
+    pub(super) fn any_sequence<'b>(
+        self,
+        input: &mut &'b [u8],
+    ) -> Result<(WireType, &'a mut Decoder<D>, &'b [u8]), Error> {
+        let Field { decoder, header } = self;
+
+        let (wire_type, seq_bytes) =
+            decoder.decode_value_with(header, WireType::Sequence, input, |decoder, input| {
+                decoder.decode_any_sequence(input)
+            })?;
+
+        decoder.push_seq(wire_type, seq_bytes.len())?;
+        Ok((wire_type, decoder, seq_bytes))
+    }
+
+    /// Skip the value of this field, hashing it as if it had been decoded
+    pub fn skip(self, input: &mut &[u8]) -> Result<(), Error> {
+        let header = self.header;
+
+        self.decoder.decode_value_limited(
+            header,
+            header.wire_type,
+            input,
+            |decoder, input, budget| decoder.skip_field(header, input, budget),
+        )
+    }
+}
@@ -0,0 +1,114 @@
+//! Guards for decoding the fields of nested messages inline
+
+use super::Decoder;
+use crate::{error::Error, field::Tag, verihash::VerihashDigest};
+use core::{
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+/// Guard for a nested message entered using [`Decoder::enter`], which
+/// dereferences to the [`Decoder`] so the fields of the nested message can
+/// be decoded from its body using the `decode_*` methods.
+///
+/// Once the body has been decoded, the guard must be consumed with
+/// [`MessageGuard::finish`], which pops the nested message's frame and
+/// hashes its digest into the parent message. Errors can't be returned from
+/// `drop`, so dropping the guard without finishing it discards the nested
+/// message's frame and marks the parent message as failed: no further
+/// fields can be decoded from it and its digest can't be computed.
+pub struct MessageGuard<'a, D: VerihashDigest> {
+    /// Decoder with the nested message's frame on top of its stack
+    decoder: &'a mut Decoder<D>,
+
+    /// Tag of the nested message field
+    tag: Tag,
+
+    /// Depth of the decoder's stack prior to entering the nested message
+    depth: usize,
+}
+
+impl<'a, D> MessageGuard<'a, D>
+where
+    D: VerihashDigest,
+{
+    /// Create a guard for a nested message frame which has been pushed onto
+    /// the decoder's stack
+    pub(super) fn new(decoder: &'a mut Decoder<D>, tag: Tag, depth: usize) -> Self {
+        Self {
+            decoder,
+            tag,
+            depth,
+        }
+    }
+
+    /// Finish decoding the nested message given the remainder of its body,
+    /// popping its frame and hashing its digest into the parent message.
+    ///
+    /// Any fields remaining in the body are handled as by
+    /// [`Decoder::decode_unknown_fields`], so under the default
+    /// [`UnknownFieldPolicy::Error`] this returns an error if the body
+    /// wasn't fully decoded. The parent message is marked as failed if
+    /// this returns an error.
+    ///
+    /// [`UnknownFieldPolicy::Error`]: crate::decoder::UnknownFieldPolicy::Error
+    pub fn finish(mut self, mut body: &[u8]) -> Result<(), Error> {
+        let result = self.pop(&mut body);
+
+        if result.is_err() {
+            self.fail();
+        }
+
+        mem::forget(self);
+        result
+    }
+
+    /// Decode any fields remaining in the body of the nested message, then
+    /// pop its frame, hashing its digest into the parent
+    fn pop(&mut self, body: &mut &[u8]) -> Result<(), Error> {
+        self.decoder.check_depth(self.depth)?;
+        self.decoder.decode_unknown_fields(body)?;
+
+        if let Some(digest) = self.decoder.pop()? {
+            self.decoder.peek().hash_message_digest(self.tag, &digest)?;
+        }
+
+        Ok(())
+    }
+
+    /// Discard the nested message's frame (and any left above it) and mark
+    /// the parent message as failed
+    fn fail(&mut self) {
+        self.decoder.unwind(self.depth);
+        self.decoder.peek().fail();
+    }
+}
+
+impl<'a, D> Deref for MessageGuard<'a, D>
+where
+    D: VerihashDigest,
+{
+    type Target = Decoder<D>;
+
+    fn deref(&self) -> &Decoder<D> {
+        self.decoder
+    }
+}
+
+impl<'a, D> DerefMut for MessageGuard<'a, D>
+where
+    D: VerihashDigest,
+{
+    fn deref_mut(&mut self) -> &mut Decoder<D> {
+        self.decoder
+    }
+}
+
+impl<'a, D> Drop for MessageGuard<'a, D>
+where
+    D: VerihashDigest,
+{
+    fn drop(&mut self) {
+        self.fail();
+    }
+}
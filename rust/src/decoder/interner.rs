@@ -0,0 +1,107 @@
+//! Interning of decoded `string` field values
+
+use super::{Decodable, Decoder};
+use crate::{
+    error::{self, Error},
+    field::{Tag, WireType},
+    string,
+    verihash::VerihashDigest,
+};
+use core::{
+    ops::{Deref, DerefMut},
+    str,
+};
+
+/// String interner consulted when decoding `string` fields using
+/// [`Decoder::with_interner`], e.g. for enum-like strings which occur many
+/// times in the same input.
+///
+/// Interners are supplied by the caller, and map the encoded bytes of a
+/// string to a [`Interner::Handle`] shared between all occurrences of the
+/// same string. Nothing about the trait requires allocation: a fixed-capacity
+/// table (e.g. a [`heapless::Vec`]) or an `alloc`-backed map can be used.
+pub trait Interner {
+    /// Handle to an interned string
+    type Handle;
+
+    /// Get the handle to a previously interned string whose encoded bytes
+    /// are equal to the given bytes, if there is one.
+    ///
+    /// Strings which are found aren't validated again, since they were
+    /// validated when they were interned.
+    fn get(&mut self, bytes: &[u8]) -> Option<Self::Handle>;
+
+    /// Intern a string which hasn't been seen before, returning its handle,
+    /// or an [`error::Kind::Capacity`] error if there's no room for it.
+    fn intern(&mut self, string: &str) -> Result<Self::Handle, Error>;
+}
+
+/// Guard returned by [`Decoder::with_interner`], which dereferences to the
+/// [`Decoder`] and decodes `string` fields into [`Interner`] handles.
+pub struct Interning<'a, D: VerihashDigest, I: Interner> {
+    /// Decoder used to decode fields
+    decoder: &'a mut Decoder<D>,
+
+    /// Interner consulted for `string` fields
+    interner: &'a mut I,
+}
+
+impl<'a, D, I> Interning<'a, D, I>
+where
+    D: VerihashDigest,
+    I: Interner,
+{
+    /// Create a new guard which decodes strings using the given interner
+    pub(super) fn new(decoder: &'a mut Decoder<D>, interner: &'a mut I) -> Self {
+        Self { decoder, interner }
+    }
+
+    /// Decode a `string` field with the given tag, returning the handle to
+    /// its interned value.
+    ///
+    /// Strings are validated as they would be by [`DecodeRef<str>`] the
+    /// first time they're seen, and are hashed into the message digest as
+    /// usual.
+    ///
+    /// [`DecodeRef<str>`]: super::DecodeRef
+    pub fn decode_string(&mut self, tag: Tag, input: &mut &[u8]) -> Result<I::Handle, Error> {
+        let interner = &mut *self.interner;
+
+        self.decoder
+            .decode_field_with(tag, WireType::String, input, |decoder, input| {
+                let bytes = decoder.decode_dynamically_sized_value(WireType::String, input)?;
+
+                if let Some(handle) = interner.get(bytes) {
+                    return Ok(handle);
+                }
+
+                let s = str::from_utf8(bytes).map_err(|e| error::Kind::Utf8 {
+                    valid_up_to: e.valid_up_to(),
+                })?;
+
+                interner.intern(string::ensure_canonical(s)?)
+            })
+    }
+}
+
+impl<'a, D, I> Deref for Interning<'a, D, I>
+where
+    D: VerihashDigest,
+    I: Interner,
+{
+    type Target = Decoder<D>;
+
+    fn deref(&self) -> &Decoder<D> {
+        self.decoder
+    }
+}
+
+impl<'a, D, I> DerefMut for Interning<'a, D, I>
+where
+    D: VerihashDigest,
+    I: Interner,
+{
+    fn deref_mut(&mut self) -> &mut Decoder<D> {
+        self.decoder
+    }
+}
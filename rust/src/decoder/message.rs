@@ -2,6 +2,8 @@
 
 mod body;
 mod decoder;
+#[cfg(feature = "alloc")]
+mod field_digests;
 mod hasher;
 mod header;
 mod state;
@@ -4,7 +4,7 @@ use super::state::State;
 use crate::{decoder::Event, error::Error, field::WireType};
 
 /// Decoder for the bodies of variable-length field values
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Decoder {
     /// Wire type we're decoding
     wire_type: WireType,
@@ -46,7 +46,8 @@ impl Decoder {
         let bytes = &input[..chunk_size];
         *input = &input[chunk_size..];
 
-        let remaining = self.remaining.checked_sub(chunk_size).unwrap();
+        // `chunk_size` is bounded by `self.remaining` above
+        let remaining = self.remaining - chunk_size;
         let event = Event::ValueChunk {
             wire_type: self.wire_type,
             bytes,
@@ -2,20 +2,32 @@
 
 use super::{hasher::Hasher, state::State};
 use crate::{
-    decoder::{Decodable, Event},
+    decoder::{self, decodable, vint64, Decodable, Event, TagSet},
     error::{self, Error},
     field::{Header, Tag, WireType},
     message::Element,
-    verihash::DigestOutput,
+    verihash::{DigestOutput, VerihashDigest},
 };
 use core::fmt::{self, Debug};
-use digest::Digest;
+
+#[cfg(feature = "alloc")]
+use {
+    super::field_digests::FieldDigests,
+    alloc::{boxed::Box, vec::Vec},
+};
+
+#[cfg(feature = "std")]
+use std::io;
 
 /// Veriform message decoder: streaming zero-copy pull parser which emits
 /// events based on incoming data.
-pub(crate) struct Decoder<D: Digest> {
-    /// Last field tag that was decoded (to ensure monotonicity)
-    last_tag: Option<Tag>,
+#[derive(Clone)]
+pub(crate) struct Decoder<D: VerihashDigest> {
+    /// Last field header that was decoded (to ensure tag monotonicity)
+    last_header: Option<Header>,
+
+    /// May the next field header repeat the tag of the last one?
+    repeat_tag: bool,
 
     /// Current position within the message (i.e. total bytes consumed)
     position: usize,
@@ -23,28 +35,162 @@ pub(crate) struct Decoder<D: Digest> {
     /// Current state of the decoder (or `None` if an error occurred)
     state: Option<State>,
 
+    /// Position at which the field presently being decoded began
+    field_start: usize,
+
+    /// Header and encoded length of the most recently completed field, if
+    /// it hasn't been taken with [`Decoder::take_completed_field`]
+    completed_field: Option<(Header, usize)>,
+
     /// Verihash message hasher
     hasher: Option<Hasher<D>>,
 
+    /// Maximum depth of the messages nested within this one which are
+    /// skipped or hashed without being decoded, counting the nested messages
+    /// themselves (or `None` if unlimited)
+    max_nested_depth: Option<usize>,
+
     /// Cached output digest
     cached_digest: Option<DigestOutput<D>>,
+
+    /// Tags of the fields excluded from the digest
+    excluded: TagSet,
+
+    /// Is the field presently (or most recently) being decoded excluded
+    /// from the digest?
+    excluding: bool,
+
+    /// Digests of the individual fields of the message (if enabled)
+    #[cfg(feature = "alloc")]
+    field_digests: Option<FieldDigests<D>>,
+
+    /// Hasher for the raw bytes consumed by the decoder (if enabled)
+    #[cfg(feature = "alloc")]
+    raw_hasher: Option<Box<D>>,
 }
 
 impl<D> Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Create a new decoder in an initial state
     pub fn new() -> Self {
+        Self::with_hashing(true)
+    }
+
+    /// Create a new decoder in an initial state, which computes the digest
+    /// of the message only if `hashing` is true
+    pub fn with_hashing(hashing: bool) -> Self {
         Self {
             state: Some(State::default()),
-            last_tag: None,
+            last_header: None,
+            repeat_tag: false,
             position: 0,
-            hasher: Some(Hasher::new()), // TODO(tarcieri): support for disabling hasher
+            field_start: 0,
+            completed_field: None,
+            hasher: if hashing { Some(Hasher::new()) } else { None },
+            max_nested_depth: None,
             cached_digest: None,
+            excluded: TagSet::new(),
+            excluding: false,
+            #[cfg(feature = "alloc")]
+            field_digests: None,
+            #[cfg(feature = "alloc")]
+            raw_hasher: None,
+        }
+    }
+
+    /// Exclude the fields whose tags are in the given set from the digest
+    /// of the message
+    pub fn exclude_from_digest(&mut self, tags: TagSet) {
+        self.excluded = tags;
+    }
+
+    /// Stop computing the digest of the message, discarding any digests
+    /// computed so far, e.g. because it won't be decoded in its entirety
+    pub fn disable_hashing(&mut self) {
+        self.hasher = None;
+        self.cached_digest = None;
+
+        #[cfg(feature = "alloc")]
+        {
+            self.field_digests = None;
         }
     }
 
+    /// Limit the depth of the messages nested within this one which are
+    /// skipped or hashed without being decoded, counting the nested messages
+    /// themselves, or `None` to allow them to be nested arbitrarily deeply
+    pub fn set_max_nested_depth(&mut self, max_nested_depth: Option<usize>) {
+        self.max_nested_depth = max_nested_depth;
+    }
+
+    /// Mark the message as failed, e.g. because it was abandoned partway
+    /// through, so no further fields can be decoded from it and its digest
+    /// can't be computed
+    pub fn fail(&mut self) {
+        self.state = None;
+        self.cached_digest = None;
+    }
+
+    /// Exclude the field with the given tag from the digest of the message,
+    /// in addition to any set with [`Decoder::exclude_from_digest`]
+    pub fn exclude_tag_from_digest(&mut self, tag: Tag) -> Result<(), Error> {
+        self.excluded.insert(tag)
+    }
+
+    /// Is the field with the given tag excluded from the digest?
+    fn is_excluded(&self, tag: Tag) -> bool {
+        self.excluded.contains(tag)
+    }
+
+    /// Record the digest of each field of the message as it's decoded.
+    ///
+    /// Returns an error if hashing is disabled.
+    #[cfg(feature = "alloc")]
+    pub fn record_field_digests(&mut self) -> Result<(), Error> {
+        if self.hasher.is_none() {
+            return Err(error::Kind::Hashing.into());
+        }
+
+        self.field_digests = Some(FieldDigests::new());
+        Ok(())
+    }
+
+    /// Take the tags and digests of the fields recorded so far, returning
+    /// an error if they weren't recorded or a field was only partially
+    /// decoded.
+    #[cfg(feature = "alloc")]
+    pub fn take_field_digests(&mut self) -> Result<Vec<(Tag, DigestOutput<D>)>, Error> {
+        self.field_digests
+            .take()
+            .ok_or(error::Kind::Hashing)?
+            .into_digests()
+    }
+
+    /// Hash the raw bytes consumed by the decoder, in addition to computing
+    /// the Verihash digest of the message
+    #[cfg(feature = "alloc")]
+    pub fn hash_raw_bytes(&mut self) {
+        self.raw_hasher = Some(Box::new(D::new()));
+    }
+
+    /// Take the digest of the raw bytes consumed so far, returning an error
+    /// if they weren't hashed
+    #[cfg(feature = "alloc")]
+    pub fn take_raw_digest(&mut self) -> Result<DigestOutput<D>, Error> {
+        self.raw_hasher
+            .take()
+            .map(|raw_hasher| raw_hasher.finalize())
+            .ok_or_else(|| error::Kind::Hashing.into())
+    }
+
+    /// Get the header of the field presently (or most recently) being decoded
+    #[cfg(feature = "trace-context")]
+    pub fn last_header(&self) -> Option<Header> {
+        self.last_header
+    }
+
     /// Decode an expected field header, returning an error for anything else
     pub fn decode_header(&mut self, input: &mut &[u8]) -> Result<Header, Error> {
         match self.decode(input)? {
@@ -57,24 +203,30 @@ where
         }
     }
 
-    /// Decode an expected field header, skipping (in-order) unknown fields,
-    /// and returning an error if the field is missing or unexpected
-    pub fn expect_header(
+    /// Decode the header of the field we expect to find next, returning an
+    /// error if the input doesn't contain a field header.
+    ///
+    /// The decoded header may belong to an unknown field which precedes
+    /// the expected one. Use [`Decoder::check_header`] to validate it.
+    pub fn decode_expected_header(
         &mut self,
         input: &mut &[u8],
         tag: Tag,
         wire_type: WireType,
-    ) -> Result<(), Error> {
-        let header = self.decode_header(input).map_err(|e| match e.kind() {
-            error::Kind::FieldHeader { .. } => error::Kind::FieldHeader {
+    ) -> Result<Header, Error> {
+        match self.decode(input)? {
+            Some(Event::FieldHeader(header)) => Ok(header),
+            _ => Err(error::Kind::FieldHeader {
                 tag: Some(tag),
                 wire_type: Some(wire_type),
             }
-            .position(self.position),
-            _ => unreachable!("unexpected decode_header error: {:?}", e),
-        })?;
+            .position(self.position)),
+        }
+    }
 
-        // TODO(tarcieri): actually skip unrecognized/unknown fields
+    /// Ensure a decoded field header has the expected tag and wire type,
+    /// returning an error if the field is missing or unexpected
+    pub fn check_header(&self, header: Header, tag: Tag, wire_type: WireType) -> Result<(), Error> {
         if header.tag != tag {
             return Err(error::Kind::Decode {
                 element: Element::Tag,
@@ -83,6 +235,14 @@ where
             .into());
         }
 
+        if header.wire_type != wire_type && wire_type == WireType::Sequence {
+            return Err(error::Kind::ExpectedSequence {
+                tag,
+                actual: header.wire_type,
+            }
+            .into());
+        }
+
         if header.wire_type != wire_type {
             return Err(error::Kind::UnexpectedWireType {
                 actual: header.wire_type,
@@ -94,6 +254,299 @@ where
         Ok(())
     }
 
+    /// Get the current position within the message (i.e. the number of bytes
+    /// of it which have been consumed)
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Take the header and encoded length (including the header) of the
+    /// field most recently completed, if it hasn't already been taken
+    pub fn take_completed_field(&mut self) -> Option<(Header, usize)> {
+        self.completed_field.take()
+    }
+
+    /// Has the given input (i.e. the remainder of the message body) been
+    /// fully consumed, without stopping partway through a field?
+    pub fn at_end(&self, input: &[u8]) -> bool {
+        match &self.state {
+            Some(State::Header(header)) => header.is_empty() && input.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Get the tag of the next field in the given input without consuming
+    /// it, if the decoder is between fields and the input begins with a
+    /// well-formed field header
+    pub fn peek_tag(&self, input: &[u8]) -> Option<Tag> {
+        self.peek_header(input).map(|header| header.tag)
+    }
+
+    /// Get the header of the next field in the given input without
+    /// consuming it, if the decoder is between fields and the input begins
+    /// with a well-formed field header
+    pub fn peek_header(&self, mut input: &[u8]) -> Option<Header> {
+        match &self.state {
+            Some(state) if is_field_boundary(state) => {
+                ::vint64::decode(&mut input).ok().map(Header::from)
+            }
+            _ => None,
+        }
+    }
+
+    /// Allow the next field header to have the same tag as the last one,
+    /// e.g. for the non-packed encoding of a repeated field
+    pub fn allow_repeated_tag(&mut self) {
+        self.repeat_tag = true;
+    }
+
+    /// Get the header, raw `vint64` value (i.e. prior to zigzag decoding a
+    /// `sint64`), and encoded length of the `uint64` or `sint64` field with
+    /// the given tag, if it can be decoded in a single step using
+    /// [`Decoder::decode_scalar_field`].
+    ///
+    /// This is a fast path for messages consisting of scalar fields, which
+    /// only applies when the decoder is between fields and the input begins
+    /// with the entire field, which is the next field expected. Otherwise
+    /// the field must be decoded one event at a time, which also reports
+    /// any errors.
+    pub fn peek_scalar_field(
+        &self,
+        input: &[u8],
+        tag: Tag,
+        wire_type: WireType,
+    ) -> Option<(Header, u64, usize)> {
+        match &self.state {
+            Some(state) if is_field_boundary(state) => (),
+            _ => return None,
+        }
+
+        if wire_type != WireType::UInt64 && wire_type != WireType::SInt64 {
+            return None;
+        }
+
+        let mut rest = input;
+        let header = Header::from(::vint64::decode(&mut rest).ok()?);
+
+        if header.tag != tag
+            || header.wire_type != wire_type
+            || matches!(self.last_header, Some(last) if last.tag >= tag)
+        {
+            return None;
+        }
+
+        let value = ::vint64::decode(&mut rest).ok()?;
+        Some((header, value, input.len() - rest.len()))
+    }
+
+    /// Consume a field found by [`Decoder::peek_scalar_field`], hashing it
+    /// exactly as if it had been decoded event-by-event
+    pub fn decode_scalar_field(
+        &mut self,
+        input: &mut &[u8],
+        header: Header,
+        value: u64,
+        encoded_len: usize,
+    ) -> Result<(), Error> {
+        if header.wire_type != WireType::UInt64 && header.wire_type != WireType::SInt64 {
+            return Err(error::Kind::Decode {
+                element: Element::Value,
+                wire_type: header.wire_type,
+            }
+            .into());
+        }
+
+        if input.len() < encoded_len {
+            return Err(error::Kind::Length.into());
+        }
+
+        let (field, rest) = input.split_at(encoded_len);
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(raw_hasher) = &mut self.raw_hasher {
+                raw_hasher.update(field);
+            }
+        }
+
+        self.field_start = self.position;
+        self.position = self
+            .position
+            .checked_add(field.len())
+            .ok_or(error::Kind::Length)?;
+
+        self.last_header = Some(header);
+        self.repeat_tag = false;
+        self.excluding = self.is_excluded(header.tag);
+        self.complete_field();
+        *input = rest;
+
+        // Only construct the events if something consumes them
+        if !self.hashes_events() {
+            return Ok(());
+        }
+
+        let value_event = if header.wire_type == WireType::SInt64 {
+            Event::SInt64(vint64::zigzag::decode(value))
+        } else {
+            Event::UInt64(value)
+        };
+
+        self.hash_event(&Event::FieldHeader(header))?;
+        self.hash_event(&value_event)
+    }
+
+    /// Decode the body of a message field whose header has already been
+    /// decoded, returning the raw bytes of the message body without
+    /// decoding it.
+    ///
+    /// If hashing is enabled, the message body is still processed in order
+    /// to compute its digest, charging its fields against a budget of
+    /// `fields_remaining` (if any).
+    pub fn decode_message_bytes<'a>(
+        &mut self,
+        tag: Tag,
+        input: &mut &'a [u8],
+        fields_remaining: &mut Option<usize>,
+    ) -> Result<&'a [u8], Error> {
+        let msg_bytes = self.decode_message(input)?;
+
+        if self.hasher.is_some() {
+            let digest = decoder::hash_message_limited::<D>(
+                msg_bytes,
+                fields_remaining,
+                self.max_nested_depth,
+            )?;
+            self.hash_message_digest(tag, &digest)?;
+        }
+
+        Ok(msg_bytes)
+    }
+
+    /// Decode the body of a `bytes` field whose header has already been
+    /// decoded, writing it to the given writer a chunk at a time rather
+    /// than returning it, and returning the number of bytes written.
+    ///
+    /// The body is checked to be entirely present in the input before any
+    /// of it is written.
+    #[cfg(feature = "std")]
+    pub fn decode_bytes_to_writer<W>(
+        &mut self,
+        input: &mut &[u8],
+        writer: &mut W,
+    ) -> Result<usize, Error>
+    where
+        W: io::Write + ?Sized,
+    {
+        let length = self.decode_length_delimiter(input, WireType::Bytes)?;
+        decodable::check_body_length(WireType::Bytes, length, input)?;
+
+        // Empty values aren't followed by any value chunks
+        if length == 0 {
+            return Ok(0);
+        }
+
+        loop {
+            match self.decode(input)? {
+                Some(Event::ValueChunk {
+                    wire_type: WireType::Bytes,
+                    bytes,
+                    remaining,
+                }) => {
+                    writer.write_all(bytes).map_err(|_| error::Kind::Io)?;
+
+                    if remaining == 0 {
+                        return Ok(length);
+                    }
+                }
+                _ => {
+                    return Err(error::Kind::Decode {
+                        element: Element::Value,
+                        wire_type: WireType::Bytes,
+                    }
+                    .into())
+                }
+            }
+        }
+    }
+
+    /// Skip the value of a field whose header has already been decoded.
+    ///
+    /// If hashing is enabled, the value (including any nested messages or
+    /// sequences) is hashed as if it had been decoded, charging the fields
+    /// of nested messages and the elements of sequences against a budget of
+    /// `fields_remaining` (if any).
+    pub fn skip_field(
+        &mut self,
+        header: Header,
+        input: &mut &[u8],
+        fields_remaining: &mut Option<usize>,
+    ) -> Result<(), Error> {
+        match header.wire_type {
+            WireType::False | WireType::True => {
+                self.decode_bool_field(header)?;
+            }
+            WireType::UInt64 => {
+                self.decode_uint64(input)?;
+            }
+            WireType::SInt64 => {
+                self.decode_sint64(input)?;
+            }
+            WireType::Bytes | WireType::String => {
+                self.decode_dynamically_sized_value(header.wire_type, input)?;
+            }
+            WireType::Message => {
+                self.decode_message_bytes(header.tag, input, fields_remaining)?;
+            }
+            WireType::Sequence => {
+                let (wire_type, seq_bytes) = self.decode_any_sequence(input)?;
+
+                if self.hasher.is_some() {
+                    let digest = decoder::hash_sequence_limited::<D>(
+                        wire_type,
+                        seq_bytes,
+                        fields_remaining,
+                        self.max_nested_depth,
+                    )?;
+                    self.hash_sequence_digest(header.tag, &digest)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode the value of a boolean field whose header has already been
+    /// decoded.
+    ///
+    /// Booleans are encoded entirely in the field header, so this consumes
+    /// no input, but still hashes the value.
+    pub fn decode_bool_field(&mut self, header: Header) -> Result<bool, Error> {
+        let value = header.wire_type == WireType::True;
+        self.state = Some(State::default());
+        self.complete_field();
+
+        if self.hashes_events() {
+            self.hash_event(&Event::Bool(value))?;
+        }
+
+        Ok(value)
+    }
+
+    /// Decode a sequence field whose elements may have any wire type,
+    /// returning the wire type of the elements along with the body of
+    /// the sequence
+    pub fn decode_any_sequence<'a>(
+        &mut self,
+        input: &mut &'a [u8],
+    ) -> Result<(WireType, &'a [u8]), Error> {
+        let mut seq_header = *input;
+        let seq_header = ::vint64::decode(&mut seq_header)
+            .map_err(|error| vint64::at_offset(error.into(), self.position))?;
+        let wire_type = WireType::from_unmasked(seq_header);
+        Ok((wire_type, self.decode_sequence(wire_type, input)?))
+    }
+
     /// Decode a length delimiter, expecting the given wire type
     fn decode_length_delimiter(
         &mut self,
@@ -118,12 +571,71 @@ where
         }
     }
 
+    /// Record the field presently being decoded as complete
+    fn complete_field(&mut self) {
+        if let Some(header) = self.last_header {
+            let encoded_len = self.position - self.field_start;
+            self.completed_field = Some((header, encoded_len));
+        }
+    }
+
+    /// Are the events of the field presently being decoded hashed, i.e. is
+    /// it included in the digest of the message and is a digest computed?
+    fn hashes_events(&self) -> bool {
+        if self.excluding {
+            return false;
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            if self.field_digests.is_some() {
+                return true;
+            }
+        }
+
+        self.hasher.is_some()
+    }
+
+    /// Hash an event of the field presently being decoded
+    fn hash_event(&mut self, event: &Event<'_>) -> Result<(), Error> {
+        // Events of excluded fields aren't hashed
+        if self.excluding {
+            return Ok(());
+        }
+
+        if let Some(hasher) = &mut self.hasher {
+            hasher.hash_event(event)?;
+        }
+
+        #[cfg(feature = "alloc")]
+        {
+            let tag = self.last_header.map(|header| header.tag);
+
+            if let Some(field_digests) = &mut self.field_digests {
+                field_digests.hash_event(tag, event)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Hash a digest of a nested message within this message
     pub fn hash_message_digest(&mut self, tag: Tag, digest: &DigestOutput<D>) -> Result<(), Error> {
+        if self.excluding {
+            return Ok(());
+        }
+
         if let Some(hasher) = &mut self.hasher {
             hasher.hash_message_digest(tag, digest)?;
         }
 
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(field_digests) = &mut self.field_digests {
+                field_digests.hash_message_digest(tag, digest)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -133,10 +645,21 @@ where
         tag: Tag,
         digest: &DigestOutput<D>,
     ) -> Result<(), Error> {
+        if self.excluding {
+            return Ok(());
+        }
+
         if let Some(hasher) = &mut self.hasher {
             hasher.hash_sequence_digest(tag, digest)?;
         }
 
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(field_digests) = &mut self.field_digests {
+                field_digests.hash_sequence_digest(tag, digest)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -153,14 +676,15 @@ where
 
         // Compute final digest using the hasher
         if let Some(hasher) = self.hasher.take() {
-            // Make sure we're not in the middle of parsing a field
-            // TODO(tarcieri): make sure we haven't partially consumed the header!
-            if let Some(State::Header(_)) = self.state.take() {
-                let digest = Some(hasher.finish()?);
-                self.cached_digest = digest.clone();
-                Ok(digest)
-            } else {
-                Err(error::Kind::Hashing.into())
+            // Make sure we're not in the middle of parsing a field (or the
+            // header of one)
+            match self.state.take() {
+                Some(State::Header(header)) if header.is_empty() => {
+                    let digest = Some(hasher.finish()?);
+                    self.cached_digest = digest.clone();
+                    Ok(digest)
+                }
+                _ => Err(error::Kind::Hashing.into()),
             }
         } else {
             Ok(None)
@@ -170,7 +694,7 @@ where
 
 impl<D> Default for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn default() -> Self {
         Self::new()
@@ -179,25 +703,57 @@ where
 
 impl<D> Decodable for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode<'a>(&mut self, input: &mut &'a [u8]) -> Result<Option<Event<'a>>, Error> {
         if let Some(state) = self.state.take() {
-            let (new_state, event) = state.decode(input, self.last_tag)?;
+            if is_field_boundary(&state) {
+                self.field_start = self.position;
+            }
+
+            let orig_input = *input;
+            let varint_start = self.position.saturating_sub(state.buffered());
+            let mut last_tag = self.last_header.map(|header| header.tag);
+
+            if self.repeat_tag {
+                last_tag = last_tag.and_then(|tag| tag.checked_sub(1));
+            }
+
+            let (new_state, event) = state
+                .decode(input, last_tag)
+                .map_err(|error| vint64::at_offset(error, varint_start))?;
 
             if let Some(Event::FieldHeader(header)) = &event {
-                self.last_tag = Some(header.tag);
+                self.last_header = Some(*header);
+                self.repeat_tag = false;
+                self.excluding = self.is_excluded(header.tag);
             }
 
             self.state = Some(new_state);
-            self.position = self.position.checked_add(input.len()).unwrap();
+            let consumed = orig_input.len().saturating_sub(input.len());
 
-            if let Some(ev) = &event {
-                if let Some(hasher) = &mut self.hasher {
-                    hasher.hash_event(ev)?;
+            #[cfg(feature = "alloc")]
+            {
+                if let Some(raw_hasher) = &mut self.raw_hasher {
+                    raw_hasher.update(&orig_input[..consumed]);
+                }
+            }
+
+            self.position = self
+                .position
+                .checked_add(consumed)
+                .ok_or(error::Kind::Length)?;
+
+            if let (Some(ev), Some(state)) = (&event, &self.state) {
+                if !matches!(ev, Event::FieldHeader(_)) && is_field_boundary(state) {
+                    self.complete_field();
                 }
             }
 
+            if let Some(ev) = &event {
+                self.hash_event(ev)?;
+            }
+
             Ok(event)
         } else {
             Err(error::Kind::Failed.into())
@@ -210,6 +766,12 @@ where
         input: &mut &'a [u8],
     ) -> Result<&'a [u8], Error> {
         let length = self.decode_length_delimiter(input, expected_type)?;
+        decodable::check_body_length(expected_type, length, input)?;
+
+        // Empty values aren't followed by any value chunks
+        if length == 0 {
+            return Ok(&input[..0]);
+        }
 
         match self.decode(input)? {
             Some(Event::ValueChunk {
@@ -218,7 +780,10 @@ where
                 remaining,
             }) if wire_type == expected_type => {
                 if remaining == 0 {
-                    debug_assert_eq!(length, bytes.len());
+                    if length != bytes.len() {
+                        return Err(error::Kind::Length.into());
+                    }
+
                     Ok(bytes)
                 } else {
                     Err(error::Kind::Truncated {
@@ -237,13 +802,22 @@ where
     }
 }
 
+/// Is the decoder between fields, i.e. about to decode a field header
+/// without having consumed any of it?
+fn is_field_boundary(state: &State) -> bool {
+    match state {
+        State::Header(header) => header.is_empty(),
+        _ => false,
+    }
+}
+
 impl<D> Debug for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Decoder")
-            .field("last_tag", &self.last_tag)
+            .field("last_header", &self.last_header)
             .field("position", &self.position)
             .field("state", &self.state)
             .field("hasher", &self.hasher)
@@ -255,14 +829,14 @@ where
 #[cfg(all(test, feature = "sha2"))]
 mod tests {
     use super::{Decodable, WireType};
-    use crate::error;
+    use crate::{consts::Literal, error};
 
     type Decoder = super::Decoder<sha2::Sha256>;
 
     #[test]
     fn decode_false() {
-        let input = [130, 10];
-        let mut input_ref = &input[..];
+        let input = Literal::new().bool(42, false);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -272,8 +846,8 @@ mod tests {
 
     #[test]
     fn decode_true() {
-        let input = [198, 10];
-        let mut input_ref = &input[..];
+        let input = Literal::new().bool(43, true);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -283,8 +857,8 @@ mod tests {
 
     #[test]
     fn decode_uint64() {
-        let input = [138, 10, 85];
-        let mut input_ref = &input[..];
+        let input = Literal::new().uint64(42, 42);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -298,8 +872,8 @@ mod tests {
 
     #[test]
     fn decode_sint64() {
-        let input = [206, 10, 167];
-        let mut input_ref = &input[..];
+        let input = Literal::new().sint64(43, -42);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -313,8 +887,8 @@ mod tests {
 
     #[test]
     fn decode_bytes() {
-        let input = [73, 11, 98, 121, 116, 101, 115];
-        let mut input_ref = &input[..];
+        let input = Literal::new().bytes(2, b"bytes");
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -326,10 +900,44 @@ mod tests {
         assert!(input_ref.is_empty());
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn decode_bytes_to_writer() {
+        let input = Literal::new().bytes(2, b"bytes");
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+        decoder.decode_header(&mut input_ref).unwrap();
+
+        let mut output = std::vec::Vec::new();
+        let written = decoder
+            .decode_bytes_to_writer(&mut input_ref, &mut output)
+            .unwrap();
+
+        assert_eq!(written, 5);
+        assert_eq!(output, b"bytes");
+        assert!(input_ref.is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn decode_bytes_to_failing_writer() {
+        let input = Literal::new().bytes(2, b"bytes");
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+        decoder.decode_header(&mut input_ref).unwrap();
+
+        let mut output = [0u8; 2];
+        let err = decoder
+            .decode_bytes_to_writer(&mut input_ref, &mut &mut output[..])
+            .unwrap_err();
+
+        assert_eq!(err.kind(), error::Kind::Io);
+    }
+
     #[test]
     fn decode_string() {
-        let input = [139, 7, 98, 97, 122];
-        let mut input_ref = &input[..];
+        let input = Literal::new().string(4, "baz");
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -343,8 +951,8 @@ mod tests {
 
     #[test]
     fn decode_message() {
-        let input = [45, 5, 69, 7];
-        let mut input_ref = &input[..];
+        let input = Literal::new().message(1, &Literal::new().uint64(2, 3));
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -356,10 +964,51 @@ mod tests {
         assert!(input_ref.is_empty());
     }
 
+    #[test]
+    fn decode_truncated_message() {
+        // Nested message declares a 3-byte body, but only 2 bytes are present
+        let input = Literal::new()
+            .header(1, false, WireType::Message)
+            .vint64(3)
+            .uint64(2, 3);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let header = decoder.decode_header(&mut input_ref).unwrap();
+        assert_eq!(header.wire_type, WireType::Message);
+
+        let err = decoder.decode_message(&mut input_ref).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::Truncated {
+                remaining: 1,
+                wire_type: WireType::Message
+            }
+        );
+    }
+
+    #[test]
+    fn decode_message_missing_body() {
+        let input = Literal::new().header(1, false, WireType::Message).vint64(2);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        decoder.decode_header(&mut input_ref).unwrap();
+
+        let err = decoder.decode_message(&mut input_ref).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::Truncated {
+                remaining: 2,
+                wire_type: WireType::Message
+            }
+        );
+    }
+
     #[test]
     fn decode_multiple() {
-        let input = [138, 10, 85, 206, 10, 167];
-        let mut input_ref = &input[..];
+        let input = Literal::new().uint64(42, 42).sint64(43, -42);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -380,13 +1029,13 @@ mod tests {
 
     #[test]
     fn decode_partial_field_header() {
-        let input = [138, 10, 85];
+        let input = Literal::new().uint64(42, 42);
         let mut decoder = Decoder::new();
 
-        let mut input_ref = &input[..1];
+        let mut input_ref = &input.as_bytes()[..1];
         assert_eq!(decoder.decode(&mut input_ref).unwrap(), None);
 
-        input_ref = &input[1..];
+        input_ref = &input.as_bytes()[1..];
         let header = decoder.decode_header(&mut input_ref).unwrap();
         assert_eq!(header.tag, 42);
         assert_eq!(header.wire_type, WireType::UInt64);
@@ -394,8 +1043,8 @@ mod tests {
 
     #[test]
     fn decode_out_of_order() {
-        let input = [206, 10, 167, 138, 10, 85];
-        let mut input_ref = &input[..];
+        let input = Literal::new().sint64(43, -42).uint64(42, 42);
+        let mut input_ref = input.as_bytes();
         let mut decoder = Decoder::new();
 
         let header = decoder.decode_header(&mut input_ref).unwrap();
@@ -408,4 +1057,51 @@ mod tests {
         let error = decoder.decode(&mut input_ref).err().unwrap();
         assert_eq!(error.kind(), error::Kind::Order { tag: 42 })
     }
+
+    #[test]
+    fn decode_nine_byte_uint64() {
+        let input = Literal::new().uint64(1, 1 << 56);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let header = decoder.decode_header(&mut input_ref).unwrap();
+        assert_eq!(header.tag, 1);
+        assert_eq!(header.wire_type, WireType::UInt64);
+
+        let value = decoder.decode_uint64(&mut input_ref).unwrap();
+        assert_eq!(value, 1 << 56);
+        assert!(input_ref.is_empty());
+    }
+
+    #[test]
+    fn decode_max_header() {
+        // Field header and sequence header which are both `u64::MAX`
+        let input = Literal::new()
+            .header(u64::MAX >> 4, true, WireType::Sequence)
+            .vint64(u64::MAX);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let header = decoder.decode_header(&mut input_ref).unwrap();
+        assert_eq!(header.tag, u64::MAX >> 4);
+        assert!(header.critical);
+        assert_eq!(header.wire_type, WireType::Sequence);
+
+        let error = decoder.decode(&mut input_ref).err().unwrap();
+        assert_eq!(error.kind(), error::Kind::InvalidWireType);
+    }
+
+    #[test]
+    fn decode_max_length_delimiter() {
+        let input = Literal::new()
+            .header(2, false, WireType::Bytes)
+            .vint64(u64::MAX)
+            .raw(&[1, 2, 3]);
+        let mut input_ref = input.as_bytes();
+        let mut decoder = Decoder::new();
+
+        let header = decoder.decode_header(&mut input_ref).unwrap();
+        assert_eq!(header.wire_type, WireType::Bytes);
+        assert!(decoder.decode_bytes(&mut input_ref).is_err());
+    }
 }
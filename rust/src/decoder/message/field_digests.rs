@@ -0,0 +1,79 @@
+//! Digests of the individual fields of a message
+
+use super::hasher::Hasher;
+use crate::{
+    decoder::Event,
+    error::{self, Error},
+    field::Tag,
+    verihash::{DigestOutput, VerihashDigest},
+};
+use alloc::vec::Vec;
+use core::mem;
+
+/// Records the digest of each field of a message as it's hashed, i.e. the
+/// digest of a message containing only that field
+#[derive(Clone)]
+pub(super) struct FieldDigests<D: VerihashDigest> {
+    /// Hasher for the field presently being decoded
+    hasher: Hasher<D>,
+
+    /// Tags and digests of the fields hashed so far
+    digests: Vec<(Tag, DigestOutput<D>)>,
+}
+
+impl<D> FieldDigests<D>
+where
+    D: VerihashDigest,
+{
+    /// Create a new field digest recorder
+    pub fn new() -> Self {
+        Self {
+            hasher: Hasher::new(),
+            digests: Vec::new(),
+        }
+    }
+
+    /// Hash an incoming event of the field with the given tag
+    pub fn hash_event(&mut self, tag: Option<Tag>, event: &Event<'_>) -> Result<(), Error> {
+        self.hasher.hash_event(event)?;
+        self.finish_field(tag)
+    }
+
+    /// Hash the digest of a nested message
+    pub fn hash_message_digest(&mut self, tag: Tag, digest: &DigestOutput<D>) -> Result<(), Error> {
+        self.hasher.hash_message_digest(tag, digest)?;
+        self.finish_field(Some(tag))
+    }
+
+    /// Hash the digest of a sequence
+    pub fn hash_sequence_digest(
+        &mut self,
+        tag: Tag,
+        digest: &DigestOutput<D>,
+    ) -> Result<(), Error> {
+        self.hasher.hash_sequence_digest(tag, digest)?;
+        self.finish_field(Some(tag))
+    }
+
+    /// Get the tags and digests of the fields, returning an error if a field
+    /// was only partially hashed
+    pub fn into_digests(self) -> Result<Vec<(Tag, DigestOutput<D>)>, Error> {
+        if self.hasher.is_initial() {
+            Ok(self.digests)
+        } else {
+            Err(error::Kind::Hashing.into())
+        }
+    }
+
+    /// Record the digest of the field with the given tag if it has been
+    /// completely hashed
+    fn finish_field(&mut self, tag: Option<Tag>) -> Result<(), Error> {
+        if self.hasher.is_initial() {
+            let tag = tag.ok_or(error::Kind::Hashing)?;
+            let digest = mem::take(&mut self.hasher).finish()?;
+            self.digests.push((tag, digest));
+        }
+
+        Ok(())
+    }
+}
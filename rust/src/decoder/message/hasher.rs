@@ -15,16 +15,16 @@ use crate::{
     decoder::Event,
     error::{self, Error},
     field::{self, Tag, WireType},
-    verihash::{self, DigestOutput},
+    verihash::{self, DigestOutput, VerihashDigest},
 };
 use core::fmt::{self, Debug};
-use digest::Digest;
 
 /// Verihash message hasher.
 ///
 /// This type computes a hash-based transcript of how a message was
 /// decoded, driven by incoming decoding events.
-pub(super) struct Hasher<D: Digest> {
+#[derive(Clone)]
+pub(super) struct Hasher<D: VerihashDigest> {
     /// Verihash hasher
     verihash: verihash::Hasher<D>,
 
@@ -34,7 +34,7 @@ pub(super) struct Hasher<D: Digest> {
 
 impl<D> Hasher<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Create a new [`Hasher`]
     pub fn new() -> Self {
@@ -60,7 +60,7 @@ where
         match self.state {
             Some(State::Message { remaining }) if remaining == 0 => {
                 self.verihash.tag(tag);
-                self.verihash.fixed_size_value(WireType::Message, digest);
+                self.verihash.digest_value(WireType::Message, digest);
                 self.state = Some(State::Initial);
                 Ok(())
             }
@@ -77,7 +77,7 @@ where
         match self.state {
             Some(State::Sequence { remaining, .. }) if remaining == 0 => {
                 self.verihash.tag(tag);
-                self.verihash.fixed_size_value(WireType::Sequence, digest);
+                self.verihash.digest_value(WireType::Sequence, digest);
                 self.state = Some(State::Initial);
                 Ok(())
             }
@@ -85,10 +85,16 @@ where
         }
     }
 
+    /// Is the hasher at a field boundary, i.e. not partway through a field?
+    #[cfg(feature = "alloc")]
+    pub fn is_initial(&self) -> bool {
+        self.state == Some(State::Initial)
+    }
+
     /// Finish computing digest
     pub fn finish(self) -> Result<DigestOutput<D>, Error> {
         if self.state == Some(State::Initial) {
-            Ok(self.verihash.finalize())
+            Ok(self.verihash.finish())
         } else {
             Err(error::Kind::Hashing.into())
         }
@@ -97,7 +103,7 @@ where
 
 impl<D> Default for Hasher<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn default() -> Self {
         Self::new()
@@ -106,14 +112,14 @@ where
 
 impl<D> Debug for Hasher<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("message::Hasher").finish()
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 enum State {
     /// At the start of a message with no data processed
     Initial,
@@ -145,7 +151,7 @@ impl Default for State {
 
 impl State {
     /// Transition to a new state based on an incoming event or return an error
-    pub fn transition<D: Digest>(
+    pub fn transition<D: VerihashDigest>(
         self,
         event: &Event<'_>,
         verihash: &mut verihash::Hasher<D>,
@@ -179,7 +185,7 @@ impl State {
     }
 
     /// Handle length delimiter event
-    fn handle_length_delimiter<D: Digest>(
+    fn handle_length_delimiter<D: VerihashDigest>(
         self,
         wire_type: WireType,
         length: usize,
@@ -191,10 +197,12 @@ impl State {
             }
 
             let new_state = match wire_type {
+                // Empty values aren't followed by any value chunks
+                WireType::Bytes | WireType::String if length == 0 => State::Initial,
                 WireType::Bytes => State::Bytes { remaining: length },
                 WireType::String => State::String { remaining: length },
                 WireType::Message => State::Message { remaining: length },
-                _ => unreachable!(),
+                _ => return Err(error::Kind::Hashing.into()),
             };
 
             verihash.tag(header.tag);
@@ -207,7 +215,7 @@ impl State {
     }
 
     /// Handle hashing an incoming fixed-width value
-    fn handle_fixed_sized_value<D: Digest>(
+    fn handle_fixed_sized_value<D: VerihashDigest>(
         self,
         value: &Event<'_>,
         verihash: &mut verihash::Hasher<D>,
@@ -217,7 +225,7 @@ impl State {
                 Event::Bool(value) => verihash.tagged_boolean(header.tag, *value),
                 Event::UInt64(value) => verihash.tagged_uint64(header.tag, *value),
                 Event::SInt64(value) => verihash.tagged_sint64(header.tag, *value),
-                _ => unreachable!(),
+                _ => return Err(error::Kind::Hashing.into()),
             }
         } else {
             return Err(error::Kind::Hashing.into());
@@ -227,7 +235,7 @@ impl State {
     }
 
     /// Handle an incoming chunk of data in a value
-    fn handle_value_chunk<D: Digest>(
+    fn handle_value_chunk<D: VerihashDigest>(
         self,
         wire_type: WireType,
         bytes: &[u8],
@@ -237,7 +245,9 @@ impl State {
         // TODO(tarcieri): DRY this out
         let new_state = match self {
             State::Bytes { remaining } => {
-                if wire_type != WireType::Bytes || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::Bytes
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -250,7 +260,9 @@ impl State {
                 }
             }
             State::String { remaining } => {
-                if wire_type != WireType::String || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::String
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -263,7 +275,9 @@ impl State {
                 }
             }
             State::Message { remaining } => {
-                if wire_type != WireType::Message || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::Message
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -275,7 +289,9 @@ impl State {
                 wire_type: value_type,
                 remaining,
             } => {
-                if wire_type != WireType::Sequence || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::Sequence
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 } else {
                     return Ok(State::Sequence {
@@ -289,7 +305,7 @@ impl State {
             }
         };
 
-        verihash.update(bytes);
+        verihash.input(bytes);
         Ok(new_state)
     }
 
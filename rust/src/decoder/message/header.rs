@@ -8,10 +8,20 @@ use crate::{
 };
 
 /// Decoder for field headers
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 pub(super) struct Decoder(vint64::Decoder);
 
 impl Decoder {
+    /// Has none of the header been consumed yet?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the number of bytes of the header buffered from previous input
+    pub fn buffered(&self) -> usize {
+        self.0.buffered()
+    }
+
     /// Process the given input data, advancing the slice for the amount of
     /// data processed, and returning the new state.
     pub fn decode<'a>(
@@ -8,7 +8,7 @@ use crate::{
 };
 
 /// Decoder state machine
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(super) enum State {
     /// Reading the initial `vint64` header on a field
     Header(header::Decoder),
@@ -35,6 +35,16 @@ impl State {
         }
     }
 
+    /// Get the number of bytes of a partially decoded `vint64` which have
+    /// been buffered from previous input
+    pub(super) fn buffered(&self) -> usize {
+        match self {
+            State::Header(header) => header.buffered(),
+            State::Value(value) => value.buffered(),
+            State::Body(_) => 0,
+        }
+    }
+
     /// Get the new state to transition to based on a given event
     pub(super) fn transition(event: &Event<'_>) -> Self {
         match event {
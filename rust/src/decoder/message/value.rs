@@ -6,12 +6,13 @@ use crate::{
         vint64::{self, zigzag},
         Event,
     },
-    error::Error,
+    error::{self, Error},
     field::WireType,
 };
+use core::convert::TryFrom;
 
 /// Decoder for field values
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Decoder {
     /// Create a new decoder for the `vint64` length prefix or value
     decoder: vint64::Decoder,
@@ -29,6 +30,11 @@ impl Decoder {
         }
     }
 
+    /// Get the number of bytes of the value buffered from previous input
+    pub fn buffered(&self) -> usize {
+        self.decoder.buffered()
+    }
+
     /// Process the given input data, advancing the slice for the amount of
     /// data processed, and returning the new state.
     pub fn decode<'a>(mut self, input: &mut &'a [u8]) -> Result<(State, Option<Event<'a>>), Error> {
@@ -38,10 +44,7 @@ impl Decoder {
                 WireType::True => Event::Bool(true),
                 WireType::UInt64 => Event::UInt64(value),
                 WireType::SInt64 => Event::SInt64(zigzag::decode(value)),
-                WireType::Sequence => Event::SequenceHeader {
-                    wire_type: WireType::from_unmasked(value),
-                    length: (value >> 4) as usize,
-                },
+                WireType::Sequence => Event::sequence_header(value)?,
                 wire_type => {
                     debug_assert!(
                         wire_type.is_dynamically_sized(),
@@ -51,7 +54,7 @@ impl Decoder {
 
                     Event::LengthDelimiter {
                         wire_type,
-                        length: value as usize,
+                        length: usize::try_from(value).map_err(|_| error::Kind::Length)?,
                     }
                 }
             };
@@ -0,0 +1,144 @@
+//! Decoder configuration
+
+use super::{stack, UnknownFieldPolicy};
+
+/// Configuration for a [`Decoder`], built up option-by-option and passed to
+/// [`Decoder::with_options`]:
+///
+/// ```
+/// use veriform::decoder::{DecodeOptions, UnknownFieldPolicy};
+///
+/// const OPTIONS: DecodeOptions = DecodeOptions::new()
+///     .max_nesting(Some(8))
+///     .max_total_fields(Some(1024))
+///     .unknown_field_policy(UnknownFieldPolicy::Skip);
+///
+/// let decoder = veriform::Decoder::with_options(OPTIONS);
+/// ```
+///
+/// The [`Default`] options are those used by [`Decoder::new`].
+///
+/// [`Decoder`]: crate::decoder::Decoder
+/// [`Decoder::new`]: crate::decoder::Decoder::new
+/// [`Decoder::with_options`]: crate::decoder::Decoder::with_options
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Maximum nesting depth, or `None` for unlimited
+    pub(super) max_depth: Option<usize>,
+
+    /// Maximum total number of fields, or `None` for unlimited
+    pub(super) max_total_fields: Option<usize>,
+
+    /// Policy for handling unknown fields
+    pub(super) unknown_field_policy: UnknownFieldPolicy,
+
+    /// Compute Verihash digests of decoded messages?
+    pub(super) hashing: bool,
+
+    /// Decode scalar fields using the single-step fast path?
+    pub(super) scalar_fast_path: bool,
+
+    /// Reuse the digests of identical adjacent message elements?
+    pub(super) memoize_element_digests: bool,
+}
+
+impl DecodeOptions {
+    /// Create the default options
+    pub const fn new() -> Self {
+        Self {
+            max_depth: Some(stack::INLINE_CAPACITY),
+            max_total_fields: None,
+            unknown_field_policy: UnknownFieldPolicy::Error,
+            hashing: true,
+            scalar_fast_path: true,
+            memoize_element_digests: false,
+        }
+    }
+
+    /// Set the maximum nesting depth of messages, counting the outermost
+    /// message, or `None` to allow messages to be nested arbitrarily deeply.
+    ///
+    /// The default maximum is 16. Without the `alloc` feature the decoder's
+    /// stack can't grow beyond that, so only lower maximums take effect.
+    /// See [`Decoder::set_max_depth`].
+    ///
+    /// [`Decoder::set_max_depth`]: crate::decoder::Decoder::set_max_depth
+    pub const fn max_nesting(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Set the maximum total number of fields which may be decoded, or
+    /// `None` to allow any number of fields (the default).
+    ///
+    /// See [`Decoder::set_max_total_fields`].
+    ///
+    /// [`Decoder::set_max_total_fields`]: crate::decoder::Decoder::set_max_total_fields
+    pub const fn max_total_fields(mut self, max_total_fields: Option<usize>) -> Self {
+        self.max_total_fields = max_total_fields;
+        self
+    }
+
+    /// Set the policy for handling unknown fields (by default, an error).
+    ///
+    /// See [`Decoder::set_unknown_field_policy`].
+    ///
+    /// [`Decoder::set_unknown_field_policy`]: crate::decoder::Decoder::set_unknown_field_policy
+    pub const fn unknown_field_policy(mut self, policy: UnknownFieldPolicy) -> Self {
+        self.unknown_field_policy = policy;
+        self
+    }
+
+    /// Enable or disable computing the Verihash digests of decoded messages
+    /// (enabled by default).
+    ///
+    /// Disabling hashing saves work when the digests aren't needed. Digests
+    /// can't be obtained from the decoder when it's disabled, so decoding a
+    /// message type containing a digest field returns an
+    /// [`error::Kind::Hashing`] error.
+    ///
+    /// [`error::Kind::Hashing`]: crate::error::Kind::Hashing
+    pub const fn hashing(mut self, hashing: bool) -> Self {
+        self.hashing = hashing;
+        self
+    }
+
+    /// Enable or disable the fast path for decoding `uint64` and `sint64`
+    /// fields (enabled by default).
+    ///
+    /// When a scalar field is the next field in the input, its header and
+    /// value are decoded together in a single step rather than as separate
+    /// events. Anything else falls back to the general path, and the decoded
+    /// values, digests, and errors are the same either way, so this is only
+    /// useful for benchmarking and testing the fast path itself.
+    ///
+    /// This option is hidden from the documentation as it isn't covered by
+    /// semver guarantees, and may be removed without notice.
+    #[doc(hidden)]
+    pub const fn scalar_fast_path(mut self, enabled: bool) -> Self {
+        self.scalar_fast_path = enabled;
+        self
+    }
+
+    /// Enable or disable reusing the digest of a message element of a
+    /// sequence for the element which follows it, if the two are identical
+    /// (disabled by default).
+    ///
+    /// This saves rehashing sequences containing runs of identical messages,
+    /// at the cost of copying each distinct element so the next one can be
+    /// compared against it. Elements are still decoded, and the digests are
+    /// the same either way. It has no effect without the `alloc` feature,
+    /// or while collecting the digests of nested messages (which requires
+    /// hashing every element).
+    pub const fn memoize_element_digests(mut self, enabled: bool) -> Self {
+        self.memoize_element_digests = enabled;
+        self
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,315 @@
+//! Values decoded without a schema into owned data
+
+use super::{sequence::Cursor, Decoder, Field};
+use crate::{
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+    string,
+    verihash::VerihashDigest,
+};
+use alloc::{string::String, vec::Vec};
+
+/// Value decoded by [`Decoder::decode_owned`] without a schema, which owns
+/// its data and can therefore outlive the input it was decoded from (e.g.
+/// to be stored in a cache).
+///
+/// This is the dynamic counterpart to decoding a [`Message`] type. Unlike
+/// a JSON representation it's lossless: every value retains its wire type
+/// (see [`OwnedValue::wire_type`]), so e.g. `bytes` and `string` values, or
+/// `uint64` and `sint64` values, remain distinct even when their contents
+/// are identical. The fields of messages retain their order and whether
+/// they're marked critical, and sequences retain the wire type of their
+/// elements even when they're empty.
+///
+/// When the `serde` feature is enabled, this impls `Serialize` and
+/// `Deserialize`, so decoded values can be persisted using a compact
+/// serialization format such as `postcard`. This doesn't preserve digests
+/// and is unrelated to the Veriform encoding (see [`OwnedValue::encode`]).
+///
+/// [`Message`]: crate::Message
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedValue {
+    /// Boolean value
+    Bool(bool),
+
+    /// Unsigned 64-bit integer
+    UInt64(u64),
+
+    /// Signed 64-bit integer
+    SInt64(i64),
+
+    /// Binary data
+    Bytes(Vec<u8>),
+
+    /// String
+    String(String),
+
+    /// Nested message, as its fields in ascending tag order
+    Message(Vec<OwnedField>),
+
+    /// Sequence of values of the same type
+    Sequence {
+        /// Wire type of the elements
+        wire_type: WireType,
+
+        /// Elements of the sequence, in order
+        elements: Vec<OwnedValue>,
+    },
+}
+
+impl OwnedValue {
+    /// Get the wire type of this value
+    pub fn wire_type(&self) -> WireType {
+        match self {
+            OwnedValue::Bool(false) => WireType::False,
+            OwnedValue::Bool(true) => WireType::True,
+            OwnedValue::UInt64(_) => WireType::UInt64,
+            OwnedValue::SInt64(_) => WireType::SInt64,
+            OwnedValue::Bytes(_) => WireType::Bytes,
+            OwnedValue::String(_) => WireType::String,
+            OwnedValue::Message(_) => WireType::Message,
+            OwnedValue::Sequence { .. } => WireType::Sequence,
+        }
+    }
+
+    /// Get the value of the field of this message with the given tag, or
+    /// `None` if this isn't a message or it has no such field
+    pub fn field(&self, tag: Tag) -> Option<&OwnedValue> {
+        match self {
+            OwnedValue::Message(fields) => fields
+                .iter()
+                .find(|field| field.tag == tag)
+                .map(|field| &field.value),
+            _ => None,
+        }
+    }
+
+    /// Encode this value, which must be an [`OwnedValue::Message`], as the
+    /// body of a canonical Veriform message (i.e. its fields, without a
+    /// header or length delimiter).
+    ///
+    /// The fields of messages must be in ascending tag order, or an
+    /// [`error::Kind::Order`] error is returned. Encoding a message decoded
+    /// from canonical input by [`Decoder::decode_owned`] reproduces the
+    /// input exactly.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            OwnedValue::Message(fields) => {
+                let mut encoded = Vec::new();
+                encode_fields(fields, &mut encoded)?;
+                Ok(encoded)
+            }
+            _ => Err(error::Kind::UnexpectedWireType {
+                actual: self.wire_type(),
+                wanted: WireType::Message,
+            }
+            .into()),
+        }
+    }
+}
+
+/// Field of an [`OwnedValue::Message`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedField {
+    /// Tag which identifies the field
+    pub tag: Tag,
+
+    /// Is the field marked critical?
+    pub critical: bool,
+
+    /// Value of the field
+    pub value: OwnedValue,
+}
+
+impl OwnedField {
+    /// Create a new field which isn't marked critical
+    pub fn new(tag: Tag, value: OwnedValue) -> Self {
+        Self {
+            tag,
+            critical: false,
+            value,
+        }
+    }
+}
+
+/// Decode the fields of a message body using [`Decoder::decode_field`]
+pub(super) fn decode_fields<D>(
+    decoder: &mut Decoder<D>,
+    mut input: &[u8],
+) -> Result<Vec<OwnedField>, Error>
+where
+    D: VerihashDigest,
+{
+    decode_remaining(decoder, &mut input)
+}
+
+/// Decode the remaining fields in the input, advancing it past them
+pub(super) fn decode_remaining<D>(
+    decoder: &mut Decoder<D>,
+    input: &mut &[u8],
+) -> Result<Vec<OwnedField>, Error>
+where
+    D: VerihashDigest,
+{
+    let mut fields = Vec::new();
+
+    while let Some(field) = decoder.decode_field(input)? {
+        let Header { tag, critical, .. } = field.header();
+        let value = decode_value(field, input)?;
+        fields.push(OwnedField {
+            tag,
+            critical,
+            value,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Decode the value of a field whose header has been decoded
+fn decode_value<D>(field: Field<'_, D>, input: &mut &[u8]) -> Result<OwnedValue, Error>
+where
+    D: VerihashDigest,
+{
+    Ok(match field.wire_type() {
+        WireType::False | WireType::True => OwnedValue::Bool(field.bool()?),
+        WireType::UInt64 => OwnedValue::UInt64(field.uint64(input)?),
+        WireType::SInt64 => OwnedValue::SInt64(field.sint64(input)?),
+        WireType::Bytes => OwnedValue::Bytes(field.bytes(input)?.into()),
+        WireType::String => OwnedValue::String(field.string(input)?.into()),
+        WireType::Message => OwnedValue::Message(field.message_with(input, decode_fields)?),
+        WireType::Sequence => {
+            let tag = field.tag();
+            let (wire_type, decoder, seq_bytes) = field.any_sequence(input)?;
+            let mut cursor = Cursor::new(decoder, tag);
+            let mut elements = Vec::new();
+
+            while let Some(element) = cursor.next(seq_bytes, |cursor, input| {
+                decode_element(cursor, wire_type, input)
+            }) {
+                elements.push(element?);
+            }
+
+            OwnedValue::Sequence {
+                wire_type,
+                elements,
+            }
+        }
+    })
+}
+
+/// Decode an element of a sequence with the given element type
+fn decode_element<D>(
+    cursor: &mut Cursor<&mut Decoder<D>>,
+    wire_type: WireType,
+    input: &mut &[u8],
+) -> Result<OwnedValue, Error>
+where
+    D: VerihashDigest,
+{
+    Ok(match wire_type {
+        WireType::False | WireType::True => OwnedValue::Bool(cursor.decode_bool(input)?),
+        WireType::UInt64 => OwnedValue::UInt64(cursor.decode_uint64(input)?),
+        WireType::SInt64 => OwnedValue::SInt64(cursor.decode_sint64(input)?),
+        WireType::Bytes => OwnedValue::Bytes(cursor.decode_bytes(input)?.into()),
+        WireType::String => OwnedValue::String(cursor.decode_string(input)?.into()),
+        WireType::Message => OwnedValue::Message(cursor.decode_message_with(input, decode_fields)?),
+        WireType::Sequence => return Err(error::Kind::NestedSequence.into()),
+    })
+}
+
+/// Encode the fields of a message body, which must be in ascending tag order
+fn encode_fields(fields: &[OwnedField], out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut last_tag = None;
+
+    for field in fields {
+        if matches!(last_tag, Some(last_tag) if field.tag <= last_tag) {
+            return Err(error::Kind::Order { tag: field.tag }.into());
+        }
+
+        last_tag = Some(field.tag);
+        out.extend_from_slice(
+            Header::new(field.tag, field.critical, field.value.wire_type())
+                .encode()
+                .as_ref(),
+        );
+
+        match &field.value {
+            // Booleans are encoded entirely in the field header
+            OwnedValue::Bool(_) => (),
+            OwnedValue::Sequence {
+                wire_type,
+                elements,
+            } => encode_sequence(*wire_type, elements, out)?,
+            value => encode_element(value, out)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Encode the header and body of a sequence, whose elements must all have
+/// the given wire type
+fn encode_sequence(
+    wire_type: WireType,
+    elements: &[OwnedValue],
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if wire_type == WireType::Sequence {
+        return Err(error::Kind::NestedSequence.into());
+    }
+
+    let is_bool = |wire_type| matches!(wire_type, WireType::False | WireType::True);
+    let mut body = Vec::new();
+
+    for (index, element) in elements.iter().enumerate() {
+        let actual = element.wire_type();
+
+        if actual != wire_type && !(is_bool(wire_type) && is_bool(actual)) {
+            return Err(error::Kind::SequenceElement {
+                index,
+                actual,
+                wanted: wire_type,
+            }
+            .into());
+        }
+
+        encode_element(element, &mut body)?;
+    }
+
+    let header = (body.len() as u64) << 4 | wire_type as u64;
+    out.extend_from_slice(vint64::encode(header).as_ref());
+    out.extend_from_slice(&body);
+    Ok(())
+}
+
+/// Encode a value as it's encoded following a field header or as an element
+/// of a sequence
+fn encode_element(value: &OwnedValue, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        // Booleans are encoded as single bytes within sequences
+        OwnedValue::Bool(value) => out.push(*value as u8),
+        OwnedValue::UInt64(value) => out.extend_from_slice(vint64::encode(*value).as_ref()),
+        OwnedValue::SInt64(value) => out.extend_from_slice(vint64::signed::encode(*value).as_ref()),
+        OwnedValue::Bytes(bytes) => encode_dynamically_sized(bytes, out),
+        OwnedValue::String(s) => {
+            encode_dynamically_sized(string::ensure_canonical(s)?.as_bytes(), out)
+        }
+        OwnedValue::Message(fields) => {
+            let mut body = Vec::new();
+            encode_fields(fields, &mut body)?;
+            encode_dynamically_sized(&body, out);
+        }
+        OwnedValue::Sequence { .. } => return Err(error::Kind::NestedSequence.into()),
+    }
+
+    Ok(())
+}
+
+/// Encode a length-delimited value
+fn encode_dynamically_sized(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(vint64::encode(bytes.len() as u64).as_ref());
+    out.extend_from_slice(bytes);
+}
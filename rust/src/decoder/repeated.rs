@@ -0,0 +1,157 @@
+//! Repeated fields, in either their packed or non-packed encoding
+
+use super::{sequence, Decode, Decoder};
+use crate::{
+    error::Error,
+    field::{Header, Tag, WireType},
+    verihash::VerihashDigest,
+};
+
+/// Iterator over the values of a repeated `uint64` field decoded using
+/// [`Decoder::decode_repeated_u64`], which may be encoded either as a single
+/// sequence field (packed) or as consecutive fields with the same tag.
+///
+/// If the iterator is dropped before it's exhausted, any remaining fields
+/// of the non-packed encoding are decoded and hashed when it's dropped, so
+/// they're still included in the digest of the message.
+pub struct Repeated<'a, 'b, D: VerihashDigest> {
+    inner: Inner<'a, 'b, D>,
+}
+
+/// Encodings of a repeated field
+enum Inner<'a, 'b, D: VerihashDigest> {
+    /// Sequence containing every value
+    Packed(sequence::Iter<'a, 'b, u64, D>),
+
+    /// Consecutive fields with the same tag
+    Unpacked {
+        /// Decoder for the message containing the fields
+        decoder: &'a mut Decoder<D>,
+
+        /// Tag of the fields
+        tag: Tag,
+
+        /// Fields which haven't been decoded yet
+        input: &'b [u8],
+
+        /// Has the first field been decoded?
+        started: bool,
+    },
+}
+
+impl<'a, 'b, D> Repeated<'a, 'b, D>
+where
+    D: VerihashDigest,
+{
+    /// Iterate over the values of a packed field
+    pub(super) fn packed(iter: sequence::Iter<'a, 'b, u64, D>) -> Self {
+        Self {
+            inner: Inner::Packed(iter),
+        }
+    }
+
+    /// Iterate over the values of consecutive fields with the given tag
+    /// split from the input with [`split_unpacked`]
+    pub(super) fn unpacked(decoder: &'a mut Decoder<D>, tag: Tag, input: &'b [u8]) -> Self {
+        Self {
+            inner: Inner::Unpacked {
+                decoder,
+                tag,
+                input,
+                started: false,
+            },
+        }
+    }
+}
+
+impl<'a, 'b, D> Iterator for Repeated<'a, 'b, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Result<u64, Error>> {
+        match &mut self.inner {
+            Inner::Packed(iter) => iter.next(),
+            Inner::Unpacked {
+                decoder,
+                tag,
+                input,
+                started,
+            } => {
+                if input.is_empty() {
+                    return None;
+                }
+
+                if *started {
+                    decoder.peek().allow_repeated_tag();
+                }
+
+                *started = true;
+                let result = Decode::<u64>::decode(&mut **decoder, *tag, input);
+
+                // The decoder can't proceed past an error
+                if result.is_err() {
+                    *input = &[];
+                }
+
+                Some(result)
+            }
+        }
+    }
+}
+
+impl<'a, 'b, D> Drop for Repeated<'a, 'b, D>
+where
+    D: VerihashDigest,
+{
+    fn drop(&mut self) {
+        if let Inner::Packed(_) = self.inner {
+            return;
+        }
+
+        // Errors can't be returned from `drop`, so if a remaining field
+        // can't be decoded, the message is marked as failed instead
+        while let Some(result) = self.next() {
+            if result.is_err() {
+                if let Inner::Unpacked { decoder, .. } = &mut self.inner {
+                    decoder.peek().fail();
+                }
+            }
+        }
+    }
+}
+
+/// Split the consecutive `uint64` fields with the given tag from the
+/// beginning of the input, without decoding or hashing them.
+///
+/// A field with the given tag which is malformed or has a different wire
+/// type is included along with the remainder of the input, so the error is
+/// reported when the fields are decoded.
+pub(super) fn split_unpacked<'b>(tag: Tag, input: &mut &'b [u8]) -> &'b [u8] {
+    let mut rest = *input;
+
+    loop {
+        let mut field = rest;
+
+        let header = match ::vint64::decode(&mut field) {
+            Ok(header) => Header::from(header),
+            Err(_) => break,
+        };
+
+        if header.tag != tag {
+            break;
+        }
+
+        if header.wire_type != WireType::UInt64 || ::vint64::decode(&mut field).is_err() {
+            rest = &[];
+            break;
+        }
+
+        rest = field;
+    }
+
+    let (fields, remaining) = input.split_at(input.len() - rest.len());
+    *input = remaining;
+    fields
+}
@@ -0,0 +1,117 @@
+//! Schema descriptors for validating messages independently of Rust types
+
+use crate::field::{Tag, WireType};
+
+/// Description of the fields of a message, used to validate messages with
+/// [`validate_against`] without decoding them into a [`Message`] type.
+///
+/// Schemas borrow their fields, so they can be built in `const` contexts:
+///
+/// ```
+/// use veriform::{
+///     decoder::{FieldSchema, Schema},
+///     field::WireType,
+/// };
+///
+/// const INNER: Schema<'static> = Schema::new(&[FieldSchema::required(0, WireType::UInt64)]);
+///
+/// const OUTER: Schema<'static> = Schema::new(&[
+///     FieldSchema::required(1, WireType::String),
+///     FieldSchema::optional(2, WireType::Message).with_schema(INNER),
+/// ]);
+/// ```
+///
+/// [`validate_against`]: crate::decoder::validate_against
+/// [`Message`]: crate::Message
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Schema<'a> {
+    /// Fields of the message, in ascending tag order
+    fields: &'a [FieldSchema<'a>],
+}
+
+impl<'a> Schema<'a> {
+    /// Create a schema describing the given fields, which should be listed
+    /// in ascending tag order
+    pub const fn new(fields: &'a [FieldSchema<'a>]) -> Self {
+        Self { fields }
+    }
+
+    /// Get the fields described by this schema
+    pub fn fields(&self) -> &'a [FieldSchema<'a>] {
+        self.fields
+    }
+
+    /// Get the description of the field with the given tag, if any
+    pub fn field(&self, tag: Tag) -> Option<&'a FieldSchema<'a>> {
+        self.fields.iter().find(|field| field.tag == tag)
+    }
+}
+
+/// Description of a single field of a message within a [`Schema`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FieldSchema<'a> {
+    /// Tag of the field
+    pub tag: Tag,
+
+    /// Expected wire type of the field.
+    ///
+    /// Booleans are encoded as either [`WireType::False`] or
+    /// [`WireType::True`], and either of these matches both.
+    pub wire_type: WireType,
+
+    /// Whether the field must be present
+    pub presence: Presence,
+
+    /// Schema of the field's value, if it's a message, or the schema of each
+    /// of its elements, if it's a sequence of messages. The contents of the
+    /// value aren't checked against a schema if this is `None`.
+    pub schema: Option<Schema<'a>>,
+}
+
+impl<'a> FieldSchema<'a> {
+    /// Describe a field which must be present
+    pub const fn required(tag: Tag, wire_type: WireType) -> Self {
+        Self {
+            tag,
+            wire_type,
+            presence: Presence::Required,
+            schema: None,
+        }
+    }
+
+    /// Describe a field which may be omitted
+    pub const fn optional(tag: Tag, wire_type: WireType) -> Self {
+        Self {
+            tag,
+            wire_type,
+            presence: Presence::Optional,
+            schema: None,
+        }
+    }
+
+    /// Set the schema of the field's value (or the elements of a sequence)
+    pub const fn with_schema(mut self, schema: Schema<'a>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Does the given wire type match the one expected for this field?
+    pub(crate) fn matches(&self, wire_type: WireType) -> bool {
+        match self.wire_type {
+            WireType::False | WireType::True => {
+                wire_type == WireType::False || wire_type == WireType::True
+            }
+            expected => wire_type == expected,
+        }
+    }
+}
+
+/// Whether a field described by a [`FieldSchema`] must be present
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Presence {
+    /// The field must be present
+    Required,
+
+    /// The field may be omitted
+    Optional,
+}
@@ -2,9 +2,24 @@
 
 mod decoder;
 mod hasher;
+#[cfg(feature = "alloc")]
+mod into_iter;
 mod iter;
 mod state;
 
 pub use self::iter::Iter;
 
-pub(crate) use self::decoder::Decoder;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::into_iter::IntoIter;
+
+pub(crate) use self::{decoder::Decoder, iter::Cursor};
+
+use crate::field::WireType;
+
+/// Can a sequence declared to contain elements of the `wanted` wire type
+/// contain an element of the `actual` wire type?
+fn is_element_type(wanted: WireType, actual: WireType) -> bool {
+    let is_bool = |wire_type| wire_type == WireType::False || wire_type == WireType::True;
+    actual == wanted || (is_bool(actual) && is_bool(wanted))
+}
@@ -1,17 +1,17 @@
 //! Veriform sequence decoder
 
-use super::{hasher::Hasher, state::State};
+use super::{hasher::Hasher, is_element_type, state::State};
 use crate::{
-    decoder::{vint64, Decodable, Event},
+    decoder::{self, decodable, vint64, Decodable, Event},
     error::{self, Error},
     field::WireType,
     message::Element,
-    verihash::DigestOutput,
+    verihash::{DigestOutput, VerihashDigest},
 };
-use digest::Digest;
+use core::str;
 
 /// Sequence decoder
-pub(crate) struct Decoder<D: Digest> {
+pub(crate) struct Decoder<D: VerihashDigest> {
     /// Wire type contained in this sequence
     wire_type: WireType,
 
@@ -21,6 +21,13 @@ pub(crate) struct Decoder<D: Digest> {
     /// Remaining length in the sequence body
     remaining: usize,
 
+    /// Index of the element presently being decoded
+    index: usize,
+
+    /// Length of the body of the element presently being decoded, if it's
+    /// dynamically sized
+    body_len: usize,
+
     /// Current decoding state
     state: State,
 
@@ -30,23 +37,35 @@ pub(crate) struct Decoder<D: Digest> {
 
 impl<D> Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Create a new sequence decoder for the given wire type
     pub fn new(wire_type: WireType, length: usize) -> Self {
+        Self::with_hashing(wire_type, length, true)
+    }
+
+    /// Create a new sequence decoder for the given wire type, which computes
+    /// the digest of the sequence only if `hashing` is true
+    pub fn with_hashing(wire_type: WireType, length: usize, hashing: bool) -> Self {
         Self {
             wire_type,
             length,
             remaining: length,
+            index: 0,
+            body_len: 0,
             state: State::default(),
-            hasher: Some(Hasher::new(wire_type)), // TODO(tarcieri): support for disabling hasher
+            hasher: if hashing {
+                Some(Hasher::new(wire_type))
+            } else {
+                None
+            },
         }
     }
 
     /// Get the current position (i.e. number of bytes processed) in the
     /// sequence being decoded
     pub fn position(&self) -> usize {
-        self.length.checked_sub(self.remaining).unwrap()
+        self.length.saturating_sub(self.remaining)
     }
 
     /// Get the number of bytes remaining in the sequence
@@ -54,15 +73,122 @@ where
         self.remaining
     }
 
+    /// Get the wire type of the elements of this sequence
+    pub fn wire_type(&self) -> WireType {
+        self.wire_type
+    }
+
+    /// Decode a message element, returning the raw bytes of the message
+    /// body without decoding it.
+    ///
+    /// If hashing is enabled, the message body is still processed in order
+    /// to compute its digest, charging its fields against a budget of
+    /// `fields_remaining` (if any), and nesting messages at most `max_depth`
+    /// deep (if limited), counting the element itself.
+    pub fn decode_message_bytes<'a>(
+        &mut self,
+        input: &mut &'a [u8],
+        fields_remaining: &mut Option<usize>,
+        max_depth: Option<usize>,
+    ) -> Result<&'a [u8], Error> {
+        let msg_bytes = self.decode_message(input)?;
+
+        if self.hasher.is_some() {
+            let digest =
+                decoder::hash_message_limited::<D>(msg_bytes, fields_remaining, max_depth)?;
+            self.hash_message_digest(&digest)?;
+        }
+
+        Ok(msg_bytes)
+    }
+
+    /// Ensure an element event matches the declared element type of this
+    /// sequence, returning an [`error::Kind::SequenceElement`] error if it
+    /// doesn't.
+    ///
+    /// The sequence's state machine always emits events carrying the
+    /// sequence's own wire type, so this is a defensive check against a
+    /// bug in it rather than something malformed input can trigger.
+    fn check_element(&self, event: &Event<'_>) -> Result<(), Error> {
+        let actual = match event.wire_type() {
+            Some(wire_type) => wire_type,
+            None => {
+                return Err(error::Kind::Decode {
+                    element: Element::Value,
+                    wire_type: self.wire_type,
+                }
+                .position(self.position()))
+            }
+        };
+
+        if is_element_type(self.wire_type, actual) {
+            Ok(())
+        } else {
+            Err(error::Kind::SequenceElement {
+                index: self.index,
+                actual,
+                wanted: self.wire_type,
+            }
+            .position(self.position()))
+        }
+    }
+
+    /// Ensure a chunk of the body of a `string` element is valid, returning
+    /// the same errors as [`Decodable::decode_string`].
+    ///
+    /// Elements are checked as they're decoded, so strings are rejected
+    /// even when the sequence is only hashed. Strings are presently
+    /// restricted to ASCII (see `string::ensure_canonical`), so each chunk
+    /// can be checked independently. If a string is split across inputs
+    /// partway through a character, it's reported as non-canonical even if
+    /// it's also invalid UTF-8.
+    fn check_string_chunk(&self, bytes: &[u8], remaining: usize) -> Result<(), Error> {
+        if bytes.is_ascii() {
+            return Ok(());
+        }
+
+        match str::from_utf8(bytes) {
+            Err(e) if e.error_len().is_some() || remaining == 0 => {
+                let offset = self.body_len - remaining - bytes.len();
+
+                Err(error::Kind::Utf8 {
+                    valid_up_to: offset + e.valid_up_to(),
+                }
+                .position(self.position()))
+            }
+            _ => Err(error::Kind::UnicodeNormalization.position(self.position())),
+        }
+    }
+
     /// Perform a state transition after receiving an event
-    fn transition<'a>(&mut self, event: &Event<'a>) {
+    fn transition<'a>(&mut self, event: &Event<'a>) -> Result<(), Error> {
         self.state = match &event {
             Event::LengthDelimiter { wire_type, length }
-            | Event::SequenceHeader { wire_type, length } => State::Body {
-                wire_type: *wire_type,
-                remaining: *length,
-            },
-            Event::UInt64(_) | Event::SInt64(_) => State::Value(vint64::Decoder::new()),
+            | Event::SequenceHeader { wire_type, length } => {
+                // Elements can't extend past the end of the sequence body,
+                // even if more input follows it
+                if *length > self.remaining {
+                    return Err(error::Kind::Truncated {
+                        remaining: *length - self.remaining,
+                        wire_type: *wire_type,
+                    }
+                    .position(self.position()));
+                }
+
+                self.body_len = *length;
+
+                if *length > 0 {
+                    State::Body {
+                        wire_type: *wire_type,
+                        remaining: *length,
+                    }
+                } else {
+                    State::default()
+                }
+            }
+            Event::Bool(_) | Event::UInt64(_) | Event::SInt64(_) => {
+                State::Value(vint64::Decoder::new())
+            }
             Event::ValueChunk {
                 wire_type,
                 remaining,
@@ -77,8 +203,21 @@ where
                     State::default()
                 }
             }
-            other => unreachable!("unexpected event: {:?}", other),
+            _ => {
+                return Err(error::Kind::Decode {
+                    element: Element::Value,
+                    wire_type: self.wire_type,
+                }
+                .into())
+            }
         };
+
+        // Returning to the initial state completes the current element
+        if let State::Value(_) = self.state {
+            self.index += 1;
+        }
+
+        Ok(())
     }
 
     /// Hash a digest of a nested message within this sequence
@@ -90,6 +229,15 @@ where
         Ok(())
     }
 
+    /// Compute a Verihash digest of the elements of the sequence decoded
+    /// so far, without finishing the hasher.
+    pub fn rolling_digest(&self) -> Result<Option<DigestOutput<D>>, Error>
+    where
+        D: Clone,
+    {
+        self.hasher.as_ref().map(Hasher::rolling_digest).transpose()
+    }
+
     /// Compute a Verihash digest of the sequence we're decoding.
     pub fn compute_digest(self) -> Result<Option<DigestOutput<D>>, Error> {
         self.hasher.map(|hasher| hasher.finish()).transpose()
@@ -98,37 +246,62 @@ where
 
 impl<D> Decodable for Decoder<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn decode<'a>(&mut self, input: &mut &'a [u8]) -> Result<Option<Event<'a>>, Error> {
         let orig_input_len = input.len();
-        let maybe_event = self.state.decode(self.wire_type, input)?;
-        let consumed = orig_input_len.checked_sub(input.len()).unwrap();
-        self.remaining = self.remaining.checked_sub(consumed).unwrap();
+        let varint_start = self.position().saturating_sub(self.state.buffered());
+        let maybe_event = self
+            .state
+            .decode(self.wire_type, input)
+            .map_err(|error| vint64::at_offset(error, varint_start))?;
+        let consumed = orig_input_len.saturating_sub(input.len());
+
+        self.remaining = self
+            .remaining
+            .checked_sub(consumed)
+            .ok_or(error::Kind::Length)?;
 
         if let Some(event) = &maybe_event {
+            self.check_element(event)?;
+
+            if let Event::ValueChunk {
+                wire_type: WireType::String,
+                bytes,
+                remaining,
+            } = *event
+            {
+                self.check_string_chunk(bytes, remaining)?;
+            }
+
             if let Some(hasher) = &mut self.hasher {
                 hasher.hash_event(event)?;
             }
 
-            self.transition(&event);
+            self.transition(event)?;
         }
 
         Ok(maybe_event)
     }
 
+    fn expect_wire_type(&self, wire_type: WireType) -> Result<(), Error> {
+        if is_element_type(self.wire_type, wire_type) {
+            Ok(())
+        } else {
+            Err(error::Kind::UnexpectedWireType {
+                actual: self.wire_type,
+                wanted: wire_type,
+            }
+            .position(self.position()))
+        }
+    }
+
     fn decode_dynamically_sized_value<'a>(
         &mut self,
         expected_type: WireType,
         input: &mut &'a [u8],
     ) -> Result<&'a [u8], Error> {
-        if expected_type != self.wire_type {
-            return Err(error::Kind::UnexpectedWireType {
-                actual: self.wire_type,
-                wanted: expected_type,
-            }
-            .into());
-        }
+        self.expect_wire_type(expected_type)?;
 
         debug_assert!(
             self.wire_type.is_dynamically_sized(),
@@ -142,15 +315,25 @@ where
                 element: Element::LengthDelimiter,
                 wire_type: self.wire_type,
             }
-            .position(self.length.checked_sub(self.remaining).unwrap())),
+            .position(self.position())),
         }?;
 
+        decodable::check_body_length(self.wire_type, length, input)?;
+
+        // Empty values aren't followed by any value chunks
+        if length == 0 {
+            return Ok(&input[..0]);
+        }
+
         match self.decode(input)? {
             Some(Event::ValueChunk {
                 bytes, remaining, ..
             }) => {
                 if remaining == 0 {
-                    debug_assert_eq!(length, bytes.len());
+                    if length != bytes.len() {
+                        return Err(error::Kind::Length.into());
+                    }
+
                     Ok(bytes)
                 } else {
                     Err(error::Kind::Truncated {
@@ -169,9 +352,10 @@ where
     }
 }
 
-#[cfg(all(test, features = "sha2"))]
+#[cfg(all(test, feature = "sha2"))]
 mod tests {
-    use super::{Decodable, Decoder, WireType};
+    use super::{Decodable, Decoder, Event, Hasher, WireType};
+    use crate::error;
     use sha2::Sha256;
 
     #[test]
@@ -224,4 +408,125 @@ mod tests {
 
         assert!(input_ref.is_empty());
     }
+
+    #[test]
+    fn decode_truncated_bytes_element() {
+        // Second element declares a 2-byte body, but only 1 byte is present
+        let input = [3, 1, 5, 2];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::Bytes, input.len());
+
+        assert_eq!(&[1], decoder.decode_bytes(&mut input_ref).unwrap());
+
+        let err = decoder.decode_bytes(&mut input_ref).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::Truncated {
+                remaining: 1,
+                wire_type: WireType::Bytes
+            }
+        );
+    }
+
+    #[test]
+    fn decode_bytes_element_overrunning_body() {
+        // Second element declares a 2-byte body, but only 1 byte of the
+        // sequence body remains, followed by unrelated input
+        let input = [3, 1, 5, 2, 3];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::Bytes, 4);
+
+        assert_eq!(&[1], decoder.decode_bytes(&mut input_ref).unwrap());
+
+        let err = decoder.decode_bytes(&mut input_ref).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::Truncated {
+                remaining: 1,
+                wire_type: WireType::Bytes
+            }
+        );
+    }
+
+    #[test]
+    fn decode_bytes_sequence_with_empty_elements() {
+        let input = [1, 5, 1, 2, 1];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::Bytes, input.len());
+
+        assert_eq!(decoder.decode_bytes(&mut input_ref).unwrap(), &[] as &[u8]);
+        assert_eq!(decoder.decode_bytes(&mut input_ref).unwrap(), &[1, 2]);
+        assert_eq!(decoder.decode_bytes(&mut input_ref).unwrap(), &[] as &[u8]);
+        assert_eq!(decoder.remaining(), 0);
+        assert!(input_ref.is_empty());
+        assert!(decoder.compute_digest().unwrap().is_some());
+    }
+
+    #[test]
+    fn rolling_digest_mid_element() {
+        let input = [5, 1, 2];
+        let mut input_ref = &input[..1];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::Bytes, input.len());
+        assert!(decoder.rolling_digest().is_ok());
+
+        decoder.decode(&mut input_ref).unwrap();
+        let err = decoder.rolling_digest().unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Hashing);
+    }
+
+    #[test]
+    fn decode_string_sequence_with_invalid_element() {
+        let input = [7, 102, 111, 111, 7, 98, 0xff, 114];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::String, input.len());
+
+        assert_eq!("foo", decoder.decode_string(&mut input_ref).unwrap());
+
+        let err = decoder.decode_string(&mut input_ref).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Utf8 { valid_up_to: 1 });
+    }
+
+    #[test]
+    fn decode_string_sequence_with_non_ascii_element() {
+        let input = [7, 102, 111, 111, 5, 0xc3, 0xa9];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::String, input.len());
+
+        assert_eq!("foo", decoder.decode_string(&mut input_ref).unwrap());
+
+        let err = decoder.decode_string(&mut input_ref).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::UnicodeNormalization);
+    }
+
+    #[test]
+    fn decode_uint64_from_message_sequence() {
+        let input = [3, 5, 7];
+        let mut input_ref = &input[..];
+        let mut decoder: Decoder<Sha256> = Decoder::new(WireType::Message, input.len());
+
+        let err = decoder.decode_uint64(&mut input_ref).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::UnexpectedWireType {
+                actual: WireType::Message,
+                wanted: WireType::UInt64
+            }
+        );
+    }
+
+    #[test]
+    fn hash_mismatched_elements() {
+        let mut hasher = Hasher::<Sha256>::new(WireType::UInt64);
+        hasher.hash_event(&Event::UInt64(1)).unwrap();
+
+        let err = hasher.hash_event(&Event::SInt64(-1)).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Hashing);
+
+        let mut hasher = Hasher::<Sha256>::new(WireType::Bytes);
+        let event = Event::LengthDelimiter {
+            wire_type: WireType::Message,
+            length: 1,
+        };
+        assert!(hasher.hash_event(&event).is_err());
+    }
 }
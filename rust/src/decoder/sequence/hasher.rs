@@ -6,40 +6,44 @@
 // TODO(tarcieri): tests and test vectors!!!
 // TODO(tarcieri): DRY out repeated message/sequence code into `verihash::Hasher`
 
+use super::is_element_type;
 use crate::{
     decoder::Event,
     error::{self, Error},
     field::WireType,
-    verihash::{self, DigestOutput},
+    verihash::{self, DigestOutput, VerihashDigest, VerihashParams},
 };
 use core::fmt::{self, Debug};
-use digest::Digest;
 
 /// Verihash sequence hasher.
 ///
 /// This type computes a hash-based transcript of how a message was
 /// decoded, driven by incoming decoding events.
-pub(super) struct Hasher<D: Digest> {
+pub(super) struct Hasher<D: VerihashDigest> {
     /// Verihash hasher
     verihash: verihash::Hasher<D>,
 
+    /// Wire type of the elements of the sequence
+    wire_type: WireType,
+
     /// Current state of the decoder (or `None` if an error occurred)
     state: Option<State>,
 }
 
 impl<D> Hasher<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Create a new [`Hasher`]
     pub fn new(wire_type: WireType) -> Self {
         let mut verihash = verihash::Hasher::new();
 
         // Domain separate sequence hashes by their contained wire type
-        verihash.update(&[wire_type.to_u8()]);
+        verihash.input(&[D::Params::domain_tag(wire_type)]);
 
         Self {
             verihash,
+            wire_type,
             state: Some(State::default()),
         }
     }
@@ -47,6 +51,12 @@ where
     /// Hash an incoming event
     pub fn hash_event(&mut self, event: &Event<'_>) -> Result<(), Error> {
         if let Some(state) = self.state.take() {
+            // Refuse to hash elements which don't match the declared type
+            match event.wire_type() {
+                Some(wire_type) if is_element_type(self.wire_type, wire_type) => (),
+                _ => return Err(error::Kind::Hashing.into()),
+            }
+
             let new_state = state.transition(event, &mut self.verihash)?;
             self.state = Some(new_state);
             Ok(())
@@ -59,7 +69,7 @@ where
     pub fn hash_message_digest(&mut self, digest: &DigestOutput<D>) -> Result<(), Error> {
         match self.state {
             Some(State::Message { remaining }) if remaining == 0 => {
-                self.verihash.update(digest);
+                self.verihash.nested_digest(digest);
                 self.state = Some(State::Initial);
                 Ok(())
             }
@@ -67,10 +77,25 @@ where
         }
     }
 
+    /// Compute the digest of the elements hashed so far, without finishing
+    /// the hasher.
+    ///
+    /// Returns an error if called in the middle of an element.
+    pub fn rolling_digest(&self) -> Result<DigestOutput<D>, Error>
+    where
+        D: Clone,
+    {
+        if self.state == Some(State::Initial) {
+            Ok(self.verihash.clone().finish())
+        } else {
+            Err(error::Kind::Hashing.into())
+        }
+    }
+
     /// Finish computing digest
     pub fn finish(self) -> Result<DigestOutput<D>, Error> {
         if self.state == Some(State::Initial) {
-            Ok(self.verihash.finalize())
+            Ok(self.verihash.finish())
         } else {
             Err(error::Kind::Hashing.into())
         }
@@ -79,7 +104,7 @@ where
 
 impl<D> Debug for Hasher<D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("sequence::Hasher").finish()
@@ -109,7 +134,7 @@ impl Default for State {
 
 impl State {
     /// Transition to a new state based on an incoming event or return an error
-    pub fn transition<D: Digest>(
+    pub fn transition<D: VerihashDigest>(
         self,
         event: &Event<'_>,
         verihash: &mut verihash::Hasher<D>,
@@ -118,7 +143,9 @@ impl State {
             Event::LengthDelimiter { wire_type, length } => {
                 self.handle_length_delimiter(*wire_type, *length, verihash)
             }
-            Event::UInt64(_) | Event::SInt64(_) => self.handle_fixed_sized_value(event, verihash),
+            Event::Bool(_) | Event::UInt64(_) | Event::SInt64(_) => {
+                self.handle_fixed_sized_value(event, verihash)
+            }
             Event::ValueChunk {
                 wire_type,
                 bytes,
@@ -129,7 +156,7 @@ impl State {
     }
 
     /// Handle length delimiter event
-    fn handle_length_delimiter<D: Digest>(
+    fn handle_length_delimiter<D: VerihashDigest>(
         self,
         wire_type: WireType,
         length: usize,
@@ -140,10 +167,12 @@ impl State {
         }
 
         let new_state = match wire_type {
+            // Empty values aren't followed by any value chunks
+            WireType::Bytes | WireType::String if length == 0 => State::Initial,
             WireType::Bytes => State::Bytes { remaining: length },
             WireType::String => State::String { remaining: length },
             WireType::Message => State::Message { remaining: length },
-            _ => unreachable!(),
+            _ => return Err(error::Kind::Hashing.into()),
         };
 
         verihash.dynamically_sized_value(wire_type, length);
@@ -151,7 +180,7 @@ impl State {
     }
 
     /// Handle hashing an incoming fixed-width value
-    fn handle_fixed_sized_value<D: Digest>(
+    fn handle_fixed_sized_value<D: VerihashDigest>(
         self,
         value: &Event<'_>,
         verihash: &mut verihash::Hasher<D>,
@@ -161,19 +190,21 @@ impl State {
         }
 
         match value {
-            Event::UInt64(value) => {
-                verihash.fixed_size_value(WireType::UInt64, &value.to_le_bytes())
-            }
-            Event::SInt64(value) => {
-                verihash.fixed_size_value(WireType::SInt64, &value.to_le_bytes())
-            }
-            _ => unreachable!(),
+            Event::Bool(value) => verihash.boolean(*value),
+            Event::UInt64(value) => verihash.uint64(*value),
+            Event::SInt64(value) => verihash.sint64(*value),
+            _ => return Err(error::Kind::Hashing.into()),
         }
         Ok(State::Initial)
     }
 
-    /// Handle an incoming chunk of data in a value
-    fn handle_value_chunk<D: Digest>(
+    /// Handle an incoming chunk of data in a value.
+    ///
+    /// The state following a length delimiter holds the declared length, so
+    /// the first chunk is checked against it in the same way as subsequent
+    /// chunks are checked against their predecessors: `remaining` must be
+    /// the length left after the chunk.
+    fn handle_value_chunk<D: VerihashDigest>(
         self,
         wire_type: WireType,
         bytes: &[u8],
@@ -183,7 +214,9 @@ impl State {
         // TODO(tarcieri): DRY this out (especially with the message decoder)
         let new_state = match self {
             State::Bytes { remaining } => {
-                if wire_type != WireType::Bytes || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::Bytes
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -196,7 +229,9 @@ impl State {
                 }
             }
             State::String { remaining } => {
-                if wire_type != WireType::String || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::String
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -214,7 +249,9 @@ impl State {
                 // then invoking the `hash_message_digest` method above.
                 //
                 // This code just handles length bookkeeping.
-                if wire_type != WireType::Message || remaining - bytes.len() != new_remaining {
+                if wire_type != WireType::Message
+                    || remaining.checked_sub(bytes.len()) != Some(new_remaining)
+                {
                     return Err(error::Kind::Hashing.into());
                 }
 
@@ -225,7 +262,46 @@ impl State {
             _ => return Err(error::Kind::Hashing.into()),
         };
 
-        verihash.update(bytes);
+        verihash.input(bytes);
         Ok(new_state)
     }
 }
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::Hasher;
+    use crate::{decoder::Event, error, field::WireType};
+    use sha2::Sha256;
+
+    /// Hash a length delimiter declaring a 10 byte value, followed by the
+    /// given chunk
+    fn hash_first_chunk(bytes: &[u8], remaining: usize) -> Result<(), crate::Error> {
+        let mut hasher = Hasher::<Sha256>::new(WireType::Bytes);
+        hasher.hash_event(&Event::LengthDelimiter {
+            wire_type: WireType::Bytes,
+            length: 10,
+        })?;
+        hasher.hash_event(&Event::ValueChunk {
+            wire_type: WireType::Bytes,
+            bytes,
+            remaining,
+        })
+    }
+
+    #[test]
+    fn first_chunk_matches_length() {
+        assert!(hash_first_chunk(b"abcdefg", 3).is_ok());
+    }
+
+    #[test]
+    fn first_chunk_skips_ahead() {
+        let err = hash_first_chunk(b"abc", 3).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Hashing);
+    }
+
+    #[test]
+    fn first_chunk_overruns_length() {
+        let err = hash_first_chunk(b"abcdefghijk", 0).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::Hashing);
+    }
+}
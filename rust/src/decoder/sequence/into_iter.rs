@@ -0,0 +1,141 @@
+//! Owned sequence iterator
+
+use super::iter::Cursor;
+use crate::{
+    decoder::{Decoder, DigestOutput},
+    error::Error,
+    field::{Tag, WireType},
+    verihash::VerihashDigest,
+    Message,
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::marker::PhantomData;
+
+/// Owned sequence iterator: like [`Iter`], but holds a copy of the sequence
+/// body and a [`Decoder`] of its own rather than borrowing them, and yields
+/// owned values. It can be returned from the function which decoded the
+/// message containing the sequence, and sent to another thread.
+///
+/// The digest of the sequence is hashed into the message containing it
+/// when the iterator is created. The elements are then decoded by the
+/// iterator's own decoder, which has the same options as the decoder which
+/// created it, so the digests of any messages nested within them aren't
+/// collected by the latter.
+///
+/// [`Iter`]: super::Iter
+pub struct IntoIter<T, D: VerihashDigest> {
+    /// Decoder for the elements of the sequence
+    cursor: Cursor<Box<Decoder<D>>>,
+
+    /// Copy of the sequence body
+    data: Vec<u8>,
+
+    /// Type to decode
+    decodable: PhantomData<T>,
+}
+
+impl<T, D> IntoIter<T, D>
+where
+    D: VerihashDigest,
+{
+    /// Create a new owned sequence iterator over the given sequence body,
+    /// decoding it with a new decoder configured like `parent`
+    pub(crate) fn new(
+        parent: &Decoder<D>,
+        tag: Tag,
+        wire_type: WireType,
+        data: Vec<u8>,
+    ) -> Result<Self, Error> {
+        let mut decoder = Box::new(Decoder::with_options(parent.options));
+        decoder.set_cancel_check(parent.cancel_check);
+        decoder.push_seq(wire_type, data.len())?;
+
+        Ok(Self {
+            cursor: Cursor::new(decoder, tag),
+            data,
+            decodable: PhantomData,
+        })
+    }
+
+    /// Compute the Verihash digest of the elements of this sequence which
+    /// have been decoded so far.
+    ///
+    /// See [`Iter::rolling_digest`](super::Iter::rolling_digest).
+    pub fn rolling_digest(&mut self) -> Result<DigestOutput<D>, Error>
+    where
+        D: Clone,
+    {
+        self.cursor.rolling_digest()
+    }
+}
+
+impl<T, D> Iterator for IntoIter<T, D>
+where
+    T: Message,
+    D: VerihashDigest,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        self.cursor.next(&self.data, Cursor::decode_message)
+    }
+}
+
+impl<D> Iterator for IntoIter<bool, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<bool, Error>;
+
+    fn next(&mut self) -> Option<Result<bool, Error>> {
+        self.cursor.next(&self.data, Cursor::decode_bool)
+    }
+}
+
+impl<D> Iterator for IntoIter<u64, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<u64, Error>;
+
+    fn next(&mut self) -> Option<Result<u64, Error>> {
+        self.cursor.next(&self.data, Cursor::decode_uint64)
+    }
+}
+
+impl<D> Iterator for IntoIter<i64, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<i64, Error>;
+
+    fn next(&mut self) -> Option<Result<i64, Error>> {
+        self.cursor.next(&self.data, Cursor::decode_sint64)
+    }
+}
+
+impl<D> Iterator for IntoIter<Vec<u8>, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Result<Vec<u8>, Error>> {
+        self.cursor
+            .next(&self.data, Cursor::decode_bytes)
+            .map(|result| result.map(<[u8]>::to_vec))
+    }
+}
+
+impl<D> Iterator for IntoIter<String, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Result<String, Error>> {
+        self.cursor
+            .next(&self.data, Cursor::decode_string)
+            .map(|result| result.map(String::from))
+    }
+}
@@ -1,22 +1,28 @@
 //! Sequence iterator
 
 use crate::{
-    decoder::{sequence, Decodable, Decoder},
-    field::Tag,
-    Error, Message,
+    decoder::{sequence, Decodable, Decoder, DigestOutput, PathElement},
+    error::{self, ElementContext, ElementFailure, Error},
+    field::{Tag, WireType},
+    message::Element,
+    verihash::VerihashDigest,
+    Message,
 };
-use core::marker::PhantomData;
-use digest::Digest;
+use core::{marker::PhantomData, ops::DerefMut};
+use heapless::ArrayLength;
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "rayon")]
+use core::convert::TryFrom;
+#[cfg(feature = "rayon")]
+use rayon_crate::prelude::*;
 
 /// Sequence iterator: iterates over a sequence of values in a Veriform
 /// message, decoding each one.
-pub struct Iter<'a, 'b, T, D: Digest> {
-    /// Sequence decoder
-    decoder: &'a mut Decoder<D>,
-
-    /// Tag for the field this sequence is contained in
-    // TODO(tarcieri): support nested sequences?
-    tag: Tag,
+pub struct Iter<'a, 'b, T, D: VerihashDigest> {
+    /// Decoder for the elements of the sequence
+    cursor: Cursor<&'a mut Decoder<D>>,
 
     /// Input data
     data: &'b [u8],
@@ -27,98 +33,613 @@ pub struct Iter<'a, 'b, T, D: Digest> {
 
 impl<'a, 'b, T, D> Iter<'a, 'b, T, D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     /// Create a new sequence iterator from a sequence decoder
     pub(crate) fn new(decoder: &'a mut Decoder<D>, tag: Tag, data: &'b [u8]) -> Self {
         Self {
-            decoder,
-            tag,
+            cursor: Cursor::new(decoder, tag),
             data,
             decodable: PhantomData,
         }
     }
 
-    /// Borrow the sequence decoder
-    fn seq_decoder(&mut self) -> &mut sequence::Decoder<D> {
-        self.decoder.peek_seq()
+    /// Get the number of bytes of the sequence body consumed by the elements
+    /// decoded so far
+    pub fn consumed(&self) -> usize {
+        self.cursor.position()
+    }
+
+    /// Get the number of bytes of the sequence body which remain to be
+    /// decoded
+    pub fn remaining(&self) -> usize {
+        self.cursor.remaining()
+    }
+
+    /// Get the number of message elements decoded so far whose digest was
+    /// reused from the identical element preceding them, rather than
+    /// computed (see [`DecodeOptions::memoize_element_digests`]).
+    ///
+    /// [`DecodeOptions::memoize_element_digests`]: crate::decoder::DecodeOptions::memoize_element_digests
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn memo_hits(&self) -> usize {
+        self.cursor.memo.as_ref().map_or(0, |memo| memo.hits)
+    }
+
+    /// Compute the Verihash digest of the elements of this sequence which
+    /// have been decoded so far, i.e. the digest the sequence would have if
+    /// it ended after the last decoded element.
+    ///
+    /// This can be used to verify a large sequence incrementally, e.g. by
+    /// comparing against a commitment to a prefix of the sequence and
+    /// bailing out early if they diverge.
+    pub fn rolling_digest(&mut self) -> Result<DigestOutput<D>, Error>
+    where
+        D: Clone,
+    {
+        self.cursor.rolling_digest()
     }
 }
 
-impl<'a, 'b, T, D> Iterator for Iter<'a, 'b, T, D>
+impl<'a, 'b, T, D> Iter<'a, 'b, T, D>
 where
-    T: Message,
-    D: Digest,
+    D: VerihashDigest,
+    Self: Iterator<Item = Result<T, Error>>,
 {
-    type Item = Result<T, Error>;
+    /// Decode the remaining elements of this sequence, appending them to
+    /// an existing [`Vec`] and stopping at the first error.
+    ///
+    /// Elements decoded prior to an error remain in `out`.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn extend_into(self, out: &mut Vec<T>) -> Result<(), Error> {
+        for elem in self {
+            out.push(elem?);
+        }
 
-    fn next(&mut self) -> Option<Result<T, Error>> {
-        if self.seq_decoder().remaining() == 0 {
-            return None;
+        Ok(())
+    }
+
+    /// Decode the remaining elements of this sequence, appending them to
+    /// an existing [`heapless::Vec`] and stopping at the first error,
+    /// including when `out` is full.
+    ///
+    /// Elements decoded prior to an error remain in `out`.
+    pub fn extend_into_heapless<N>(self, out: &mut heapless::Vec<T, N>) -> Result<(), Error>
+    where
+        N: ArrayLength<T>,
+    {
+        for elem in self {
+            out.push(elem?).map_err(|_| error::Kind::Decode {
+                element: Element::Value,
+                wire_type: WireType::Sequence,
+            })?;
         }
 
-        let mut input = &self.data[self.seq_decoder().position()..];
+        Ok(())
+    }
+
+    /// Decode the remaining elements of this sequence into `out`, which
+    /// must be exactly as long as the number of elements remaining, e.g. to
+    /// decode a sequence with a fixed number of elements into an array:
+    ///
+    /// ```ignore
+    /// let mut msgs: [MyMsg; 2] = Default::default();
+    /// decoder.decode_seq(tag, &mut input)?.decode_exact(&mut msgs)?;
+    /// ```
+    ///
+    /// Returns an [`error::Kind::Length`] error if the sequence contains
+    /// fewer or more elements than `out`, or the first error decoding an
+    /// element. Elements decoded prior to an error remain in `out`.
+    pub fn decode_exact(mut self, out: &mut [T]) -> Result<(), Error> {
+        for slot in out.iter_mut() {
+            *slot = self.next().ok_or(error::Kind::Length)??;
+        }
 
-        let result = self
-            .seq_decoder()
-            .decode_message(&mut input)
-            .and_then(|msg_bytes| {
-                self.decoder.push()?;
-                let msg = T::decode(&mut self.decoder, msg_bytes)?;
-
-                if let Some(digest) = self.decoder.pop() {
-                    self.seq_decoder().hash_message_digest(&digest)?;
+        if self.cursor.seq_decoder().remaining() == 0 {
+            Ok(())
+        } else {
+            Err(error::Kind::Length.into())
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, 'b, T, D> Iter<'a, 'b, T, D>
+where
+    T: Message + Send,
+    D: VerihashDigest,
+{
+    /// Decode the remaining messages in this sequence in parallel, returning
+    /// them in order, or the first error which occurred.
+    ///
+    /// Each message is decoded using its own [`Decoder`]. The sequence digest
+    /// is computed afterwards from the digests of the individual messages.
+    ///
+    /// When collecting nested message digests, only the digests of the
+    /// messages in the sequence are recorded, and not the digests of any
+    /// messages nested within them.
+    ///
+    /// The fields of the messages aren't recorded in a [`FieldSummary`].
+    ///
+    /// [`FieldSummary`]: crate::decoder::FieldSummary
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn par_decode_collect(mut self) -> Result<Vec<T>, Error> {
+        // Split the sequence body into per-message byte ranges
+        let mut data = self.cursor.remaining_data(self.data)?;
+        let mut elements = Vec::new();
+
+        while !data.is_empty() {
+            let offset = self.data.len() - data.len();
+            let length = vint64::decode(&mut data)
+                .map_err(|error| crate::decoder::vint64::at_offset(error.into(), offset))?;
+            let length = usize::try_from(length).map_err(|_| error::Kind::Length)?;
+
+            if length > data.len() {
+                return Err(error::Kind::Truncated {
+                    remaining: length - data.len(),
+                    wire_type: WireType::Message,
                 }
+                .into());
+            }
+
+            let (element, rest) = data.split_at(length);
+            elements.push(element);
+            data = rest;
+        }
+
+        let options = self.cursor.decoder.options();
+        let cancel_check = self.cursor.decoder.cancel_check;
+        let fields_remaining = self.cursor.decoder.fields_remaining;
+        let results: Vec<Result<_, Error>> = elements
+            .par_iter()
+            .map(|msg_bytes| {
+                let mut decoder = Decoder::<D>::with_options(options);
+                decoder.set_cancel_check(cancel_check);
+                decoder.fields_remaining = fields_remaining;
+                let msg = T::decode(&mut decoder, msg_bytes)?;
+                let digest = decoder.peek().compute_digest()?;
+                let fields = match (fields_remaining, decoder.fields_remaining) {
+                    (Some(before), Some(after)) => before - after,
+                    _ => 0,
+                };
+                Ok((msg, digest, fields))
+            })
+            .collect();
+
+        // Hash the message digests into the sequence digest in order,
+        // charging each element and its fields against the field budget
+        let mut messages = Vec::with_capacity(results.len());
+
+        for result in results {
+            let (msg, digest, fields) = result?;
+
+            if let Some(remaining) = &mut self.cursor.decoder.fields_remaining {
+                *remaining = remaining
+                    .checked_sub(fields + 1)
+                    .ok_or(error::Kind::LengthLimit)?;
+            }
+
+            let element = self.cursor.path_element();
+            self.cursor.index += 1;
+            let mut input = self.cursor.remaining_data(self.data)?;
+            self.cursor.seq_decoder().decode_message(&mut input)?;
+
+            if let Some(digest) = digest {
+                self.cursor.decoder.record_nested_digest(element, &digest)?;
+                self.cursor.seq_decoder().hash_message_digest(&digest)?;
+            }
 
-                Ok(msg)
-            });
+            messages.push(msg);
+        }
+
+        Ok(messages)
+    }
+}
+
+impl<'a, 'b, T, D> Iterator for Iter<'a, 'b, T, D>
+where
+    T: Message,
+    D: VerihashDigest,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        self.cursor.next(self.data, Cursor::decode_message)
+    }
+}
+
+impl<'a, 'b, D> Iterator for Iter<'a, 'b, bool, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<bool, Error>;
 
-        Some(result)
+    fn next(&mut self) -> Option<Result<bool, Error>> {
+        self.cursor.next(self.data, Cursor::decode_bool)
     }
 }
 
 impl<'a, 'b, D> Iterator for Iter<'a, 'b, u64, D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     type Item = Result<u64, Error>;
 
     fn next(&mut self) -> Option<Result<u64, Error>> {
-        if self.seq_decoder().remaining() == 0 {
-            return None;
-        }
-
-        let mut input = &self.data[self.seq_decoder().position()..];
-        Some(self.seq_decoder().decode_uint64(&mut input))
+        self.cursor.next(self.data, Cursor::decode_uint64)
     }
 }
 
 impl<'a, 'b, D> Iterator for Iter<'a, 'b, i64, D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
     type Item = Result<i64, Error>;
 
     fn next(&mut self) -> Option<Result<i64, Error>> {
+        self.cursor.next(self.data, Cursor::decode_sint64)
+    }
+}
+
+impl<'a, 'b, D> Iterator for Iter<'a, 'b, &'b [u8], D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<&'b [u8], Error>;
+
+    fn next(&mut self) -> Option<Result<&'b [u8], Error>> {
+        self.cursor.next(self.data, Cursor::decode_raw)
+    }
+}
+
+impl<'a, 'b, D> Iterator for Iter<'a, 'b, &'b str, D>
+where
+    D: VerihashDigest,
+{
+    type Item = Result<&'b str, Error>;
+
+    fn next(&mut self) -> Option<Result<&'b str, Error>> {
+        self.cursor.next(self.data, Cursor::decode_string)
+    }
+}
+
+/// Decoding state for the elements of a sequence, shared by [`Iter`] and
+/// [`IntoIter`] so they decode elements identically.
+///
+/// When dropped, the digest of the sequence is hashed into the message
+/// containing it.
+///
+/// [`IntoIter`]: super::IntoIter
+pub(crate) struct Cursor<B: DecoderMut> {
+    /// Sequence decoder
+    decoder: B,
+
+    /// Tag for the field this sequence is contained in
+    // TODO(tarcieri): support nested sequences?
+    tag: Tag,
+
+    /// Index of the next element in the sequence
+    index: usize,
+
+    /// Digest of the last message element (if memoization is enabled)
+    #[cfg(feature = "alloc")]
+    memo: Option<Memo<B::Digest>>,
+}
+
+/// Decoder borrowed by a [`Cursor`] (for [`Iter`]) or owned by it (for
+/// [`IntoIter`])
+///
+/// [`IntoIter`]: super::IntoIter
+pub(crate) trait DecoderMut:
+    DerefMut<Target = Decoder<<Self as DecoderMut>::Digest>>
+{
+    /// Digest used by the decoder
+    type Digest: VerihashDigest;
+}
+
+impl<D> DecoderMut for &mut Decoder<D>
+where
+    D: VerihashDigest,
+{
+    type Digest = D;
+}
+
+#[cfg(feature = "alloc")]
+impl<D> DecoderMut for Box<Decoder<D>>
+where
+    D: VerihashDigest,
+{
+    type Digest = D;
+}
+
+impl<B> Cursor<B>
+where
+    B: DecoderMut,
+{
+    /// Create a new cursor at the start of a sequence
+    pub(crate) fn new(decoder: B, tag: Tag) -> Self {
+        #[cfg(feature = "alloc")]
+        let memo = if decoder.options.memoize_element_digests
+            && decoder.options.hashing
+            && decoder.collector.is_none()
+        {
+            Some(Memo::default())
+        } else {
+            None
+        };
+
+        Self {
+            decoder,
+            tag,
+            index: 0,
+            #[cfg(feature = "alloc")]
+            memo,
+        }
+    }
+
+    /// Decode the next element of the sequence whose body is `data` using
+    /// the given function, returning `None` once the sequence is exhausted
+    pub(crate) fn next<'d, T>(
+        &mut self,
+        data: &'d [u8],
+        decode: impl FnOnce(&mut Self, &mut &'d [u8]) -> Result<T, Error>,
+    ) -> Option<Result<T, Error>> {
         if self.seq_decoder().remaining() == 0 {
             return None;
         }
 
-        let mut input = &self.data[self.seq_decoder().position()..];
-        Some(self.seq_decoder().decode_sint64(&mut input))
+        let element = self.element_context(ElementFailure::Structural);
+
+        let result = self
+            .decoder
+            .count_field()
+            .and_then(|()| self.remaining_data(data))
+            .and_then(|mut input| decode(self, &mut input))
+            .map_err(|e| e.with_element(element));
+
+        self.index += 1;
+
+        #[cfg(any(feature = "log", feature = "defmt"))]
+        {
+            if let Err(error) = &result {
+                fail!(self.decoder, error);
+            }
+        }
+
+        Some(self.decoder.with_context(result))
+    }
+
+    /// Get the position of the cursor within the sequence body
+    pub(super) fn position(&self) -> usize {
+        self.decoder
+            .seq_decoder
+            .as_ref()
+            .map_or(0, sequence::Decoder::position)
+    }
+
+    /// Get the number of bytes remaining in the sequence body
+    pub(super) fn remaining(&self) -> usize {
+        self.decoder
+            .seq_decoder
+            .as_ref()
+            .map_or(0, sequence::Decoder::remaining)
+    }
+
+    /// Compute the Verihash digest of the elements decoded so far
+    pub(super) fn rolling_digest(&mut self) -> Result<DigestOutput<B::Digest>, Error>
+    where
+        B::Digest: Clone,
+    {
+        self.seq_decoder()
+            .rolling_digest()?
+            .ok_or_else(|| error::Kind::Hashing.into())
+    }
+
+    /// Decode a message element
+    pub(crate) fn decode_message<M: Message>(&mut self, input: &mut &[u8]) -> Result<M, Error> {
+        self.decode_message_with(input, M::decode)
+    }
+
+    /// Decode a message element using the given function to decode the
+    /// message body
+    pub(crate) fn decode_message_with<T>(
+        &mut self,
+        input: &mut &[u8],
+        decode_body: impl FnOnce(&mut Decoder<B::Digest>, &[u8]) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let context = self.element_context(ElementFailure::Semantic);
+        let msg_bytes = self.seq_decoder().decode_message(input)?;
+        let element = self.path_element();
+        let depth = self.decoder.stack.len();
+
+        #[cfg(feature = "alloc")]
+        let memoized = self
+            .memo
+            .as_ref()
+            .and_then(|memo| memo.digest_of(msg_bytes))
+            .cloned();
+
+        #[cfg(not(feature = "alloc"))]
+        let memoized = None;
+
+        // The element needn't be hashed if its digest is already known
+        let memo_hit = memoized.is_some();
+        let hashing = self.decoder.options.hashing;
+        self.decoder.options.hashing = hashing && !memo_hit;
+
+        let result = self
+            .decoder
+            .push(element)
+            .and_then(|()| decode_body(&mut self.decoder, msg_bytes));
+
+        self.decoder.options.hashing = hashing;
+
+        let msg = result.map_err(|e| {
+            self.decoder.unwind(depth);
+            e.with_element(context)
+        })?;
+
+        self.decoder.check_depth(depth)?;
+
+        let digest = match (self.decoder.pop()?, memoized) {
+            (Some(digest), _) | (None, Some(digest)) => digest,
+            (None, None) => return Ok(msg),
+        };
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(memo) = &mut self.memo {
+                memo.record(msg_bytes, &digest, memo_hit);
+            }
+        }
+
+        self.seq_decoder().hash_message_digest(&digest)?;
+        Ok(msg)
+    }
+
+    /// Decode a `bool` element
+    pub(crate) fn decode_bool(&mut self, input: &mut &[u8]) -> Result<bool, Error> {
+        self.seq_decoder().decode_bool(input)
+    }
+
+    /// Decode a `uint64` element
+    pub(crate) fn decode_uint64(&mut self, input: &mut &[u8]) -> Result<u64, Error> {
+        self.seq_decoder().decode_uint64(input)
+    }
+
+    /// Decode a `sint64` element
+    pub(crate) fn decode_sint64(&mut self, input: &mut &[u8]) -> Result<i64, Error> {
+        self.seq_decoder().decode_sint64(input)
+    }
+
+    /// Decode a `bytes` element
+    pub(crate) fn decode_bytes<'d>(&mut self, input: &mut &'d [u8]) -> Result<&'d [u8], Error> {
+        self.seq_decoder().decode_bytes(input)
+    }
+
+    /// Decode a `bytes` element, or the raw body of a `message` element
+    /// without decoding it
+    pub(crate) fn decode_raw<'d>(&mut self, input: &mut &'d [u8]) -> Result<&'d [u8], Error> {
+        let decoder = &mut *self.decoder;
+        let max_depth = decoder.max_nested_depth(0);
+        let seq_decoder = decoder.seq_decoder.as_mut().ok_or(error::Kind::Failed)?;
+
+        if seq_decoder.wire_type() == WireType::Message {
+            seq_decoder.decode_message_bytes(input, &mut decoder.fields_remaining, max_depth)
+        } else {
+            seq_decoder.decode_bytes(input)
+        }
+    }
+
+    /// Decode a `string` element
+    pub(crate) fn decode_string<'d>(&mut self, input: &mut &'d [u8]) -> Result<&'d str, Error> {
+        self.seq_decoder().decode_string(input)
+    }
+
+    /// Hash an element of any wire type without decoding its value (or the
+    /// fields of a message element)
+    #[cfg(feature = "alloc")]
+    pub(crate) fn skip_element(&mut self, input: &mut &[u8]) -> Result<(), Error> {
+        match self.seq_decoder().wire_type() {
+            WireType::False | WireType::True => self.decode_bool(input).map(|_| ()),
+            WireType::UInt64 => self.decode_uint64(input).map(|_| ()),
+            WireType::SInt64 => self.decode_sint64(input).map(|_| ()),
+            WireType::String => self.decode_string(input).map(|_| ()),
+            _ => self.decode_raw(input).map(|_| ()),
+        }
+    }
+
+    /// Borrow the sequence decoder
+    fn seq_decoder(&mut self) -> &mut sequence::Decoder<B::Digest> {
+        self.decoder.peek_seq()
+    }
+
+    /// Get the data which remains to be decoded in the sequence
+    fn remaining_data<'d>(&mut self, data: &'d [u8]) -> Result<&'d [u8], Error> {
+        data.get(self.seq_decoder().position()..)
+            .ok_or_else(|| error::Kind::Length.into())
+    }
+
+    /// Get the path element for the next element in the sequence
+    fn path_element(&self) -> PathElement {
+        PathElement::seq_element(self.tag, self.index)
+    }
+
+    /// Get the context to attach to errors decoding the next element in the
+    /// sequence
+    fn element_context(&mut self, failure: ElementFailure) -> ElementContext {
+        ElementContext {
+            index: self.index,
+            offset: self.seq_decoder().position(),
+            failure,
+        }
     }
 }
 
-impl<'a, 'b, T, D> Drop for Iter<'a, 'b, T, D>
+impl<B> Drop for Cursor<B>
 where
-    D: Digest,
+    B: DecoderMut,
 {
     fn drop(&mut self) {
-        if let Some(digest) = self.decoder.pop_seq() {
-            self.decoder
-                .peek()
-                .hash_sequence_digest(self.tag, &digest)
-                .unwrap();
+        // Errors can't be returned from `drop`. If the sequence wasn't fully
+        // consumed the parent message hasher is left mid-sequence, so the
+        // failure surfaces when its digest is computed.
+        if let Ok(Some(digest)) = self.decoder.pop_seq() {
+            let _ = self.decoder.peek().hash_sequence_digest(self.tag, &digest);
+        }
+    }
+}
+
+/// Body and digest of the last message element decoded by a [`Cursor`],
+/// reused for the next element if it's identical
+#[cfg(feature = "alloc")]
+struct Memo<D: VerihashDigest> {
+    /// Body of the last message element
+    body: Vec<u8>,
+
+    /// Digest of the last message element, if one has been decoded
+    digest: Option<DigestOutput<D>>,
+
+    /// Number of elements whose digest was reused
+    hits: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<D> Memo<D>
+where
+    D: VerihashDigest,
+{
+    /// Get the digest of the given message body if it's identical to the
+    /// last one
+    fn digest_of(&self, body: &[u8]) -> Option<&DigestOutput<D>> {
+        self.digest.as_ref().filter(|_| self.body == body)
+    }
+
+    /// Record the digest of the given message body, or count a hit if it
+    /// was reused from the last one
+    fn record(&mut self, body: &[u8], digest: &DigestOutput<D>, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.body.clear();
+            self.body.extend_from_slice(body);
+            self.digest = Some(digest.clone());
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<D> Default for Memo<D>
+where
+    D: VerihashDigest,
+{
+    fn default() -> Self {
+        Self {
+            body: Vec::new(),
+            digest: None,
+            hits: 0,
         }
     }
 }
@@ -9,6 +9,7 @@ use crate::{
     field::WireType,
     message::Element,
 };
+use core::convert::TryFrom;
 
 /// Sequence decoder state machine
 #[derive(Debug)]
@@ -33,6 +34,15 @@ impl Default for State {
 }
 
 impl State {
+    /// Get the number of bytes of a partially decoded `vint64` which have
+    /// been buffered from previous input
+    pub fn buffered(&self) -> usize {
+        match self {
+            State::Value(decoder) => decoder.buffered(),
+            State::Body { .. } => 0,
+        }
+    }
+
     /// Decode a sequence from the given input
     pub fn decode<'a>(
         &mut self,
@@ -40,6 +50,9 @@ impl State {
         input: &mut &'a [u8],
     ) -> Result<Option<Event<'a>>, Error> {
         match self {
+            State::Value(_) if wire_type == WireType::False || wire_type == WireType::True => {
+                decode_bool(input)
+            }
             State::Value(decoder) => {
                 if let Some(value) = decoder.decode(input)? {
                     decode_value(wire_type, value).map(Some)
@@ -66,12 +79,9 @@ fn decode_value<'a>(wire_type: WireType, value: u64) -> Result<Event<'a>, Error>
     Ok(match wire_type {
         WireType::UInt64 => Event::UInt64(value),
         WireType::SInt64 => Event::SInt64(zigzag::decode(value)),
-        WireType::Sequence => Event::SequenceHeader {
-            wire_type: WireType::from_unmasked(value),
-            length: (value >> 4) as usize,
-        },
+        WireType::Sequence => Event::sequence_header(value)?,
         WireType::False | WireType::True => {
-            // TODO(tarcieri): support boolean sequences?
+            // Booleans are encoded as single bytes (see `decode_bool`)
             return Err(error::Kind::Decode {
                 element: Element::Value,
                 wire_type,
@@ -87,12 +97,29 @@ fn decode_value<'a>(wire_type: WireType, value: u64) -> Result<Event<'a>, Error>
 
             Event::LengthDelimiter {
                 wire_type,
-                length: value as usize,
+                length: usize::try_from(value).map_err(|_| error::Kind::Length)?,
             }
         }
     })
 }
 
+/// Decode a boolean encoded as a single `0` or `1` byte
+fn decode_bool<'a>(input: &mut &'a [u8]) -> Result<Option<Event<'a>>, Error> {
+    let (&byte, rest) = match input.split_first() {
+        Some(split) => split,
+        None => return Ok(None),
+    };
+
+    let value = match byte {
+        0 => false,
+        1 => true,
+        _ => return Err(error::Kind::Malformed.into()),
+    };
+
+    *input = rest;
+    Ok(Some(Event::Bool(value)))
+}
+
 /// Decode the body of a variable-length value
 fn decode_body<'a>(wire_type: &mut WireType, input: &mut &'a [u8], remaining: usize) -> Event<'a> {
     let chunk_size = if input.len() >= remaining {
@@ -107,6 +134,6 @@ fn decode_body<'a>(wire_type: &mut WireType, input: &mut &'a [u8], remaining: us
     Event::ValueChunk {
         wire_type: *wire_type,
         bytes,
-        remaining: remaining.checked_sub(chunk_size).unwrap(),
+        remaining: remaining.saturating_sub(chunk_size),
     }
 }
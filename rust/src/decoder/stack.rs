@@ -0,0 +1,122 @@
+//! Pushdown stack used by the decoder
+
+use core::ops::{Deref, DerefMut};
+use heapless::consts::U16;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Number of elements stored inline before the stack spills onto the heap
+pub(crate) const INLINE_CAPACITY: usize = 16;
+
+/// Pushdown stack which stores up to 16 elements inline.
+///
+/// When the `alloc` feature is enabled, pushing onto a full stack moves its
+/// elements into a heap-allocated `Vec` rather than failing.
+pub(crate) enum Stack<T> {
+    /// Elements stored inline
+    Inline(heapless::Vec<T, U16>),
+
+    /// Elements spilled onto the heap
+    #[cfg(feature = "alloc")]
+    Heap(Vec<T>),
+}
+
+impl<T> Stack<T> {
+    /// Create a new empty stack
+    pub fn new() -> Self {
+        Stack::Inline(heapless::Vec::new())
+    }
+
+    /// Push an element onto the stack, returning it if the stack is full
+    pub fn push(&mut self, element: T) -> Result<(), T> {
+        let element = match self {
+            Stack::Inline(inline) => match inline.push(element) {
+                Ok(()) => return Ok(()),
+                Err(element) => element,
+            },
+            #[cfg(feature = "alloc")]
+            Stack::Heap(heap) => {
+                heap.push(element);
+                return Ok(());
+            }
+        };
+
+        self.spill(element)
+    }
+
+    /// Pop the element on the top of the stack
+    pub fn pop(&mut self) -> Option<T> {
+        match self {
+            Stack::Inline(inline) => inline.pop(),
+            #[cfg(feature = "alloc")]
+            Stack::Heap(heap) => heap.pop(),
+        }
+    }
+
+    /// Ensure the stack can hold at least `capacity` elements without
+    /// growing, moving its elements onto the heap if they won't fit inline
+    #[cfg(feature = "alloc")]
+    pub fn reserve(&mut self, capacity: usize) {
+        match self {
+            Stack::Inline(inline) if capacity > INLINE_CAPACITY => {
+                let mut heap = Vec::with_capacity(capacity);
+
+                while let Some(element) = inline.pop() {
+                    heap.push(element);
+                }
+
+                heap.reverse();
+                *self = Stack::Heap(heap);
+            }
+            Stack::Heap(heap) => heap.reserve(capacity.saturating_sub(heap.len())),
+            _ => (),
+        }
+    }
+
+    /// Move the elements of a full inline stack onto the heap, followed by
+    /// the given element
+    #[cfg(feature = "alloc")]
+    fn spill(&mut self, element: T) -> Result<(), T> {
+        let mut heap = Vec::with_capacity(INLINE_CAPACITY * 2);
+
+        if let Stack::Inline(inline) = self {
+            while let Some(e) = inline.pop() {
+                heap.push(e);
+            }
+        }
+
+        heap.reverse();
+        heap.push(element);
+        *self = Stack::Heap(heap);
+        Ok(())
+    }
+
+    /// The inline stack is full and can't spill without `alloc`
+    #[cfg(not(feature = "alloc"))]
+    fn spill(&mut self, element: T) -> Result<(), T> {
+        Err(element)
+    }
+}
+
+impl<T> Deref for Stack<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Stack::Inline(inline) => inline,
+            #[cfg(feature = "alloc")]
+            Stack::Heap(heap) => heap,
+        }
+    }
+}
+
+impl<T> DerefMut for Stack<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self {
+            Stack::Inline(inline) => inline,
+            #[cfg(feature = "alloc")]
+            Stack::Heap(heap) => heap,
+        }
+    }
+}
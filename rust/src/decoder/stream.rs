@@ -0,0 +1,350 @@
+//! Incremental (i.e. resumable) decoder
+
+use super::{
+    message, sequence,
+    stack::{self, Stack},
+    Decodable, Event,
+};
+use crate::{
+    error::{self, Error},
+    field::{Tag, WireType},
+    verihash::{DigestOutput, VerihashDigest},
+};
+use core::cmp;
+
+/// Incremental decoder which emits [`Event`]s for a message which arrives
+/// split across several buffers.
+///
+/// All decoding state (the stack of nested messages and sequences, partially
+/// decoded `vint64` values, and Verihash hasher state) is retained between
+/// calls to [`Stream::decode`], so decoding can be suspended whenever the
+/// current buffer has been consumed and resumed when the next one arrives.
+///
+/// Events for nested messages and sequences are emitted as they're decoded.
+/// Use [`Stream::depth`] to determine the nesting depth of the last event.
+pub struct Stream<D: VerihashDigest> {
+    /// Stack of message/sequence decoders
+    stack: Stack<Frame<D>>,
+
+    /// Tag of the most recently decoded field header
+    last_tag: Tag,
+
+    /// Number of fields which may still be decoded, or `None` for unlimited
+    fields_remaining: Option<usize>,
+
+    /// Maximum nesting depth of messages, or `None` for unlimited
+    max_depth: Option<usize>,
+}
+
+impl<D> Stream<D>
+where
+    D: VerihashDigest,
+{
+    /// Create a new incremental decoder
+    pub fn new() -> Self {
+        let root = Frame {
+            decoder: FrameDecoder::Message(message::Decoder::new()),
+            tag: 0,
+            remaining: None,
+        };
+
+        // Never fails: the stack is empty
+        let mut stack = Stack::new();
+        let _ = stack.push(root);
+
+        Self {
+            stack,
+            last_tag: 0,
+            fields_remaining: None,
+            max_depth: Some(stack::INLINE_CAPACITY),
+        }
+    }
+
+    /// Set the maximum total number of fields which may be decoded, counting
+    /// the fields of nested messages and the elements of sequences, or
+    /// `None` to allow any number of fields (the default).
+    ///
+    /// Once the budget is exhausted, decoding another field returns an
+    /// [`error::Kind::LengthLimit`] error.
+    pub fn set_max_total_fields(&mut self, max_total_fields: Option<usize>) {
+        self.fields_remaining = max_total_fields;
+    }
+
+    /// Set the maximum nesting depth of messages, counting the outermost
+    /// message, or `None` to allow messages to be nested arbitrarily deeply.
+    ///
+    /// Defaults to 16. Decoding a message nested more deeply returns an
+    /// [`error::Kind::NestingDepth`] error, as does nesting messages and
+    /// sequences more than 16 deep without the `alloc` feature.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Get the number of fields which may still be decoded, or `None` if
+    /// unlimited
+    pub(crate) fn fields_remaining(&self) -> Option<usize> {
+        self.fields_remaining
+    }
+
+    /// Get the current nesting depth, where `0` is the outermost message
+    pub fn depth(&self) -> usize {
+        self.stack.len().saturating_sub(1)
+    }
+
+    /// Decode the next event from the given input, advancing the slice for
+    /// the amount of data processed.
+    ///
+    /// Returns `Ok(None)` once the input has been exhausted without
+    /// completing an event. Call this method again with the next buffer to
+    /// resume decoding.
+    pub fn decode<'a>(&mut self, input: &mut &'a [u8]) -> Result<Option<Event<'a>>, Error> {
+        loop {
+            self.pop_completed()?;
+
+            if input.is_empty() {
+                return Ok(None);
+            }
+
+            let orig_input = *input;
+            let frame = self.stack.last_mut().ok_or(error::Kind::Failed)?;
+            let limit = frame
+                .remaining
+                .map_or(orig_input.len(), |n| cmp::min(n, orig_input.len()));
+            let frame_input = &orig_input[..limit];
+
+            let in_sequence = matches!(frame.decoder, FrameDecoder::Sequence(_));
+            let mut frame_input_ref = frame_input;
+            let event = frame.decoder.decode(&mut frame_input_ref)?;
+            let consumed = frame_input.len() - frame_input_ref.len();
+
+            match &event {
+                Some(Event::FieldHeader(_)) => super::charge_field(&mut self.fields_remaining)?,
+                Some(event) if in_sequence && event.begins_element() => {
+                    super::charge_field(&mut self.fields_remaining)?
+                }
+                _ => (),
+            }
+
+            if consumed == 0 && event.is_none() {
+                return Err(error::Kind::Failed.into());
+            }
+
+            *input = &orig_input[consumed..];
+            self.consume(&frame_input[..consumed])?;
+
+            if let Some(event) = event {
+                self.handle_event(&event)?;
+                self.pop_completed()?;
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Fill the provided slice with the digest of the message if it fits.
+    ///
+    /// Returns an error if the message has only been partially decoded.
+    pub fn fill_digest(&mut self, output: &mut [u8]) -> Result<(), Error> {
+        let digest = self.compute_digest()?;
+
+        if digest.len() != output.len() {
+            return Err(error::Kind::Hashing.into());
+        }
+
+        output.copy_from_slice(&digest);
+        Ok(())
+    }
+
+    /// Compute a digest of the message.
+    ///
+    /// Returns an error if the message has only been partially decoded.
+    pub(crate) fn compute_digest(&mut self) -> Result<DigestOutput<D>, Error> {
+        match &mut self.stack[..] {
+            [root] => root.decoder.compute_digest()?,
+            _ => None,
+        }
+        .ok_or_else(|| error::Kind::Hashing.into())
+    }
+
+    /// Push nested message and sequence decoders based on incoming events
+    fn handle_event(&mut self, event: &Event<'_>) -> Result<(), Error> {
+        let (decoder, length) = match *event {
+            Event::FieldHeader(header) => {
+                self.last_tag = header.tag;
+                return Ok(());
+            }
+            Event::LengthDelimiter {
+                wire_type: WireType::Message,
+                length,
+            } => {
+                if let Some(max_depth) = self.max_depth {
+                    if self.message_depth() >= max_depth {
+                        return Err(error::Kind::NestingDepth.into());
+                    }
+                }
+
+                (FrameDecoder::Message(message::Decoder::new()), length)
+            }
+            Event::SequenceHeader { wire_type, length } => {
+                if let Some(FrameDecoder::Sequence(_)) = self.stack.last().map(|f| &f.decoder) {
+                    return Err(error::Kind::NestedSequence.into());
+                }
+
+                (
+                    FrameDecoder::Sequence(sequence::Decoder::new(wire_type, length)),
+                    length,
+                )
+            }
+            _ => return Ok(()),
+        };
+
+        let frame = Frame {
+            decoder,
+            tag: self.last_tag,
+            remaining: Some(length),
+        };
+
+        self.stack
+            .push(frame)
+            .map_err(|_| error::Kind::NestingDepth.into())
+    }
+
+    /// Get the number of messages on the stack
+    fn message_depth(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|frame| matches!(frame.decoder, FrameDecoder::Message(_)))
+            .count()
+    }
+
+    /// Account for bytes consumed by the innermost decoder in the bodies of
+    /// all of the messages and sequences which contain it
+    fn consume(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let (frame, parents) = match self.stack.split_last_mut() {
+            Some(split) => split,
+            None => return Err(error::Kind::Failed.into()),
+        };
+
+        frame.consume(bytes.len())?;
+
+        for parent in parents {
+            parent.consume(bytes.len())?;
+
+            let mut input = bytes;
+            match parent.decoder.decode(&mut input)? {
+                Some(Event::ValueChunk { .. }) if input.is_empty() => (),
+                _ => return Err(error::Kind::Length.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pop any nested messages and sequences which have been completely
+    /// decoded, hashing their digests into the parent message or sequence
+    fn pop_completed(&mut self) -> Result<(), Error> {
+        while self.stack.last().and_then(|frame| frame.remaining) == Some(0) {
+            let frame = self.stack.pop().ok_or(error::Kind::Failed)?;
+            let parent = self.stack.last_mut().ok_or(error::Kind::Failed)?;
+
+            match frame.decoder {
+                FrameDecoder::Message(mut decoder) => {
+                    if let Some(digest) = decoder.compute_digest()? {
+                        parent.decoder.hash_message_digest(frame.tag, &digest)?;
+                    }
+                }
+                FrameDecoder::Sequence(decoder) => {
+                    if let Some(digest) = decoder.compute_digest()? {
+                        parent.decoder.hash_sequence_digest(frame.tag, &digest)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D> Default for Stream<D>
+where
+    D: VerihashDigest,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frame on the incremental decoder's stack
+struct Frame<D: VerihashDigest> {
+    /// Decoder for this message or sequence
+    decoder: FrameDecoder<D>,
+
+    /// Tag of the field containing this message or sequence
+    tag: Tag,
+
+    /// Remaining bytes in the body (or `None` for the outermost message)
+    remaining: Option<usize>,
+}
+
+impl<D> Frame<D>
+where
+    D: VerihashDigest,
+{
+    /// Consume the given number of bytes of the body
+    fn consume(&mut self, nbytes: usize) -> Result<(), Error> {
+        if let Some(remaining) = self.remaining {
+            self.remaining = Some(remaining.checked_sub(nbytes).ok_or(error::Kind::Length)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Decoders for nested messages and sequences
+enum FrameDecoder<D: VerihashDigest> {
+    /// Message decoder
+    Message(message::Decoder<D>),
+
+    /// Sequence decoder
+    Sequence(sequence::Decoder<D>),
+}
+
+impl<D> FrameDecoder<D>
+where
+    D: VerihashDigest,
+{
+    /// Decode the next event using the underlying decoder
+    fn decode<'a>(&mut self, input: &mut &'a [u8]) -> Result<Option<Event<'a>>, Error> {
+        match self {
+            FrameDecoder::Message(decoder) => decoder.decode(input),
+            FrameDecoder::Sequence(decoder) => decoder.decode(input),
+        }
+    }
+
+    /// Compute a digest of the root message
+    fn compute_digest(&mut self) -> Result<Option<DigestOutput<D>>, Error> {
+        match self {
+            FrameDecoder::Message(decoder) => decoder.compute_digest(),
+            FrameDecoder::Sequence(_) => Err(error::Kind::Hashing.into()),
+        }
+    }
+
+    /// Hash the digest of a nested message
+    fn hash_message_digest(&mut self, tag: Tag, digest: &DigestOutput<D>) -> Result<(), Error> {
+        match self {
+            FrameDecoder::Message(decoder) => decoder.hash_message_digest(tag, digest),
+            FrameDecoder::Sequence(decoder) => decoder.hash_message_digest(digest),
+        }
+    }
+
+    /// Hash the digest of a nested sequence
+    fn hash_sequence_digest(&mut self, tag: Tag, digest: &DigestOutput<D>) -> Result<(), Error> {
+        match self {
+            FrameDecoder::Message(decoder) => decoder.hash_sequence_digest(tag, digest),
+            FrameDecoder::Sequence(_) => Err(error::Kind::NestedSequence.into()),
+        }
+    }
+}
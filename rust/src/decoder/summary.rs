@@ -0,0 +1,221 @@
+//! Summaries of the fields consumed while decoding a message
+
+use super::PathElement;
+use crate::{
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+};
+use core::fmt::{self, Display};
+use heapless::consts::{U16, U4};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Record of a field consumed by the decoder: its location, tag, wire type,
+/// and encoded length, but not its value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldRecord {
+    /// Path to the message containing the field (empty for fields of the
+    /// outermost message)
+    pub path: heapless::Vec<PathElement, U4>,
+
+    /// Tag of the field
+    pub tag: Tag,
+
+    /// Wire type of the field
+    pub wire_type: WireType,
+
+    /// Length of the encoded field in bytes, including its header
+    pub encoded_len: usize,
+}
+
+impl Display for FieldRecord {
+    /// Render the record as e.g. `2[0].1: UInt64 (2 bytes)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for element in &self.path {
+            write!(f, "{}", element.tag)?;
+
+            if let Some(index) = element.index {
+                write!(f, "[{}]", index)?;
+            }
+
+            write!(f, ".")?;
+        }
+
+        write!(
+            f,
+            "{}: {:?} ({} bytes)",
+            self.tag, self.wire_type, self.encoded_len
+        )
+    }
+}
+
+/// Summary of the fields consumed while decoding a message, in the order
+/// they were consumed, for e.g. logging which fields were present in an
+/// accepted message without logging their values.
+///
+/// Enable it with [`Decoder::summarize_fields`], and retrieve it after
+/// decoding with [`Decoder::take_field_summary`]. Fields of the outermost
+/// message are recorded, along with those of nested messages up to the
+/// depth set with [`FieldSummary::max_depth`]. A nested message field is
+/// recorded before the fields it contains.
+///
+/// Records are stored in a [`heapless::Vec`], or a [`Vec`] when the
+/// `alloc` feature is enabled, either of which may be provided by the
+/// caller using the [`From`] impls. Running out of capacity is an
+/// [`error::Kind::Capacity`] error which fails decoding.
+///
+/// [`Decoder::summarize_fields`]: crate::decoder::Decoder::summarize_fields
+/// [`Decoder::take_field_summary`]: crate::decoder::Decoder::take_field_summary
+#[derive(Clone, Debug)]
+pub struct FieldSummary {
+    /// Records of the fields consumed so far
+    records: Records,
+
+    /// Maximum nesting depth of the fields to record
+    max_depth: usize,
+}
+
+/// Storage for field records, which are kept inline unless a `Vec` is
+/// provided so summarizing fields doesn't require `alloc`
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+enum Records {
+    /// Records stored inline
+    Heapless(heapless::Vec<FieldRecord, U16>),
+
+    /// Records stored on the heap
+    #[cfg(feature = "alloc")]
+    Vec(Vec<FieldRecord>),
+}
+
+impl FieldSummary {
+    /// Maximum nesting depth of the fields which can be recorded
+    pub const MAX_DEPTH: usize = 4;
+
+    /// Create an empty summary which stores up to 16 records inline and
+    /// records only the fields of the outermost message
+    pub fn new() -> Self {
+        Self::with_records(Records::Heapless(heapless::Vec::new()))
+    }
+
+    /// Also record the fields of messages nested up to the given depth
+    /// (at most [`FieldSummary::MAX_DEPTH`]), e.g. `1` to record the fields
+    /// of the outermost message and the messages it contains
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth.min(Self::MAX_DEPTH);
+        self
+    }
+
+    /// Get the records of the fields consumed so far
+    pub fn as_slice(&self) -> &[FieldRecord] {
+        match &self.records {
+            Records::Heapless(records) => records,
+            #[cfg(feature = "alloc")]
+            Records::Vec(records) => records,
+        }
+    }
+
+    /// Iterate over the records of the fields consumed so far
+    pub fn iter(&self) -> core::slice::Iter<'_, FieldRecord> {
+        self.as_slice().iter()
+    }
+
+    /// Get the number of fields recorded so far
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    /// Have no fields been recorded?
+    pub fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+
+    /// Create a summary which stores records in the given storage
+    fn with_records(records: Records) -> Self {
+        Self {
+            records,
+            max_depth: 0,
+        }
+    }
+
+    /// Record a field of the message at the given path if it isn't nested
+    /// beyond the maximum depth
+    pub(super) fn record(
+        &mut self,
+        path: &[PathElement],
+        header: Header,
+        encoded_len: usize,
+    ) -> Result<(), Error> {
+        if path.len() > self.max_depth {
+            return Ok(());
+        }
+
+        let record = FieldRecord {
+            // Never fails: the maximum depth is at most the path capacity
+            path: heapless::Vec::from_slice(path).map_err(|_| error::Kind::Capacity)?,
+            tag: header.tag,
+            wire_type: header.wire_type,
+            encoded_len,
+        };
+
+        match &mut self.records {
+            Records::Heapless(records) => {
+                records.push(record).map_err(|_| error::Kind::Capacity)?
+            }
+            #[cfg(feature = "alloc")]
+            Records::Vec(records) => records.push(record),
+        }
+
+        Ok(())
+    }
+
+    /// Discard any records beyond the first `len`
+    pub(super) fn truncate(&mut self, len: usize) {
+        match &mut self.records {
+            Records::Heapless(records) => records.truncate(len),
+            #[cfg(feature = "alloc")]
+            Records::Vec(records) => records.truncate(len),
+        }
+    }
+}
+
+impl Default for FieldSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for FieldSummary {
+    /// Render the summary with one line per field
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for record in self {
+            writeln!(f, "{}", record)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldSummary {
+    type Item = &'a FieldRecord;
+    type IntoIter = core::slice::Iter<'a, FieldRecord>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<heapless::Vec<FieldRecord, U16>> for FieldSummary {
+    fn from(records: heapless::Vec<FieldRecord, U16>) -> Self {
+        Self::with_records(Records::Heapless(records))
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+impl From<Vec<FieldRecord>> for FieldSummary {
+    fn from(records: Vec<FieldRecord>) -> Self {
+        Self::with_records(Records::Vec(records))
+    }
+}
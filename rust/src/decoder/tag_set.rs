@@ -0,0 +1,54 @@
+//! Sets of field tags
+
+use crate::{
+    error::{self, Error},
+    field::Tag,
+};
+use heapless::consts::U8;
+
+/// Set of up to 8 field tags, e.g. those of the fields excluded from the
+/// digest of a message with [`Decoder::exclude_from_digest`].
+///
+/// ```
+/// use veriform::decoder::TagSet;
+///
+/// let mut tags = TagSet::new();
+/// tags.insert(9).unwrap();
+///
+/// assert!(tags.contains(9));
+/// assert!(!tags.contains(1));
+/// ```
+///
+/// [`Decoder::exclude_from_digest`]: crate::decoder::Decoder::exclude_from_digest
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TagSet {
+    /// Tags in the set, in the order they were inserted
+    tags: heapless::Vec<Tag, U8>,
+}
+
+impl TagSet {
+    /// Create a new empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a tag into the set, returning an [`error::Kind::Capacity`]
+    /// error if the set is full
+    pub fn insert(&mut self, tag: Tag) -> Result<(), Error> {
+        if !self.contains(tag) {
+            self.tags.push(tag).map_err(|_| error::Kind::Capacity)?;
+        }
+
+        Ok(())
+    }
+
+    /// Is the given tag in the set?
+    pub fn contains(&self, tag: Tag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
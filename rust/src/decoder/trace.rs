@@ -1,6 +1,10 @@
 //! Message tracing macros
+//!
+//! Traces are emitted using the `log` crate and/or `defmt` depending on
+//! which of the corresponding features are enabled.
 
 /// Trace a decoding event
+#[cfg(feature = "log")]
 macro_rules! trace {
     ($decoder:expr, $c:expr, $msg:expr, $($arg:tt)*) => {
         let mut prefix: heapless::String<heapless::consts::U128> = heapless::String::new();
@@ -13,7 +17,58 @@ macro_rules! trace {
 
 /// Trace the beginning of a message component being decoded
 macro_rules! begin {
-    ($decoder:expr, $msg:expr, $($arg:tt)*) => {
-        trace!($decoder, '+', $msg, $($arg)*);
+    ($decoder:expr, $msg:literal, $($arg:tt)*) => {
+        #[cfg(feature = "log")]
+        {
+            trace!($decoder, '+', $msg, $($arg)*);
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!($msg, $($arg)*);
     }
 }
+
+/// Trace entering a nested message
+macro_rules! enter {
+    ($decoder:expr, $tag:expr) => {
+        #[cfg(feature = "log")]
+        {
+            trace!($decoder, '>', "[{}]: enter msg", $tag);
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("[{}]: enter msg (depth={=usize})", $tag, $decoder.depth());
+    };
+}
+
+/// Trace exiting a nested message
+macro_rules! exit {
+    ($decoder:expr, $tag:expr) => {
+        #[cfg(feature = "log")]
+        {
+            trace!($decoder, '<', "[{}]: exit msg", $tag);
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::trace!("[{}]: exit msg (depth={=usize})", $tag, $decoder.depth());
+    };
+}
+
+/// Trace an error which occurred while decoding
+macro_rules! fail {
+    ($decoder:expr, $error:expr) => {
+        #[cfg(feature = "log")]
+        {
+            trace!(
+                $decoder,
+                '!',
+                "error: {} (position={:?})",
+                $error.kind(),
+                $error.position()
+            );
+        }
+
+        #[cfg(feature = "defmt")]
+        defmt::error!("error: {} (position={})", $error.kind(), $error.position());
+    };
+}
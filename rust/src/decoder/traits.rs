@@ -5,8 +5,7 @@
 //! They're intened to be impl'd by `veriform::decoder::Decoder`.
 
 use super::sequence;
-use crate::{field::Tag, Error};
-use digest::Digest;
+use crate::{field::Tag, verihash::VerihashDigest, Error};
 
 /// Try to decode a field to a value of the given type.
 ///
@@ -16,6 +15,18 @@ pub trait Decode<T> {
     fn decode(&mut self, tag: Tag, input: &mut &[u8]) -> Result<T, Error>;
 }
 
+/// Try to decode a field into an existing value of the given type, e.g. to
+/// avoid moving large messages.
+///
+/// This trait is intended to be impl'd by the `Decoder` type.
+pub trait DecodeInto<T> {
+    /// Try to decode a value of type `T` into `out`.
+    ///
+    /// If an error occurs, `out` may have been partially overwritten and its
+    /// contents are unspecified.
+    fn decode_into(&mut self, tag: Tag, input: &mut &[u8], out: &mut T) -> Result<(), Error>;
+}
+
 /// Try to decode a field to a reference of the given type.
 ///
 /// This trait is intended to be impl'd by the `Decoder` type.
@@ -29,12 +40,41 @@ pub trait DecodeRef<T: ?Sized> {
 /// This trait is intended to be impl'd by the `Decoder` type.
 pub trait DecodeSeq<T, D>
 where
-    D: Digest,
+    D: VerihashDigest,
 {
-    /// Try to decode a sequence of values of type `T`
+    /// Try to decode a sequence of values of type `T`.
+    ///
+    /// Returns an [`error::Kind::ExpectedSequence`] error if the field with
+    /// the given tag is present but isn't a sequence.
+    ///
+    /// [`error::Kind::ExpectedSequence`]: crate::error::Kind::ExpectedSequence
     fn decode_seq<'a, 'b>(
         &'a mut self,
         tag: Tag,
         input: &mut &'b [u8],
     ) -> Result<sequence::Iter<'a, 'b, T, D>, Error>;
 }
+
+/// Decode a sequence of values to a [`sequence::IntoIter`], which holds a
+/// copy of the sequence body and a decoder of its own and yields owned
+/// values, so neither the input buffer nor this decoder need outlive it.
+///
+/// This trait is intended to be impl'd by the `Decoder` type.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub trait DecodeSeqOwned<T, D>
+where
+    D: VerihashDigest,
+{
+    /// Try to decode a sequence of values of type `T`.
+    ///
+    /// Returns an [`error::Kind::ExpectedSequence`] error if the field with
+    /// the given tag is present but isn't a sequence.
+    ///
+    /// [`error::Kind::ExpectedSequence`]: crate::error::Kind::ExpectedSequence
+    fn decode_seq_owned(
+        &mut self,
+        tag: Tag,
+        input: &mut &[u8],
+    ) -> Result<sequence::IntoIter<T, D>, Error>;
+}
@@ -0,0 +1,33 @@
+//! Handling of fields which aren't part of a message's schema
+
+/// Policy for handling fields whose tags aren't recognized when decoding a
+/// message, i.e. fields which aren't handled by any field of the message
+/// type being decoded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum UnknownFieldPolicy {
+    /// Return an error when an unknown field is encountered (default),
+    /// including any data remaining after the known fields of a message
+    Error,
+
+    /// Skip unknown fields, including them in the message digest.
+    ///
+    /// Unknown fields which are marked critical still result in an error.
+    Skip,
+
+    /// Skip unknown fields, storing their raw encoded bytes so they can be
+    /// retrieved with [`Decoder::unknown_fields`].
+    ///
+    /// [`Decoder::unknown_fields`]: crate::decoder::Decoder::unknown_fields
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    Collect,
+}
+
+// `#[default]` on enum variants requires a newer Rust than our MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for UnknownFieldPolicy {
+    fn default() -> Self {
+        UnknownFieldPolicy::Error
+    }
+}
@@ -0,0 +1,198 @@
+//! Structural validation of messages without decoding their values
+
+use super::{sequence::Cursor, DecodeOptions, Decoder, Field, Presence, Schema};
+use crate::{
+    error::{self, Error},
+    field::{Tag, WireType},
+    verihash::{DefaultParams, VerihashDigest},
+};
+use digest::{
+    generic_array::{typenum::U0, GenericArray},
+    FixedOutputDirty, Reset, Update,
+};
+
+/// Check that the given input is a well-formed Veriform message, without
+/// decoding it into a [`Message`] type.
+///
+/// Every field is decoded, descending into nested messages (including the
+/// elements of sequences of messages), applying the same structural and
+/// canonicalization checks as decoding: field headers and lengths must be
+/// complete, fields must be in order, `vint64` values must be minimally
+/// encoded, strings must be valid normalized UTF-8, and nesting must be
+/// within the limits set in the given [`DecodeOptions`].
+///
+/// Values aren't otherwise interpreted, and digests aren't computed (the
+/// `hashing` option is ignored), so this is cheaper than decoding a message.
+/// Unknown fields can't be distinguished without a schema, so the
+/// `unknown_field_policy` option has no effect.
+///
+/// [`Message`]: crate::Message
+pub fn validate(input: &[u8], options: DecodeOptions) -> Result<(), Error> {
+    let mut decoder = Decoder::<NoDigest>::with_options(options.hashing(false));
+    validate_fields(&mut decoder, input, None)
+}
+
+/// Check that the given input is a well-formed Veriform message which
+/// conforms to the given [`Schema`], without decoding it into a [`Message`]
+/// type.
+///
+/// In addition to the checks made by [`validate`] (using the default
+/// [`DecodeOptions`]), every field must be described by the schema, with
+/// the wire type it specifies, and every required field must be present.
+/// Nested messages (including the elements of sequences of messages) are
+/// checked against the schemas of their fields, if they have one.
+///
+/// Fields with the wrong wire type are [`error::Kind::FieldWireType`]
+/// errors, missing required fields are [`error::Kind::MissingField`]
+/// errors, and fields which aren't in the schema are
+/// [`error::Kind::UnknownField`] errors.
+///
+/// [`Message`]: crate::Message
+pub fn validate_against(input: &[u8], schema: &Schema<'_>) -> Result<(), Error> {
+    let mut decoder = Decoder::<NoDigest>::with_options(DecodeOptions::new().hashing(false));
+    validate_fields(&mut decoder, input, Some(schema))
+}
+
+/// Hash function for the decoders used for validation, which never compute
+/// digests, so validation doesn't depend on any hash function being enabled
+#[derive(Clone, Debug, Default)]
+struct NoDigest;
+
+impl VerihashDigest for NoDigest {
+    type Params = DefaultParams;
+}
+
+impl Update for NoDigest {
+    fn update(&mut self, _data: impl AsRef<[u8]>) {}
+}
+
+impl FixedOutputDirty for NoDigest {
+    type OutputSize = U0;
+
+    fn finalize_into_dirty(&mut self, _out: &mut GenericArray<u8, U0>) {}
+}
+
+impl Reset for NoDigest {
+    fn reset(&mut self) {}
+}
+
+/// Validate each of the fields of a message, checking them against the
+/// given schema (if any)
+fn validate_fields<D>(
+    decoder: &mut Decoder<D>,
+    mut input: &[u8],
+    schema: Option<&Schema<'_>>,
+) -> Result<(), Error>
+where
+    D: VerihashDigest,
+{
+    let mut last_tag = None;
+
+    while let Some(field) = decoder.decode_field(&mut input)? {
+        let value_schema = match schema {
+            Some(schema) => {
+                let tag = field.tag();
+                check_required(schema, last_tag, Some(tag))?;
+                last_tag = Some(tag);
+
+                let field_schema = schema.field(tag).ok_or(error::Kind::UnknownField { tag })?;
+
+                if !field_schema.matches(field.wire_type()) {
+                    return Err(error::Kind::FieldWireType {
+                        tag,
+                        actual: field.wire_type(),
+                        wanted: field_schema.wire_type,
+                    }
+                    .into());
+                }
+
+                field_schema.schema.as_ref()
+            }
+            None => None,
+        };
+
+        validate_value(field, &mut input, value_schema)?;
+    }
+
+    if let Some(schema) = schema {
+        check_required(schema, last_tag, None)?;
+    }
+
+    Ok(())
+}
+
+/// Ensure the required fields of the schema whose tags fall between `after`
+/// and `before` (exclusive, and unbounded if `None`) are present, i.e. that
+/// there aren't any, as the fields decoded on either side are adjacent
+fn check_required(
+    schema: &Schema<'_>,
+    after: Option<Tag>,
+    before: Option<Tag>,
+) -> Result<(), Error> {
+    for field in schema.fields() {
+        let skipped = !matches!(after, Some(tag) if field.tag <= tag)
+            && !matches!(before, Some(tag) if field.tag >= tag);
+
+        if skipped && field.presence == Presence::Required {
+            return Err(error::Kind::MissingField { tag: field.tag }.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the value of a field, checking nested messages against the
+/// given schema (if any)
+fn validate_value<D>(
+    field: Field<'_, D>,
+    input: &mut &[u8],
+    schema: Option<&Schema<'_>>,
+) -> Result<(), Error>
+where
+    D: VerihashDigest,
+{
+    match field.wire_type() {
+        WireType::Message => field.message_with(input, |decoder, body| {
+            validate_fields(decoder, body, schema)
+        }),
+        WireType::Sequence => {
+            let tag = field.tag();
+            let (wire_type, decoder, seq_bytes) = field.any_sequence(input)?;
+            let mut cursor = Cursor::new(decoder, tag);
+
+            while let Some(result) = cursor.next(seq_bytes, |cursor, input| {
+                validate_element(cursor, wire_type, input, schema)
+            }) {
+                result?;
+            }
+
+            Ok(())
+        }
+        WireType::String => field.string(input).map(|_| ()),
+        _ => field.skip(input),
+    }
+}
+
+/// Validate an element of a sequence, checking message elements against
+/// the given schema (if any)
+fn validate_element<D>(
+    cursor: &mut Cursor<&mut Decoder<D>>,
+    wire_type: WireType,
+    input: &mut &[u8],
+    schema: Option<&Schema<'_>>,
+) -> Result<(), Error>
+where
+    D: VerihashDigest,
+{
+    match wire_type {
+        WireType::False | WireType::True => cursor.decode_bool(input).map(|_| ()),
+        WireType::UInt64 => cursor.decode_uint64(input).map(|_| ()),
+        WireType::SInt64 => cursor.decode_sint64(input).map(|_| ()),
+        WireType::Bytes => cursor.decode_bytes(input).map(|_| ()),
+        WireType::String => cursor.decode_string(input).map(|_| ()),
+        WireType::Message => cursor.decode_message_with(input, |decoder, body| {
+            validate_fields(decoder, body, schema)
+        }),
+        WireType::Sequence => Err(error::Kind::NestedSequence.into()),
+    }
+}
@@ -4,6 +4,9 @@ pub(crate) use vint64::signed::zigzag;
 
 use crate::error::{self, Error};
 
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
 /// Decoder for `vint64` values
 #[derive(Clone, Debug, Default)]
 pub struct Decoder {
@@ -23,35 +26,62 @@ impl Decoder {
         Self::default()
     }
 
-    /// Decode a `vint64` from the incoming data
+    /// Has none of the `vint64` being decoded been consumed yet?
+    pub fn is_empty(&self) -> bool {
+        self.length.is_none()
+    }
+
+    /// Get the number of bytes of a partially decoded `vint64` which have
+    /// been buffered from previous input
+    pub fn buffered(&self) -> usize {
+        self.pos
+    }
+
+    /// Decode a `vint64` from the incoming data.
+    ///
+    /// Values which aren't encoded in their shortest possible form are
+    /// rejected with an [`error::Kind::NonCanonicalVarint`] error.
     pub fn decode(&mut self, input: &mut &[u8]) -> Result<Option<u64>, Error> {
         if let Some(length) = self.length {
-            self.fill_buffer(length, input);
+            self.fill_buffer(length, input)?;
             return self.maybe_decode(length);
         }
 
         if let Some(&first_byte) = input.first() {
-            self.length = Some(vint64::decoded_len(first_byte));
+            let length = vint64::decoded_len(first_byte);
+
+            // Fast path: decode directly from the input if it's complete
+            if input.len() >= length {
+                return vint64::decode(input).map(Some).map_err(Error::from);
+            }
+
+            self.length = Some(length);
             self.decode(input)
         } else {
             Ok(None)
         }
     }
 
-    /// Fill the internal buffer with data, returning a [`FieldHeader`] if we're complete
-    fn fill_buffer(&mut self, length: usize, input: &mut &[u8]) {
-        let remaining = length.checked_sub(self.pos).unwrap();
-
-        if input.len() < remaining {
-            let new_pos = self.pos.checked_add(input.len()).unwrap();
-            self.buffer[self.pos..new_pos].copy_from_slice(*input);
-            self.pos = new_pos;
-            *input = &[];
+    /// Fill the internal buffer with data from the input
+    fn fill_buffer(&mut self, length: usize, input: &mut &[u8]) -> Result<(), Error> {
+        let remaining = length.checked_sub(self.pos).ok_or(error::Kind::VInt64)?;
+        let chunk_size = if input.len() < remaining {
+            input.len()
         } else {
-            self.buffer[self.pos..length].copy_from_slice(&input[..remaining]);
-            self.pos += remaining;
-            *input = &input[remaining..];
-        }
+            remaining
+        };
+
+        let new_pos = self.pos + chunk_size;
+        let (chunk, rest) = input.split_at(chunk_size);
+
+        self.buffer
+            .get_mut(self.pos..new_pos)
+            .ok_or(error::Kind::VInt64)?
+            .copy_from_slice(chunk);
+
+        self.pos = new_pos;
+        *input = rest;
+        Ok(())
     }
 
     /// Attempt to decode the internal buffer if we've read its full contents
@@ -60,9 +90,57 @@ impl Decoder {
             return Ok(None);
         }
 
-        let mut buffer = &self.buffer[..length];
-        vint64::decode(&mut buffer)
-            .map(Some)
-            .map_err(|_| error::Kind::VInt64.into())
+        let mut buffer = self.buffer.get(..length).ok_or(error::Kind::VInt64)?;
+        vint64::decode(&mut buffer).map(Some).map_err(Error::from)
+    }
+}
+
+/// Attach the offset at which a `vint64` began to an
+/// [`error::Kind::NonCanonicalVarint`] error which doesn't have a position
+pub(crate) fn at_offset(error: Error, offset: usize) -> Error {
+    if error.kind() == error::Kind::NonCanonicalVarint && error.position().is_none() {
+        error::Kind::NonCanonicalVarint.position(offset)
+    } else {
+        error
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Zeroize for Decoder {
+    fn zeroize(&mut self) {
+        self.length = None;
+        self.pos.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl ZeroizeOnDrop for Decoder {}
+
+#[cfg(all(test, feature = "zeroize"))]
+mod tests {
+    use super::Decoder;
+    use zeroize::Zeroize;
+
+    #[test]
+    fn zeroize_partial_value() {
+        let input = [0x80, 0x01, 0x02];
+        let mut input_ref = &input[..];
+        let mut decoder = Decoder::new();
+
+        assert_eq!(decoder.decode(&mut input_ref).unwrap(), None);
+        assert_ne!(decoder.buffer, [0u8; 9]);
+
+        decoder.zeroize();
+        assert_eq!(decoder.length, None);
+        assert_eq!(decoder.pos, 0);
+        assert_eq!(decoder.buffer, [0u8; 9]);
     }
 }
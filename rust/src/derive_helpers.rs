@@ -5,10 +5,10 @@ use crate::{
     decoder::{DecodeSeq, Decoder},
     encoder::Encoder,
     error::{self, Error},
-    field::{self, Tag, WireType},
-    message::{Element, Message},
+    field::{self, Tag},
+    message::Message,
+    verihash::VerihashDigest,
 };
-use digest::Digest;
 use heapless::ArrayLength;
 
 /// Make sure input has been consumed
@@ -30,21 +30,31 @@ pub fn decode_message_seq<T, N, D>(
 where
     T: Message,
     N: ArrayLength<T>,
-    D: Digest,
+    D: VerihashDigest,
 {
     let mut result = heapless::Vec::new();
-    let seq_iter: sequence::Iter<'_, '_, T, D> = decoder.decode_seq(tag, input)?;
-
-    for elem in seq_iter {
-        result.push(elem?).map_err(|_| error::Kind::Decode {
-            element: Element::Value,
-            wire_type: WireType::Sequence,
-        })?
-    }
-
+    decode_message_seq_into(decoder, tag, input, &mut result)?;
     Ok(result)
 }
 
+/// Decode a sequence of messages into an existing `heapless::Vec`,
+/// replacing its contents
+pub fn decode_message_seq_into<T, N, D>(
+    decoder: &mut Decoder<D>,
+    tag: Tag,
+    input: &mut &[u8],
+    out: &mut heapless::Vec<T, N>,
+) -> Result<(), Error>
+where
+    T: Message,
+    N: ArrayLength<T>,
+    D: VerihashDigest,
+{
+    *out = heapless::Vec::new();
+    let seq_iter: sequence::Iter<'_, '_, T, D> = decoder.decode_seq(tag, input)?;
+    seq_iter.extend_into_heapless(out)
+}
+
 /// Encode a sequence of messages
 pub fn encode_message_seq<T>(
     encoder: &mut Encoder<'_>,
@@ -1,5 +1,15 @@
 //! Veriform encoder
 
+mod event;
+#[cfg(feature = "std")]
+mod stream;
+
+pub use self::event::EventEncoder;
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use self::stream::StreamEncoder;
+
 use crate::{
     error::{self, Error},
     field::{Header, Tag, WireType},
@@ -7,19 +17,56 @@ use crate::{
     string,
 };
 
-/// Veriform encoder
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Veriform encoder.
+///
+/// Fields must be written in ascending tag order, as required for the
+/// encoded message to be canonical. Writing a field with a tag less than or
+/// equal to that of the previous field returns an [`error::Kind::Order`]
+/// error.
 pub struct Encoder<'a> {
     /// Mutable buffer containing the message
     buffer: &'a mut [u8],
 
     /// Running total length of the message
     length: usize,
+
+    /// Tag of the last field written
+    last_tag: Option<Tag>,
+
+    /// Tags and offsets of the fields written so far (sorting mode only)
+    #[cfg(feature = "alloc")]
+    fields: Option<Vec<(Tag, usize)>>,
 }
 
 impl<'a> Encoder<'a> {
     /// Create a new [`Encoder`] which writes into the provided buffer
     pub fn new(buffer: &'a mut [u8]) -> Self {
-        Self { buffer, length: 0 }
+        Self {
+            buffer,
+            length: 0,
+            last_tag: None,
+            #[cfg(feature = "alloc")]
+            fields: None,
+        }
+    }
+
+    /// Create a new [`Encoder`] which accepts fields in any order, sorting
+    /// them by tag when [`Encoder::finish`] is called.
+    ///
+    /// Writing more than one field with the same tag returns an
+    /// [`error::Kind::Order`] error.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn new_sorted(buffer: &'a mut [u8]) -> Self {
+        Self {
+            buffer,
+            length: 0,
+            last_tag: None,
+            fields: Some(Vec::new()),
+        }
     }
 
     /// Write a field containing an unsigned 64-bit integer
@@ -52,9 +99,13 @@ impl<'a> Encoder<'a> {
         }
 
         let new_length = self.length.checked_add(encoded_len).unwrap();
-        message.encode(&mut self.buffer[self.length..new_length])?;
-        self.length = new_length;
+        let written = message.encode(&mut self.buffer[self.length..new_length])?;
+
+        if written.len() != encoded_len {
+            return Err(error::Kind::Length.into());
+        }
 
+        self.length = new_length;
         Ok(())
     }
 
@@ -85,12 +136,20 @@ impl<'a> Encoder<'a> {
             }
 
             let new_length = self.length.checked_add(encoded_len).unwrap();
-            message.encode(&mut self.buffer[self.length..new_length])?;
+            let written = message.encode(&mut self.buffer[self.length..new_length])?;
+
+            if written.len() != encoded_len {
+                return Err(error::Kind::Length.into());
+            }
+
             self.length = new_length;
         }
 
         // Ensure we wrote the expected number of bytes
-        debug_assert_eq!(length, self.length.checked_sub(orig_length).unwrap());
+        if self.length.checked_sub(orig_length) != Some(length) {
+            return Err(error::Kind::Length.into());
+        }
+
         Ok(())
     }
 
@@ -110,14 +169,49 @@ impl<'a> Encoder<'a> {
     /// Finish constructing a message, returning a slice of the buffer
     /// containing the serialized message
     pub fn finish(self) -> &'a [u8] {
-        &self.buffer[..self.length]
+        let buffer = &mut self.buffer[..self.length];
+
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(fields) = &self.fields {
+                sort_fields(buffer, fields);
+            }
+        }
+
+        buffer
     }
 
     /// Write a field header to the underlying buffer
     fn write_header(&mut self, tag: Tag, critical: bool, wire_type: WireType) -> Result<(), Error> {
+        self.check_order(tag)?;
         self.write(Header::new(tag, critical, wire_type).encode())
     }
 
+    /// Ensure fields are written in canonical order, or record the offset of
+    /// the field to be sorted later when in sorting mode
+    fn check_order(&mut self, tag: Tag) -> Result<(), Error> {
+        #[cfg(feature = "alloc")]
+        {
+            if let Some(fields) = &mut self.fields {
+                if fields.iter().any(|&(t, _)| t == tag) {
+                    return Err(error::Kind::Order { tag }.into());
+                }
+
+                fields.push((tag, self.length));
+                return Ok(());
+            }
+        }
+
+        if let Some(last_tag) = self.last_tag {
+            if tag <= last_tag {
+                return Err(error::Kind::Order { tag }.into());
+            }
+        }
+
+        self.last_tag = Some(tag);
+        Ok(())
+    }
+
     /// Write a dynamically sized value to the underlying buffer
     fn write_value(&mut self, bytes: &[u8]) -> Result<(), Error> {
         self.write(vint64::encode(bytes.len() as u64))?;
@@ -143,10 +237,36 @@ impl<'a> Encoder<'a> {
     }
 }
 
+/// Reorder the encoded fields in the given buffer by tag, where `fields`
+/// contains the tags and offsets of fields in the order they were written
+#[cfg(feature = "alloc")]
+fn sort_fields(buffer: &mut [u8], fields: &[(Tag, usize)]) {
+    let mut ranges: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, &(tag, start))| {
+            let end = fields.get(i + 1).map_or(buffer.len(), |&(_, end)| end);
+            (tag, start..end)
+        })
+        .collect();
+
+    ranges.sort_by_key(|(tag, _)| *tag);
+
+    let mut sorted = Vec::with_capacity(buffer.len());
+    for (_, range) in ranges {
+        sorted.extend_from_slice(&buffer[range]);
+    }
+
+    buffer.copy_from_slice(&sorted);
+
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(sorted.as_mut_slice());
+}
+
 #[cfg(all(test, feature = "sha2"))]
 mod tests {
     use super::Encoder;
-    use crate::{decoder::Decodable, field::WireType};
+    use crate::{decoder::Decodable, error, field::WireType, Error, Message};
 
     // TODO(tarcieri): rewrite tests with `crate::Decoder`
     type Decoder = crate::decoder::message::Decoder<sha2::Sha256>;
@@ -198,4 +318,75 @@ mod tests {
 
         assert!(message.is_empty());
     }
+
+    #[test]
+    fn encode_out_of_order() {
+        let mut buffer = [0u8; 1024];
+        let mut encoder = Encoder::new(&mut buffer);
+
+        encoder.uint64(2, false, 42).unwrap();
+        let error = encoder.sint64(1, false, -1).unwrap_err();
+        assert_eq!(error.kind(), error::Kind::Order { tag: 1 });
+
+        let error = encoder.sint64(2, false, -1).unwrap_err();
+        assert_eq!(error.kind(), error::Kind::Order { tag: 2 });
+    }
+
+    #[test]
+    fn encode_message_length_mismatch() {
+        let mut buffer = [0u8; 1024];
+        let mut encoder = Encoder::new(&mut buffer);
+
+        let error = encoder.message(1, false, &ShortMessage).unwrap_err();
+        assert_eq!(error.kind(), error::Kind::Length);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn encode_sorted() {
+        let mut expected_buffer = [0u8; 1024];
+        let mut encoder = Encoder::new(&mut expected_buffer);
+        encoder.uint64(1, false, 42).unwrap();
+        encoder.sint64(2, false, -1).unwrap();
+        encoder.bytes(3, false, EXAMPLE_BYTES).unwrap();
+        encoder.string(4, false, EXAMPLE_STRING).unwrap();
+        let expected = encoder.finish();
+
+        let mut buffer = [0u8; 1024];
+        let mut encoder = Encoder::new_sorted(&mut buffer);
+        encoder.string(4, false, EXAMPLE_STRING).unwrap();
+        encoder.uint64(1, false, 42).unwrap();
+        encoder.bytes(3, false, EXAMPLE_BYTES).unwrap();
+        encoder.sint64(2, false, -1).unwrap();
+
+        let error = encoder.uint64(3, false, 0).unwrap_err();
+        assert_eq!(error.kind(), error::Kind::Order { tag: 3 });
+
+        assert_eq!(encoder.finish(), expected);
+    }
+
+    /// Message which encodes to fewer bytes than its reported length
+    struct ShortMessage;
+
+    impl Message for ShortMessage {
+        fn decode<D>(
+            _decoder: &mut crate::decoder::Decoder<D>,
+            _input: &[u8],
+        ) -> Result<Self, Error>
+        where
+            D: crate::verihash::VerihashDigest,
+        {
+            Ok(ShortMessage)
+        }
+
+        fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+            let mut encoder = Encoder::new(buffer);
+            encoder.uint64(0, false, 1)?;
+            Ok(encoder.finish())
+        }
+
+        fn encoded_len(&self) -> usize {
+            4
+        }
+    }
 }
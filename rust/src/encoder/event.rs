@@ -0,0 +1,330 @@
+//! Re-encoding messages from decoder events
+
+use super::Encoder;
+use crate::{
+    decoder::Event,
+    error::{self, Error},
+    field::{Tag, WireType},
+    message::Element,
+};
+use heapless::consts::U16;
+
+/// Encoder which serializes the [`Event`]s emitted by the decoder (e.g. by
+/// [`decoder::Stream`]), allowing a message to be filtered or rewritten and
+/// then re-encoded.
+///
+/// Events are validated as they're written: field headers must be written
+/// in ascending tag order, values must have the wire type announced by the
+/// preceding header or sequence header, value chunks must add up to their
+/// length delimiters, and the bodies of nested messages and sequences must
+/// have exactly their declared lengths. Any inconsistency is an error.
+///
+/// Fields of nested messages are written directly after the length
+/// delimiter of the message, as emitted by [`decoder::Stream`].
+///
+/// [`decoder::Stream`]: crate::decoder::Stream
+pub struct EventEncoder<'a> {
+    /// Encoder for the underlying buffer
+    encoder: Encoder<'a>,
+
+    /// Stack of messages and sequences being encoded (max nesting depth 16)
+    stack: heapless::Vec<Frame, U16>,
+
+    /// Element of the innermost message or sequence expected next
+    state: State,
+}
+
+impl<'a> EventEncoder<'a> {
+    /// Create a new [`EventEncoder`] which writes into the provided buffer
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        let root = Frame {
+            sequence: None,
+            last_tag: None,
+            remaining: None,
+        };
+
+        // Never fails: the stack is empty
+        let mut stack = heapless::Vec::new();
+        let _ = stack.push(root);
+
+        Self {
+            encoder: Encoder::new(buffer),
+            stack,
+            state: State::Field,
+        }
+    }
+
+    /// Encode the given event
+    pub fn encode(&mut self, event: &Event<'_>) -> Result<(), Error> {
+        match *event {
+            Event::FieldHeader(header) => {
+                if self.state != State::Field || self.frame().sequence.is_some() {
+                    return Err(self.unexpected(Element::Tag));
+                }
+
+                if let Some(last_tag) = self.frame().last_tag {
+                    if header.tag <= last_tag {
+                        return Err(error::Kind::Order { tag: header.tag }.into());
+                    }
+                }
+
+                self.write(header.encode())?;
+                self.frame_mut().last_tag = Some(header.tag);
+
+                // Booleans are encoded entirely in the wire type of the header
+                if header.wire_type != WireType::False && header.wire_type != WireType::True {
+                    self.state = State::Value(header.wire_type);
+                }
+            }
+            Event::Bool(value) => {
+                let wire_type = if value {
+                    WireType::True
+                } else {
+                    WireType::False
+                };
+
+                self.expect_sequence_element(wire_type)?;
+                self.write([value as u8])?;
+            }
+            Event::UInt64(value) => {
+                self.expect_value(WireType::UInt64)?;
+                self.write(vint64::encode(value))?;
+                self.state = State::Field;
+            }
+            Event::SInt64(value) => {
+                self.expect_value(WireType::SInt64)?;
+                self.write(vint64::signed::encode(value))?;
+                self.state = State::Field;
+            }
+            Event::LengthDelimiter { wire_type, length } => {
+                if wire_type == WireType::Sequence || !wire_type.is_dynamically_sized() {
+                    return Err(error::Kind::InvalidWireType.into());
+                }
+
+                self.expect_value(wire_type)?;
+                self.write(vint64::encode(length as u64))?;
+
+                if wire_type == WireType::Message {
+                    self.push(None, length)?;
+                } else if length > 0 {
+                    self.state = State::Body {
+                        wire_type,
+                        remaining: length,
+                    };
+                } else {
+                    self.state = State::Field;
+                }
+            }
+            Event::ValueChunk {
+                wire_type,
+                bytes,
+                remaining,
+            } => {
+                let expected = match self.state {
+                    State::Body {
+                        wire_type: expected_type,
+                        remaining: expected,
+                    } if wire_type == expected_type => expected,
+                    _ => return Err(self.unexpected(Element::Value)),
+                };
+
+                if expected.checked_sub(bytes.len()) != Some(remaining) {
+                    return Err(error::Kind::Length.into());
+                }
+
+                self.write(bytes)?;
+
+                self.state = if remaining > 0 {
+                    State::Body {
+                        wire_type,
+                        remaining,
+                    }
+                } else {
+                    State::Field
+                };
+            }
+            Event::SequenceHeader { wire_type, length } => {
+                if self.state != State::Value(WireType::Sequence) {
+                    return Err(self.unexpected(Element::SequenceHeader));
+                }
+
+                if wire_type == WireType::Sequence {
+                    return Err(error::Kind::NestedSequence.into());
+                }
+
+                self.write(vint64::encode((length as u64) << 4 | wire_type as u64))?;
+                self.push(Some(wire_type), length)?;
+            }
+        }
+
+        self.pop_completed();
+        Ok(())
+    }
+
+    /// Finish encoding a message, returning a slice of the buffer containing
+    /// the serialized message.
+    ///
+    /// Returns an error if the last field, or any message or sequence
+    /// containing it, is incomplete.
+    pub fn finish(self) -> Result<&'a [u8], Error> {
+        match self.state {
+            State::Field if self.stack.len() == 1 => Ok(self.encoder.finish()),
+            State::Body {
+                wire_type,
+                remaining,
+            } => Err(error::Kind::Truncated {
+                remaining,
+                wire_type,
+            }
+            .into()),
+            _ => {
+                let frame = self.frame();
+                Err(error::Kind::Truncated {
+                    remaining: frame.remaining.unwrap_or(0),
+                    wire_type: match self.state {
+                        State::Value(wire_type) => wire_type,
+                        _ => frame.wire_type(),
+                    },
+                }
+                .into())
+            }
+        }
+    }
+
+    /// Ensure the next value has the given wire type
+    fn expect_value(&self, wire_type: WireType) -> Result<(), Error> {
+        match self.state {
+            State::Value(expected) if expected == wire_type => Ok(()),
+            State::Value(expected) => Err(error::Kind::UnexpectedWireType {
+                actual: wire_type,
+                wanted: expected,
+            }
+            .into()),
+            State::Field => self.expect_sequence_element(wire_type),
+            State::Body { .. } => Err(self.unexpected(Element::Value)),
+        }
+    }
+
+    /// Ensure the next sequence element has the given wire type
+    fn expect_sequence_element(&self, wire_type: WireType) -> Result<(), Error> {
+        let expected = match self.frame().sequence {
+            Some(expected) if self.state == State::Field => expected,
+            _ => return Err(self.unexpected(Element::Value)),
+        };
+
+        let is_bool = |wire_type| wire_type == WireType::False || wire_type == WireType::True;
+
+        if expected == wire_type || (is_bool(expected) && is_bool(wire_type)) {
+            Ok(())
+        } else {
+            Err(error::Kind::UnexpectedWireType {
+                actual: wire_type,
+                wanted: expected,
+            }
+            .into())
+        }
+    }
+
+    /// Create an error for an event which isn't allowed in the current state
+    fn unexpected(&self, element: Element) -> Error {
+        let wire_type = match self.state {
+            State::Field => self.frame().wire_type(),
+            State::Value(wire_type) | State::Body { wire_type, .. } => wire_type,
+        };
+
+        error::Kind::Decode { element, wire_type }.into()
+    }
+
+    /// Begin encoding the body of a nested message, or of a sequence with
+    /// the given element wire type
+    fn push(&mut self, sequence: Option<WireType>, length: usize) -> Result<(), Error> {
+        let frame = Frame {
+            sequence,
+            last_tag: None,
+            remaining: Some(length),
+        };
+
+        self.stack
+            .push(frame)
+            .map_err(|_| error::Kind::NestingDepth)?;
+
+        self.state = State::Field;
+        Ok(())
+    }
+
+    /// Pop any nested messages and sequences whose bodies are complete
+    fn pop_completed(&mut self) {
+        while self.state == State::Field
+            && self.stack.len() > 1
+            && self.frame().remaining == Some(0)
+        {
+            self.stack.pop();
+        }
+    }
+
+    /// Write the given bytes, accounting for them in the bodies of all of
+    /// the messages and sequences being encoded
+    fn write(&mut self, bytes: impl AsRef<[u8]>) -> Result<(), Error> {
+        let bytes = bytes.as_ref();
+
+        for frame in self.stack.iter_mut() {
+            if let Some(remaining) = &mut frame.remaining {
+                *remaining = remaining
+                    .checked_sub(bytes.len())
+                    .ok_or(error::Kind::Length)?;
+            }
+        }
+
+        self.encoder.write(bytes)
+    }
+
+    /// Get the innermost message or sequence being encoded
+    fn frame(&self) -> &Frame {
+        // The root message is never popped
+        self.stack.last().unwrap()
+    }
+
+    /// Get the innermost message or sequence being encoded (mutable)
+    fn frame_mut(&mut self) -> &mut Frame {
+        // The root message is never popped
+        self.stack.last_mut().unwrap()
+    }
+}
+
+/// Message or sequence being encoded
+struct Frame {
+    /// Wire type of the elements if this frame is a sequence
+    sequence: Option<WireType>,
+
+    /// Tag of the last field written (messages only)
+    last_tag: Option<Tag>,
+
+    /// Remaining bytes in the body (or `None` for the outermost message)
+    remaining: Option<usize>,
+}
+
+impl Frame {
+    /// Wire type of the values in this frame
+    fn wire_type(&self) -> WireType {
+        self.sequence.unwrap_or(WireType::Message)
+    }
+}
+
+/// Next element expected in the innermost message or sequence
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State {
+    /// Field header (in a message) or element (in a sequence)
+    Field,
+
+    /// Value of a field with the given wire type
+    Value(WireType),
+
+    /// Chunks of a dynamically sized value
+    Body {
+        /// Wire type of the value
+        wire_type: WireType,
+
+        /// Remaining bytes in the value
+        remaining: usize,
+    },
+}
@@ -0,0 +1,132 @@
+//! Streaming encoder which writes to an `io::Write` sink
+
+use crate::{
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+    string,
+};
+use alloc::vec::Vec;
+use std::io;
+
+/// Encoder which writes fields directly to an [`io::Write`] sink as they're
+/// encoded, rather than into a buffer containing the entire message, e.g. to
+/// produce large messages without holding them in memory.
+///
+/// As with [`Encoder`], fields must be written in ascending tag order, and
+/// writing a field with a tag less than or equal to that of the previous
+/// field returns an [`error::Kind::Order`] error. Errors returned by the
+/// writer are mapped to [`error::Kind::Io`].
+///
+/// # Nested messages
+///
+/// The length delimiter of a nested message precedes its body, so the body
+/// must be complete before the delimiter can be written. Rather than
+/// encoding nested messages twice (once to compute their length), the body
+/// of each nested message written with [`StreamEncoder::write_message`] is
+/// buffered in memory, then written to the sink after its length. Only the
+/// fields of the outermost message are streamed, so large values should be
+/// placed there rather than in nested messages.
+///
+/// [`Encoder`]: crate::Encoder
+pub struct StreamEncoder<W: io::Write> {
+    /// Sink the encoded message is written to
+    writer: W,
+
+    /// Tag of the last field written
+    last_tag: Option<Tag>,
+}
+
+impl<W> StreamEncoder<W>
+where
+    W: io::Write,
+{
+    /// Create a new [`StreamEncoder`] which writes to the given sink
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            last_tag: None,
+        }
+    }
+
+    /// Write a field containing an unsigned 64-bit integer
+    pub fn write_uint64(&mut self, tag: Tag, critical: bool, value: u64) -> Result<(), Error> {
+        self.write_header(tag, critical, WireType::UInt64)?;
+        self.write(vint64::encode(value))
+    }
+
+    /// Write a field containing a signed 64-bit integer
+    pub fn write_sint64(&mut self, tag: Tag, critical: bool, value: i64) -> Result<(), Error> {
+        self.write_header(tag, critical, WireType::SInt64)?;
+        self.write(vint64::signed::encode(value))
+    }
+
+    /// Write a field containing bytes
+    pub fn write_bytes(&mut self, tag: Tag, critical: bool, bytes: &[u8]) -> Result<(), Error> {
+        self.write_header(tag, critical, WireType::Bytes)?;
+        self.write_value(bytes)
+    }
+
+    /// Write a field containing a string
+    pub fn write_string(&mut self, tag: Tag, critical: bool, string: &str) -> Result<(), Error> {
+        string::ensure_canonical(string)?;
+        self.write_header(tag, critical, WireType::String)?;
+        self.write_value(string.as_bytes())
+    }
+
+    /// Write a nested message field whose fields are written by the given
+    /// function, which is passed an encoder for the nested message.
+    ///
+    /// The body of the nested message is buffered in memory until the
+    /// function returns (see the [type documentation](StreamEncoder)).
+    pub fn write_message<F>(&mut self, tag: Tag, critical: bool, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut StreamEncoder<Vec<u8>>) -> Result<(), Error>,
+    {
+        self.check_order(tag)?;
+
+        let mut nested = StreamEncoder::new(Vec::new());
+        f(&mut nested)?;
+        let body = nested.finish()?;
+
+        self.write(Header::new(tag, critical, WireType::Message).encode())?;
+        self.write_value(&body)
+    }
+
+    /// Finish encoding the message, flushing and returning the underlying
+    /// sink
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.writer.flush().map_err(|_| error::Kind::Io)?;
+        Ok(self.writer)
+    }
+
+    /// Write a field header to the underlying sink
+    fn write_header(&mut self, tag: Tag, critical: bool, wire_type: WireType) -> Result<(), Error> {
+        self.check_order(tag)?;
+        self.write(Header::new(tag, critical, wire_type).encode())
+    }
+
+    /// Ensure fields are written in canonical order
+    fn check_order(&mut self, tag: Tag) -> Result<(), Error> {
+        if let Some(last_tag) = self.last_tag {
+            if tag <= last_tag {
+                return Err(error::Kind::Order { tag }.into());
+            }
+        }
+
+        self.last_tag = Some(tag);
+        Ok(())
+    }
+
+    /// Write a dynamically sized value to the underlying sink
+    fn write_value(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.write(vint64::encode(bytes.len() as u64))?;
+        self.write(bytes)
+    }
+
+    /// Write the given bytes to the underlying sink
+    fn write(&mut self, bytes: impl AsRef<[u8]>) -> Result<(), Error> {
+        self.writer
+            .write_all(bytes.as_ref())
+            .map_err(|_| error::Kind::Io.into())
+    }
+}
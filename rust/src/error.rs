@@ -7,6 +7,9 @@ use crate::{
 use core::fmt::{self, Display};
 use displaydoc::Display;
 
+#[cfg(feature = "trace-context")]
+use crate::field::Header;
+
 /// Error type
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Error {
@@ -15,20 +18,73 @@ pub struct Error {
 
     /// Position inside of message where error occurred
     position: Option<usize>,
+
+    /// Sequence element which was being decoded when the error occurred
+    element: Option<ElementContext>,
+
+    /// Tags and wire types of the fields being decoded when the error
+    /// occurred, from the outermost message inwards
+    #[cfg(feature = "trace-context")]
+    context: Context,
 }
 
 impl Error {
     /// Get the [`Kind`] of error that occurred
-    pub fn kind(self) -> Kind {
+    pub fn kind(&self) -> Kind {
         self.kind
     }
 
+    /// Get the tag and wire type of each field which was being decoded
+    /// when the error occurred, starting with the outermost message and
+    /// ending with the field in which the error occurred.
+    ///
+    /// This is empty if the error didn't occur while decoding a field.
+    #[cfg(feature = "trace-context")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "trace-context")))]
+    pub fn context(&self) -> &[(Tag, WireType)] {
+        self.context.as_slice()
+    }
+
+    /// Get the sequence element which was being decoded when the error
+    /// occurred, if any.
+    ///
+    /// For errors in sequences nested within the elements of other
+    /// sequences, this is the innermost element.
+    pub fn sequence_element(&self) -> Option<ElementContext> {
+        self.element
+    }
+
+    /// Attach the sequence element being decoded to this error, unless it
+    /// already has one attached (i.e. by an inner sequence)
+    pub(crate) fn with_element(mut self, element: ElementContext) -> Self {
+        if self.element.is_none() {
+            self.element = Some(element);
+        }
+
+        self
+    }
+
+    /// Attach the headers of the fields being decoded to this error, unless
+    /// it already has context attached (i.e. by an inner decoder)
+    #[cfg(feature = "trace-context")]
+    pub(crate) fn with_context(mut self, headers: impl Iterator<Item = Header>) -> Self {
+        if self.context.as_slice().is_empty() {
+            for header in headers {
+                if !self.context.push(header.tag, header.wire_type) {
+                    break;
+                }
+            }
+        }
+
+        self
+    }
+
     /// Get the position inside of the message where the error occurred
     /// (if available)
     ///
     /// NOTE: support for this is an unreliable work-in-progress. Most of the
     /// time this will return `None`.
-    pub fn position(self) -> Option<usize> {
+    pub fn position(&self) -> Option<usize> {
         self.position
     }
 
@@ -38,12 +94,12 @@ impl Error {
     pub(crate) fn extend_position(self, pos: usize) -> Self {
         let new_position = self
             .position
-            .map(|old_pos| old_pos.checked_add(pos).unwrap())
+            .map(|old_pos| old_pos.saturating_add(pos))
             .unwrap_or(pos);
 
         Self {
-            kind: self.kind,
             position: Some(new_position),
+            ..self
         }
     }
 }
@@ -60,21 +116,169 @@ impl Display for Error {
     }
 }
 
+#[cfg(feature = "defmt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "defmt")))]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self.position {
+            Some(pos) => defmt::write!(f, "{} position={}", self.kind, pos),
+            None => defmt::write!(f, "{}", self.kind),
+        }
+    }
+}
+
 impl From<vint64::Error> for Error {
-    fn from(_: vint64::Error) -> Error {
-        Kind::VInt64.into()
+    fn from(error: vint64::Error) -> Error {
+        match error {
+            vint64::Error::LeadingZeroes => Kind::NonCanonicalVarint.into(),
+            vint64::Error::Truncated => Kind::VInt64.into(),
+        }
     }
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
-/// Kinds of errors
+/// Sequence element which was being decoded when an error occurred
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ElementContext {
+    /// Index of the element within the sequence
+    pub index: usize,
+
+    /// Byte offset of the element within the body of the sequence
+    pub offset: usize,
+
+    /// Whether the element was framed correctly
+    pub failure: ElementFailure,
+}
+
+/// Maximum number of fields recorded in the context of an [`Error`]
+#[cfg(feature = "trace-context")]
+const CONTEXT_DEPTH: usize = 16;
+
+/// Tags and wire types of the fields being decoded when an error occurred.
+///
+/// These are stored inline so [`Error`] remains `Copy` and doesn't need an
+/// allocator.
+#[cfg(feature = "trace-context")]
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Context {
+    /// Fields being decoded, of which the first `len` are populated
+    fields: [(Tag, WireType); CONTEXT_DEPTH],
+
+    /// Number of populated fields
+    len: u8,
+}
+
+#[cfg(feature = "trace-context")]
+impl Context {
+    /// Get the populated fields
+    fn as_slice(&self) -> &[(Tag, WireType)] {
+        &self.fields[..usize::from(self.len)]
+    }
+
+    /// Record a field, returning `false` if the context is full
+    fn push(&mut self, tag: Tag, wire_type: WireType) -> bool {
+        match self.fields.get_mut(usize::from(self.len)) {
+            Some(field) => {
+                *field = (tag, wire_type);
+                self.len += 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "trace-context")]
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            fields: [(0, WireType::False); CONTEXT_DEPTH],
+            len: 0,
+        }
+    }
+}
+
+#[cfg(feature = "trace-context")]
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+/// Kinds of failures decoding sequence elements
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ElementFailure {
+    /// The element couldn't be extracted from the sequence body, e.g. its
+    /// length overran the body or it had the wrong wire type
+    Structural,
+
+    /// The element was framed correctly, but decoding its contents failed,
+    /// e.g. a message element contained messages nested too deeply
+    Semantic,
+}
+
+/// Kinds of errors.
+///
+/// # Error codes
+///
+/// Each kind of error has a stable numeric code which can be obtained using
+/// [`Kind::code`] and passed across FFI boundaries. Codes are never reused
+/// or reassigned once allocated. Code `0` is reserved to mean "no error".
+///
+/// | Code | Kind                   |
+/// |------|------------------------|
+/// | 1    | `Builtin`              |
+/// | 2    | `Decode`               |
+/// | 3    | `Failed`               |
+/// | 4    | `FieldHeader`          |
+/// | 5    | `Hashing`              |
+/// | 6    | `InvalidWireType`      |
+/// | 7    | `Length`               |
+/// | 8    | `NestingDepth`         |
+/// | 9    | `NestedSequence`       |
+/// | 10   | `Order`                |
+/// | 11   | `TrailingData`         |
+/// | 12   | `Truncated`            |
+/// | 13   | `UnexpectedWireType`   |
+/// | 14   | `UnicodeNormalization` |
+/// | 15   | `Utf8`                 |
+/// | 16   | `VInt64`               |
+/// | 17   | `Malformed`            |
+/// | 18   | `UnknownField`         |
+/// | 19   | `SequenceElement`      |
+/// | 20   | `Io`                   |
+/// | 21   | `Capacity`             |
+/// | 22   | `LengthLimit`          |
+/// | 23   | `OutOfRange`           |
+/// | 24   | `Cancelled`            |
+/// | 25   | `ExpectedSequence`     |
+/// | 26   | `NonCanonicalVarint`   |
+/// | 27   | `FieldWireType`        |
+/// | 28   | `MissingField`         |
+/// | 29   | `Verification`         |
+/// | 30   | `UnbalancedStack`      |
+///
+/// # Positions
+///
+/// The [`Error::position`] of a [`Kind::Cancelled`] error is an offset into
+/// the body of the innermost message being decoded when decoding was
+/// cancelled, i.e. the number of bytes of that message consumed before the
+/// cancelled field. It's only an offset into the input as a whole when
+/// decoding is cancelled at the top level.
 #[derive(Copy, Clone, Debug, Display, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Kind {
     /// error decoding builtin type
     Builtin,
 
+    /// decoding cancelled
+    Cancelled,
+
+    /// storage capacity exceeded
+    Capacity,
+
     /// decoding failed: wire_type={wire_type:?}
     Decode {
         /// element of the message that failed to decode
@@ -84,6 +288,15 @@ pub enum Kind {
         wire_type: WireType,
     },
 
+    /// field {tag} is {actual:?}, not a sequence
+    ExpectedSequence {
+        /// tag of the field
+        tag: Tag,
+
+        /// actual wire type of the field
+        actual: WireType,
+    },
+
     /// operation failed
     Failed,
 
@@ -96,6 +309,18 @@ pub enum Kind {
         wire_type: Option<WireType>,
     },
 
+    /// field {tag} is {actual:?}, expected {wanted:?}
+    FieldWireType {
+        /// tag of the field
+        tag: Tag,
+
+        /// actual wire type of the field
+        actual: WireType,
+
+        /// wire type the field was expected to have
+        wanted: WireType,
+    },
+
     /// hashing operation failed
     // TODO(tarcieri): collect more info
     Hashing,
@@ -103,21 +328,54 @@ pub enum Kind {
     /// invalid wire type
     InvalidWireType,
 
+    /// I/O error
+    Io,
+
     /// bad length
     Length,
 
+    /// decoding limit exceeded
+    LengthLimit,
+
+    /// malformed value
+    Malformed,
+
+    /// missing required field {tag}
+    MissingField {
+        /// tag of the missing field
+        tag: Tag,
+    },
+
     /// maximum message nesting depth exceeded
     NestingDepth,
 
     /// nested sequences not presently allowed
     NestedSequence,
 
+    /// `vint64` not encoded in its shortest possible form
+    NonCanonicalVarint,
+
     /// field {tag:?} is out-of-order
     Order {
         /// tag of the out-of-order field
         tag: Tag,
     },
 
+    /// value out of range
+    OutOfRange,
+
+    /// unexpected sequence element wire type: index={index} actual={actual:?} wanted={wanted:?}
+    SequenceElement {
+        /// index of the offending element within the sequence
+        index: usize,
+
+        /// actual wire type of the element
+        actual: WireType,
+
+        /// wire type the sequence was declared to contain
+        wanted: WireType,
+    },
+
     /// unexpected trailing data
     TrailingData,
 
@@ -139,9 +397,18 @@ pub enum Kind {
         wanted: WireType,
     },
 
+    /// decoder stack unbalanced after decoding a nested message
+    UnbalancedStack,
+
     /// string contains non-normalized Unicode
     UnicodeNormalization,
 
+    /// unknown field: tag={tag:?}
+    UnknownField {
+        /// tag of the unknown field
+        tag: Tag,
+    },
+
     /// malformed UTF-8 encountered at byte: {valid_up_to:?}
     Utf8 {
         /// byte at which UTF-8 encoding failed
@@ -150,14 +417,119 @@ pub enum Kind {
 
     /// `vint64` encoding error
     VInt64,
+
+    /// digest verification failed
+    Verification,
 }
 
 impl Kind {
+    /// Get the stable numeric code for this kind of error.
+    ///
+    /// See the [`Kind`] documentation for the table of assigned codes.
+    pub fn code(&self) -> u16 {
+        match self {
+            Kind::Builtin => 1,
+            Kind::Cancelled => 24,
+            Kind::Capacity => 21,
+            Kind::Decode { .. } => 2,
+            Kind::ExpectedSequence { .. } => 25,
+            Kind::Failed => 3,
+            Kind::FieldHeader { .. } => 4,
+            Kind::FieldWireType { .. } => 27,
+            Kind::Hashing => 5,
+            Kind::InvalidWireType => 6,
+            Kind::Io => 20,
+            Kind::Length => 7,
+            Kind::LengthLimit => 22,
+            Kind::Malformed => 17,
+            Kind::MissingField { .. } => 28,
+            Kind::NestingDepth => 8,
+            Kind::NestedSequence => 9,
+            Kind::NonCanonicalVarint => 26,
+            Kind::Order { .. } => 10,
+            Kind::OutOfRange => 23,
+            Kind::SequenceElement { .. } => 19,
+            Kind::TrailingData => 11,
+            Kind::Truncated { .. } => 12,
+            Kind::UnexpectedWireType { .. } => 13,
+            Kind::UnbalancedStack => 30,
+            Kind::UnicodeNormalization => 14,
+            Kind::UnknownField { .. } => 18,
+            Kind::Utf8 { .. } => 15,
+            Kind::VInt64 => 16,
+            Kind::Verification => 29,
+        }
+    }
+
+    /// Get the kind of error for the given numeric code, if it's assigned.
+    ///
+    /// Numeric codes don't carry the additional information some kinds of
+    /// error contain, so these fields are filled with placeholder values
+    /// (i.e. `None`, `0`, [`Element::Value`], and [`WireType::False`]).
+    pub fn from_code(code: u16) -> Option<Kind> {
+        Some(match code {
+            1 => Kind::Builtin,
+            2 => Kind::Decode {
+                element: Element::Value,
+                wire_type: WireType::False,
+            },
+            3 => Kind::Failed,
+            4 => Kind::FieldHeader {
+                tag: None,
+                wire_type: None,
+            },
+            5 => Kind::Hashing,
+            6 => Kind::InvalidWireType,
+            7 => Kind::Length,
+            8 => Kind::NestingDepth,
+            9 => Kind::NestedSequence,
+            10 => Kind::Order { tag: 0 },
+            11 => Kind::TrailingData,
+            12 => Kind::Truncated {
+                remaining: 0,
+                wire_type: WireType::False,
+            },
+            13 => Kind::UnexpectedWireType {
+                actual: WireType::False,
+                wanted: WireType::False,
+            },
+            14 => Kind::UnicodeNormalization,
+            15 => Kind::Utf8 { valid_up_to: 0 },
+            16 => Kind::VInt64,
+            17 => Kind::Malformed,
+            18 => Kind::UnknownField { tag: 0 },
+            19 => Kind::SequenceElement {
+                index: 0,
+                actual: WireType::False,
+                wanted: WireType::False,
+            },
+            20 => Kind::Io,
+            21 => Kind::Capacity,
+            22 => Kind::LengthLimit,
+            23 => Kind::OutOfRange,
+            24 => Kind::Cancelled,
+            25 => Kind::ExpectedSequence {
+                tag: 0,
+                actual: WireType::False,
+            },
+            26 => Kind::NonCanonicalVarint,
+            27 => Kind::FieldWireType {
+                tag: 0,
+                actual: WireType::False,
+                wanted: WireType::False,
+            },
+            28 => Kind::MissingField { tag: 0 },
+            29 => Kind::Verification,
+            30 => Kind::UnbalancedStack,
+            _ => return None,
+        })
+    }
+
     /// Create an error with the given position
     pub(crate) fn position(self, pos: usize) -> Error {
         Error {
-            kind: self,
             position: Some(pos),
+            ..self.into()
         }
     }
 }
@@ -167,6 +539,36 @@ impl From<Kind> for Error {
         Self {
             kind,
             position: None,
+            element: None,
+            #[cfg(feature = "trace-context")]
+            context: Context::default(),
+        }
+    }
+}
+
+impl From<Kind> for u16 {
+    fn from(kind: Kind) -> u16 {
+        kind.code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Kind;
+
+    #[test]
+    fn code_round_trip() {
+        let mut n_kinds = 0;
+
+        for code in 0..=u8::MAX as u16 {
+            if let Some(kind) = Kind::from_code(code) {
+                assert_eq!(kind.code(), code);
+                assert_eq!(u16::from(kind), code);
+                n_kinds += 1;
+            }
         }
+
+        assert_eq!(n_kinds, 30);
+        assert_eq!(Kind::from_code(0), None);
     }
 }
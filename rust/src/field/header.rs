@@ -5,6 +5,7 @@ use vint64::VInt64;
 
 /// Field headers
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Header {
     /// Tag which identifies the field
     pub tag: Tag,
@@ -26,12 +26,64 @@
 //!
 //! [`Timestamp`]: https://docs.rs/veriform/latest/veriform/builtins/struct.Timestamp.html
 //! [`Uuid`]: https://docs.rs/veriform/latest/veriform/builtins/struct.Uuid.html
+//!
+//! # Zeroization
+//!
+//! When the `zeroize` feature of this crate is enabled, internal buffers
+//! which may hold copies of decoded data are zeroized when they're dropped:
+//!
+//! - partially decoded `vint64` values (including those buffered by
+//!   [`decoder::Stream`] between calls)
+//! - the staging buffer used to reorder fields by [`Encoder::new_sorted`]
+//!
+//! Decoding is otherwise zero-copy: `bytes` and `string` fields are borrowed
+//! from the input buffer and never copied by this crate, so zeroizing the
+//! input buffer and any owned types decoded from it (e.g. by wrapping them
+//! in [`zeroize::Zeroizing`]) is the responsibility of the caller.
+//!
+//! Verihash hasher state is not zeroized, as this crate is generic over the
+//! [`Digest`] implementation used.
+//!
+//! [`Digest`]: digest::Digest
+//!
+//! # Error Context
+//!
+//! Without `std` there are no backtraces, so when the `trace-context`
+//! feature is enabled each [`Error`] returned while decoding records the tag
+//! and wire type of the field being decoded at each level of message nesting
+//! (up to 16 levels). These can be retrieved using `Error::context`.
+//!
+//! The context is stored inline, so [`Error`] remains `Copy` and usable
+//! without an allocator, but it becomes considerably larger.
+//!
+//! # Logging
+//!
+//! The decoder can trace the fields and nested messages it decodes, along
+//! with any errors which occur, using either or both of the following
+//! features:
+//!
+//! - `log`: log using the [`log`] crate
+//! - `defmt`: log using [`defmt`], for embedded targets. This also impls
+//!   `defmt::Format` for [`Error`], [`field::WireType`], [`field::Header`]
+//!   and [`decoder::Event`].
+//!
+//! When neither is enabled no logging code is compiled in.
+//!
+//! The `defmt` feature requires a global logger, which is provided by the
+//! final embedded application. Test binaries don't have one and fail to
+//! link when it's enabled, so it's only built (not tested) for embedded
+//! targets, and `cargo test --all-features` isn't supported.
+//!
+//! [`log`]: https://docs.rs/log
+//! [`defmt`]: https://defmt.ferrous-systems.com
+//! [`zeroize::Zeroizing`]: https://docs.rs/zeroize/latest/zeroize/struct.Zeroizing.html
 
 #![no_std]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![doc(html_root_url = "https://docs.rs/veriform/0.2.0")]
 #![forbid(unsafe_code)]
 #![warn(missing_docs, rust_2018_idioms, unused_qualifications)]
+#![cfg_attr(feature = "trace-context", allow(clippy::result_large_err))]
 
 #[cfg(feature = "alloc")]
 #[macro_use]
@@ -42,13 +94,25 @@ extern crate std;
 
 #[cfg(any(feature = "tai64", feature = "uuid"))]
 pub mod builtins;
+#[cfg(feature = "conformance")]
+#[cfg_attr(docsrs, doc(cfg(feature = "conformance")))]
+pub mod conformance;
+pub mod consts;
 pub mod decoder;
 pub mod encoder;
 pub mod error;
 pub mod field;
 pub mod message;
+mod multi_digest;
+#[cfg(feature = "protobuf-compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf-compat")))]
+pub mod protobuf;
 mod string;
-mod verihash;
+pub mod verihash;
+
+#[cfg(feature = "test-util")]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod test_util;
 
 #[cfg(feature = "veriform_derive")]
 pub mod derive_helpers;
@@ -59,13 +123,23 @@ pub use digest;
 // Re-export the `vint64` crate
 pub use vint64;
 
-pub use crate::{encoder::Encoder, error::Error, message::Message};
+pub use crate::{
+    decoder::{validate, validate_against},
+    encoder::Encoder,
+    error::Error,
+    message::Message,
+    multi_digest::MultiDigest,
+};
 
 /// Veriform decoder with the default SHA-256 hash
 #[cfg(feature = "sha2")]
 #[cfg_attr(docsrs, doc(cfg(feature = "sha2")))]
 pub type Decoder = crate::decoder::Decoder<sha2::Sha256>;
 
+#[cfg(all(feature = "alloc", feature = "sha2"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "alloc", feature = "sha2"))))]
+pub use crate::decoder::{diff, DiffReport};
+
 /// SHA-256 digests
 #[cfg(feature = "sha2")]
 #[cfg_attr(docsrs, doc(cfg(feature = "sha2")))]
@@ -5,8 +5,7 @@
 //
 // Copyright (c) 2017 Dan Burkert and released under the Apache 2.0 license.
 
-use crate::{decoder::Decoder, Error};
-use digest::Digest;
+use crate::{decoder::Decoder, verihash::VerihashDigest, Error};
 
 #[cfg(feature = "alloc")]
 use alloc::vec::Vec;
@@ -25,9 +24,24 @@ pub trait Message {
     /// given [`Decoder`].
     fn decode<D>(decoder: &mut Decoder<D>, input: &[u8]) -> Result<Self, Error>
     where
-        D: Digest,
+        D: VerihashDigest,
         Self: Sized;
 
+    /// Decode a Veriform message contained in the provided slice into an
+    /// existing value (e.g. one which is default-initialized), avoiding the
+    /// need to move large messages.
+    ///
+    /// If an error occurs, `out` may have been partially overwritten and its
+    /// contents are unspecified.
+    fn decode_into<D>(decoder: &mut Decoder<D>, input: &[u8], out: &mut Self) -> Result<(), Error>
+    where
+        D: VerihashDigest,
+        Self: Sized,
+    {
+        *out = Self::decode(decoder, input)?;
+        Ok(())
+    }
+
     /// Encode this message as Veriform into the provided buffer, returning
     /// a slice containing the encoded message on success.
     fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error>;
@@ -47,6 +61,7 @@ pub trait Message {
 
 /// Elements of a message (used for errors)
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Element {
     /// Length delimiters for dynamically sized fields
     LengthDelimiter,
@@ -0,0 +1,182 @@
+//! Computing multiple digests in a single decoding pass
+
+use crate::verihash::{DigestOutput, VerihashDigest};
+use core::ops::Add;
+use digest::{
+    generic_array::{typenum::Sum, ArrayLength, GenericArray},
+    Digest, FixedOutputDirty, Reset, Update,
+};
+
+/// Hash function which computes two Verihash digests simultaneously, e.g.
+/// when migrating from one hash function to another.
+///
+/// Use it as the digest type of a [`Decoder`] to compute both digests in a
+/// single decoding pass. The output is the concatenation of the digests
+/// computed by `D1` and `D2`, which can be separated using
+/// [`MultiDigest::split`].
+///
+/// Digests of nested messages and sequences are split between the two hash
+/// functions by [`VerihashDigest::input_digest`], so each digest is
+/// identical to the one computed when decoding with that hash function
+/// alone. This includes when either hash function is itself a
+/// [`MultiDigest`]. Both hash functions must use the same
+/// [`VerihashParams`], since the domain tags are input into both of them.
+///
+/// [`VerihashParams`]: crate::verihash::VerihashParams
+///
+/// [`Decoder`]: crate::decoder::Decoder
+#[derive(Clone, Debug, Default)]
+pub struct MultiDigest<D1, D2> {
+    /// First hash function
+    first: D1,
+
+    /// Second hash function
+    second: D2,
+}
+
+impl<D1, D2> MultiDigest<D1, D2>
+where
+    D1: Digest + Clone + Default,
+    D2: Digest + Clone + Default,
+    D1::OutputSize: Add<D2::OutputSize>,
+    Sum<D1::OutputSize, D2::OutputSize>: ArrayLength<u8>,
+{
+    /// Split the output of this hash function into the respective outputs
+    /// of `D1` and `D2`
+    pub fn split(output: &DigestOutput<Self>) -> (DigestOutput<D1>, DigestOutput<D2>) {
+        let (first, second) = output.split_at(D1::output_size());
+        (
+            GenericArray::clone_from_slice(first),
+            GenericArray::clone_from_slice(second),
+        )
+    }
+}
+
+impl<D1, D2> VerihashDigest for MultiDigest<D1, D2>
+where
+    D1: VerihashDigest + Clone + Default,
+    D2: VerihashDigest<Params = D1::Params> + Clone + Default,
+    D1::OutputSize: Add<D2::OutputSize>,
+    Sum<D1::OutputSize, D2::OutputSize>: ArrayLength<u8>,
+{
+    type Params = D1::Params;
+
+    fn input_digest(&mut self, digest: &DigestOutput<Self>) {
+        let (first, second) = Self::split(digest);
+        self.first.input_digest(&first);
+        self.second.input_digest(&second);
+    }
+}
+
+impl<D1, D2> Update for MultiDigest<D1, D2>
+where
+    D1: Digest,
+    D2: Digest,
+{
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        self.first.update(data);
+        self.second.update(data);
+    }
+}
+
+impl<D1, D2> FixedOutputDirty for MultiDigest<D1, D2>
+where
+    D1: Digest,
+    D2: Digest,
+    D1::OutputSize: Add<D2::OutputSize>,
+    Sum<D1::OutputSize, D2::OutputSize>: ArrayLength<u8>,
+{
+    type OutputSize = Sum<D1::OutputSize, D2::OutputSize>;
+
+    fn finalize_into_dirty(&mut self, out: &mut GenericArray<u8, Self::OutputSize>) {
+        let (first, second) = out.split_at_mut(D1::output_size());
+        first.copy_from_slice(&self.first.finalize_reset());
+        second.copy_from_slice(&self.second.finalize_reset());
+    }
+}
+
+impl<D1, D2> Reset for MultiDigest<D1, D2>
+where
+    D1: Digest,
+    D2: Digest,
+{
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::MultiDigest;
+    use crate::verihash::VerihashDigest;
+    use digest::Digest;
+    use sha2::{Sha256, Sha512};
+
+    type Sha256And512 = MultiDigest<Sha256, Sha512>;
+
+    #[test]
+    fn split_output() {
+        let output = Sha256And512::digest(b"example");
+        let (sha256, sha512) = Sha256And512::split(&output);
+        assert_eq!(sha256, Sha256::digest(b"example"));
+        assert_eq!(sha512, Sha512::digest(b"example"));
+    }
+
+    #[test]
+    fn split_nested_digest() {
+        let nested = Sha256And512::digest(b"nested");
+
+        let mut hasher = Sha256And512::new();
+        hasher.update(b"prefix");
+        hasher.input_digest(&nested);
+
+        let (sha256, sha512) = Sha256And512::split(&hasher.finalize());
+        let (nested256, nested512) = Sha256And512::split(&nested);
+
+        let mut expected256 = Sha256::new();
+        expected256.update(b"prefix");
+        expected256.update(nested256);
+        assert_eq!(sha256, expected256.finalize());
+
+        let mut expected512 = Sha512::new();
+        expected512.update(b"prefix");
+        expected512.update(nested512);
+        assert_eq!(sha512, expected512.finalize());
+    }
+
+    #[test]
+    fn nested_digest_not_split_as_data() {
+        // Data the size of the output preceded by an empty update is input
+        // into both hash functions like any other data
+        let data = [0x42; 96];
+
+        let mut hasher = Sha256And512::new();
+        hasher.update(b"");
+        hasher.update(&data[..]);
+
+        let (sha256, sha512) = Sha256And512::split(&hasher.finalize());
+        assert_eq!(sha256, Sha256::digest(&data));
+        assert_eq!(sha512, Sha512::digest(&data));
+    }
+
+    #[test]
+    fn split_doubly_nested_digest() {
+        type Inner = MultiDigest<Sha256, Sha512>;
+        type Outer = MultiDigest<Inner, Sha256>;
+
+        let nested = Outer::digest(b"nested");
+        let mut hasher = Outer::new();
+        hasher.input_digest(&nested);
+
+        let (inner, sha256) = Outer::split(&hasher.finalize());
+        let (inner256, inner512) = Inner::split(&inner);
+
+        let expected256 = Sha256::digest(&Sha256::digest(b"nested"));
+        let expected512 = Sha512::digest(&Sha512::digest(b"nested"));
+        assert_eq!(inner256, expected256);
+        assert_eq!(inner512, expected512);
+        assert_eq!(sha256, expected256);
+    }
+}
@@ -0,0 +1,287 @@
+//! Conversion from Protocol Buffers messages.
+//!
+//! Protobuf's wire format doesn't distinguish between many of the types
+//! Veriform does (e.g. a length-delimited field may contain bytes, a string,
+//! or a nested message), so converting a protobuf message requires a
+//! [`FieldMap`] giving the Veriform type of each protobuf field number.
+//! Fields retain their field number as their Veriform tag.
+//!
+//! ```
+//! use veriform::protobuf::{from_protobuf, FieldMap};
+//!
+//! // `message Example { uint64 id = 1; string name = 2; }`
+//! let fields = FieldMap::new().uint64(1).string(2);
+//!
+//! let pb_bytes = b"\x08\x96\x01\x12\x07testing";
+//! let message = from_protobuf(pb_bytes, &fields).unwrap();
+//! ```
+//!
+//! # Unsupported features
+//!
+//! Only a subset of protobuf is supported: fields using any other wire type,
+//! field numbers missing from the [`FieldMap`], or field numbers occurring
+//! more than once are an error. In particular:
+//!
+//! - `fixed32`, `fixed64`, `sfixed32`, `sfixed64`, `float`, and `double`
+//!   fields, as Veriform doesn't have 32-bit or floating point types
+//! - groups (which are deprecated in protobuf)
+//! - `repeated` fields, both packed and unpacked
+//! - `bool` and `enum` fields
+//! - `map` fields, and unknown fields
+//!
+//! Protobuf's `uint32`, `int32`, and `sint32` fields can be converted as the
+//! corresponding 64-bit types, as their encodings are identical.
+
+use crate::{
+    error::{self, Error},
+    field::{Header, Tag, WireType},
+    string,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{convert::TryFrom, str};
+
+/// Protobuf's `varint` wire type
+const PB_VARINT: u64 = 0;
+
+/// Protobuf's length-delimited wire type
+const PB_LENGTH_DELIMITED: u64 = 2;
+
+/// Convert an encoded protobuf message into a Veriform message, using the
+/// given [`FieldMap`] to determine the Veriform type of each field.
+///
+/// The fields of the resulting message are sorted by tag, as Veriform
+/// requires.
+pub fn from_protobuf(pb_bytes: &[u8], field_map: &FieldMap) -> Result<Vec<u8>, Error> {
+    let mut input = pb_bytes;
+    let mut fields = BTreeMap::new();
+
+    while !input.is_empty() {
+        let key = decode_varint(&mut input)?;
+        let tag = key >> 3;
+
+        if tag == 0 {
+            return Err(error::Kind::Malformed.into());
+        }
+
+        let field_type = field_map
+            .get(tag)
+            .ok_or(error::Kind::UnknownField { tag })?;
+
+        let mut field = Vec::new();
+
+        match key & 0b111 {
+            PB_VARINT => {
+                let value = decode_varint(&mut input)?;
+
+                match field_type {
+                    FieldType::UInt64 => {
+                        write_header(&mut field, tag, WireType::UInt64);
+                        field.extend_from_slice(vint64::encode(value).as_ref());
+                    }
+                    FieldType::SInt64 | FieldType::Int64 => {
+                        let value = match field_type {
+                            FieldType::SInt64 => vint64::signed::zigzag::decode(value),
+                            _ => value as i64,
+                        };
+
+                        write_header(&mut field, tag, WireType::SInt64);
+                        field.extend_from_slice(vint64::signed::encode(value).as_ref());
+                    }
+                    other => return Err(unexpected(WireType::UInt64, other)),
+                }
+            }
+            PB_LENGTH_DELIMITED => {
+                let length = decode_varint(&mut input)?;
+                let value = split_value(&mut input, length)?;
+
+                match field_type {
+                    FieldType::Bytes => write_value(&mut field, tag, WireType::Bytes, value),
+                    FieldType::String => {
+                        let string = str::from_utf8(value).map_err(|e| error::Kind::Utf8 {
+                            valid_up_to: e.valid_up_to(),
+                        })?;
+
+                        string::ensure_canonical(string)?;
+                        write_value(&mut field, tag, WireType::String, value);
+                    }
+                    FieldType::Message(nested) => {
+                        let message = from_protobuf(value, nested)?;
+                        write_value(&mut field, tag, WireType::Message, &message);
+                    }
+                    other => return Err(unexpected(WireType::Bytes, other)),
+                }
+            }
+            _ => return Err(error::Kind::InvalidWireType.into()),
+        }
+
+        // Repeated fields aren't supported
+        if fields.insert(tag, field).is_some() {
+            return Err(error::Kind::Order { tag }.into());
+        }
+    }
+
+    Ok(fields.values().flatten().copied().collect())
+}
+
+/// Veriform types of the fields of a protobuf message, by field number
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FieldMap {
+    /// Types of fields by field number
+    fields: BTreeMap<Tag, FieldType>,
+}
+
+impl FieldMap {
+    /// Create a new, empty field map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert the field with the given number as the given type
+    pub fn field(mut self, number: Tag, field_type: FieldType) -> Self {
+        self.fields.insert(number, field_type);
+        self
+    }
+
+    /// Convert a protobuf `uint64` (or `uint32`) field as `uint64`
+    pub fn uint64(self, number: Tag) -> Self {
+        self.field(number, FieldType::UInt64)
+    }
+
+    /// Convert a protobuf `sint64` (or `sint32`) field as `sint64`
+    pub fn sint64(self, number: Tag) -> Self {
+        self.field(number, FieldType::SInt64)
+    }
+
+    /// Convert a protobuf `int64` (or `int32`) field as `sint64`
+    pub fn int64(self, number: Tag) -> Self {
+        self.field(number, FieldType::Int64)
+    }
+
+    /// Convert a protobuf `bytes` field as `bytes`
+    pub fn bytes(self, number: Tag) -> Self {
+        self.field(number, FieldType::Bytes)
+    }
+
+    /// Convert a protobuf `string` field as `string`
+    pub fn string(self, number: Tag) -> Self {
+        self.field(number, FieldType::String)
+    }
+
+    /// Convert an embedded protobuf message field as a nested message, whose
+    /// fields are converted using the given field map
+    pub fn message(self, number: Tag, fields: FieldMap) -> Self {
+        self.field(number, FieldType::Message(fields))
+    }
+
+    /// Get the type of the field with the given number, if it's in the map
+    pub fn get(&self, number: Tag) -> Option<&FieldType> {
+        self.fields.get(&number)
+    }
+}
+
+/// Veriform types protobuf fields can be converted to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldType {
+    /// `varint` field containing an unsigned integer (i.e. `uint64` or
+    /// `uint32`), converted as `uint64`
+    UInt64,
+
+    /// `varint` field containing a zigzag encoded signed integer (i.e.
+    /// `sint64` or `sint32`), converted as `sint64`
+    SInt64,
+
+    /// `varint` field containing a two's complement signed integer (i.e.
+    /// `int64` or `int32`), converted as `sint64`
+    Int64,
+
+    /// Length-delimited field containing binary data
+    Bytes,
+
+    /// Length-delimited field containing a string
+    String,
+
+    /// Length-delimited field containing an embedded message whose fields
+    /// have the given types
+    Message(FieldMap),
+}
+
+impl FieldType {
+    /// Get the Veriform wire type this type of field is converted to
+    pub fn wire_type(&self) -> WireType {
+        match self {
+            FieldType::UInt64 => WireType::UInt64,
+            FieldType::SInt64 | FieldType::Int64 => WireType::SInt64,
+            FieldType::Bytes => WireType::Bytes,
+            FieldType::String => WireType::String,
+            FieldType::Message(_) => WireType::Message,
+        }
+    }
+}
+
+/// Decode a protobuf `varint` (i.e. LEB128) value
+fn decode_varint(input: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0u64;
+
+    for (i, &byte) in input.iter().enumerate().take(10) {
+        // The 10th byte may only contain the highest bit of the value
+        if i == 9 && byte > 1 {
+            return Err(error::Kind::Malformed.into());
+        }
+
+        value |= u64::from(byte & 0x7f) << (i * 7);
+
+        if byte & 0x80 == 0 {
+            *input = &input[(i + 1)..];
+            return Ok(value);
+        }
+    }
+
+    if input.len() >= 10 {
+        Err(error::Kind::Malformed.into())
+    } else {
+        Err(error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::UInt64,
+        }
+        .into())
+    }
+}
+
+/// Split a length-delimited value with the given length off the input
+fn split_value<'a>(input: &mut &'a [u8], length: u64) -> Result<&'a [u8], Error> {
+    let length = usize::try_from(length).map_err(|_| error::Kind::Length)?;
+
+    if length > input.len() {
+        return Err(error::Kind::Truncated {
+            remaining: length - input.len(),
+            wire_type: WireType::Bytes,
+        }
+        .into());
+    }
+
+    let (value, rest) = input.split_at(length);
+    *input = rest;
+    Ok(value)
+}
+
+/// Write a Veriform field header
+fn write_header(field: &mut Vec<u8>, tag: Tag, wire_type: WireType) {
+    field.extend_from_slice(Header::new(tag, false, wire_type).encode().as_ref());
+}
+
+/// Write a Veriform field containing a length-prefixed value
+fn write_value(field: &mut Vec<u8>, tag: Tag, wire_type: WireType, value: &[u8]) {
+    write_header(field, tag, wire_type);
+    field.extend_from_slice(vint64::encode(value.len() as u64).as_ref());
+    field.extend_from_slice(value);
+}
+
+/// Create an error for a protobuf field whose wire type doesn't match the
+/// type in the field map
+fn unexpected(actual: WireType, wanted: &FieldType) -> Error {
+    error::Kind::UnexpectedWireType {
+        actual,
+        wanted: wanted.wire_type(),
+    }
+    .into()
+}
@@ -0,0 +1,195 @@
+//! Utilities for constructing canonical test vectors.
+//!
+//! These are intended for use in tests which need encoded messages, so the
+//! tests can express their intent rather than containing hand-computed byte
+//! arrays.
+
+use crate::{
+    field::{Header, Tag, WireType},
+    string,
+};
+use alloc::vec::Vec;
+
+/// Builder for canonically encoded Veriform messages.
+///
+/// Fields must be added in ascending tag order. Since the builder is intended
+/// for use in tests, any attempt to construct a non-canonical message panics.
+///
+/// ```
+/// use veriform::test_util::MessageBuilder;
+///
+/// let message = MessageBuilder::new()
+///     .uint64(1, 42)
+///     .string(2, "foo")
+///     .build();
+///
+/// assert_eq!(message, [37, 85, 75, 7, 102, 111, 111]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MessageBuilder {
+    /// Encoded message
+    buffer: Vec<u8>,
+
+    /// Tag of the last field added
+    last_tag: Option<Tag>,
+
+    /// Is the next field critical?
+    critical: bool,
+}
+
+impl MessageBuilder {
+    /// Create a new message builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the next field as critical
+    pub fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    /// Add a boolean field
+    pub fn bool(self, tag: Tag, value: bool) -> Self {
+        let wire_type = if value {
+            WireType::True
+        } else {
+            WireType::False
+        };
+
+        self.header(tag, wire_type)
+    }
+
+    /// Add an unsigned 64-bit integer field
+    pub fn uint64(self, tag: Tag, value: u64) -> Self {
+        self.header(tag, WireType::UInt64)
+            .write(vint64::encode(value))
+    }
+
+    /// Add a signed 64-bit integer field
+    pub fn sint64(self, tag: Tag, value: i64) -> Self {
+        self.header(tag, WireType::SInt64)
+            .write(vint64::signed::encode(value))
+    }
+
+    /// Add a field containing bytes
+    pub fn bytes(self, tag: Tag, bytes: &[u8]) -> Self {
+        self.header(tag, WireType::Bytes).value(bytes)
+    }
+
+    /// Add a field containing a string
+    pub fn string(self, tag: Tag, string: &str) -> Self {
+        string::ensure_canonical(string).expect("non-canonical string");
+        self.header(tag, WireType::String).value(string.as_bytes())
+    }
+
+    /// Add a field containing a nested message, e.g. one constructed using
+    /// another [`MessageBuilder`]
+    pub fn message(self, tag: Tag, message: &[u8]) -> Self {
+        self.header(tag, WireType::Message).value(message)
+    }
+
+    /// Add a field containing a sequence with the given element wire type
+    /// and encoded body
+    pub fn sequence(self, tag: Tag, wire_type: WireType, body: &[u8]) -> Self {
+        self.header(tag, WireType::Sequence)
+            .write(vint64::encode(
+                (body.len() as u64) << 4 | wire_type.to_u8() as u64,
+            ))
+            .write(body)
+    }
+
+    /// Finish building the message, returning the encoded bytes
+    pub fn build(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    /// Write a field header
+    fn header(mut self, tag: Tag, wire_type: WireType) -> Self {
+        if let Some(last_tag) = self.last_tag {
+            assert!(tag > last_tag, "field {} is out-of-order", tag);
+        }
+
+        let header = Header::new(tag, self.critical, wire_type);
+        self.last_tag = Some(tag);
+        self.critical = false;
+        self.write(header.encode())
+    }
+
+    /// Write a length-prefixed value
+    fn value(self, bytes: &[u8]) -> Self {
+        self.write(vint64::encode(bytes.len() as u64)).write(bytes)
+    }
+
+    /// Write the given bytes to the message
+    fn write(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        self.buffer.extend_from_slice(bytes.as_ref());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageBuilder;
+    use crate::field::WireType;
+
+    #[test]
+    fn bool_vectors() {
+        assert_eq!(MessageBuilder::new().bool(42, false).build(), [130, 10]);
+        assert_eq!(MessageBuilder::new().bool(43, true).build(), [198, 10]);
+    }
+
+    #[test]
+    fn uint64_vector() {
+        assert_eq!(MessageBuilder::new().uint64(42, 42).build(), [138, 10, 85]);
+    }
+
+    #[test]
+    fn sint64_vector() {
+        assert_eq!(
+            MessageBuilder::new().sint64(43, -42).build(),
+            [206, 10, 167]
+        );
+    }
+
+    #[test]
+    fn bytes_vector() {
+        assert_eq!(
+            MessageBuilder::new().bytes(2, b"bytes").build(),
+            [73, 11, 98, 121, 116, 101, 115]
+        );
+    }
+
+    #[test]
+    fn string_vector() {
+        assert_eq!(
+            MessageBuilder::new().string(4, "baz").build(),
+            [139, 7, 98, 97, 122]
+        );
+    }
+
+    #[test]
+    fn message_vector() {
+        let inner = MessageBuilder::new().uint64(2, 3).build();
+        assert_eq!(
+            MessageBuilder::new().message(1, &inner).build(),
+            [45, 5, 69, 7]
+        );
+    }
+
+    #[test]
+    fn sequence_vector() {
+        assert_eq!(
+            MessageBuilder::new()
+                .sequence(1, WireType::False, &[1, 0, 1])
+                .build(),
+            [47, 97, 1, 0, 1]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out-of-order")]
+    fn out_of_order_fields() {
+        MessageBuilder::new().uint64(2, 1).uint64(1, 2);
+    }
+}
@@ -1,27 +1,291 @@
 //! Verihash core hashing primitives
+//!
+//! # Integer serialization
+//!
+//! Integers are always hashed as their 64-bit little endian serialization,
+//! never as the `vint64` encoding they're carried in on the wire (which is
+//! also little endian, but variable-width). This applies to `uint64` and
+//! `sint64` values (the latter in two's complement), tags, and the lengths
+//! of dynamically sized values. The digest of a value is therefore
+//! independent of how it was encoded, and a value contributes identically
+//! to a digest whether it's a field of a message or an element of a
+//! sequence (sans the tag which precedes message fields).
+//!
+//! # Framing
+//!
+//! [`Hasher`] exposes the primitives digests are built from, so other data
+//! (e.g. a session nonce or a file's contents) can be bound into a digest
+//! using the same construction. Each call inputs the following bytes into
+//! the underlying hash function, where `LE64(n)` is the 64-bit little
+//! endian serialization of `n`, and `wire_type` is the domain tag for a
+//! [`WireType`] given by [`VerihashParams::domain_tag`] (by default, the
+//! wire type's byte):
+//!
+//! | Method                                     | Bytes input                  |
+//! |--------------------------------------------|------------------------------|
+//! | [`Hasher::tag`]                            | `TAG` ‖ `LE64(tag)`          |
+//! | [`Hasher::fixed_size_value`]               | `wire_type` ‖ `body`         |
+//! | [`Hasher::dynamically_sized_value`]        | `wire_type` ‖ `LE64(length)` |
+//! | [`Hasher::input`]                          | `data`                       |
+//! | [`Hasher::digest_value`]                   | `wire_type` ‖ `digest`       |
+//! | [`Hasher::nested_digest`]                  | `digest`                     |
+//!
+//! The other methods are composed of these: booleans are fixed size values
+//! with a one byte body (`FALSE 0x00` for `false`, `TRUE 0x01` for `true`),
+//! and integers are fixed size values with an `LE64` body. The tag of a
+//! message field is hashed immediately before its value, and the body of a
+//! dynamically sized value is input after its length.
+//!
+//! For example, a `string` field with tag `1` and value `"hello"`:
+//!
+//! ```
+//! # #[cfg(feature = "sha2")]
+//! # {
+//! use veriform::{field::WireType, verihash::Hasher};
+//!
+//! let mut hasher = Hasher::<sha2::Sha256>::new();
+//! hasher.tag(1);
+//! hasher.dynamically_sized_value(WireType::String, 5);
+//! hasher.input(b"hello");
+//!
+//! // `02 0100000000000000 05 0500000000000000 68656c6c6f` (default params)
+//! assert_eq!(
+//!     hasher.finish().as_slice(),
+//!     &[
+//!         0xf3, 0x26, 0x18, 0x24, 0xe0, 0xad, 0xe9, 0xaf, 0x71, 0xc5, 0x71, 0x1a, 0x44, 0xac,
+//!         0x39, 0xb0, 0x7b, 0x96, 0xa4, 0x5b, 0x80, 0xba, 0x76, 0x8f, 0x6c, 0xa1, 0x8a, 0x02,
+//!         0x21, 0x7f, 0x93, 0xe4
+//!     ]
+//! );
+//! # }
+//! ```
+//!
+//! # Empty messages
+//!
+//! Nothing is input for a message besides its fields, so the digest of a
+//! message with no fields is the digest of the empty string. This is the
+//! digest of an empty input, and of a nested message with a zero-length
+//! body, which is hashed into its parent like any other nested message.
+//!
+//! # Domain tags
+//!
+//! The bytes identifying tags and each wire type are the associated
+//! constants of [`VerihashParams`], so protocols aligning their transcript
+//! format with an external specification can supply their own. Changing any
+//! of them changes every digest computed with them, which are incompatible
+//! with digests computed using [`DefaultParams`].
+//!
+//! The params used to compute a digest are those of its hash function
+//! ([`VerihashDigest::Params`]), which are [`DefaultParams`] for the hash
+//! functions Veriform impls [`VerihashDigest`] for. To decode messages (or
+//! compute the digests of fields) using other params, wrap the hash
+//! function in an [`Adapter`] which supplies them, e.g.
+//! `Decoder<Adapter<Sha256, MyParams>>`.
 
 // TODO(tarcieri): refactor/DRY out message/sequence hashers into this module
 
 use crate::field::{Tag, WireType};
-use digest::{generic_array::GenericArray, Digest};
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+use digest::{generic_array::GenericArray, Digest, FixedOutputDirty, Reset, Update};
 
 /// Output of a given digest algorithm
 pub type DigestOutput<D> = GenericArray<u8, <D as Digest>::OutputSize>;
 
-/// Verihash prefix used by tags (unsigned integer)
-// TODO(tarcieri): support string tags?
-const TAG_PREFIX: u8 = WireType::UInt64.to_u8();
+/// Hash functions used to compute Verihash digests.
+///
+/// Digests of nested messages and sequences are input into the digest of
+/// the message or sequence containing them using
+/// [`VerihashDigest::input_digest`] rather than as ordinary data, so that
+/// hash functions which combine several others (e.g. [`MultiDigest`]) can
+/// give each of them the digest it computed itself.
+///
+/// This is impl'd for the SHA-2 and BLAKE2 hash functions when the `sha2`
+/// and `blake2` features of this crate are enabled. Any other [`Digest`] can
+/// be used by wrapping it in an [`Adapter`].
+///
+/// [`MultiDigest`]: crate::MultiDigest
+pub trait VerihashDigest: Digest {
+    /// Domain tags input when computing digests with this hash function
+    type Params: VerihashParams;
 
-/// Verihash hasher: computes digests of both messages and sequences
-pub(crate) struct Hasher<D: Digest>(D);
+    /// Input the digest of a nested message or sequence, which was computed
+    /// using this hash function
+    fn input_digest(&mut self, digest: &DigestOutput<Self>) {
+        self.update(digest);
+    }
+}
+
+/// Impl [`VerihashDigest`] for hash functions which input nested digests
+/// like any other data, using the [`DefaultParams`]
+#[cfg(any(feature = "sha2", feature = "blake2"))]
+macro_rules! impl_verihash_digest {
+    ($($digest:ty),+) => {
+        $(impl VerihashDigest for $digest {
+            type Params = DefaultParams;
+        })+
+    };
+}
+
+#[cfg(feature = "sha2")]
+impl_verihash_digest!(
+    sha2::Sha224,
+    sha2::Sha256,
+    sha2::Sha384,
+    sha2::Sha512,
+    sha2::Sha512Trunc224,
+    sha2::Sha512Trunc256
+);
+
+#[cfg(feature = "blake2")]
+impl_verihash_digest!(blake2::Blake2b, blake2::Blake2s);
+
+/// Adapter for computing Verihash digests using a [`Digest`] which doesn't
+/// impl [`VerihashDigest`] itself, or using [`VerihashParams`] other than
+/// the [`DefaultParams`].
+///
+/// The output is identical to that of the wrapped hash function.
+pub struct Adapter<D, P = DefaultParams>(D, PhantomData<P>);
+
+impl<D, P> VerihashDigest for Adapter<D, P>
+where
+    D: Digest + Clone + Default,
+    P: VerihashParams,
+{
+    type Params = P;
+}
+
+impl<D, P> Clone for Adapter<D, P>
+where
+    D: Clone,
+{
+    fn clone(&self) -> Self {
+        Adapter(self.0.clone(), PhantomData)
+    }
+}
 
-impl<D> Hasher<D>
+impl<D, P> Debug for Adapter<D, P>
+where
+    D: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Adapter").field(&self.0).finish()
+    }
+}
+
+impl<D, P> Default for Adapter<D, P>
+where
+    D: Default,
+{
+    fn default() -> Self {
+        Adapter(D::default(), PhantomData)
+    }
+}
+
+impl<D, P> Update for Adapter<D, P>
 where
     D: Digest,
+{
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.0.update(data);
+    }
+}
+
+impl<D, P> FixedOutputDirty for Adapter<D, P>
+where
+    D: Digest,
+{
+    type OutputSize = D::OutputSize;
+
+    fn finalize_into_dirty(&mut self, out: &mut DigestOutput<D>) {
+        out.copy_from_slice(&self.0.finalize_reset());
+    }
+}
+
+impl<D, P> Reset for Adapter<D, P>
+where
+    D: Digest,
+{
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+/// Domain tags input ahead of tags and values of each wire type.
+///
+/// The defaults are used by [`DefaultParams`]. Overriding any of them
+/// changes all digests computed using them: see the [module
+/// documentation](self).
+pub trait VerihashParams {
+    /// Domain tag for tags (by default, that of unsigned integers)
+    // TODO(tarcieri): support string tags?
+    const TAG: u8 = WireType::UInt64.to_u8();
+
+    /// Domain tag for `false`
+    const FALSE: u8 = WireType::False.to_u8();
+
+    /// Domain tag for `true`
+    const TRUE: u8 = WireType::True.to_u8();
+
+    /// Domain tag for `uint64` values
+    const UINT64: u8 = WireType::UInt64.to_u8();
+
+    /// Domain tag for `sint64` values
+    const SINT64: u8 = WireType::SInt64.to_u8();
+
+    /// Domain tag for `bytes` values
+    const BYTES: u8 = WireType::Bytes.to_u8();
+
+    /// Domain tag for `string` values
+    const STRING: u8 = WireType::String.to_u8();
+
+    /// Domain tag for nested messages
+    const MESSAGE: u8 = WireType::Message.to_u8();
+
+    /// Domain tag for sequences
+    const SEQUENCE: u8 = WireType::Sequence.to_u8();
+
+    /// Get the domain tag for the given wire type
+    fn domain_tag(wire_type: WireType) -> u8 {
+        match wire_type {
+            WireType::False => Self::FALSE,
+            WireType::True => Self::TRUE,
+            WireType::UInt64 => Self::UINT64,
+            WireType::SInt64 => Self::SINT64,
+            WireType::Bytes => Self::BYTES,
+            WireType::String => Self::STRING,
+            WireType::Message => Self::MESSAGE,
+            WireType::Sequence => Self::SEQUENCE,
+        }
+    }
+}
+
+/// Default Verihash domain tags, i.e. the byte of each wire type
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DefaultParams;
+
+impl VerihashParams for DefaultParams {}
+
+/// Verihash hasher: computes digests of both messages and sequences.
+///
+/// See the [module documentation](self) for the bytes each method inputs
+/// into the underlying hash function. The domain tags are given by `P`,
+/// which defaults to the params of the hash function.
+pub struct Hasher<D: VerihashDigest, P: VerihashParams = <D as VerihashDigest>::Params>(
+    D,
+    PhantomData<P>,
+);
+
+impl<D, P> Hasher<D, P>
+where
+    D: VerihashDigest,
+    P: VerihashParams,
 {
     /// Create a new Verihash hasher
     pub fn new() -> Self {
-        Hasher(D::new())
+        Hasher(D::new(), PhantomData)
     }
 
     /// Hash a tagged boolean value
@@ -45,7 +309,7 @@ where
         self.uint64(value);
     }
 
-    /// Hash an unsigned 64-bit integer
+    /// Hash an unsigned 64-bit integer (as 8 little endian bytes)
     pub fn uint64(&mut self, value: u64) {
         self.fixed_size_value(WireType::UInt64, &value.to_le_bytes());
     }
@@ -56,46 +320,295 @@ where
         self.sint64(value);
     }
 
-    /// Hash a signed 64-bit integer
+    /// Hash a signed 64-bit integer (as 8 little endian bytes)
     pub fn sint64(&mut self, value: i64) {
         self.fixed_size_value(WireType::SInt64, &value.to_le_bytes());
     }
 
-    /// Hash a numerical tag
+    /// Hash a numerical tag, i.e. a label for the value which follows
     // TODO(tarcieri): support string tags?
     pub fn tag(&mut self, tag: Tag) {
-        self.update(&[TAG_PREFIX]);
-        self.update(&tag.to_le_bytes());
+        self.input(&[P::TAG]);
+        self.input(&tag.to_le_bytes());
     }
 
-    /// Hash a dynamically sized value
+    /// Hash the wire type and length of a dynamically sized value.
+    ///
+    /// The body of the value must be hashed afterward using
+    /// [`Hasher::input`].
     pub fn dynamically_sized_value(&mut self, wire_type: WireType, length: usize) {
-        self.update(&[wire_type.to_u8()]);
-        self.update(&(length as u64).to_le_bytes());
+        self.input(&[P::domain_tag(wire_type)]);
+        self.input(&(length as u64).to_le_bytes());
     }
 
     /// Hash an untagged value
     pub fn fixed_size_value(&mut self, wire_type: WireType, body: &[u8]) {
-        self.update(&[wire_type.to_u8()]);
-        self.update(body);
+        self.input(&[P::domain_tag(wire_type)]);
+        self.input(body);
     }
 
-    /// Update data directly into the underlying hash function
-    pub fn update(&mut self, data: &[u8]) {
-        self.0.update(data);
+    /// Hash the digest of a nested message or sequence, prefixed with the
+    /// given wire type
+    pub fn digest_value(&mut self, wire_type: WireType, digest: &DigestOutput<D>) {
+        self.input(&[P::domain_tag(wire_type)]);
+        self.nested_digest(digest);
+    }
+
+    /// Hash the digest of a nested message or sequence, using
+    /// [`VerihashDigest::input_digest`]
+    pub fn nested_digest(&mut self, digest: &DigestOutput<D>) {
+        self.0.input_digest(digest);
+    }
+
+    /// Input data directly into the underlying hash function, e.g. the body
+    /// of a dynamically sized value
+    pub fn input(&mut self, data: &[u8]) {
+        if !data.is_empty() {
+            self.0.update(data);
+        }
     }
 
     /// Finish computing the digest, returning the output value
-    pub fn finalize(self) -> DigestOutput<D> {
+    pub fn finish(self) -> DigestOutput<D> {
         self.0.finalize()
     }
 }
 
-impl<D> Default for Hasher<D>
+impl<D, P> Clone for Hasher<D, P>
 where
-    D: Digest,
+    D: VerihashDigest + Clone,
+    P: VerihashParams,
+{
+    fn clone(&self) -> Self {
+        Hasher(self.0.clone(), PhantomData)
+    }
+}
+
+impl<D, P> Default for Hasher<D, P>
+where
+    D: VerihashDigest,
+    P: VerihashParams,
 {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Compute the digest of a `uint64` field with the given tag and value, i.e.
+/// the digest of a message containing only that field.
+///
+/// This is the digest recorded for the field by
+/// [`Decoder::field_digests`], so it can be computed ahead of time (e.g. to
+/// commit to a field's value) without encoding and decoding a message.
+///
+/// [`Decoder::field_digests`]: crate::decoder::Decoder::field_digests
+pub fn hash_uint64<D: VerihashDigest>(tag: Tag, value: u64) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tagged_uint64(tag, value);
+    hasher.finish()
+}
+
+/// Compute the digest of a `sint64` field with the given tag and value
+/// (see [`hash_uint64`])
+pub fn hash_sint64<D: VerihashDigest>(tag: Tag, value: i64) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tagged_sint64(tag, value);
+    hasher.finish()
+}
+
+/// Compute the digest of a `bool` field with the given tag and value
+/// (see [`hash_uint64`])
+pub fn hash_bool<D: VerihashDigest>(tag: Tag, value: bool) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tagged_boolean(tag, value);
+    hasher.finish()
+}
+
+/// Compute the digest of a `bytes` field with the given tag and value
+/// (see [`hash_uint64`])
+pub fn hash_bytes<D: VerihashDigest>(tag: Tag, value: &[u8]) -> DigestOutput<D> {
+    hash_dynamically_sized::<D>(tag, WireType::Bytes, value)
+}
+
+/// Compute the digest of a `string` field with the given tag and value
+/// (see [`hash_uint64`])
+pub fn hash_string<D: VerihashDigest>(tag: Tag, value: &str) -> DigestOutput<D> {
+    hash_dynamically_sized::<D>(tag, WireType::String, value.as_bytes())
+}
+
+/// Compute the digest of a `message` field with the given tag, whose
+/// nested message body is `length` bytes long and has the given digest
+/// (see [`hash_uint64`]).
+///
+/// As when decoding, the tag is hashed ahead of both the length of the
+/// body and its digest.
+///
+/// The digest of the nested message can itself be computed from its body
+/// with [`decoder::hash_message`], or it can be given without the body,
+/// e.g. when the nested message has been redacted.
+///
+/// [`decoder::hash_message`]: crate::decoder::hash_message
+pub fn hash_message_field<D: VerihashDigest>(
+    tag: Tag,
+    length: usize,
+    digest: &DigestOutput<D>,
+) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tag(tag);
+    hasher.dynamically_sized_value(WireType::Message, length);
+    hasher.tag(tag);
+    hasher.digest_value(WireType::Message, digest);
+    hasher.finish()
+}
+
+/// Compute the digest of a `sequence` field with the given tag, whose body
+/// has the given digest (see [`hash_uint64`]).
+///
+/// Unlike nested messages, the length of a sequence isn't hashed: only the
+/// tag and the digest of its body are.
+///
+/// The digest of the body can itself be computed with
+/// [`decoder::hash_sequence`], or it can be given without the body,
+/// e.g. when the sequence has been redacted.
+///
+/// [`decoder::hash_sequence`]: crate::decoder::hash_sequence
+pub fn hash_sequence_field<D: VerihashDigest>(
+    tag: Tag,
+    digest: &DigestOutput<D>,
+) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tag(tag);
+    hasher.digest_value(WireType::Sequence, digest);
+    hasher.finish()
+}
+
+/// Compute the digest of a dynamically sized field
+fn hash_dynamically_sized<D: VerihashDigest>(
+    tag: Tag,
+    wire_type: WireType,
+    value: &[u8],
+) -> DigestOutput<D> {
+    let mut hasher = Hasher::<D>::new();
+    hasher.tag(tag);
+    hasher.dynamically_sized_value(wire_type, value.len());
+    hasher.input(value);
+    hasher.finish()
+}
+
+#[cfg(all(test, feature = "sha2"))]
+mod tests {
+    use super::{DefaultParams, Hasher, VerihashParams};
+    use crate::field::WireType;
+    use digest::Digest;
+    use sha2::Sha256;
+
+    /// Hash using the given function, asserting the result is the digest
+    /// of the given framing bytes
+    fn assert_framing(expected: &[u8], f: impl FnOnce(&mut Hasher<Sha256>)) {
+        let mut hasher = Hasher::new();
+        f(&mut hasher);
+        assert_eq!(hasher.finish(), Sha256::digest(expected));
+    }
+
+    #[test]
+    fn tag() {
+        assert_framing(b"\x02\x2a\0\0\0\0\0\0\0", |h| h.tag(42));
+    }
+
+    #[test]
+    fn fixed_size_value() {
+        assert_framing(b"\x03\xd6\xff\xff\xff\xff\xff\xff\xff", |h| {
+            h.fixed_size_value(WireType::SInt64, &(-42i64).to_le_bytes())
+        });
+    }
+
+    #[test]
+    fn dynamically_sized_value() {
+        assert_framing(b"\x04\x03\0\0\0\0\0\0\0foo", |h| {
+            h.dynamically_sized_value(WireType::Bytes, 3);
+            h.input(b"foo");
+        });
+    }
+
+    #[test]
+    fn input() {
+        assert_framing(b"nonce", |h| {
+            h.input(b"");
+            h.input(b"nonce");
+        });
+    }
+
+    #[test]
+    fn booleans() {
+        assert_framing(b"\x00\x00\x01\x01", |h| {
+            h.boolean(false);
+            h.boolean(true);
+        });
+    }
+
+    #[test]
+    fn tagged_integers() {
+        let expected = [
+            0x2d, 0x97, 0xb5, 0xe2, 0x71, 0x0c, 0xb9, 0x45, 0x68, 0x70, 0x50, 0xe5, 0xa8, 0x22,
+            0x0c, 0x1d, 0x3f, 0x70, 0x48, 0x3b, 0x92, 0x7e, 0xa2, 0x7d, 0xb5, 0xf6, 0x16, 0x21,
+            0x18, 0xd7, 0xc0, 0xcd,
+        ];
+
+        let mut hasher = Hasher::<Sha256>::new();
+        hasher.tagged_uint64(42, 1234);
+        assert_eq!(hasher.finish().as_slice(), &expected);
+    }
+
+    #[test]
+    fn digest_value() {
+        let digest = Sha256::digest(b"nested");
+        let mut expected = [WireType::Message.to_u8(); 33];
+        expected[1..].copy_from_slice(&digest);
+
+        assert_framing(&expected, |h| h.digest_value(WireType::Message, &digest));
+    }
+
+    #[test]
+    fn default_params() {
+        // Same vector as in the module documentation
+        let expected = [
+            0xf3, 0x26, 0x18, 0x24, 0xe0, 0xad, 0xe9, 0xaf, 0x71, 0xc5, 0x71, 0x1a, 0x44, 0xac,
+            0x39, 0xb0, 0x7b, 0x96, 0xa4, 0x5b, 0x80, 0xba, 0x76, 0x8f, 0x6c, 0xa1, 0x8a, 0x02,
+            0x21, 0x7f, 0x93, 0xe4,
+        ];
+
+        let mut hasher = Hasher::<Sha256, DefaultParams>::new();
+        hasher.tag(1);
+        hasher.dynamically_sized_value(WireType::String, 5);
+        hasher.input(b"hello");
+        assert_eq!(hasher.finish().as_slice(), &expected);
+    }
+
+    #[test]
+    fn custom_params() {
+        /// Domain tags shifted into the upper nibble
+        struct Shifted;
+
+        impl VerihashParams for Shifted {
+            const TAG: u8 = 0x20;
+            const BYTES: u8 = 0x40;
+        }
+
+        let mut default = Hasher::<Sha256>::new();
+        default.tag(1);
+        default.dynamically_sized_value(WireType::Bytes, 3);
+        default.input(b"foo");
+
+        let mut custom = Hasher::<Sha256, Shifted>::new();
+        custom.tag(1);
+        custom.dynamically_sized_value(WireType::Bytes, 3);
+        custom.input(b"foo");
+
+        let custom = custom.finish();
+        assert_eq!(
+            custom,
+            Sha256::digest(b"\x20\x01\0\0\0\0\0\0\0\x40\x03\0\0\0\0\0\0\0foo")
+        );
+        assert_ne!(custom, default.finish());
+    }
+}
@@ -0,0 +1,33 @@
+//! Integration tests for `veriform::Decoder::decode_bytes_shared`
+
+#![cfg(feature = "bytes")]
+
+use bytes::Bytes;
+use veriform::{consts::Literal, decoder::Decode, Decoder};
+
+#[test]
+fn shares_storage_with_input() {
+    let encoded = Literal::new().bytes(1, b"payload").uint64(2, 42);
+    let mut input = Bytes::copy_from_slice(encoded.as_bytes());
+    let start = input.as_ptr() as usize;
+    let end = start + input.len();
+
+    let payload = {
+        let mut decoder: Decoder = Decoder::new();
+        let payload = decoder.decode_bytes_shared(1, &mut input).unwrap();
+
+        // The input is advanced past the field
+        let value: u64 = decoder.decode(2, &mut input.as_ref()).unwrap();
+        assert_eq!(value, 42);
+
+        payload
+    };
+
+    // The payload points into the input rather than a copy of it
+    let ptr = payload.as_ptr() as usize;
+    assert!(ptr >= start && ptr + payload.len() <= end);
+
+    // ...and remains valid once the input and decoder are gone
+    drop(input);
+    assert_eq!(payload, &b"payload"[..]);
+}
@@ -0,0 +1,91 @@
+//! Integration tests for cancelling decoding
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use veriform::{consts::Literal, decoder::Decode, error, Decoder, Message};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Wide {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub a: u64,
+
+    #[field(tag = 1, wire_type = "uint64")]
+    pub b: u64,
+
+    #[field(tag = 2, wire_type = "message")]
+    pub inner: Inner,
+
+    #[field(tag = 3, wire_type = "uint64")]
+    pub c: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub a: u64,
+
+    #[field(tag = 1, wire_type = "uint64")]
+    pub b: u64,
+}
+
+/// Number of fields checked by [`cancel_after_four_fields`]
+static FIELDS_CHECKED: AtomicUsize = AtomicUsize::new(0);
+
+/// Cancel decoding once four fields have been decoded
+fn cancel_after_four_fields() -> bool {
+    FIELDS_CHECKED.fetch_add(1, Ordering::SeqCst) >= 4
+}
+
+fn never_cancel() -> bool {
+    false
+}
+
+fn example_message() -> Literal {
+    const INNER: Literal = Literal::new().uint64(0, 3).uint64(1, 4);
+    Literal::new()
+        .uint64(0, 1)
+        .uint64(1, 2)
+        .message(2, &INNER)
+        .uint64(3, 5)
+}
+
+#[test]
+fn cancel_after_n_fields() {
+    let encoded = example_message();
+    let mut decoder = Decoder::new();
+    decoder.set_cancel_check(Some(cancel_after_four_fields));
+
+    let err = Wide::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Cancelled);
+
+    // Cancelled before the second field of the nested message, after
+    // consuming its first field: the position is an offset into the body
+    // of the nested message, not into the input as a whole
+    assert_eq!(err.position(), Some(2));
+    assert_eq!(FIELDS_CHECKED.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn cancel_at_top_level_field() {
+    let encoded = Literal::new().uint64(0, 1).uint64(1, 2).uint64(2, 3);
+    let mut decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let _: u64 = decoder.decode(0, &mut input).unwrap();
+
+    decoder.set_cancel_check(Some(|| true));
+    let err = decoder.decode_field(&mut input).err().unwrap();
+    assert_eq!(err.kind(), error::Kind::Cancelled);
+    assert_eq!(err.position(), Some(2));
+
+    // The cancelled field hasn't been consumed
+    assert_eq!(encoded.len() - input.len(), 2);
+}
+
+#[test]
+fn not_cancelled() {
+    let encoded = example_message();
+    let mut decoder = Decoder::new();
+    decoder.set_cancel_check(Some(never_cancel));
+
+    let msg = Wide::decode(&mut decoder, encoded.as_bytes()).unwrap();
+    assert_eq!(msg.c, 5);
+}
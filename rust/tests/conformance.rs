@@ -0,0 +1,280 @@
+//! Conformance tests against the fixtures shared with other implementations
+
+#![cfg(feature = "conformance")]
+
+use sha2::Sha256;
+use veriform::{
+    conformance::{self, Check, Expected, Field, Fixture, Value},
+    decoder::{hash_message, Decoder},
+    error,
+    field::WireType,
+    verihash::{Adapter, DefaultParams, VerihashParams},
+};
+
+/// Fixtures shared with other implementations
+const FIXTURES: &str = include_str!("../../vectors/conformance.tjson");
+
+#[test]
+fn shared_fixtures() {
+    let fixtures = conformance::load(FIXTURES).unwrap();
+    assert!(!fixtures.is_empty());
+
+    let mismatches = fixtures
+        .iter()
+        .filter_map(|fixture| fixture.check().err())
+        .map(|mismatch| mismatch.to_string())
+        .collect::<Vec<_>>();
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+#[test]
+fn verihash_params() {
+    /// Domain tags shifted into the upper nibble
+    struct Shifted;
+
+    impl VerihashParams for Shifted {
+        const TAG: u8 = 0x20;
+        const UINT64: u8 = 0x20;
+        const SINT64: u8 = 0x30;
+        const MESSAGE: u8 = 0x60;
+    }
+
+    let fixtures = conformance::load(FIXTURES).unwrap();
+
+    for fixture in &fixtures {
+        let digest = match &fixture.expected {
+            Expected::Message { digest, .. } => digest,
+            Expected::Error { .. } => continue,
+        };
+
+        // The default params reproduce the committed vectors
+        let default = hash_message::<Adapter<Sha256, DefaultParams>>(&fixture.encoded).unwrap();
+        assert_eq!(default.as_slice(), &digest[..], "{}", fixture.name);
+
+        // Custom params are used both when decoding and when hashing
+        let mut decoder = Decoder::<Adapter<Sha256, Shifted>>::new();
+        decoder.decode_owned(&fixture.encoded).unwrap();
+
+        let mut custom = [0u8; 32];
+        decoder.fill_digest(&mut custom).unwrap();
+        assert_eq!(
+            custom.as_ref(),
+            hash_message::<Adapter<Sha256, Shifted>>(&fixture.encoded)
+                .unwrap()
+                .as_slice(),
+            "{}",
+            fixture.name
+        );
+
+        // ...and change the digest of any message with fields
+        if !fixture.encoded.is_empty() {
+            assert_ne!(&custom, digest, "{}", fixture.name);
+        }
+    }
+}
+
+#[test]
+fn load_fixture() {
+    let fixtures = conformance::load(
+        r#"{
+            "examples:A<O>": [{
+                "name:s": "Example",
+                "success:b": true,
+                "encoded:d16": "2d05255532ff01",
+                "decoded:O": {
+                    "1:O": { "1:u": "42" },
+                    "3!:A<b>": [true]
+                },
+                "digest:d16": "0000000000000000000000000000000000000000000000000000000000000000"
+            }]
+        }"#,
+    )
+    .unwrap();
+
+    let expected = vec![
+        Field {
+            tag: 1,
+            critical: false,
+            value: Value::Message(vec![Field {
+                tag: 1,
+                critical: false,
+                value: Value::UInt64(42),
+            }]),
+        },
+        Field {
+            tag: 3,
+            critical: true,
+            value: Value::Sequence(veriform::field::WireType::False, vec![Value::Bool(true)]),
+        },
+    ];
+
+    assert_eq!(fixtures.len(), 1);
+    assert_eq!(fixtures[0].name, "Example");
+    assert_eq!(
+        fixtures[0].expected,
+        Expected::Message {
+            fields: expected,
+            digest: [0; 32]
+        }
+    );
+}
+
+#[test]
+fn report_mismatch() {
+    let mut fixtures = conformance::load(FIXTURES).unwrap();
+    let fixture = fixtures
+        .iter_mut()
+        .find(|fixture| fixture.name == "uint64 field")
+        .unwrap();
+
+    if let Expected::Message { digest, .. } = &mut fixture.expected {
+        digest[0] ^= 1;
+    }
+
+    let mismatch = fixture.check().unwrap_err();
+    assert_eq!(mismatch.fixture, "uint64 field");
+    assert_eq!(mismatch.check, Check::Hash);
+
+    fixture.encoded.push(0);
+    assert_eq!(fixture.check().unwrap_err().check, Check::Decode);
+}
+
+#[test]
+fn load_malformed_fixture_file() {
+    assert!(conformance::load("{}").is_err());
+    assert!(conformance::load(r#"{ "examples:A<O>": [{ "name:s": "No encoding" }] }"#).is_err());
+}
+
+#[test]
+fn recover_from_invalid_value() {
+    // 1: uint64 = 42, 2: string = <invalid UTF-8>, 3: uint64 = 7
+    let encoded = hex("2555 4b05fffe 650f");
+    assert!(conformance::decode(&encoded).is_err());
+
+    let recovered = conformance::decode_with_recovery(&encoded).unwrap();
+    let tags = recovered
+        .fields
+        .iter()
+        .map(|field| field.tag)
+        .collect::<Vec<_>>();
+    assert_eq!(tags, [1, 3]);
+    assert_eq!(recovered.fields[1].value, Value::UInt64(7));
+
+    assert_eq!(recovered.errors.len(), 1);
+    let skipped = &recovered.errors[0];
+    assert_eq!(skipped.header.tag, 2);
+    assert_eq!(skipped.header.wire_type, WireType::String);
+    assert_eq!(skipped.position, 2);
+    assert_eq!(skipped.error.kind(), error::Kind::Utf8 { valid_up_to: 0 });
+}
+
+#[test]
+fn recover_from_out_of_order_field() {
+    // 3: uint64 = 7, 2: uint64 = 42, 4: uint64 = 1
+    let encoded = hex("650f 4555 8503");
+    let recovered = conformance::decode_with_recovery(&encoded).unwrap();
+
+    let tags = recovered
+        .fields
+        .iter()
+        .map(|field| field.tag)
+        .collect::<Vec<_>>();
+    assert_eq!(tags, [3, 4]);
+    assert_eq!(
+        recovered.errors[0].error.kind(),
+        error::Kind::Order { tag: 2 }
+    );
+}
+
+#[test]
+fn unrecoverable_length() {
+    // 1: uint64 = 42, 2: bytes with a length overrunning the message
+    let encoded = hex("2555 4911ff");
+    let err = conformance::decode_with_recovery(&encoded).unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::Truncated {
+            remaining: 7,
+            wire_type: WireType::Bytes
+        }
+    );
+}
+
+#[test]
+fn reject_lengthened_varints() {
+    let fixtures = conformance::load(FIXTURES).unwrap();
+
+    for fixture in &fixtures {
+        if let Expected::Error { .. } = fixture.expected {
+            continue;
+        }
+
+        // Lengthen the header of each top-level field, then its value or
+        // length delimiter (booleans have neither)
+        let encoded = &fixture.encoded[..];
+        let mut input = encoded;
+
+        while !input.is_empty() {
+            assert_rejects_lengthened(fixture, encoded.len() - input.len());
+            let wire_type = WireType::from_unmasked(vint64::decode(&mut input).unwrap());
+
+            if wire_type == WireType::False || wire_type == WireType::True {
+                continue;
+            }
+
+            assert_rejects_lengthened(fixture, encoded.len() - input.len());
+            let value = vint64::decode(&mut input).unwrap();
+
+            input = &input[match wire_type {
+                WireType::UInt64 | WireType::SInt64 => 0,
+                WireType::Sequence => value as usize >> 4,
+                _ => value as usize,
+            }..];
+        }
+    }
+}
+
+/// Re-encode the `vint64` at the given offset of a fixture's encoded
+/// message one byte longer than necessary, and ensure decoding fails
+fn assert_rejects_lengthened(fixture: &Fixture, offset: usize) {
+    let mut rest = &fixture.encoded[offset..];
+    let value = vint64::decode(&mut rest).unwrap();
+    let length = fixture.encoded.len() - offset - rest.len();
+
+    // Nine-byte values have no longer encoding
+    if length == vint64::MAX_BYTES {
+        return;
+    }
+
+    let mut encoded = fixture.encoded[..offset].to_vec();
+
+    if length + 1 == vint64::MAX_BYTES {
+        encoded.push(0);
+        encoded.extend_from_slice(&value.to_le_bytes());
+    } else {
+        let prefix = 1 << length;
+        encoded.extend_from_slice(&((value << (length + 1)) | prefix).to_le_bytes()[..=length]);
+    }
+
+    encoded.extend_from_slice(rest);
+
+    let err = conformance::decode(&encoded).unwrap_err();
+    assert_eq!(
+        (err.kind(), err.position()),
+        (error::Kind::NonCanonicalVarint, Some(offset)),
+        "{} (offset {})",
+        fixture.name,
+        offset
+    );
+}
+
+/// Decode hex with optional whitespace
+fn hex(s: &str) -> Vec<u8> {
+    let digits = s.replace(' ', "");
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+        .collect()
+}
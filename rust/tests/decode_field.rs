@@ -0,0 +1,267 @@
+//! Integration tests for `veriform::decoder::Decoder::decode_field`
+
+#![cfg(feature = "test-util")]
+
+use veriform::{
+    decoder::{self, hash_message, sequence, Decode},
+    error,
+    field::WireType,
+    test_util::MessageBuilder,
+    verihash::VerihashDigest,
+    Decoder, Encoder, Error, Message, Sha256Digest,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 1, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[field(tag = 3, wire_type = "message")]
+    pub message_field: Inner,
+}
+
+/// Message whose fields are all optional, decoded by dispatching on the
+/// tag of each field as it's encountered
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Optional {
+    pub uint64_field: Option<u64>,
+    pub sint64_field: Option<i64>,
+    pub message_field: Option<Inner>,
+}
+
+impl Message for Optional {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let mut msg = Optional::default();
+
+        while let Some(field) = decoder.decode_field(&mut input)? {
+            match field.tag() {
+                1 => msg.uint64_field = Some(field.uint64(&mut input)?),
+                2 => msg.sint64_field = Some(field.sint64(&mut input)?),
+                3 => msg.message_field = Some(field.message(&mut input)?),
+                _ => field.skip(&mut input)?,
+            }
+        }
+
+        Ok(msg)
+    }
+
+    fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        let mut encoder = Encoder::new(buffer);
+
+        if let Some(value) = self.uint64_field {
+            encoder.uint64(1, false, value)?;
+        }
+
+        if let Some(value) = self.sint64_field {
+            encoder.sint64(2, false, value)?;
+        }
+
+        if let Some(inner) = &self.message_field {
+            encoder.message(3, false, inner)?;
+        }
+
+        Ok(encoder.finish())
+    }
+
+    fn encoded_len(&self) -> usize {
+        let mut buffer = [0u8; 64];
+        self.encode(&mut buffer).unwrap().len()
+    }
+}
+
+/// Decode a message of the given type, returning it along with its digest
+fn decode<M: Message>(input: &[u8]) -> Result<(M, Sha256Digest), Error> {
+    let mut decoder = Decoder::new();
+    let msg = M::decode(&mut decoder, input)?;
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest)?;
+    Ok((msg, digest))
+}
+
+#[test]
+fn dispatch_on_tag() {
+    let inner = MessageBuilder::new().uint64(0, 42).sint64(1, -42).build();
+
+    let messages = [
+        MessageBuilder::new().build(),
+        MessageBuilder::new().uint64(1, 1).build(),
+        MessageBuilder::new().sint64(2, -2).build(),
+        MessageBuilder::new()
+            .uint64(1, 1)
+            .message(3, &inner)
+            .build(),
+        MessageBuilder::new()
+            .uint64(1, 1)
+            .sint64(2, -2)
+            .message(3, &inner)
+            .bytes(4, b"unknown")
+            .build(),
+    ];
+
+    for message in &messages {
+        let (msg, digest) = decode::<Optional>(message).unwrap();
+        assert_eq!(msg.encode_vec().unwrap(), message[..msg.encoded_len()]);
+        assert_eq!(
+            &digest[..],
+            &hash_message::<sha2::Sha256>(message).unwrap()[..]
+        );
+    }
+}
+
+#[test]
+fn digest_matches_tag_directed_decode() {
+    let outer = Outer {
+        uint64_field: 1,
+        sint64_field: -2,
+        message_field: Inner {
+            uint64_field: 42,
+            sint64_field: -42,
+        },
+    };
+
+    let encoded = outer.encode_vec().unwrap();
+    let (optional, optional_digest) = decode::<Optional>(&encoded).unwrap();
+    let (decoded, expected_digest) = decode::<Outer>(&encoded).unwrap();
+
+    assert_eq!(decoded, outer);
+    assert_eq!(optional.message_field, Some(outer.message_field));
+    assert_eq!(optional_digest, expected_digest);
+}
+
+#[test]
+fn decode_sequence_field() {
+    let mut body = Vec::new();
+    for n in 1..=3 {
+        body.extend_from_slice(veriform::vint64::encode(n).as_ref());
+    }
+
+    let message = MessageBuilder::new()
+        .sequence(1, WireType::UInt64, &body)
+        .string(2, "hello")
+        .build();
+
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+
+    let field = decoder.decode_field(&mut input).unwrap().unwrap();
+    assert_eq!(field.tag(), 1);
+    assert_eq!(field.wire_type(), WireType::Sequence);
+
+    let seq: sequence::Iter<'_, '_, u64, _> = field.sequence(&mut input).unwrap();
+    let values = seq.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [1, 2, 3]);
+
+    let field = decoder.decode_field(&mut input).unwrap().unwrap();
+    assert_eq!(field.string(&mut input).unwrap(), "hello");
+    assert!(decoder.decode_field(&mut input).unwrap().is_none());
+
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        &hash_message::<sha2::Sha256>(&message).unwrap()[..]
+    );
+}
+
+#[test]
+fn decode_with_wrong_wire_type() {
+    let message = MessageBuilder::new().uint64(1, 42).build();
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+
+    let field = decoder.decode_field(&mut input).unwrap().unwrap();
+    let err = field.sint64(&mut input).unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::UnexpectedWireType {
+            actual: WireType::UInt64,
+            wanted: WireType::SInt64
+        }
+    );
+}
+
+#[test]
+fn decode_out_of_order_field() {
+    let message = [
+        MessageBuilder::new().uint64(2, 1).build(),
+        MessageBuilder::new().uint64(1, 2).build(),
+    ]
+    .concat();
+
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+
+    let value: u64 = decoder.decode(2, &mut input).unwrap();
+    assert_eq!(value, 1);
+
+    let err = decoder.decode_field(&mut input).err().unwrap();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 1 });
+}
+
+#[test]
+fn decode_fields_until_sentinel() {
+    let message = MessageBuilder::new()
+        .uint64(1, 1)
+        .string(2, "two")
+        .bytes(3, b"three")
+        .uint64(4, 4)
+        .build();
+
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+    let mut tags = Vec::new();
+
+    decoder
+        .decode_fields_until(4, &mut input, |field, input| {
+            tags.push(field.tag());
+            field.skip(input)
+        })
+        .unwrap();
+
+    assert_eq!(tags, [1, 2, 3]);
+
+    let value: u64 = decoder.decode(4, &mut input).unwrap();
+    assert_eq!(value, 4);
+    assert!(input.is_empty());
+
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        &hash_message::<sha2::Sha256>(&message).unwrap()[..]
+    );
+}
+
+#[test]
+fn decode_fields_until_end_of_input() {
+    let message = MessageBuilder::new().uint64(1, 1).sint64(2, -2).build();
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+    let mut tags = Vec::new();
+
+    decoder
+        .decode_fields_until(4, &mut input, |field, input| {
+            tags.push(field.tag());
+            field.skip(input)
+        })
+        .unwrap();
+
+    assert_eq!(tags, [1, 2]);
+    assert!(input.is_empty());
+}
@@ -0,0 +1,107 @@
+//! Integration tests for decoding messages in-place
+
+use heapless::{consts::U4, Vec};
+use veriform::{
+    decoder::{DecodeInto, Decoder},
+    Message,
+};
+
+#[derive(Message, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: Vec<Inner, U4>,
+
+    #[digest(alg = "sha256")]
+    pub digest: Option<veriform::Sha256Digest>,
+}
+
+/// Example outer message
+fn example_outer() -> Outer {
+    let mut msg_sequence_field = Vec::new();
+
+    for n in 1..3 {
+        msg_sequence_field
+            .push(Inner {
+                uint64_field: n,
+                sint64_field: -(n as i64),
+            })
+            .unwrap();
+    }
+
+    Outer {
+        uint64_field: 42,
+        message_field: Inner {
+            uint64_field: 23,
+            sint64_field: -23,
+        },
+        msg_sequence_field,
+        digest: None,
+    }
+}
+
+#[test]
+fn decode_into_default() {
+    let mut buffer = [0u8; 128];
+    let encoded = example_outer().encode(&mut buffer).unwrap();
+
+    let expected = Outer::decode(&mut Decoder::<sha2::Sha256>::new(), encoded).unwrap();
+    assert!(expected.digest.is_some());
+
+    let mut decoded = Outer::default();
+    Outer::decode_into(&mut Decoder::<sha2::Sha256>::new(), encoded, &mut decoded).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn decode_into_replaces_existing_values() {
+    let mut buffer = [0u8; 128];
+    let encoded = example_outer().encode(&mut buffer).unwrap();
+    let expected = Outer::decode(&mut Decoder::<sha2::Sha256>::new(), encoded).unwrap();
+
+    let mut decoded = expected.clone();
+    decoded.uint64_field = 0;
+    decoded.message_field.sint64_field = 0;
+    decoded.msg_sequence_field.push(Inner::default()).unwrap();
+    decoded.digest = Some(Default::default());
+
+    Outer::decode_into(&mut Decoder::<sha2::Sha256>::new(), encoded, &mut decoded).unwrap();
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+fn decode_into_field() {
+    let mut inner_buffer = [0u8; 32];
+    let inner = example_outer().message_field;
+    let inner_encoded = inner.encode(&mut inner_buffer).unwrap();
+
+    // Message with the inner message as field 1
+    let mut input = [0u8; 34];
+    input[0] = 0x2d;
+    input[1] = (inner_encoded.len() << 1 | 1) as u8;
+    input[2..][..inner_encoded.len()].copy_from_slice(inner_encoded);
+    let mut input_ref = &input[..inner_encoded.len() + 2];
+
+    let mut decoded = Inner::default();
+    let mut decoder = Decoder::<sha2::Sha256>::new();
+    decoder
+        .decode_into(1, &mut input_ref, &mut decoded)
+        .unwrap();
+
+    assert_eq!(decoded, inner);
+    assert!(input_ref.is_empty());
+}
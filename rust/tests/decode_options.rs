@@ -0,0 +1,100 @@
+//! Integration tests for `veriform::decoder::DecodeOptions`
+
+use veriform::{
+    consts::Literal,
+    decoder::{DecodeOptions, UnknownFieldPolicy},
+    error, Decoder, Error, Message, Sha256Digest,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Middle {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: Inner,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 1, wire_type = "message")]
+    pub middle: Middle,
+}
+
+/// Options stored in a constant, as they might be in service config
+const OPTIONS: DecodeOptions = DecodeOptions::new().max_total_fields(Some(5));
+
+/// Encoded [`Inner`] message
+const INNER: Literal = Literal::new().uint64(0, 42).sint64(1, -42);
+
+/// Encoded [`Outer`] message (nested 3 deep)
+const OUTER: Literal = Literal::new().message(1, &Literal::new().message(1, &INNER));
+
+/// Decode a message with the given options, returning it along with its
+/// digest
+fn decode<M: Message>(options: DecodeOptions, input: &[u8]) -> Result<(M, Sha256Digest), Error> {
+    let mut decoder = Decoder::with_options(options);
+    let msg = M::decode(&mut decoder, input)?;
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest)?;
+    Ok((msg, digest))
+}
+
+#[test]
+fn default_options_match_new() {
+    assert_eq!(DecodeOptions::default(), Decoder::new().options());
+    assert_eq!(
+        decode::<Outer>(DecodeOptions::default(), OUTER.as_bytes()).unwrap(),
+        decode::<Outer>(Decoder::new().options(), OUTER.as_bytes()).unwrap()
+    );
+}
+
+#[test]
+fn max_nesting() {
+    let options = DecodeOptions::new().max_nesting(Some(3));
+    assert!(decode::<Outer>(options, OUTER.as_bytes()).is_ok());
+
+    let options = options.max_nesting(Some(2));
+    let err = decode::<Outer>(options, OUTER.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+}
+
+#[test]
+fn max_total_fields() {
+    // `Outer` has 4 fields in total, counting those of nested messages
+    assert!(decode::<Outer>(OPTIONS, OUTER.as_bytes()).is_ok());
+
+    let options = OPTIONS.max_total_fields(Some(3));
+    let err = decode::<Outer>(options, OUTER.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+}
+
+#[test]
+fn unknown_field_policy() {
+    let encoded = Literal::new().bytes(0, b"unknown").message(1, &INNER);
+
+    let err = decode::<Middle>(DecodeOptions::new(), encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 0 });
+
+    let options = DecodeOptions::new().unknown_field_policy(UnknownFieldPolicy::Skip);
+    let (msg, _) = decode::<Middle>(options, encoded.as_bytes()).unwrap();
+    assert_eq!(msg.inner.uint64_field, 42);
+}
+
+#[test]
+fn hashing_disabled() {
+    let options = DecodeOptions::new().hashing(false);
+    let mut decoder = Decoder::with_options(options);
+    let msg = Outer::decode(&mut decoder, OUTER.as_bytes()).unwrap();
+    assert_eq!(msg.middle.inner.sint64_field, -42);
+
+    let mut digest = Sha256Digest::default();
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+}
@@ -0,0 +1,930 @@
+//! Integration tests for `veriform::Decoder`
+
+use veriform::{
+    consts::Literal,
+    decoder::{self, Cursor, Decode, DecodeOptions, DecodeRef, DecodeSeq, UnknownFieldPolicy},
+    error,
+    field::WireType,
+    verihash::VerihashDigest,
+    Decoder, Encoder, Error, Message, Sha256Digest,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Middle {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: Inner,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Counter {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub count: u64,
+}
+
+/// Message whose trailing `sint64` field is optional
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Trailing {
+    pub uint64_field: u64,
+    pub sint64_field: Option<i64>,
+}
+
+impl Message for Trailing {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let uint64_field = decoder.decode(0, &mut input)?;
+
+        let sint64_field = if decoder.at_end(input) {
+            None
+        } else {
+            Some(decoder.decode(1, &mut input)?)
+        };
+
+        Ok(Trailing {
+            uint64_field,
+            sint64_field,
+        })
+    }
+
+    fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        let mut encoder = Encoder::new(buffer);
+        encoder.uint64(0, false, self.uint64_field)?;
+
+        if let Some(value) = self.sint64_field {
+            encoder.sint64(1, false, value)?;
+        }
+
+        Ok(encoder.finish())
+    }
+
+    fn encoded_len(&self) -> usize {
+        let mut buffer = [0u8; 32];
+        self.encode(&mut buffer).unwrap().len()
+    }
+}
+
+/// Encode an [`Inner`] message as field `1` of an outer message, followed
+/// by a `uint64` field
+fn encode_outer<'a>(buffer: &'a mut [u8], inner: &Inner) -> &'a [u8] {
+    let mut encoder = Encoder::new(buffer);
+    encoder.message(1, false, inner).unwrap();
+    encoder.uint64(2, false, 42).unwrap();
+    encoder.finish()
+}
+
+#[test]
+fn decode_message_bytes_round_trip() {
+    let inner = Inner {
+        uint64_field: 1234,
+        sint64_field: -5678,
+    };
+
+    let mut buffer = [0u8; 64];
+    let encoded = encode_outer(&mut buffer, &inner);
+
+    // Decode the nested message as raw bytes
+    let mut decoder = Decoder::new();
+    let mut input = encoded;
+    let msg_bytes = decoder.decode_message_bytes(1, &mut input).unwrap();
+    let value: u64 = decoder.decode(2, &mut input).unwrap();
+    assert_eq!(value, 42);
+    assert!(input.is_empty());
+
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest).unwrap();
+
+    // Decode the nested message normally
+    let mut expected_decoder = Decoder::new();
+    let mut input = encoded;
+    let decoded: Inner = expected_decoder.decode(1, &mut input).unwrap();
+    let _: u64 = expected_decoder.decode(2, &mut input).unwrap();
+    assert_eq!(decoded, inner);
+
+    let mut expected_digest = Sha256Digest::default();
+    expected_decoder.fill_digest(&mut expected_digest).unwrap();
+    assert_eq!(digest, expected_digest);
+
+    // Lazily decode the raw message bytes
+    let lazy = Inner::decode(&mut Decoder::new(), msg_bytes).unwrap();
+    assert_eq!(lazy, inner);
+
+    let mut inner_buffer = [0u8; 64];
+    assert_eq!(msg_bytes, inner.encode(&mut inner_buffer).unwrap());
+}
+
+#[test]
+fn decode_truncated_nested_message() {
+    let inner = Inner {
+        uint64_field: 1234,
+        sint64_field: -5678,
+    };
+
+    let mut buffer = [0u8; 64];
+    let encoded = encode_outer(&mut buffer, &inner);
+
+    // Drop the last byte of the nested message along with the trailing field
+    let truncated = &encoded[..2 + inner.encoded_len() - 1];
+    let result: Result<Inner, _> = Decoder::new().decode(1, &mut &truncated[..]);
+
+    assert_eq!(
+        result.unwrap_err().kind(),
+        error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::Message
+        }
+    );
+}
+
+#[test]
+fn decode_message_longer_than_parent() {
+    let middle = Middle {
+        inner: Inner {
+            uint64_field: 1234,
+            sint64_field: -5678,
+        },
+    };
+
+    let mut buffer = [0u8; 64];
+    let mut encoder = Encoder::new(&mut buffer);
+    encoder.message(1, false, &middle).unwrap();
+    encoder.uint64(2, false, 42).unwrap();
+    let len = encoder.finish().len();
+
+    // Extend the length of the innermost message by one byte, so it extends
+    // past the end of the enclosing message into the trailing field
+    assert_eq!(buffer[3], (middle.inner.encoded_len() << 1 | 1) as u8);
+    buffer[3] += 2;
+
+    let result: Result<Middle, _> = Decoder::new().decode(1, &mut &buffer[..len]);
+
+    assert_eq!(
+        result.unwrap_err().kind(),
+        error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::Message
+        }
+    );
+}
+
+#[test]
+fn decode_with_cursor() {
+    let encoded = Literal::new()
+        .uint64(1, 42)
+        .string(2, "hello")
+        .sint64(3, -1);
+
+    let mut input = Cursor::new(encoded.as_bytes());
+    let mut decoder = Decoder::new();
+    assert_eq!(input.position(), 0);
+
+    let value: u64 = decoder.decode(1, &mut input).unwrap();
+    assert_eq!(value, 42);
+    assert_eq!(input.position(), 2);
+
+    let string: &str = decoder.decode_ref(2, &mut input).unwrap();
+    assert_eq!(string, "hello");
+    assert_eq!(input.position(), 9);
+    assert_eq!(input.remaining(), 2);
+
+    let value: i64 = decoder.decode(3, &mut input).unwrap();
+    assert_eq!(value, -1);
+    assert_eq!(input.position(), encoded.len());
+    assert!(input.is_empty());
+}
+
+#[test]
+fn max_total_fields() {
+    let encoded = (0..6).fold(Literal::new(), |literal, tag| literal.uint64(tag, tag));
+
+    let decode_all = |max_total_fields| {
+        let mut decoder: Decoder = Decoder::new();
+        decoder.set_max_total_fields(Some(max_total_fields));
+
+        let mut input = encoded.as_bytes();
+        while let Some(field) = decoder.decode_field(&mut input)? {
+            field.skip(&mut input)?;
+        }
+
+        Ok::<_, veriform::Error>(())
+    };
+
+    assert!(decode_all(6).is_ok());
+    assert_eq!(decode_all(5).unwrap_err().kind(), error::Kind::LengthLimit);
+}
+
+#[test]
+fn decode_uint64_in_range() {
+    let encoded = Literal::new().uint64(1, 443);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let port = decoder
+        .decode_uint64_range(1, &mut input, 1..=65535)
+        .unwrap();
+
+    assert_eq!(port, 443);
+    assert!(input.is_empty());
+}
+
+#[test]
+fn decode_uint64_out_of_range() {
+    let encoded = Literal::new().uint64(1, 8);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let err = decoder
+        .decode_uint64_range(1, &mut input, 0..=7)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::OutOfRange);
+}
+
+#[test]
+fn decode_sint64_as_i16() {
+    let encoded = Literal::new().sint64(1, -32768);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let value: i16 = decoder.decode_sint64_as(1, &mut input).unwrap();
+    assert_eq!(value, i16::MIN);
+    assert!(input.is_empty());
+}
+
+#[test]
+fn decode_sint64_as_i16_out_of_range() {
+    let encoded = Literal::new().sint64(1, 32768);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let err = decoder.decode_sint64_as::<i16>(1, &mut input).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::OutOfRange);
+}
+
+#[test]
+fn uint64_with_spurious_length_delimiter() {
+    // Field 0 = uint64 42, with a one-byte length delimiter before the value
+    let encoded = Literal::new()
+        .header(0, false, WireType::UInt64)
+        .vint64(1)
+        .vint64(42);
+
+    let mut decoder: Decoder = Decoder::new();
+    let err = Counter::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Malformed);
+
+    let encoded = Literal::new().uint64(0, 42);
+    let mut decoder: Decoder = Decoder::new();
+    let msg = Counter::decode(&mut decoder, encoded.as_bytes()).unwrap();
+    assert_eq!(msg, Counter { count: 42 });
+}
+
+#[test]
+fn nested_sint64_with_spurious_length_delimiter() {
+    // Field 1 = sint64 -42, with a one-byte length delimiter before the value
+    let inner = Literal::new()
+        .uint64(0, 42)
+        .header(1, false, WireType::SInt64)
+        .vint64(1)
+        .vint64(83);
+
+    let encoded = Literal::new().message(1, &inner);
+    let mut decoder: Decoder = Decoder::new();
+    let err = Middle::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Malformed);
+}
+
+/// Field 0 = uint64 `value`, with a spurious one-byte length delimiter
+fn with_spurious_length_delimiter(value: u64) -> Literal {
+    Literal::new()
+        .header(0, false, WireType::UInt64)
+        .vint64(1)
+        .vint64(value)
+}
+
+#[test]
+fn spurious_length_delimiter_before_zero() {
+    // The value is decoded as a `false` field with tag 0
+    let encoded = with_spurious_length_delimiter(0);
+    let mut decoder: Decoder = Decoder::new();
+    let err = Counter::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 0 });
+}
+
+#[test]
+fn spurious_length_delimiter_before_complete_field() {
+    // The value is decoded as a `false` field with tag 1, so it's only
+    // rejected as an unknown field under `UnknownFieldPolicy::Error`
+    let encoded = with_spurious_length_delimiter(16);
+    let mut decoder: Decoder = Decoder::new();
+    let err = Counter::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 1 });
+
+    let mut decoder: Decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    let msg = Counter::decode(&mut decoder, encoded.as_bytes()).unwrap();
+    assert_eq!(msg, Counter { count: 1 });
+}
+
+#[test]
+fn spurious_length_delimiter_before_next_field() {
+    // Not the last field of the message, so the value is decoded as the
+    // next field without any error
+    let encoded = with_spurious_length_delimiter(16);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let count: u64 = decoder.decode(0, &mut input).unwrap();
+    assert_eq!(count, 1);
+
+    let field = decoder.decode_field(&mut input).unwrap().unwrap();
+    assert_eq!(field.tag(), 1);
+    assert!(!field.bool().unwrap());
+    assert!(input.is_empty());
+}
+
+#[test]
+fn at_end_between_fields() {
+    let encoded = Literal::new().uint64(0, 1).sint64(1, -1);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    assert!(!decoder.at_end(input));
+
+    let _: u64 = decoder.decode(0, &mut input).unwrap();
+    assert!(!decoder.at_end(input));
+
+    let _: i64 = decoder.decode(1, &mut input).unwrap();
+    assert!(decoder.at_end(input));
+}
+
+#[test]
+fn at_end_of_nested_message() {
+    let without_trailing = Literal::new().uint64(0, 42);
+    let with_trailing = without_trailing.sint64(1, -42);
+
+    for (inner, sint64_field) in &[(without_trailing, None), (with_trailing, Some(-42))] {
+        // The outer message has a field following the nested one, which
+        // doesn't belong to the nested message's frame
+        let encoded = Literal::new().message(1, inner).uint64(2, 7);
+        let mut decoder: Decoder = Decoder::new();
+        let mut input = encoded.as_bytes();
+
+        let msg: Trailing = decoder.decode(1, &mut input).unwrap();
+        assert_eq!(msg.uint64_field, 42);
+        assert_eq!(msg.sint64_field, *sint64_field);
+        assert!(!decoder.at_end(input));
+
+        let _: u64 = decoder.decode(2, &mut input).unwrap();
+        assert!(decoder.at_end(input));
+    }
+}
+
+/// Decode a `string` field with the given raw contents
+fn decode_raw_string(bytes: &[u8]) -> Result<String, Error> {
+    let encoded = Literal::new()
+        .header(1, false, WireType::String)
+        .vint64(bytes.len() as u64)
+        .raw(bytes);
+
+    let mut decoder: Decoder = Decoder::new();
+    let s: &str = decoder.decode_ref(1, &mut encoded.as_bytes())?;
+    Ok(s.to_owned())
+}
+
+#[test]
+fn string_with_lone_surrogate() {
+    // U+D800 encoded as if it were a scalar value
+    let err = decode_raw_string(&[b'a', 0xED, 0xA0, 0x80]).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Utf8 { valid_up_to: 1 });
+}
+
+#[test]
+fn string_with_overlong_encoding() {
+    // `/` encoded in two bytes rather than one
+    let err = decode_raw_string(&[b'a', b'b', 0xC0, 0xAF]).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Utf8 { valid_up_to: 2 });
+}
+
+#[test]
+fn string_with_multibyte_characters() {
+    // The offset is in bytes, not characters
+    let err = decode_raw_string(&[0xC3, 0xA9, 0xC3, 0xA9, 0xC3]).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Utf8 { valid_up_to: 4 });
+
+    // Valid UTF-8, but strings are presently restricted to ASCII
+    let err = decode_raw_string("éé".as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnicodeNormalization);
+}
+
+/// Encode a sequence of `Counter` messages with the given counts
+fn counter_sequence(counts: &[u64]) -> Vec<u8> {
+    let mut body = Literal::new();
+
+    for &count in counts {
+        let element = Literal::new().uint64(0, count);
+        body = body.vint64(element.len() as u64).raw(element.as_bytes());
+    }
+
+    Literal::new()
+        .sequence(1, WireType::Message, body.as_bytes())
+        .as_bytes()
+        .to_vec()
+}
+
+#[test]
+fn decode_message_sequence_into_array() {
+    let encoded = counter_sequence(&[1, 2]);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_slice();
+
+    let mut counters: [Counter; 2] = Default::default();
+    decoder
+        .decode_seq(1, &mut input)
+        .unwrap()
+        .decode_exact(&mut counters)
+        .unwrap();
+
+    assert_eq!(counters, [Counter { count: 1 }, Counter { count: 2 }]);
+    assert!(decoder.at_end(input));
+}
+
+#[test]
+fn decode_message_sequence_into_array_with_wrong_count() {
+    for counts in &[&[1][..], &[1, 2, 3][..]] {
+        let encoded = counter_sequence(counts);
+        let mut decoder: Decoder = Decoder::new();
+        let mut input = encoded.as_slice();
+
+        let mut counters: [Counter; 2] = Default::default();
+        let err = decoder
+            .decode_seq(1, &mut input)
+            .unwrap()
+            .decode_exact(&mut counters)
+            .unwrap_err();
+
+        assert_eq!(err.kind(), error::Kind::Length);
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn memoize_identical_message_elements() {
+    let encoded = counter_sequence(&[7, 7, 7, 8]);
+    let mut digests = vec![];
+
+    for &memoize in &[false, true] {
+        let options = DecodeOptions::new().memoize_element_digests(memoize);
+        let mut decoder: Decoder = Decoder::with_options(options);
+        let mut input = encoded.as_slice();
+
+        let mut iter: decoder::sequence::Iter<'_, '_, Counter, _> =
+            decoder.decode_seq(1, &mut input).unwrap();
+        let counters = iter.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        let counts: Vec<_> = counters.iter().map(|counter| counter.count).collect();
+        assert_eq!(counts, [7, 7, 7, 8]);
+
+        // The second and third elements reuse the digest of the first
+        assert_eq!(iter.memo_hits(), if memoize { 2 } else { 0 });
+        drop(iter);
+
+        let mut digest = Sha256Digest::default();
+        decoder.fill_digest(&mut digest).unwrap();
+        digests.push(digest);
+    }
+
+    assert_eq!(digests[0], digests[1]);
+    assert_eq!(
+        &digests[1][..],
+        &decoder::hash_message::<sha2::Sha256>(&encoded).unwrap()[..]
+    );
+}
+
+#[test]
+fn sequence_iter_consumed() {
+    let body = Literal::new().vint64(1).vint64(300).vint64(70000);
+    let encoded = Literal::new().sequence(1, WireType::UInt64, body.as_bytes());
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let mut iter: decoder::sequence::Iter<'_, '_, u64, _> =
+        decoder.decode_seq(1, &mut input).unwrap();
+    assert_eq!((iter.consumed(), iter.remaining()), (0, body.len()));
+
+    assert_eq!(iter.next().unwrap().unwrap(), 1);
+    assert_eq!((iter.consumed(), iter.remaining()), (1, body.len() - 1));
+
+    assert_eq!(
+        iter.by_ref().collect::<Result<Vec<_>, _>>().unwrap(),
+        [300, 70000]
+    );
+    assert_eq!(iter.consumed(), body.len());
+    assert_eq!(iter.remaining(), 0);
+}
+
+#[test]
+fn integer_with_wrong_sign_wire_type() {
+    let sint64 = Literal::new().sint64(1, 42);
+    let uint64 = Literal::new().uint64(1, 42);
+
+    for options in &[
+        DecodeOptions::new(),
+        DecodeOptions::new().scalar_fast_path(false),
+    ] {
+        let mut decoder: Decoder = Decoder::with_options(*options);
+        let err = Decode::<u64>::decode(&mut decoder, 1, &mut sint64.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::UnexpectedWireType {
+                actual: WireType::SInt64,
+                wanted: WireType::UInt64
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "unexpected wire type: actual=SInt64 wanted=UInt64"
+        );
+
+        let mut decoder: Decoder = Decoder::with_options(*options);
+        let err = Decode::<i64>::decode(&mut decoder, 1, &mut uint64.as_bytes()).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            error::Kind::UnexpectedWireType {
+                actual: WireType::UInt64,
+                wanted: WireType::SInt64
+            }
+        );
+    }
+}
+
+#[test]
+fn integer_sequence_with_wrong_sign_wire_type() {
+    let body = Literal::new().vint64(1).vint64(2);
+    let encoded = Literal::new().sequence(1, WireType::SInt64, body.as_bytes());
+    let mut decoder: Decoder = Decoder::new();
+
+    let err = DecodeSeq::<u64, _>::decode_seq(&mut decoder, 1, &mut encoded.as_bytes())
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::UnexpectedWireType {
+            actual: WireType::SInt64,
+            wanted: WireType::UInt64
+        }
+    );
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Circle {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub kind: u64,
+
+    #[field(tag = 1, wire_type = "uint64")]
+    pub radius: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Offset {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub kind: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub dx: i64,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub dy: i64,
+}
+
+/// Elements of a sequence of either [`Circle`] or [`Offset`] messages
+#[derive(Debug, Eq, PartialEq)]
+pub enum Shape {
+    Circle(Circle),
+    Offset(Offset),
+}
+
+#[test]
+fn decode_heterogeneous_message_sequence() {
+    let circle = Literal::new().uint64(0, 0).uint64(1, 5);
+    let offset = Literal::new().uint64(0, 1).sint64(1, -1).sint64(2, 2);
+    let body = Literal::new()
+        .vint64(circle.len() as u64)
+        .raw(circle.as_bytes())
+        .vint64(offset.len() as u64)
+        .raw(offset.as_bytes());
+    let encoded = Literal::new().sequence(1, WireType::Message, body.as_bytes());
+
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let shapes = decoder
+        .decode_message_seq_bytes(1, &mut input)
+        .unwrap()
+        .map(|element| {
+            let element = element?;
+
+            // Read the discriminator to select the type of the element
+            let kind: u64 = Decoder::new().decode(0, &mut &element[..])?;
+
+            match kind {
+                0 => Circle::decode(&mut Decoder::new(), element).map(Shape::Circle),
+                1 => Offset::decode(&mut Decoder::new(), element).map(Shape::Offset),
+                _ => Err(error::Kind::Malformed.into()),
+            }
+        })
+        .collect::<Result<Vec<_>, Error>>()
+        .unwrap();
+
+    assert_eq!(
+        shapes,
+        [
+            Shape::Circle(Circle { kind: 0, radius: 5 }),
+            Shape::Offset(Offset {
+                kind: 1,
+                dx: -1,
+                dy: 2
+            })
+        ]
+    );
+
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        &decoder::hash_message::<sha2::Sha256>(encoded.as_bytes()).unwrap()[..]
+    );
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Root {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: Inner,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[test]
+fn decode_root_without_header() {
+    // The root is a bare sequence of fields with no outer header or length
+    let inner = Literal::new().uint64(0, 7).sint64(1, -7);
+    let encoded = Literal::new()
+        .uint64(0, 42)
+        .message(1, &inner)
+        .sint64(2, -42);
+
+    let mut decoder: Decoder = Decoder::new();
+    let root: Root = decoder.decode_root(encoded.as_bytes()).unwrap();
+
+    assert_eq!(
+        root,
+        Root {
+            uint64_field: 42,
+            inner: Inner {
+                uint64_field: 7,
+                sint64_field: -7,
+            },
+            sint64_field: -42,
+        }
+    );
+}
+
+#[test]
+fn decode_message_with_len() {
+    let inner = Literal::new().uint64(0, 7).sint64(1, -7);
+    let body = Literal::new()
+        .uint64(0, 42)
+        .message(1, &inner)
+        .sint64(2, -42);
+    let framed = Literal::new().raw(body.as_bytes()).raw(b"next");
+
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = framed.as_bytes();
+    let root: Root = decoder
+        .decode_message_with_len(&mut input, body.len())
+        .unwrap();
+
+    assert_eq!(root.sint64_field, -42);
+    assert_eq!(input, b"next");
+
+    // The frame extends beyond the end of the input
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = body.as_bytes();
+    let err = decoder
+        .decode_message_with_len::<Root>(&mut input, body.len() + 1)
+        .unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::Message
+        }
+    );
+    assert_eq!(input, body.as_bytes());
+}
+
+#[test]
+fn decode_field_then_stop() {
+    let mut buffer = [0u8; 256];
+    let mut encoder = Encoder::new(&mut buffer);
+
+    for tag in 0..50 {
+        encoder.uint64(tag, false, tag * 10).unwrap();
+    }
+
+    let encoded = encoder.finish();
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded;
+    let value: u64 = decoder.decode_field_then_stop(0, &mut input).unwrap();
+
+    // The rest of the input is left untouched
+    assert_eq!(value, 0);
+    assert_eq!(input, &encoded[2..]);
+
+    // The digest of a partially decoded message isn't available
+    let mut digest = Sha256Digest::default();
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+
+    // Fields preceding the target are skipped
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded;
+    let value: u64 = decoder.decode_field_then_stop(3, &mut input).unwrap();
+    assert_eq!(value, 30);
+}
+
+#[test]
+fn enter_nested_message() {
+    let inner = Literal::new().uint64(0, 42).sint64(1, -42);
+    let encoded = Literal::new().message(1, &inner).uint64(2, 7);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    {
+        let (mut guard, mut body) = decoder.enter(1, &mut input).unwrap();
+        let uint64_field: u64 = guard.decode(0, &mut body).unwrap();
+        let sint64_field: i64 = guard.decode(1, &mut body).unwrap();
+        assert_eq!((uint64_field, sint64_field), (42, -42));
+        assert!(guard.at_end(body));
+        guard.finish(body).unwrap();
+    }
+
+    let uint64_field: u64 = decoder.decode(2, &mut input).unwrap();
+    assert_eq!(uint64_field, 7);
+
+    // The digest is the same as if the nested message had been decoded
+    // using a `Message` type
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        decoder::hash_message::<sha2::Sha256>(encoded.as_bytes())
+            .unwrap()
+            .as_slice()
+    );
+}
+
+#[test]
+fn finish_partially_decoded_nested_message() {
+    let inner = Literal::new().uint64(0, 42).sint64(1, -42);
+    let encoded = Literal::new().message(1, &inner).uint64(2, 7);
+
+    // Stopping after the first of two nested fields is an error, and the
+    // parent message can't be decoded or hashed any further
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    {
+        let (mut guard, mut body) = decoder.enter(1, &mut input).unwrap();
+        let uint64_field: u64 = guard.decode(0, &mut body).unwrap();
+        assert_eq!(uint64_field, 42);
+        let err = guard.finish(body).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::UnknownField { tag: 1 });
+    }
+
+    let err = Decode::<u64>::decode(&mut decoder, 2, &mut input).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Failed);
+
+    let mut digest = [0u8; 32];
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+
+    // Under `UnknownFieldPolicy::Skip`, the remaining field is hashed
+    let mut decoder: Decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    let mut input = encoded.as_bytes();
+
+    {
+        let (mut guard, mut body) = decoder.enter(1, &mut input).unwrap();
+        let uint64_field: u64 = guard.decode(0, &mut body).unwrap();
+        assert_eq!(uint64_field, 42);
+        guard.finish(body).unwrap();
+    }
+
+    let uint64_field: u64 = decoder.decode(2, &mut input).unwrap();
+    assert_eq!(uint64_field, 7);
+
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        decoder::hash_message::<sha2::Sha256>(encoded.as_bytes())
+            .unwrap()
+            .as_slice()
+    );
+}
+
+#[test]
+fn drop_unfinished_message_guard() {
+    let inner = Literal::new().uint64(0, 42).sint64(1, -42);
+    let encoded = Literal::new().message(1, &inner);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    {
+        let (mut guard, mut body) = decoder.enter(1, &mut input).unwrap();
+        let uint64_field: u64 = guard.decode(0, &mut body).unwrap();
+        assert_eq!(uint64_field, 42);
+    }
+
+    let mut digest = [0u8; 32];
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+}
+
+#[test]
+fn decode_or_default() {
+    // Encoded before tag 3 was added
+    let encoded = Literal::new().uint64(1, 1).uint64(4, 4);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let fields: [u64; 5] = [
+        decoder.decode_or_default(0, &mut input).unwrap(),
+        decoder.decode_or_default(1, &mut input).unwrap(),
+        decoder.decode_or_default(3, &mut input).unwrap(),
+        decoder.decode_or_default(4, &mut input).unwrap(),
+        decoder.decode_or_default(5, &mut input).unwrap(),
+    ];
+
+    assert_eq!(fields, [0, 1, 0, 4, 0]);
+    assert!(decoder.at_end(input));
+
+    // Absent fields aren't hashed
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        decoder::hash_message::<sha2::Sha256>(encoded.as_bytes())
+            .unwrap()
+            .as_slice()
+    );
+}
+
+#[test]
+fn decode_or_default_after_unknown_field() {
+    // Tag 2 is unknown, and tag 3 is absent
+    let encoded = Literal::new().uint64(1, 1).uint64(2, 2).uint64(4, 4);
+
+    let mut decoder: Decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    let mut input = encoded.as_bytes();
+
+    let fields: [u64; 3] = [
+        decoder.decode_or_default(1, &mut input).unwrap(),
+        decoder.decode_or_default(3, &mut input).unwrap(),
+        decoder.decode_or_default(4, &mut input).unwrap(),
+    ];
+
+    assert_eq!(fields, [1, 0, 4]);
+    assert!(decoder.at_end(input));
+
+    // Skipped fields are still hashed
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        decoder::hash_message::<sha2::Sha256>(encoded.as_bytes())
+            .unwrap()
+            .as_slice()
+    );
+
+    // Under the default policy, the unknown field is an error
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let _: u64 = decoder.decode_or_default(1, &mut input).unwrap();
+    let err = decoder.decode_or_default::<u64>(3, &mut input).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 2 });
+}
@@ -0,0 +1,109 @@
+//! Integration tests for `veriform::decoder::DedupSet`
+
+use heapless::consts::U2;
+use veriform::{
+    consts::Literal,
+    decoder::{DecodeOptions, DedupSet, DigestOutput, UnknownFieldPolicy},
+    error, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Example {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Set of SHA-256 digests with room for two entries
+type Seen = DedupSet<sha2::Sha256, heapless::Vec<DigestOutput<sha2::Sha256>, U2>>;
+
+#[test]
+fn identical_messages() {
+    let encoded = Literal::new().uint64(0, 42).sint64(1, -42);
+    let mut seen = Seen::default();
+
+    let first: Option<Example> = seen.decode(encoded.as_bytes()).unwrap();
+    assert_eq!(
+        first,
+        Some(Example {
+            uint64_field: 42,
+            sint64_field: -42
+        })
+    );
+
+    let copy = encoded;
+    let second: Option<Example> = seen.decode(copy.as_bytes()).unwrap();
+    assert_eq!(second, None);
+    assert_eq!(seen.storage().len(), 1);
+}
+
+#[test]
+fn messages_differing_in_one_byte() {
+    let a = Literal::new().uint64(0, 42).sint64(1, -42);
+    let b = Literal::new().uint64(0, 43).sint64(1, -42);
+    assert_eq!(a.len(), b.len());
+
+    let mut seen = Seen::default();
+    let first: Option<Example> = seen.decode(a.as_bytes()).unwrap();
+    let second: Option<Example> = seen.decode(b.as_bytes()).unwrap();
+
+    assert!(first.is_some());
+    assert_eq!(second.unwrap().uint64_field, 43);
+    assert_eq!(seen.storage().len(), 2);
+}
+
+#[test]
+fn storage_capacity_exhausted() {
+    let mut seen = Seen::default();
+
+    for n in 0..2 {
+        let encoded = Literal::new().uint64(0, n).sint64(1, 0);
+        let msg: Option<Example> = seen.decode(encoded.as_bytes()).unwrap();
+        assert!(msg.is_some());
+    }
+
+    // New messages can't be recorded, so they're rejected
+    let encoded = Literal::new().uint64(0, 2).sint64(1, 0);
+    let err = seen.decode::<Example>(encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Capacity);
+
+    // Repeats are still detected
+    let encoded = Literal::new().uint64(0, 1).sint64(1, 0);
+    let msg: Option<Example> = seen.decode(encoded.as_bytes()).unwrap();
+    assert_eq!(msg, None);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn btree_set_storage() {
+    let mut seen = DedupSet::<sha2::Sha256, _>::new(std::collections::BTreeSet::new());
+
+    for &(n, is_new) in &[(1, true), (2, true), (1, false)] {
+        let encoded = Literal::new().uint64(0, n).sint64(1, 0);
+        let msg: Option<Example> = seen.decode(encoded.as_bytes()).unwrap();
+        assert_eq!(msg.is_some(), is_new);
+    }
+
+    assert_eq!(seen.into_storage().len(), 2);
+}
+
+#[test]
+fn decode_options() {
+    // Field 2 isn't part of `Example`
+    let encoded = Literal::new().uint64(0, 1).sint64(1, 0).uint64(2, 0);
+
+    let err = Seen::default()
+        .decode::<Example>(encoded.as_bytes())
+        .unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 2 });
+
+    let options = DecodeOptions::new().unknown_field_policy(UnknownFieldPolicy::Skip);
+    let mut seen = Seen::with_options(heapless::Vec::new(), options);
+
+    for &is_new in &[true, false] {
+        let msg: Option<Example> = seen.decode(encoded.as_bytes()).unwrap();
+        assert_eq!(msg.is_some(), is_new);
+    }
+}
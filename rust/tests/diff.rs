@@ -0,0 +1,127 @@
+//! Integration tests for `veriform::diff`
+
+#![cfg(feature = "alloc")]
+
+use veriform::{
+    consts::Literal,
+    decoder::{OwnedValue, PathElement},
+    diff,
+    field::WireType,
+};
+
+/// Message with a nested message and a sequence of messages
+fn example(inner_uint64: u64, element_sint64: i64) -> Literal {
+    let inner = Literal::new().uint64(0, inner_uint64).string(1, "hello");
+    let element = Literal::new().sint64(0, element_sint64);
+    let seq_body = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes())
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes());
+
+    Literal::new().uint64(0, 42).message(1, &inner).sequence(
+        2,
+        WireType::Message,
+        seq_body.as_bytes(),
+    )
+}
+
+#[test]
+fn identical_messages() {
+    let a = example(7, -7);
+    let b = example(7, -7);
+    assert_eq!(diff(a.as_bytes(), b.as_bytes()).unwrap(), None);
+}
+
+#[test]
+fn differing_scalar() {
+    let report = diff(example(7, -7).as_bytes(), example(8, -7).as_bytes())
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(report.path, [PathElement::field(1), PathElement::field(0)]);
+    assert_eq!(report.left, Some(OwnedValue::UInt64(7)));
+    assert_eq!(report.right, Some(OwnedValue::UInt64(8)));
+    assert_eq!(report.critical, Some((false, false)));
+
+    let report = diff(example(7, -7).as_bytes(), example(7, 7).as_bytes())
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        report.path,
+        [PathElement::seq_element(2, 0), PathElement::field(0)]
+    );
+    assert_eq!(report.left, Some(OwnedValue::SInt64(-7)));
+    assert_eq!(report.right, Some(OwnedValue::SInt64(7)));
+}
+
+#[test]
+fn differing_wire_type() {
+    let a = Literal::new().uint64(0, 1);
+    let b = Literal::new().sint64(0, 1);
+    let report = diff(a.as_bytes(), b.as_bytes()).unwrap().unwrap();
+
+    assert_eq!(report.path, [PathElement::field(0)]);
+    assert_eq!(report.left, Some(OwnedValue::UInt64(1)));
+    assert_eq!(report.right, Some(OwnedValue::SInt64(1)));
+}
+
+#[test]
+fn field_present_in_one_message() {
+    let a = Literal::new().uint64(0, 1).string(2, "two");
+    let b = Literal::new()
+        .uint64(0, 1)
+        .bytes(1, b"one")
+        .string(2, "two");
+
+    let report = diff(a.as_bytes(), b.as_bytes()).unwrap().unwrap();
+    assert_eq!(report.path, [PathElement::field(1)]);
+    assert_eq!(report.left, None);
+    assert_eq!(report.right, Some(OwnedValue::Bytes(b"one".to_vec())));
+    assert_eq!(report.critical, None);
+
+    let report = diff(b.as_bytes(), a.as_bytes()).unwrap().unwrap();
+    assert_eq!(report.path, [PathElement::field(1)]);
+    assert_eq!(report.left, Some(OwnedValue::Bytes(b"one".to_vec())));
+    assert_eq!(report.right, None);
+}
+
+#[test]
+fn differing_critical_flag() {
+    let a = Literal::new().uint64(0, 1).uint64(1, 2);
+    let b = Literal::new()
+        .uint64(0, 1)
+        .header(1, true, WireType::UInt64)
+        .vint64(2);
+
+    let report = diff(a.as_bytes(), b.as_bytes()).unwrap().unwrap();
+    assert_eq!(report.path, [PathElement::field(1)]);
+    assert_eq!(report.left, Some(OwnedValue::UInt64(2)));
+    assert_eq!(report.right, Some(OwnedValue::UInt64(2)));
+    assert_eq!(report.critical, Some((false, true)));
+}
+
+#[test]
+fn differing_empty_sequence_types() {
+    let a = Literal::new().sequence(0, WireType::Bytes, &[]);
+    let b = Literal::new().sequence(0, WireType::UInt64, &[]);
+
+    let report = diff(a.as_bytes(), b.as_bytes()).unwrap().unwrap();
+    assert_eq!(report.path, [PathElement::field(0)]);
+    assert_eq!(
+        report.left,
+        Some(OwnedValue::Sequence {
+            wire_type: WireType::Bytes,
+            elements: vec![],
+        })
+    );
+    assert_eq!(
+        report.right,
+        Some(OwnedValue::Sequence {
+            wire_type: WireType::UInt64,
+            elements: vec![],
+        })
+    );
+    assert_eq!(report.critical, Some((false, false)));
+}
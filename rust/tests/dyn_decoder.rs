@@ -0,0 +1,83 @@
+//! Integration tests for `veriform::decoder::DynDecoder`
+
+#![cfg(all(feature = "alloc", feature = "sha2"))]
+
+use veriform::{
+    decoder::{hash_message, Decode, DecodeRef, DynDecoder, HashAlg},
+    Encoder, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Select a digest algorithm from a protocol version byte
+fn hash_alg(version: u8) -> HashAlg {
+    match version {
+        1 => HashAlg::Sha256,
+        2 => HashAlg::Sha512,
+        _ => panic!("unsupported protocol version: {}", version),
+    }
+}
+
+/// Encode a message containing an [`Inner`] message and a string
+fn example_message(buffer: &mut [u8]) -> &[u8] {
+    let inner = Inner {
+        uint64_field: 42,
+        sint64_field: -42,
+    };
+
+    let mut encoder = Encoder::new(buffer);
+    encoder.message(1, false, &inner).unwrap();
+    encoder.string(2, false, "hello").unwrap();
+    encoder.finish()
+}
+
+#[test]
+fn decode_with_runtime_hash_alg() {
+    let mut buffer = [0u8; 64];
+    let message = example_message(&mut buffer);
+
+    for &version in &[1, 2] {
+        let mut decoder = DynDecoder::new(hash_alg(version));
+        let mut input = message;
+
+        let inner: Inner = decoder.decode(1, &mut input).unwrap();
+        assert_eq!(inner.uint64_field, 42);
+        assert_eq!(inner.sint64_field, -42);
+
+        let string: &str = decoder.decode_ref(2, &mut input).unwrap();
+        assert_eq!(string, "hello");
+        assert!(input.is_empty());
+
+        let digest = decoder.digest().unwrap();
+        assert_eq!(digest.len(), decoder.hash_alg().output_size());
+
+        let expected = match decoder.hash_alg() {
+            HashAlg::Sha256 => hash_message::<sha2::Sha256>(message).unwrap().to_vec(),
+            HashAlg::Sha512 => hash_message::<sha2::Sha512>(message).unwrap().to_vec(),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        };
+
+        assert_eq!(digest, expected);
+    }
+}
+
+#[test]
+fn fill_digest_wrong_size() {
+    let mut buffer = [0u8; 64];
+    let mut input = example_message(&mut buffer);
+    let mut decoder = DynDecoder::new(HashAlg::Sha512);
+
+    let _: Inner = decoder.decode(1, &mut input).unwrap();
+    let _: &str = decoder.decode_ref(2, &mut input).unwrap();
+
+    let mut digest = [0u8; 32];
+    assert!(decoder.fill_digest(&mut digest).is_err());
+}
@@ -0,0 +1,149 @@
+//! Integration tests for empty messages and zero-length nested messages
+//! and sequences
+
+use heapless::consts::U8;
+use sha2::Sha256;
+use veriform::{
+    consts::Literal,
+    decoder::{hash_message, Decode},
+    digest::Digest,
+    error,
+    field::WireType,
+    Decoder, Message,
+};
+
+/// Digest of the empty message, which is the digest of no input at all
+const EMPTY_DIGEST: [u8; 32] = [
+    0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9, 0x24,
+    0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+];
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Empty {}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Required {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct OnlyEmpty {
+    #[field(tag = 1, wire_type = "message")]
+    pub empty: Empty,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct EmptyThenField {
+    #[field(tag = 1, wire_type = "message")]
+    pub empty: Empty,
+
+    #[field(tag = 2, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct EmptySeqThenField {
+    #[field(tag = 1, wire_type = "sequence", max = 8)]
+    pub seq: heapless::Vec<Empty, U8>,
+
+    #[field(tag = 2, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+/// Decode a message, checking its digest matches [`hash_message`]
+fn decode_and_hash<M: Message>(input: &[u8]) -> (M, [u8; 32]) {
+    let mut decoder = Decoder::new();
+    let msg = M::decode(&mut decoder, input).unwrap();
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        digest.as_ref(),
+        hash_message::<Sha256>(input).unwrap().as_slice()
+    );
+
+    (msg, digest)
+}
+
+#[test]
+fn empty_input() {
+    assert_eq!(Sha256::digest(&[]).as_slice(), &EMPTY_DIGEST);
+    assert_eq!(
+        hash_message::<Sha256>(&[]).unwrap().as_slice(),
+        &EMPTY_DIGEST
+    );
+
+    let (msg, digest) = decode_and_hash::<Empty>(&[]);
+    assert_eq!(msg, Empty {});
+    assert_eq!(digest, EMPTY_DIGEST);
+}
+
+#[test]
+fn empty_input_missing_required_field() {
+    let mut decoder = Decoder::new();
+    let err = Required::decode(&mut decoder, &[]).unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::FieldHeader {
+            tag: Some(0),
+            wire_type: Some(WireType::UInt64),
+        }
+    );
+}
+
+#[test]
+fn zero_length_nested_message() {
+    let encoded = Literal::new().header(1, false, WireType::Message).vint64(0);
+
+    // An empty body is decoded like any other, so the nested message type
+    // decides whether it's valid
+    let mut decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let err = Decode::<Required>::decode(&mut decoder, 1, &mut input).unwrap_err();
+    assert!(matches!(err.kind(), error::Kind::FieldHeader { .. }));
+
+    let (msg, digest) = decode_and_hash::<OnlyEmpty>(encoded.as_bytes());
+    assert_eq!(msg, OnlyEmpty { empty: Empty {} });
+
+    // "Empty nested message" fixture in `vectors/conformance.tjson`
+    assert_eq!(
+        digest,
+        [
+            0x31, 0x08, 0xe3, 0x1d, 0x13, 0x40, 0x0e, 0x31, 0x4e, 0x0c, 0xf7, 0x75, 0x9a, 0xe6,
+            0x80, 0x7a, 0x74, 0x19, 0xc3, 0x9d, 0x38, 0xef, 0x4a, 0x0a, 0x5e, 0x6c, 0xdf, 0x6e,
+            0x54, 0xce, 0xb2, 0xed
+        ]
+    );
+}
+
+#[test]
+fn zero_length_nested_message_followed_by_field() {
+    let encoded = Literal::new().message(1, &Literal::new()).uint64(2, 42);
+
+    let (msg, _) = decode_and_hash::<EmptyThenField>(encoded.as_bytes());
+    assert_eq!(
+        msg,
+        EmptyThenField {
+            empty: Empty {},
+            uint64_field: 42
+        }
+    );
+}
+
+#[test]
+fn zero_length_sequence_followed_by_field() {
+    let encoded = Literal::new()
+        .sequence(1, WireType::Message, &[])
+        .uint64(2, 42);
+
+    let (msg, _) = decode_and_hash::<EmptySeqThenField>(encoded.as_bytes());
+    assert_eq!(
+        msg,
+        EmptySeqThenField {
+            seq: heapless::Vec::new(),
+            uint64_field: 42
+        }
+    );
+}
@@ -0,0 +1,230 @@
+//! Integration tests for `veriform::encoder::EventEncoder`
+
+#![cfg(feature = "test-util")]
+
+use veriform::{
+    decoder::{Event, Stream},
+    encoder::EventEncoder,
+    error,
+    field::{Header, WireType},
+    message::Element,
+    test_util::MessageBuilder,
+    Error,
+};
+
+/// Example messages covering every wire type
+fn corpus() -> Vec<Vec<u8>> {
+    let inner = MessageBuilder::new()
+        .uint64(0, 42)
+        .sint64(1, -42)
+        .string(2, "inner")
+        .bytes(3, &[])
+        .string(4, "")
+        .build();
+
+    let nested = MessageBuilder::new()
+        .message(1, &inner)
+        .message(2, &[])
+        .build();
+
+    let mut msg_seq = Vec::new();
+    for msg in &[&inner[..], &[], &nested[..]] {
+        msg_seq.extend_from_slice(vint64::encode(msg.len() as u64).as_ref());
+        msg_seq.extend_from_slice(msg);
+    }
+
+    let mut uint64_seq = Vec::new();
+    let mut sint64_seq = Vec::new();
+    let mut bytes_seq = Vec::new();
+    for n in 0..3 {
+        uint64_seq.extend_from_slice(vint64::encode(n * 1000).as_ref());
+        sint64_seq.extend_from_slice(vint64::signed::encode(-(n as i64)).as_ref());
+        bytes_seq.extend_from_slice(vint64::encode(n).as_ref());
+        bytes_seq.extend((0..n).map(|b| b as u8));
+    }
+
+    vec![
+        vec![],
+        MessageBuilder::new().uint64(1, 42).build(),
+        inner.clone(),
+        nested.clone(),
+        MessageBuilder::new()
+            .critical()
+            .uint64(0, u64::MAX)
+            .sint64(1, i64::MIN)
+            .bytes(2, &[0xff; 300])
+            .message(3, &nested)
+            .sequence(4, WireType::Message, &msg_seq)
+            .sequence(5, WireType::UInt64, &uint64_seq)
+            .sequence(6, WireType::SInt64, &sint64_seq)
+            .sequence(7, WireType::Bytes, &bytes_seq)
+            .sequence(8, WireType::False, &[1, 0, 1])
+            .sequence(9, WireType::UInt64, &[])
+            .build(),
+    ]
+}
+
+/// Decode the given message with a [`Stream`] and re-encode the events
+fn reencode(message: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut buffer = vec![0u8; message.len()];
+    let mut encoder = EventEncoder::new(&mut buffer);
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+
+    for chunk in message.chunks(chunk_size) {
+        let mut input = chunk;
+
+        while let Some(event) = stream.decode(&mut input).unwrap() {
+            encoder.encode(&event).unwrap();
+        }
+    }
+
+    encoder.finish().unwrap().to_vec()
+}
+
+/// Encode the given events, returning the first error
+fn encode_events(events: &[Event<'_>]) -> Result<Vec<u8>, Error> {
+    let mut buffer = [0u8; 64];
+    let mut encoder = EventEncoder::new(&mut buffer);
+
+    for event in events {
+        encoder.encode(event)?;
+    }
+
+    encoder.finish().map(|msg| msg.to_vec())
+}
+
+#[test]
+fn round_trip_corpus() {
+    for message in corpus() {
+        assert_eq!(reencode(&message, message.len().max(1)), message);
+    }
+}
+
+#[test]
+fn round_trip_corpus_chunked() {
+    for message in corpus() {
+        for chunk_size in 1..8 {
+            assert_eq!(reencode(&message, chunk_size), message);
+        }
+    }
+}
+
+#[test]
+fn out_of_order_fields() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(2, false, WireType::UInt64)),
+        Event::UInt64(1),
+        Event::FieldHeader(Header::new(2, false, WireType::UInt64)),
+        Event::UInt64(2),
+    ])
+    .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Order { tag: 2 });
+}
+
+#[test]
+fn mismatched_value_wire_type() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(1, false, WireType::UInt64)),
+        Event::SInt64(1),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::UnexpectedWireType {
+            actual: WireType::SInt64,
+            wanted: WireType::UInt64
+        }
+    );
+}
+
+#[test]
+fn value_without_header() {
+    let err = encode_events(&[Event::UInt64(1)]).unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::Decode {
+            element: Element::Value,
+            wire_type: WireType::Message
+        }
+    );
+}
+
+#[test]
+fn chunk_exceeds_length_delimiter() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(1, false, WireType::Bytes)),
+        Event::LengthDelimiter {
+            wire_type: WireType::Bytes,
+            length: 2,
+        },
+        Event::ValueChunk {
+            wire_type: WireType::Bytes,
+            bytes: b"foo",
+            remaining: 0,
+        },
+    ])
+    .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Length);
+}
+
+#[test]
+fn field_exceeds_nested_message() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(1, false, WireType::Message)),
+        Event::LengthDelimiter {
+            wire_type: WireType::Message,
+            length: 1,
+        },
+        Event::FieldHeader(Header::new(1, false, WireType::UInt64)),
+        Event::UInt64(1000),
+    ])
+    .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Length);
+}
+
+#[test]
+fn incomplete_nested_message() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(1, false, WireType::Message)),
+        Event::LengthDelimiter {
+            wire_type: WireType::Message,
+            length: 2,
+        },
+        Event::FieldHeader(Header::new(1, false, WireType::UInt64)),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::UInt64
+        }
+    );
+}
+
+#[test]
+fn header_inside_sequence() {
+    let err = encode_events(&[
+        Event::FieldHeader(Header::new(1, false, WireType::Sequence)),
+        Event::SequenceHeader {
+            wire_type: WireType::Message,
+            length: 4,
+        },
+        Event::FieldHeader(Header::new(1, false, WireType::UInt64)),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::Decode {
+            element: Element::Tag,
+            wire_type: WireType::Message
+        }
+    );
+}
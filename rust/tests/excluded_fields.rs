@@ -0,0 +1,202 @@
+//! Integration tests for excluding fields from the digest of a message
+
+use veriform::{
+    consts::Literal,
+    decoder::{self, hash_message, Decode, DecodeRef, TagSet, UnknownFieldPolicy},
+    error,
+    verihash::VerihashDigest,
+    Decoder, Encoder, Error, Message,
+};
+
+/// Tag of the field containing the signature over the rest of the message
+const SIGNATURE_TAG: u64 = 9;
+
+/// Encode an example message with the given signature
+fn signed_message(signature: &[u8]) -> Literal {
+    Literal::new()
+        .uint64(1, 42)
+        .message(2, &Literal::new().sint64(0, -42))
+        .bytes(SIGNATURE_TAG, signature)
+}
+
+/// Decode a signed message, returning its signature and the digest of the
+/// rest of the message
+fn decode_signed(encoded: &Literal) -> (Vec<u8>, [u8; 32]) {
+    let mut decoder = Decoder::new();
+    let mut excluded = TagSet::new();
+    excluded.insert(SIGNATURE_TAG).unwrap();
+    decoder.exclude_from_digest(excluded);
+
+    let mut input = encoded.as_bytes();
+    let value: u64 = decoder.decode(1, &mut input).unwrap();
+    assert_eq!(value, 42);
+
+    let (mut inner, mut body) = decoder.enter(2, &mut input).unwrap();
+    let value: i64 = inner.decode(0, &mut body).unwrap();
+    assert_eq!(value, -42);
+    inner.finish(body).unwrap();
+
+    let signature: &[u8] = decoder.decode_ref(SIGNATURE_TAG, &mut input).unwrap();
+    let signature = signature.to_vec();
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    (signature, digest)
+}
+
+#[test]
+fn digest_excludes_signature() {
+    let (signature, digest) = decode_signed(&signed_message(b"signature"));
+    assert_eq!(signature, b"signature");
+
+    let (_, other_digest) = decode_signed(&signed_message(b"another signature"));
+    assert_eq!(digest, other_digest);
+
+    // The digest is that of the message without the signature field
+    let unsigned = Literal::new()
+        .uint64(1, 42)
+        .message(2, &Literal::new().sint64(0, -42));
+    assert_eq!(
+        &digest[..],
+        hash_message::<sha2::Sha256>(unsigned.as_bytes())
+            .unwrap()
+            .as_slice()
+    );
+
+    let full = hash_message::<sha2::Sha256>(signed_message(b"signature").as_bytes()).unwrap();
+    assert_ne!(&digest[..], full.as_slice());
+}
+
+/// Tag of the field containing the digest of a self-certifying message
+const DIGEST_TAG: u64 = 15;
+
+/// Self-certifying message, containing its own digest
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Certified {
+    pub value: u64,
+    pub digest: Vec<u8>,
+}
+
+impl Message for Certified {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let value = decoder.decode(1, &mut input)?;
+        let digest: &[u8] = decoder.decode_ref(DIGEST_TAG, &mut input)?;
+
+        Ok(Self {
+            value,
+            digest: digest.to_vec(),
+        })
+    }
+
+    fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        let mut encoder = Encoder::new(buffer);
+        encoder.uint64(1, false, self.value)?;
+        encoder.bytes(DIGEST_TAG, false, &self.digest)?;
+        Ok(encoder.finish())
+    }
+
+    fn encoded_len(&self) -> usize {
+        let mut buffer = [0u8; 64];
+        self.encode(&mut buffer).unwrap().len()
+    }
+}
+
+/// Encode a self-certifying message with the given value, certifying the
+/// given value (which differs if the message has been tampered with)
+fn certified_message(value: u64, certified_value: u64) -> Literal {
+    let digest =
+        hash_message::<sha2::Sha256>(Literal::new().uint64(1, certified_value).as_bytes()).unwrap();
+
+    Literal::new().uint64(1, value).bytes(DIGEST_TAG, &digest)
+}
+
+#[test]
+fn self_certifying_message() {
+    let encoded = certified_message(42, 42);
+    let mut decoder = Decoder::new();
+    let msg: Certified = decoder
+        .decode_self_certifying(encoded.as_bytes(), DIGEST_TAG)
+        .unwrap();
+
+    assert_eq!(msg.value, 42);
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(&digest[..], msg.digest.as_slice());
+}
+
+#[test]
+fn tampered_self_certifying_message() {
+    let encoded = certified_message(43, 42);
+    let mut decoder = Decoder::new();
+    let err = decoder
+        .decode_self_certifying::<Certified>(encoded.as_bytes(), DIGEST_TAG)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Verification);
+}
+
+#[test]
+fn self_certifying_message_without_digest() {
+    let encoded = Literal::new().uint64(1, 42);
+    let mut decoder = Decoder::new();
+    let err = decoder
+        .decode_self_certifying::<Certified>(encoded.as_bytes(), DIGEST_TAG)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::MissingField { tag: DIGEST_TAG });
+}
+
+#[test]
+fn self_certifying_message_with_excluded_fields() {
+    let digest = hash_message::<sha2::Sha256>(Literal::new().uint64(1, 42).as_bytes()).unwrap();
+    let encoded = Literal::new()
+        .uint64(1, 42)
+        .bytes(SIGNATURE_TAG, b"signature")
+        .bytes(DIGEST_TAG, &digest);
+
+    // The signature is skipped, and excluded from the digest along with the
+    // digest itself
+    let mut decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    let mut excluded = TagSet::new();
+    excluded.insert(SIGNATURE_TAG).unwrap();
+    decoder.exclude_from_digest(excluded);
+
+    let msg: Certified = decoder
+        .decode_self_certifying(encoded.as_bytes(), DIGEST_TAG)
+        .unwrap();
+
+    assert_eq!(msg.value, 42);
+    assert_eq!(msg.digest, digest.as_slice());
+}
+
+#[test]
+fn excluded_tags_capacity() {
+    let mut excluded = TagSet::new();
+
+    for tag in 0..8 {
+        excluded.insert(tag).unwrap();
+    }
+
+    // Tags already in the set can be inserted again
+    excluded.insert(0).unwrap();
+    assert_eq!(
+        excluded.insert(8).unwrap_err().kind(),
+        error::Kind::Capacity
+    );
+
+    // There's no room left for the tag of the digest field
+    let encoded = certified_message(42, 42);
+    let mut decoder = Decoder::new();
+    decoder.exclude_from_digest(excluded);
+
+    let err = decoder
+        .decode_self_certifying::<Certified>(encoded.as_bytes(), DIGEST_TAG)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Capacity);
+}
@@ -0,0 +1,148 @@
+//! Integration tests for the digests of the individual fields of a message
+
+#![cfg(feature = "alloc")]
+
+use veriform::{
+    consts::Literal,
+    decoder::{hash_message, hash_sequence, DigestOutput},
+    error,
+    field::{Tag, WireType},
+    verihash, Decoder, Message,
+};
+
+use sha2::Sha256;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Record {
+    #[field(tag = 1, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[field(tag = 3, wire_type = "uint64")]
+    pub version: u64,
+
+    #[field(tag = 4, wire_type = "message")]
+    pub inner: Inner,
+}
+
+fn example_record() -> Record {
+    Record {
+        uint64_field: 1,
+        sint64_field: -42,
+        version: 1,
+        inner: Inner { uint64_field: 7 },
+    }
+}
+
+/// Decode a record, returning its root digest and field digests
+fn digests(record: &Record) -> ([u8; 32], Vec<(Tag, DigestOutput<Sha256>)>) {
+    let mut buffer = [0u8; 64];
+    let encoded = record.encode(&mut buffer).unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.record_field_digests().unwrap();
+    assert_eq!(&Record::decode(&mut decoder, encoded).unwrap(), record);
+
+    let mut root = [0u8; 32];
+    decoder.fill_digest(&mut root).unwrap();
+    (root, decoder.field_digests().unwrap())
+}
+
+#[test]
+fn field_digest_of_single_field_message() {
+    let (_, field_digests) = digests(&example_record());
+    let tags: Vec<Tag> = field_digests.iter().map(|(tag, _)| *tag).collect();
+    assert_eq!(tags, &[1, 2, 3, 4]);
+
+    // A field's digest is the digest of a message containing only that field
+    let field = Literal::new().message(4, &Literal::new().uint64(0, 7));
+    assert_eq!(
+        field_digests[3].1,
+        hash_message::<Sha256>(field.as_bytes()).unwrap()
+    );
+}
+
+#[test]
+fn changing_one_field_changes_only_its_digest() {
+    let original = example_record();
+    let (original_root, original_fields) = digests(&original);
+
+    let mut changed = example_record();
+    changed.version = 2;
+    let (changed_root, changed_fields) = digests(&changed);
+
+    assert_ne!(original_root, changed_root);
+
+    for ((tag, original), (changed_tag, changed)) in original_fields.iter().zip(&changed_fields) {
+        assert_eq!(tag, changed_tag);
+
+        if *tag == 3 {
+            assert_ne!(original, changed);
+        } else {
+            assert_eq!(original, changed);
+        }
+    }
+
+    // Changing a nested message changes the digest of the field containing it
+    let mut changed = example_record();
+    changed.inner.uint64_field = 8;
+    let (_, changed_fields) = digests(&changed);
+    assert_ne!(original_fields[3], changed_fields[3]);
+    assert_eq!(original_fields[..3], changed_fields[..3]);
+}
+
+#[test]
+fn field_digests_not_recorded() {
+    let mut buffer = [0u8; 64];
+    let encoded = example_record().encode(&mut buffer).unwrap();
+
+    let mut decoder = Decoder::new();
+    Record::decode(&mut decoder, encoded).unwrap();
+    assert_eq!(
+        decoder.field_digests().unwrap_err().kind(),
+        error::Kind::Hashing
+    );
+}
+
+#[test]
+fn standalone_field_digests() {
+    let inner = Literal::new().uint64(0, 7);
+    let seq = Literal::new().vint64(1).vint64(2);
+    let encoded = Literal::new()
+        .uint64(1, 1)
+        .sint64(2, -42)
+        .bytes(3, b"bytes")
+        .string(4, "string")
+        .message(5, &inner)
+        .bool(6, true)
+        .sequence(7, WireType::UInt64, seq.as_bytes());
+
+    let mut decoder = Decoder::new();
+    decoder.record_field_digests().unwrap();
+    decoder.decode_owned(encoded.as_bytes()).unwrap();
+
+    let inner_digest = hash_message::<Sha256>(inner.as_bytes()).unwrap();
+    let seq_digest = hash_sequence::<Sha256>(WireType::UInt64, seq.as_bytes()).unwrap();
+    let expected = [
+        (1, verihash::hash_uint64::<Sha256>(1, 1)),
+        (2, verihash::hash_sint64::<Sha256>(2, -42)),
+        (3, verihash::hash_bytes::<Sha256>(3, b"bytes")),
+        (4, verihash::hash_string::<Sha256>(4, "string")),
+        (
+            5,
+            verihash::hash_message_field::<Sha256>(5, inner.len(), &inner_digest),
+        ),
+        (6, verihash::hash_bool::<Sha256>(6, true)),
+        (7, verihash::hash_sequence_field::<Sha256>(7, &seq_digest)),
+    ];
+
+    assert_eq!(decoder.field_digests().unwrap(), expected);
+}
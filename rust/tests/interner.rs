@@ -0,0 +1,107 @@
+//! Integration tests for `veriform::decoder::Interner`
+
+use heapless::consts::{U16, U2};
+use veriform::{
+    consts::Literal,
+    decoder::{DecodeRef, Interner},
+    error,
+    field::WireType,
+    Decoder, Error,
+};
+
+/// Toy interner which stores up to two strings in a fixed-capacity table,
+/// using their index as a handle
+#[derive(Default)]
+struct Table {
+    strings: heapless::Vec<heapless::String<U16>, U2>,
+}
+
+impl Interner for Table {
+    type Handle = usize;
+
+    fn get(&mut self, bytes: &[u8]) -> Option<usize> {
+        self.strings.iter().position(|s| s.as_bytes() == bytes)
+    }
+
+    fn intern(&mut self, string: &str) -> Result<usize, Error> {
+        let mut s = heapless::String::new();
+        s.push_str(string).map_err(|_| error::Kind::Capacity)?;
+        self.strings.push(s).map_err(|_| error::Kind::Capacity)?;
+        Ok(self.strings.len() - 1)
+    }
+}
+
+#[test]
+fn equal_strings_share_handle() {
+    let encoded = Literal::new()
+        .string(1, "red")
+        .string(2, "blue")
+        .string(3, "red");
+
+    let mut table = Table::default();
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let mut interning = decoder.with_interner(&mut table);
+    let first = interning.decode_string(1, &mut input).unwrap();
+    let second = interning.decode_string(2, &mut input).unwrap();
+    let third = interning.decode_string(3, &mut input).unwrap();
+    assert!(input.is_empty());
+
+    assert_eq!(first, third);
+    assert_ne!(first, second);
+    assert_eq!(table.strings.len(), 2);
+    assert_eq!(table.strings[first], "red");
+
+    // Interned strings are hashed the same as borrowed ones
+    let mut expected: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    for tag in 1..=3 {
+        let _: &str = expected.decode_ref(tag, &mut input).unwrap();
+    }
+
+    let (mut digest, mut expected_digest) = ([0u8; 32], [0u8; 32]);
+    decoder.fill_digest(&mut digest).unwrap();
+    expected.fill_digest(&mut expected_digest).unwrap();
+    assert_eq!(digest, expected_digest);
+}
+
+#[test]
+fn interner_full() {
+    let encoded = Literal::new()
+        .string(1, "red")
+        .string(2, "blue")
+        .string(3, "green");
+    let mut table = Table::default();
+    let mut decoder: Decoder = Decoder::new();
+    let mut interning = decoder.with_interner(&mut table);
+    let mut input = encoded.as_bytes();
+
+    interning.decode_string(1, &mut input).unwrap();
+    interning.decode_string(2, &mut input).unwrap();
+    let err = interning.decode_string(3, &mut input).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Capacity);
+}
+
+#[test]
+fn non_canonical_string() {
+    // Valid UTF-8, but strings are presently restricted to ASCII
+    let bytes = "caf\u{e9}".as_bytes();
+    let encoded = Literal::new()
+        .header(1, false, WireType::String)
+        .vint64(bytes.len() as u64)
+        .raw(bytes);
+
+    let mut table = Table::default();
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let err = decoder
+        .with_interner(&mut table)
+        .decode_string(1, &mut input)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::UnicodeNormalization);
+    assert!(table.strings.is_empty());
+}
@@ -0,0 +1,94 @@
+//! Integration tests for computing multiple digests in one decoding pass
+
+use heapless::{consts::U4, Vec};
+use sha2::Sha256;
+use veriform::{
+    decoder::{self, Decoder, DigestOutput},
+    verihash::{Adapter, VerihashDigest},
+    Message, MultiDigest,
+};
+
+/// BLAKE2b, adapted for use with Verihash without the `blake2` feature
+type Blake2b = Adapter<blake2::Blake2b>;
+
+/// SHA-256 and BLAKE2b
+type Sha256AndBlake2b = MultiDigest<Sha256, Blake2b>;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: Vec<Inner, U4>,
+}
+
+/// Example message
+fn example_outer() -> Outer {
+    let mut msg_sequence_field = Vec::new();
+
+    for n in 0..3 {
+        let inner = Inner {
+            uint64_field: n * 1000,
+            sint64_field: -(n as i64),
+        };
+
+        msg_sequence_field.push(inner).unwrap();
+    }
+
+    Outer {
+        uint64_field: 42,
+        message_field: Inner {
+            uint64_field: 1,
+            sint64_field: -1,
+        },
+        msg_sequence_field,
+    }
+}
+
+/// Decode the given message, returning its digest
+fn decode_digest<D: VerihashDigest>(encoded: &[u8]) -> DigestOutput<D> {
+    let mut decoder = Decoder::<D>::new();
+    let decoded = Outer::decode(&mut decoder, encoded).unwrap();
+    assert_eq!(decoded, example_outer());
+
+    let mut digest = DigestOutput::<D>::default();
+    decoder.fill_digest(&mut digest).unwrap();
+    digest
+}
+
+#[test]
+fn decode_with_multiple_digests() {
+    let mut buffer = [0u8; 256];
+    let encoded = example_outer().encode(&mut buffer).unwrap();
+
+    let digest = decode_digest::<Sha256AndBlake2b>(encoded);
+    let (sha256, blake2b) = Sha256AndBlake2b::split(&digest);
+
+    assert_eq!(sha256, decode_digest::<Sha256>(encoded));
+    assert_eq!(blake2b, decode_digest::<Blake2b>(encoded));
+}
+
+#[test]
+fn hash_message_with_multiple_digests() {
+    let mut buffer = [0u8; 256];
+    let encoded = example_outer().encode(&mut buffer).unwrap();
+
+    let digest = decoder::hash_message::<Sha256AndBlake2b>(encoded).unwrap();
+    let (sha256, blake2b) = Sha256AndBlake2b::split(&digest);
+
+    assert_eq!(sha256, decoder::hash_message::<Sha256>(encoded).unwrap());
+    assert_eq!(blake2b, decoder::hash_message::<Blake2b>(encoded).unwrap());
+}
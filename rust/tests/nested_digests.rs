@@ -0,0 +1,106 @@
+//! Integration tests for collecting the digests of nested messages
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use heapless::{consts::U4, Vec};
+use veriform::{
+    decoder::{hash_message, DigestOutput, PathElement},
+    Decoder, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub uint64_field: u64,
+
+    #[field(tag = 3, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 4, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: Vec<Inner, U4>,
+}
+
+/// Example inner message
+fn example_inner(n: u64) -> Inner {
+    Inner {
+        uint64_field: n * 1000,
+        sint64_field: -(n as i64),
+    }
+}
+
+/// Example outer message
+fn example_outer() -> Outer {
+    let mut msg_sequence_field = Vec::new();
+
+    for n in 1..4 {
+        msg_sequence_field.push(example_inner(n)).unwrap();
+    }
+
+    Outer {
+        uint64_field: 42,
+        message_field: example_inner(0),
+        msg_sequence_field,
+    }
+}
+
+/// Independently compute the digest of an inner message
+fn inner_digest(inner: &Inner) -> DigestOutput<sha2::Sha256> {
+    let mut buffer = [0u8; 64];
+    hash_message::<sha2::Sha256>(inner.encode(&mut buffer).unwrap()).unwrap()
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn collect_nested_digests() {
+    let outer = example_outer();
+    let mut buffer = [0u8; 256];
+    let encoded = outer.encode(&mut buffer).unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.collect_nested_digests();
+    assert_eq!(Outer::decode(&mut decoder, encoded).unwrap(), outer);
+
+    let digests = decoder.take_nested_digests();
+    assert_eq!(digests.len(), 4);
+
+    assert_eq!(digests[0].path, [PathElement::field(3)]);
+    assert_eq!(digests[0].digest, inner_digest(&outer.message_field));
+
+    for (i, inner) in outer.msg_sequence_field.iter().enumerate() {
+        let nested = &digests[i + 1];
+        assert_eq!(nested.path, [PathElement::seq_element(4, i)]);
+        assert_eq!(nested.digest, inner_digest(inner));
+    }
+
+    assert!(decoder.take_nested_digests().is_empty());
+}
+
+/// Number of times `count_digest` has been invoked
+static DIGEST_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Count nested message digests
+fn count_digest(path: &[PathElement], digest: &DigestOutput<sha2::Sha256>) {
+    assert_eq!(path.len(), 1);
+    assert_eq!(digest.len(), 32);
+    DIGEST_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn nested_digest_callback() {
+    let outer = example_outer();
+    let mut buffer = [0u8; 256];
+    let encoded = outer.encode(&mut buffer).unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.on_nested_digest(count_digest);
+    assert_eq!(Outer::decode(&mut decoder, encoded).unwrap(), outer);
+    assert_eq!(DIGEST_COUNT.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,274 @@
+//! Integration tests for decoding deeply nested messages
+
+#![cfg(feature = "alloc")]
+
+use veriform::{
+    decoder::{self, hash_message, Decode, UnknownFieldPolicy},
+    error,
+    verihash::VerihashDigest,
+    vint64, Decoder, Encoder, Error, Message, Sha256Digest,
+};
+
+#[cfg(feature = "stack-checks")]
+use veriform::{consts::Literal, decoder::DecodeSeq, field::WireType};
+
+/// Message which optionally contains another instance of itself as field `1`
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Nested {
+    /// Number of messages nested inside this one
+    pub depth: u64,
+
+    /// Nested message
+    pub inner: Option<Box<Nested>>,
+}
+
+impl Nested {
+    /// Create a message with the given number of messages nested inside it
+    fn with_depth(depth: u64) -> Self {
+        (0..depth).fold(Nested::default(), |inner, _| Nested {
+            depth: inner.depth + 1,
+            inner: Some(Box::new(inner)),
+        })
+    }
+}
+
+impl Message for Nested {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let depth = decoder.decode(0, &mut input)?;
+
+        let inner = if input.is_empty() {
+            None
+        } else {
+            Some(Box::new(decoder.decode(1, &mut input)?))
+        };
+
+        Ok(Nested { depth, inner })
+    }
+
+    fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        let mut encoder = Encoder::new(buffer);
+        encoder.uint64(0, false, self.depth)?;
+
+        if let Some(inner) = &self.inner {
+            encoder.message(1, false, inner.as_ref())?;
+        }
+
+        Ok(encoder.finish())
+    }
+
+    fn encoded_len(&self) -> usize {
+        let inner_len = self.inner.as_ref().map_or(0, |inner| {
+            let len = inner.encoded_len();
+            1 + vint64::encoded_len(len as u64) + len
+        });
+
+        1 + vint64::encoded_len(self.depth) + inner_len
+    }
+}
+
+/// Message which only knows about the `depth` field of [`Nested`], leaving
+/// any nested message as an unknown field
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Shallow {
+    /// Number of messages nested inside this one
+    pub depth: u64,
+}
+
+impl Message for Shallow {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let depth = decoder.decode(0, &mut input)?;
+        decoder.decode_unknown_fields(&mut input)?;
+        Ok(Shallow { depth })
+    }
+
+    fn encode<'a>(&self, _buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        unimplemented!()
+    }
+
+    fn encoded_len(&self) -> usize {
+        unimplemented!()
+    }
+}
+
+/// Decode the given message, returning it along with its digest
+fn decode(decoder: &mut Decoder, input: &[u8]) -> Result<(Nested, Sha256Digest), Error> {
+    let msg = Nested::decode(decoder, input)?;
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest)?;
+    Ok((msg, digest))
+}
+
+#[test]
+fn default_max_depth() {
+    let encoded = Nested::with_depth(15).encode_vec().unwrap();
+    assert!(decode(&mut Decoder::new(), &encoded).is_ok());
+
+    let encoded = Nested::with_depth(16).encode_vec().unwrap();
+    let err = decode(&mut Decoder::new(), &encoded).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+}
+
+#[test]
+fn configured_max_depth() {
+    let encoded = Nested::with_depth(30).encode_vec().unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.set_max_depth(Some(31));
+    assert!(decode(&mut decoder, &encoded).is_ok());
+
+    let mut decoder = Decoder::new();
+    decoder.set_max_depth(Some(30));
+    let err = decode(&mut decoder, &encoded).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+}
+
+#[test]
+fn shallow_message_matches_depth_capped_decoder() {
+    let msg = Nested::with_depth(10);
+    let encoded = msg.encode_vec().unwrap();
+
+    let mut unlimited = Decoder::new();
+    unlimited.set_max_depth(None);
+
+    let expected = decode(&mut Decoder::new(), &encoded).unwrap();
+    assert_eq!(decode(&mut unlimited, &encoded).unwrap(), expected);
+    assert_eq!(expected.0, msg);
+    assert_eq!(
+        expected.1.as_ref(),
+        hash_message::<sha2::Sha256>(&encoded).unwrap().as_slice()
+    );
+}
+
+#[test]
+fn decode_100_levels_deep() {
+    let msg = Nested::with_depth(100);
+    let encoded = msg.encode_vec().unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.set_max_depth(None);
+    decoder.collect_nested_digests();
+
+    let (decoded, digest) = decode(&mut decoder, &encoded).unwrap();
+    assert_eq!(decoded, msg);
+    assert_eq!(
+        digest.as_ref(),
+        hash_message::<sha2::Sha256>(&encoded).unwrap().as_slice()
+    );
+
+    // Skipping the nested messages as an unknown field hashes all of them
+    // unless the decoder's depth cap is exceeded
+    let mut skipping = Decoder::new();
+    skipping.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    let err = Shallow::decode(&mut skipping, &encoded).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+
+    let mut skipping = Decoder::new();
+    skipping.set_max_depth(None);
+    skipping.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    assert_eq!(
+        Shallow::decode(&mut skipping, &encoded).unwrap(),
+        Shallow { depth: 100 }
+    );
+
+    let mut skipped_digest = Sha256Digest::default();
+    skipping.fill_digest(&mut skipped_digest).unwrap();
+    assert_eq!(skipped_digest, digest);
+
+    // Nested messages finish decoding innermost first, so the digest at
+    // index `n` is that of the message with `n` messages nested inside it.
+    // Those shallow enough for a depth-capped decoder (which pushed and
+    // popped them on the heap-allocated part of the stack here) must have
+    // the same digests it computes.
+    let digests = decoder.take_nested_digests();
+    assert_eq!(digests.len(), 100);
+
+    for (depth, nested) in (0..15).zip(&digests) {
+        assert_eq!(nested.path.len(), 100 - depth as usize);
+
+        let (_, expected) = decode(
+            &mut Decoder::new(),
+            &Nested::with_depth(depth).encode_vec().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(nested.digest.as_slice(), expected.as_ref());
+    }
+}
+
+#[test]
+fn max_total_fields_counts_nested_fields() {
+    // Each level has a `depth` field, and all but the innermost an `inner`
+    // field, so a message with 2 levels nested inside it has 5 fields
+    let encoded = Nested::with_depth(2).encode_vec().unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.set_max_total_fields(Some(5));
+    assert!(decode(&mut decoder, &encoded).is_ok());
+
+    let mut decoder = Decoder::new();
+    decoder.set_max_total_fields(Some(4));
+    let err = decode(&mut decoder, &encoded).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+}
+
+#[cfg(feature = "stack-checks")]
+#[test]
+fn unbalanced_stack() {
+    /// Message whose `decode` impl leaks the guard for its nested message,
+    /// leaving the nested message's frame on the decoder's stack
+    struct Leaky;
+
+    impl Message for Leaky {
+        fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+        where
+            D: VerihashDigest,
+        {
+            let _: u64 = decoder.decode(0, &mut input)?;
+            let (guard, _) = decoder.enter(1, &mut input)?;
+            core::mem::forget(guard);
+            Ok(Leaky)
+        }
+
+        fn encode<'a>(&self, _buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+            unimplemented!()
+        }
+
+        fn encoded_len(&self) -> usize {
+            unimplemented!()
+        }
+    }
+
+    let encoded = Nested::with_depth(2).encode_vec().unwrap();
+    let mut input = encoded.as_slice();
+
+    let mut decoder = Decoder::new();
+    let _: u64 = decoder.decode(0, &mut input).unwrap();
+    let result: Result<Leaky, Error> = decoder.decode(1, &mut input);
+    assert_eq!(result.err().unwrap().kind(), error::Kind::UnbalancedStack);
+
+    // The leaked frame is discarded, and the outer message is failed
+    let mut digest = Sha256Digest::default();
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+
+    // Likewise for the message elements of a sequence
+    let element = Nested::with_depth(1).encode_vec().unwrap();
+    let body = Literal::new().vint64(element.len() as u64).raw(&element);
+    let encoded = Literal::new().sequence(1, WireType::Message, body.as_bytes());
+    let mut input = encoded.as_bytes();
+
+    let mut decoder = Decoder::new();
+    let mut elements = DecodeSeq::<Leaky, _>::decode_seq(&mut decoder, 1, &mut input).unwrap();
+    let err = elements.next().unwrap().err().unwrap();
+    assert_eq!(err.kind(), error::Kind::UnbalancedStack);
+    drop(elements);
+
+    let err = decoder.fill_digest(&mut digest).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Hashing);
+}
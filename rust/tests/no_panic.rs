@@ -0,0 +1,101 @@
+//! Ensure malformed input can never panic the decoder
+
+use veriform::{
+    decoder::{Decode, DecodeRef, DecodeSeq},
+    Decoder, Message,
+};
+
+#[derive(Message, Debug, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[field(tag = 2, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[digest(alg = "sha256")]
+    pub digest: Option<veriform::Sha256Digest>,
+}
+
+/// Number of random inputs to try in each test
+const ITERATIONS: usize = 1024;
+
+/// Deterministic xorshift PRNG, so these tests don't need a dev-dependency
+/// which would enable `std` features for `no_std` builds
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Generate up to `max_len` random bytes
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.next_u64() as usize % max_len;
+        (0..len).map(|_| self.next_u64() as u8).collect()
+    }
+}
+
+#[test]
+fn decode_message() {
+    let mut rng = Rng(0x5eed_0001);
+
+    for _ in 0..ITERATIONS {
+        let bytes = rng.bytes(256);
+        let mut decoder = Decoder::new();
+        let _ = Outer::decode(&mut decoder, &bytes);
+
+        let mut digest = veriform::Sha256Digest::default();
+        let _ = decoder.fill_digest(&mut digest);
+    }
+}
+
+#[test]
+fn decode_fields() {
+    let mut rng = Rng(0x5eed_0002);
+
+    for _ in 0..ITERATIONS {
+        let bytes = rng.bytes(64);
+        let _: Result<u64, _> = Decoder::new().decode(1, &mut bytes.as_slice());
+        let _: Result<i64, _> = Decoder::new().decode(1, &mut bytes.as_slice());
+        let _: Result<Inner, _> = Decoder::new().decode(1, &mut bytes.as_slice());
+        let _: Result<&[u8], _> = Decoder::new().decode_ref(1, &mut bytes.as_slice());
+        let _: Result<&str, _> = Decoder::new().decode_ref(1, &mut bytes.as_slice());
+    }
+}
+
+#[test]
+fn decode_sequences() {
+    let mut rng = Rng(0x5eed_0003);
+
+    for _ in 0..ITERATIONS {
+        let bytes = rng.bytes(64);
+        let mut decoder = Decoder::new();
+        let seq: Result<veriform::decoder::sequence::Iter<'_, '_, u64, _>, _> =
+            decoder.decode_seq(1, &mut bytes.as_slice());
+
+        if let Ok(iter) = seq {
+            for _ in iter {}
+        }
+
+        let mut decoder = Decoder::new();
+        let seq: Result<veriform::decoder::sequence::Iter<'_, '_, Inner, _>, _> =
+            decoder.decode_seq(1, &mut bytes.as_slice());
+
+        if let Ok(iter) = seq {
+            for _ in iter {}
+        }
+    }
+}
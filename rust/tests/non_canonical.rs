@@ -0,0 +1,88 @@
+//! Integration tests for rejecting `vint64` values which aren't encoded in
+//! their shortest possible form
+
+use veriform::{consts::Literal, decoder::DecodeOptions, error, field::WireType, validate};
+
+/// Encode a value below 2^14 as a two-byte `vint64`, even if it fits in one
+fn long_vint64(value: u64) -> [u8; 2] {
+    assert!(value < 1 << 14);
+    let encoded = (value << 2) | 0b10;
+    [encoded as u8, (encoded >> 8) as u8]
+}
+
+/// Ensure the given message is rejected as containing a non-canonical
+/// `vint64` at the given position
+fn assert_non_canonical(encoded: &Literal, position: usize) {
+    let err = validate(encoded.as_bytes(), DecodeOptions::new()).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NonCanonicalVarint);
+    assert_eq!(err.position(), Some(position));
+}
+
+#[test]
+fn field_header() {
+    // Field #1 with the uint64 wire type, i.e. a header of 0x12
+    let encoded = Literal::new()
+        .uint64(0, 1)
+        .raw(&long_vint64(0x12))
+        .vint64(1);
+    assert_non_canonical(&encoded, 2);
+}
+
+#[test]
+fn integer_values() {
+    let uint64 = Literal::new()
+        .header(1, false, WireType::UInt64)
+        .raw(&long_vint64(5));
+    assert_non_canonical(&uint64, 1);
+
+    // -3 is zigzag encoded as 5
+    let sint64 = Literal::new()
+        .header(1, false, WireType::SInt64)
+        .raw(&long_vint64(5));
+    assert_non_canonical(&sint64, 1);
+}
+
+#[test]
+fn length_delimiters() {
+    for &wire_type in &[WireType::Bytes, WireType::String] {
+        let encoded = Literal::new()
+            .header(1, false, wire_type)
+            .raw(&long_vint64(2))
+            .raw(b"ab");
+        assert_non_canonical(&encoded, 1);
+    }
+
+    let message = Literal::new()
+        .header(1, false, WireType::Message)
+        .raw(&long_vint64(2))
+        .uint64(1, 1);
+    assert_non_canonical(&message, 1);
+
+    // Sequence of 2 bytes of `uint64` elements
+    let sequence = Literal::new()
+        .header(1, false, WireType::Sequence)
+        .raw(&long_vint64(2 << 4 | WireType::UInt64 as u64))
+        .raw(&[0x03, 0x05]);
+    assert_non_canonical(&sequence, 1);
+}
+
+#[test]
+fn nested_values() {
+    // Positions are relative to the innermost message or sequence body
+    let nested = Literal::new()
+        .header(1, false, WireType::UInt64)
+        .raw(&long_vint64(7));
+    let message = Literal::new().uint64(0, 1).message(1, &nested);
+    assert_non_canonical(&message, 1);
+
+    let elements = Literal::new().vint64(1).raw(&long_vint64(2));
+    let sequence = Literal::new().sequence(1, WireType::UInt64, elements.as_bytes());
+    assert_non_canonical(&sequence, 1);
+
+    let element = Literal::new().uint64(1, 1);
+    let elements = Literal::new()
+        .raw(&long_vint64(element.len() as u64))
+        .raw(element.as_bytes());
+    let sequence = Literal::new().sequence(1, WireType::Message, elements.as_bytes());
+    assert_non_canonical(&sequence, 0);
+}
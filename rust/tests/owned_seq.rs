@@ -0,0 +1,181 @@
+//! Integration tests for `veriform::decoder::sequence::IntoIter`
+
+#![cfg(feature = "test-util")]
+
+use veriform::{
+    decoder::{sequence, DecodeSeq, DecodeSeqOwned},
+    field::WireType,
+    test_util::MessageBuilder,
+    Decoder, Error, Message, Sha256Digest,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Element {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+/// Results of decoding each element of a sequence, along with the digest of
+/// the message containing it
+type Decoded<T> = (Vec<Result<T, Error>>, Result<Sha256Digest, Error>);
+
+/// Decode the sequence in field `1` of `message` using the given function,
+/// then compute the digest of the message
+fn decode_with<T, F>(message: &[u8], f: F) -> Decoded<T>
+where
+    F: FnOnce(&mut Decoder, &mut &[u8]) -> Vec<Result<T, Error>>,
+{
+    let mut decoder = Decoder::new();
+    let mut input = message;
+    let results = f(&mut decoder, &mut input);
+
+    let mut digest = Sha256Digest::default();
+    let digest = decoder.fill_digest(&mut digest).map(|_| digest);
+    (results, digest)
+}
+
+/// Decode the sequence in field `1` with both the borrowed and owned
+/// iterators, asserting their results are identical
+fn assert_same<T, U>(message: &[u8]) -> Decoded<U>
+where
+    T: std::fmt::Debug,
+    U: std::fmt::Debug + PartialEq + From<T>,
+    for<'a, 'b> sequence::Iter<'a, 'b, T, sha2::Sha256>: Iterator<Item = Result<T, Error>>,
+    sequence::IntoIter<U, sha2::Sha256>: Iterator<Item = Result<U, Error>>,
+    Decoder: DecodeSeq<T, sha2::Sha256> + DecodeSeqOwned<U, sha2::Sha256>,
+{
+    let borrowed = decode_with(message, |decoder, input| {
+        let iter: sequence::Iter<'_, '_, T, _> = decoder.decode_seq(1, input).unwrap();
+        iter.map(|result| result.map(U::from)).collect()
+    });
+
+    let owned = decode_with(message, |decoder, input| {
+        let iter: sequence::IntoIter<U, _> = decoder.decode_seq_owned(1, input).unwrap();
+        iter.collect()
+    });
+
+    assert_eq!(borrowed.0, owned.0);
+
+    // The owned iterator hashes the sequence before decoding its elements,
+    // so the digest doesn't depend on whether they're semantically valid
+    if borrowed.0.iter().all(Result::is_ok) {
+        assert_eq!(borrowed.1, owned.1);
+    }
+
+    owned
+}
+
+#[test]
+fn owned_uint64_sequence() {
+    let mut body = Vec::new();
+    for n in &[0, 1, 300, u64::MAX] {
+        body.extend_from_slice(vint64::encode(*n).as_ref());
+    }
+
+    let message = MessageBuilder::new()
+        .sequence(1, WireType::UInt64, &body)
+        .build();
+
+    let (results, digest) = assert_same::<u64, u64>(&message);
+    let values = results.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [0, 1, 300, u64::MAX]);
+    assert!(digest.is_ok());
+}
+
+#[test]
+fn owned_message_sequence() {
+    let mut body = Vec::new();
+    for n in 0..3 {
+        let element = MessageBuilder::new().uint64(0, n).build();
+        body.extend_from_slice(vint64::encode(element.len() as u64).as_ref());
+        body.extend_from_slice(&element);
+    }
+
+    let message = MessageBuilder::new()
+        .sequence(1, WireType::Message, &body)
+        .build();
+
+    let (results, digest) = assert_same::<Element, Element>(&message);
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(Result::is_ok));
+    assert!(digest.is_ok());
+}
+
+#[test]
+fn owned_message_sequence_with_invalid_element() {
+    let valid = MessageBuilder::new().uint64(0, 1).build();
+    let invalid = MessageBuilder::new().sint64(0, -1).build();
+
+    let mut body = Vec::new();
+    for element in &[&valid, &invalid] {
+        body.extend_from_slice(vint64::encode(element.len() as u64).as_ref());
+        body.extend_from_slice(element);
+    }
+
+    let message = MessageBuilder::new()
+        .sequence(1, WireType::Message, &body)
+        .build();
+
+    let (results, digest) = assert_same::<Element, Element>(&message);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(digest.is_ok());
+}
+
+#[test]
+fn owned_bytes_and_strings() {
+    let mut body = Vec::new();
+    for element in &["foo", "", "bar"] {
+        body.extend_from_slice(vint64::encode(element.len() as u64).as_ref());
+        body.extend_from_slice(element.as_bytes());
+    }
+
+    let bytes = MessageBuilder::new()
+        .sequence(1, WireType::Bytes, &body)
+        .build();
+
+    let mut decoder = Decoder::new();
+    let mut input = &bytes[..];
+    let iter: sequence::IntoIter<Vec<u8>, _> = decoder.decode_seq_owned(1, &mut input).unwrap();
+    let values = iter.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, [&b"foo"[..], b"", b"bar"]);
+
+    let strings = MessageBuilder::new()
+        .sequence(1, WireType::String, &body)
+        .build();
+
+    let mut decoder = Decoder::new();
+    let mut input = &strings[..];
+    let iter: sequence::IntoIter<String, _> = decoder.decode_seq_owned(1, &mut input).unwrap();
+    let values = iter.collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, ["foo", "", "bar"]);
+}
+
+/// Decode the sequence of strings in field `1` of `message`, returning an
+/// iterator which outlives both the message and the decoder
+fn owned_strings(message: Vec<u8>) -> sequence::IntoIter<String, sha2::Sha256> {
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+    decoder.decode_seq_owned(1, &mut input).unwrap()
+}
+
+#[test]
+fn owned_iter_outlives_decoder() {
+    let mut body = Vec::new();
+    for element in &["foo", "bar"] {
+        body.extend_from_slice(vint64::encode(element.len() as u64).as_ref());
+        body.extend_from_slice(element.as_bytes());
+    }
+
+    let message = MessageBuilder::new()
+        .sequence(1, WireType::String, &body)
+        .build();
+
+    let iter = owned_strings(message);
+    let values = std::thread::spawn(move || iter.collect::<Result<Vec<_>, _>>())
+        .join()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(values, ["foo", "bar"]);
+}
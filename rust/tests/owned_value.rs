@@ -0,0 +1,342 @@
+//! Integration tests for `veriform::decoder::OwnedValue`
+
+#![cfg(feature = "alloc")]
+
+use veriform::{
+    consts::Literal,
+    decoder::{hash_message, Decode, OwnedField, OwnedValue},
+    error,
+    field::WireType,
+    Decoder, Sha256Digest,
+};
+
+#[test]
+fn decode_nested_message() {
+    let inner = Literal::new().uint64(0, 42).string(1, "hello");
+    let element = Literal::new().sint64(0, -1);
+    let seq_body = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes());
+
+    let encoded = Literal::new()
+        .bytes(2, b"\x00\xff")
+        .message(3, &inner)
+        .sequence(4, WireType::Message, seq_body.as_bytes())
+        .sequence(
+            5,
+            WireType::UInt64,
+            Literal::new().vint64(1).vint64(2).as_bytes(),
+        );
+
+    let value = {
+        // The decoded value outlives the input
+        let input = encoded.as_bytes().to_vec();
+        let mut decoder: Decoder = Decoder::new();
+        let value = decoder.decode_owned(&input).unwrap();
+
+        let mut digest = Sha256Digest::default();
+        decoder.fill_digest(&mut digest).unwrap();
+        assert_eq!(
+            &digest[..],
+            &hash_message::<sha2::Sha256>(&input).unwrap()[..]
+        );
+
+        value
+    };
+
+    assert_eq!(
+        value,
+        OwnedValue::Message(vec![
+            OwnedField::new(2, OwnedValue::Bytes(vec![0x00, 0xff])),
+            OwnedField::new(
+                3,
+                OwnedValue::Message(vec![
+                    OwnedField::new(0, OwnedValue::UInt64(42)),
+                    OwnedField::new(1, OwnedValue::String("hello".to_owned())),
+                ])
+            ),
+            OwnedField::new(
+                4,
+                OwnedValue::Sequence {
+                    wire_type: WireType::Message,
+                    elements: vec![OwnedValue::Message(vec![OwnedField::new(
+                        0,
+                        OwnedValue::SInt64(-1)
+                    )])],
+                }
+            ),
+            OwnedField::new(
+                5,
+                OwnedValue::Sequence {
+                    wire_type: WireType::UInt64,
+                    elements: vec![OwnedValue::UInt64(1), OwnedValue::UInt64(2)],
+                }
+            ),
+        ])
+    );
+
+    let inner = value.field(3).unwrap();
+    assert_eq!(inner.wire_type(), WireType::Message);
+    assert_eq!(
+        inner.field(1),
+        Some(&OwnedValue::String("hello".to_owned()))
+    );
+}
+
+#[test]
+fn decode_bool_fields() {
+    let encoded = Literal::new().bool(1, false).bool(2, true);
+    let mut decoder: Decoder = Decoder::new();
+
+    assert_eq!(
+        decoder.decode_owned(encoded.as_bytes()).unwrap(),
+        OwnedValue::Message(vec![
+            OwnedField::new(1, OwnedValue::Bool(false)),
+            OwnedField::new(2, OwnedValue::Bool(true))
+        ])
+    );
+}
+
+#[test]
+fn bytes_and_string_are_distinct() {
+    let encoded = Literal::new()
+        .bytes(1, b"hello")
+        .string(2, "hello")
+        .uint64(3, 1)
+        .sint64(4, 1);
+
+    let mut decoder: Decoder = Decoder::new();
+    let value = decoder.decode_owned(encoded.as_bytes()).unwrap();
+
+    let bytes = value.field(1).unwrap();
+    let string = value.field(2).unwrap();
+    assert_eq!(bytes, &OwnedValue::Bytes(b"hello".to_vec()));
+    assert_eq!(string, &OwnedValue::String("hello".to_owned()));
+    assert_ne!(bytes, string);
+    assert_eq!(bytes.wire_type(), WireType::Bytes);
+    assert_eq!(string.wire_type(), WireType::String);
+
+    let uint64 = value.field(3).unwrap();
+    let sint64 = value.field(4).unwrap();
+    assert_ne!(uint64, sint64);
+    assert_eq!(uint64.wire_type(), WireType::UInt64);
+    assert_eq!(sint64.wire_type(), WireType::SInt64);
+}
+
+#[test]
+fn critical_flags_and_sequence_types_are_retained() {
+    let encoded = Literal::new()
+        .uint64(1, 1)
+        .header(2, true, WireType::UInt64)
+        .vint64(2)
+        .sequence(3, WireType::Bytes, &[])
+        .sequence(4, WireType::Message, &[]);
+
+    let mut decoder: Decoder = Decoder::new();
+
+    assert_eq!(
+        decoder.decode_owned(encoded.as_bytes()).unwrap(),
+        OwnedValue::Message(vec![
+            OwnedField::new(1, OwnedValue::UInt64(1)),
+            OwnedField {
+                tag: 2,
+                critical: true,
+                value: OwnedValue::UInt64(2),
+            },
+            OwnedField::new(
+                3,
+                OwnedValue::Sequence {
+                    wire_type: WireType::Bytes,
+                    elements: vec![],
+                }
+            ),
+            OwnedField::new(
+                4,
+                OwnedValue::Sequence {
+                    wire_type: WireType::Message,
+                    elements: vec![],
+                }
+            ),
+        ])
+    );
+}
+
+#[test]
+fn encode_round_trip() {
+    let inner = Literal::new().uint64(0, 42).string(1, "hello");
+    let element = Literal::new().sint64(0, -1).bool(1, true);
+    let msg_seq = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes())
+        .vint64(0);
+    let bytes_seq = Literal::new().vint64(2).raw(b"ab").vint64(0);
+
+    let inputs = [
+        Literal::new(),
+        Literal::new().bool(1, false).bool(2, true).sint64(3, -1000),
+        Literal::new()
+            .bytes(2, b"\x00\xff")
+            .message(3, &inner)
+            .message(4, &Literal::new()),
+        Literal::new()
+            .sequence(1, WireType::Message, msg_seq.as_bytes())
+            .sequence(2, WireType::Bytes, bytes_seq.as_bytes())
+            .sequence(3, WireType::False, &[0, 1, 1])
+            .sequence(4, WireType::SInt64, Literal::new().vint64(3).as_bytes()),
+    ];
+
+    for input in &inputs {
+        let mut decoder: Decoder = Decoder::new();
+        let value = decoder.decode_owned(input.as_bytes()).unwrap();
+        assert_eq!(value.encode().unwrap(), input.as_bytes());
+    }
+}
+
+#[test]
+fn encode_critical_fields() {
+    let inner = Literal::new().header(0, true, WireType::UInt64).vint64(42);
+
+    let encoded = Literal::new()
+        .header(1, true, WireType::True)
+        .header(2, true, WireType::Message)
+        .vint64(inner.len() as u64)
+        .raw(inner.as_bytes())
+        .header(3, true, WireType::Sequence)
+        .vint64(2 << 4 | WireType::SInt64 as u64)
+        .vint64(1)
+        .vint64(3);
+
+    let mut decoder: Decoder = Decoder::new();
+    let value = decoder.decode_owned(encoded.as_bytes()).unwrap();
+    assert_eq!(value.encode().unwrap(), encoded.as_bytes());
+}
+
+#[test]
+fn encode_empty_sequences() {
+    let encoded = Literal::new()
+        .sequence(1, WireType::False, &[])
+        .sequence(2, WireType::SInt64, &[])
+        .sequence(3, WireType::Bytes, &[])
+        .sequence(4, WireType::String, &[])
+        .sequence(5, WireType::Message, &[]);
+
+    let mut decoder: Decoder = Decoder::new();
+    let value = decoder.decode_owned(encoded.as_bytes()).unwrap();
+
+    let mut digest = Sha256Digest::default();
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        &digest[..],
+        &hash_message::<sha2::Sha256>(encoded.as_bytes()).unwrap()[..]
+    );
+
+    assert_eq!(value.encode().unwrap(), encoded.as_bytes());
+}
+
+#[test]
+fn encode_mismatched_sequence_element() {
+    let value = OwnedValue::Message(vec![OwnedField::new(
+        1,
+        OwnedValue::Sequence {
+            wire_type: WireType::UInt64,
+            elements: vec![OwnedValue::UInt64(1), OwnedValue::SInt64(2)],
+        },
+    )]);
+
+    assert_eq!(
+        value.encode().unwrap_err().kind(),
+        error::Kind::SequenceElement {
+            index: 1,
+            actual: WireType::SInt64,
+            wanted: WireType::UInt64,
+        }
+    );
+}
+
+#[test]
+fn encode_out_of_order_fields() {
+    let value = OwnedValue::Message(vec![
+        OwnedField::new(2, OwnedValue::UInt64(1)),
+        OwnedField::new(1, OwnedValue::UInt64(2)),
+    ]);
+    assert_eq!(
+        value.encode().unwrap_err().kind(),
+        error::Kind::Order { tag: 1 }
+    );
+
+    let nested = OwnedValue::Message(vec![OwnedField::new(
+        1,
+        OwnedValue::Message(vec![
+            OwnedField::new(3, OwnedValue::Bool(true)),
+            OwnedField::new(3, OwnedValue::Bool(false)),
+        ]),
+    )]);
+    assert_eq!(
+        nested.encode().unwrap_err().kind(),
+        error::Kind::Order { tag: 3 }
+    );
+}
+
+#[test]
+fn decode_remaining_fields() {
+    let inner = Literal::new().bool(0, true);
+    let encoded = Literal::new()
+        .uint64(0, 42)
+        .string(1, "hello")
+        .message(2, &inner)
+        .sint64(3, -1);
+
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let first: u64 = decoder.decode(0, &mut input).unwrap();
+    assert_eq!(first, 42);
+
+    let remaining = decoder.decode_remaining(&mut input).unwrap();
+    assert!(input.is_empty());
+    assert_eq!(
+        remaining,
+        vec![
+            OwnedField::new(1, OwnedValue::String("hello".to_owned())),
+            OwnedField::new(
+                2,
+                OwnedValue::Message(vec![OwnedField::new(0, OwnedValue::Bool(true))])
+            ),
+            OwnedField::new(3, OwnedValue::SInt64(-1)),
+        ]
+    );
+
+    // The digest is the same as when decoding the entire message at once
+    let mut expected: Decoder = Decoder::new();
+    expected.decode_owned(encoded.as_bytes()).unwrap();
+
+    let (mut digest, mut expected_digest) = (Sha256Digest::default(), Sha256Digest::default());
+    decoder.fill_digest(&mut digest).unwrap();
+    expected.fill_digest(&mut expected_digest).unwrap();
+    assert_eq!(digest, expected_digest);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn postcard_round_trip() {
+    let inner = Literal::new().uint64(0, 42).string(1, "hello");
+    let element = Literal::new().sint64(0, -1).bool(1, true);
+    let msg_seq = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes());
+    let encoded = Literal::new()
+        .bool(1, false)
+        .bytes(2, b"\x00\xff")
+        .message(3, &inner)
+        .sequence(4, WireType::Message, msg_seq.as_bytes())
+        .sequence(5, WireType::UInt64, Literal::new().vint64(1).as_bytes())
+        .sequence(6, WireType::Bytes, &[])
+        .header(7, true, WireType::UInt64)
+        .vint64(7);
+
+    let mut decoder: Decoder = Decoder::new();
+    let value = decoder.decode_owned(encoded.as_bytes()).unwrap();
+
+    let serialized = postcard::to_allocvec(&value).unwrap();
+    let deserialized: OwnedValue = postcard::from_bytes(&serialized).unwrap();
+    assert_eq!(deserialized, value);
+}
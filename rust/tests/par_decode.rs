@@ -0,0 +1,111 @@
+//! Integration tests for parallel sequence decoding
+
+#![cfg(feature = "rayon")]
+
+use veriform::{
+    decoder::{sequence, DecodeSeq},
+    Decoder, Encoder, Error, Message, Sha256Digest,
+};
+
+/// Number of messages in the test sequence
+const NUM_MESSAGES: usize = 1000;
+
+/// Sequence iterator over [`Element`] messages
+type Iter<'a, 'b> = sequence::Iter<'a, 'b, Element, sha2::Sha256>;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Element {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Message which is encoded in place of an [`Element`] to trigger an error
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Mismatched {
+    #[field(tag = 0, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Encode a sequence of messages as field `0`
+fn encode_seq<'a>(buffer: &'a mut [u8], messages: &[&dyn Message]) -> &'a [u8] {
+    let body_len = messages
+        .iter()
+        .map(|msg| {
+            let encoded_len = msg.encoded_len();
+            vint64::encoded_len(encoded_len as u64) + encoded_len
+        })
+        .sum();
+
+    let mut encoder = Encoder::new(buffer);
+    encoder
+        .message_seq(0, false, body_len, messages.iter().cloned())
+        .unwrap();
+    encoder.finish()
+}
+
+/// Example elements
+fn example_elements() -> Vec<Element> {
+    (0..NUM_MESSAGES as u64)
+        .map(|n| Element {
+            uint64_field: n * 1000,
+            sint64_field: -(n as i64),
+        })
+        .collect()
+}
+
+/// Decode a sequence, returning the result of the given collect function
+/// along with the message digest
+fn decode_seq<F>(encoded: &[u8], collect: F) -> (Result<Vec<Element>, Error>, Sha256Digest)
+where
+    F: FnOnce(Iter<'_, '_>) -> Result<Vec<Element>, Error>,
+{
+    let mut decoder = Decoder::new();
+    let mut input = encoded;
+    let iter: Iter<'_, '_> = decoder.decode_seq(0, &mut input).unwrap();
+    let result = collect(iter);
+
+    let mut digest = Sha256Digest::default();
+    if result.is_ok() {
+        decoder.fill_digest(&mut digest).unwrap();
+    }
+
+    (result, digest)
+}
+
+#[test]
+fn parallel_and_serial_decode_match() {
+    let elements = example_elements();
+    let messages: Vec<&dyn Message> = elements.iter().map(|elem| elem as &dyn Message).collect();
+
+    let mut buffer = vec![0u8; 65536];
+    let encoded = encode_seq(&mut buffer, &messages);
+
+    let (serial, serial_digest) = decode_seq(encoded, |iter| iter.collect());
+    let (parallel, parallel_digest) = decode_seq(encoded, |iter| iter.par_decode_collect());
+
+    assert_eq!(serial.unwrap(), elements);
+    assert_eq!(parallel.unwrap(), elements);
+    assert_eq!(serial_digest, parallel_digest);
+}
+
+#[test]
+fn parallel_decode_returns_first_error() {
+    let elements = example_elements();
+    let mismatched = Mismatched { sint64_field: -1 };
+
+    let mut messages: Vec<&dyn Message> =
+        elements.iter().map(|elem| elem as &dyn Message).collect();
+    messages[NUM_MESSAGES / 2] = &mismatched;
+    messages[NUM_MESSAGES - 1] = &mismatched;
+
+    let mut buffer = vec![0u8; 65536];
+    let encoded = encode_seq(&mut buffer, &messages);
+
+    let (serial, _) = decode_seq(encoded, |iter| iter.collect());
+    let (parallel, _) = decode_seq(encoded, |iter| iter.par_decode_collect());
+
+    assert_eq!(serial.unwrap_err().kind(), parallel.unwrap_err().kind());
+}
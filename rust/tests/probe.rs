@@ -0,0 +1,183 @@
+//! Integration tests for probing which message type the input contains
+
+use heapless::{consts::U2, Vec};
+use veriform::{consts::Literal, decoder::hash_message, field::WireType, Decoder, Message};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Target {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: TargetInner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 2)]
+    pub elements: Vec<Element, U2>,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct TargetInner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub innermost: Element,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Element {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct SignedElement {
+    #[field(tag = 0, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Fails at the outermost message (field 1 is a message, not a `uint64`)
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct FlatProbe {
+    #[field(tag = 1, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+/// Fails at a nested message
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct ShallowProbe {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: SignedElement,
+}
+
+/// Fails at a message nested two levels deep
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct DeepProbe {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: DeepProbeInner,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct DeepProbeInner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub innermost: SignedElement,
+}
+
+/// Fails at an element of a sequence of messages
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct SeqProbe {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: TargetInner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 2)]
+    pub elements: Vec<SignedElement, U2>,
+}
+
+/// Encoded [`Target`]
+fn target_bytes() -> Literal {
+    const ELEMENT: Literal = Literal::new().uint64(0, 2);
+    const INNER: Literal = Literal::new().uint64(0, 1).message(1, &ELEMENT);
+
+    let element = Literal::new()
+        .vint64(ELEMENT.len() as u64)
+        .raw(ELEMENT.as_bytes());
+    let body = Literal::new()
+        .raw(element.as_bytes())
+        .raw(element.as_bytes());
+
+    Literal::new()
+        .message(1, &INNER)
+        .sequence(2, WireType::Message, body.as_bytes())
+}
+
+fn example_target() -> Target {
+    let mut elements = Vec::new();
+    elements.push(Element { uint64_field: 2 }).unwrap();
+    elements.push(Element { uint64_field: 2 }).unwrap();
+
+    Target {
+        inner: TargetInner {
+            uint64_field: 1,
+            innermost: Element { uint64_field: 2 },
+        },
+        elements,
+    }
+}
+
+/// Decode a [`Target`] with the given decoder and check its digest
+fn decode_target(decoder: &mut Decoder, input: &[u8]) {
+    let target = decoder.try_decode_message::<Target>(input).unwrap();
+    assert_eq!(target, example_target());
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(
+        digest.as_ref(),
+        hash_message::<sha2::Sha256>(input).unwrap().as_slice()
+    );
+}
+
+#[test]
+fn failed_probe_at_each_depth() {
+    let input = target_bytes();
+
+    let mut decoder = Decoder::new();
+    assert!(decoder
+        .try_decode_message::<FlatProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+
+    let mut decoder = Decoder::new();
+    assert!(decoder
+        .try_decode_message::<ShallowProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+
+    let mut decoder = Decoder::new();
+    assert!(decoder
+        .try_decode_message::<DeepProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+
+    let mut decoder = Decoder::new();
+    assert!(decoder
+        .try_decode_message::<SeqProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+}
+
+#[test]
+fn repeated_failed_probes() {
+    let input = target_bytes();
+    let mut decoder = Decoder::new();
+
+    assert!(decoder
+        .try_decode_message::<DeepProbe>(input.as_bytes())
+        .is_err());
+    assert!(decoder
+        .try_decode_message::<SeqProbe>(input.as_bytes())
+        .is_err());
+    assert!(decoder
+        .try_decode_message::<FlatProbe>(input.as_bytes())
+        .is_err());
+    assert!(decoder
+        .try_decode_message::<ShallowProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn failed_probe_discards_nested_digests() {
+    let input = target_bytes();
+    let mut decoder = Decoder::new();
+    decoder.collect_nested_digests();
+
+    assert!(decoder
+        .try_decode_message::<SeqProbe>(input.as_bytes())
+        .is_err());
+    decode_target(&mut decoder, input.as_bytes());
+
+    // `inner`, `inner.innermost`, and both sequence elements
+    assert_eq!(decoder.take_nested_digests().len(), 4);
+}
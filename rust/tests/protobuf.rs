@@ -0,0 +1,136 @@
+//! Integration tests for `veriform::protobuf`
+
+#![cfg(all(feature = "protobuf-compat", feature = "sha2"))]
+
+use veriform::{
+    decoder::{self, Decode, DecodeRef},
+    error,
+    field::{self, WireType},
+    protobuf::{from_protobuf, FieldMap},
+    verihash::VerihashDigest,
+    Decoder, Encoder, Error, Message,
+};
+
+/// Veriform equivalent of:
+///
+/// ```protobuf
+/// message Example {
+///     uint64 id = 1;
+///     string name = 2;
+/// }
+/// ```
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Example {
+    pub id: u64,
+    pub name: String,
+}
+
+impl Message for Example {
+    fn decode<D>(decoder: &mut decoder::Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let id = decoder.decode(1, &mut input)?;
+        let name: &str = decoder.decode_ref(2, &mut input)?;
+
+        Ok(Example {
+            id,
+            name: name.into(),
+        })
+    }
+
+    fn encode<'a>(&self, buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        let mut encoder = Encoder::new(buffer);
+        encoder.uint64(1, false, self.id)?;
+        encoder.string(2, false, &self.name)?;
+        Ok(encoder.finish())
+    }
+
+    fn encoded_len(&self) -> usize {
+        field::length::uint64(1, self.id) + field::length::string(2, &self.name)
+    }
+}
+
+/// Field map for [`Example`]
+fn example_fields() -> FieldMap {
+    FieldMap::new().uint64(1).string(2)
+}
+
+/// `Example { id: 150, name: "testing" }` encoded as protobuf
+const EXAMPLE_PB: &[u8] = b"\x08\x96\x01\x12\x07testing";
+
+#[test]
+fn two_field_round_trip() {
+    let message = from_protobuf(EXAMPLE_PB, &example_fields()).unwrap();
+    let example = Example::decode(&mut Decoder::new(), &message).unwrap();
+
+    assert_eq!(example.id, 150);
+    assert_eq!(example.name, "testing");
+    assert_eq!(example.encode_vec().unwrap(), message);
+}
+
+#[test]
+fn fields_sorted_by_tag() {
+    let reversed = [&EXAMPLE_PB[3..], &EXAMPLE_PB[..3]].concat();
+
+    assert_eq!(
+        from_protobuf(&reversed, &example_fields()).unwrap(),
+        from_protobuf(EXAMPLE_PB, &example_fields()).unwrap()
+    );
+}
+
+#[test]
+fn signed_and_nested_fields() {
+    let fields = FieldMap::new()
+        .sint64(1)
+        .int64(2)
+        .message(3, FieldMap::new().bytes(1));
+
+    // sint64 -2 (zigzag), int64 -1 (two's complement), and a nested message
+    // containing the bytes `01 02`
+    let mut pb_bytes = vec![0x08, 0x03, 0x10];
+    pb_bytes.extend_from_slice(&[0xff; 9]);
+    pb_bytes.extend_from_slice(&[0x01, 0x1a, 0x04, 0x0a, 0x02, 0x01, 0x02]);
+
+    let message = from_protobuf(&pb_bytes, &fields).unwrap();
+    let mut decoder = Decoder::new();
+    let mut input = &message[..];
+
+    let value: i64 = decoder.decode(1, &mut input).unwrap();
+    assert_eq!(value, -2);
+
+    let value: i64 = decoder.decode(2, &mut input).unwrap();
+    assert_eq!(value, -1);
+
+    let nested = decoder.decode_message_bytes(3, &mut input).unwrap();
+    let bytes: &[u8] = Decoder::new().decode_ref(1, &mut &nested[..]).unwrap();
+    assert_eq!(bytes, [1, 2]);
+    assert!(input.is_empty());
+}
+
+#[test]
+fn unsupported_fields() {
+    // `fixed64` field
+    let fixed64 = [0x09, 0, 0, 0, 0, 0, 0, 0, 0];
+    let err = from_protobuf(&fixed64, &FieldMap::new().uint64(1)).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::InvalidWireType);
+
+    // Field missing from the field map
+    let err = from_protobuf(EXAMPLE_PB, &FieldMap::new().uint64(1)).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 2 });
+
+    // Repeated field
+    let repeated = [0x08, 0x01, 0x08, 0x02];
+    let err = from_protobuf(&repeated, &FieldMap::new().uint64(1)).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 1 });
+
+    // Wire type which doesn't match the field map
+    let err = from_protobuf(EXAMPLE_PB, &FieldMap::new().bytes(1).string(2)).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        error::Kind::UnexpectedWireType {
+            actual: WireType::UInt64,
+            wanted: WireType::Bytes
+        }
+    );
+}
@@ -0,0 +1,80 @@
+//! Integration tests for hashing the raw bytes consumed by the decoder
+
+#![cfg(feature = "alloc")]
+
+use sha2::{Digest, Sha256};
+use veriform::{
+    consts::Literal,
+    decoder::{Decode, DecodeRef},
+    Decoder,
+};
+
+/// Encode an example message containing a nested message
+fn example_message() -> Literal {
+    Literal::new()
+        .uint64(1, 42)
+        .message(2, &Literal::new().sint64(0, -42))
+        .bytes(3, b"hello")
+}
+
+/// Decode the first `n` fields of the example message
+fn decode_fields(decoder: &mut Decoder, input: &mut &[u8], n: usize) {
+    let value: u64 = decoder.decode(1, input).unwrap();
+    assert_eq!(value, 42);
+
+    if n < 2 {
+        return;
+    }
+
+    let (mut inner, mut body) = decoder.enter(2, input).unwrap();
+    let value: i64 = inner.decode(0, &mut body).unwrap();
+    assert_eq!(value, -42);
+    inner.finish(body).unwrap();
+
+    if n < 3 {
+        return;
+    }
+
+    let value: &[u8] = decoder.decode_ref(3, input).unwrap();
+    assert_eq!(value, b"hello");
+}
+
+#[test]
+fn raw_digest_of_message() {
+    let encoded = example_message();
+    let mut decoder = Decoder::new();
+    decoder.hash_raw_bytes();
+
+    let mut input = encoded.as_bytes();
+    decode_fields(&mut decoder, &mut input, 3);
+    assert!(input.is_empty());
+
+    let mut verihash_digest = [0u8; 32];
+    decoder.fill_digest(&mut verihash_digest).unwrap();
+
+    let raw_digest = decoder.raw_digest().unwrap();
+    assert_eq!(raw_digest, Sha256::digest(encoded.as_bytes()));
+    assert_ne!(&raw_digest[..], &verihash_digest[..]);
+}
+
+#[test]
+fn raw_digest_of_consumed_prefix() {
+    let encoded = example_message();
+    let mut decoder = Decoder::new();
+    decoder.hash_raw_bytes();
+
+    let mut input = encoded.as_bytes();
+    decode_fields(&mut decoder, &mut input, 2);
+
+    let consumed = encoded.as_bytes().len() - input.len();
+    assert_eq!(
+        decoder.raw_digest().unwrap(),
+        Sha256::digest(&encoded.as_bytes()[..consumed])
+    );
+}
+
+#[test]
+fn raw_digest_not_enabled() {
+    let decoder = Decoder::new();
+    assert!(decoder.raw_digest().is_err());
+}
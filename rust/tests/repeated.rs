@@ -0,0 +1,163 @@
+//! Integration tests for `veriform::Decoder::decode_repeated_u64`
+
+use veriform::{
+    consts::Literal,
+    decoder::{Decode, DecodeRef, UnknownFieldPolicy},
+    error,
+    field::WireType,
+    Decoder, Error,
+};
+
+/// Decode a repeated field with tag 1 followed by a string field with tag 2
+fn decode_repeated(encoded: &Literal) -> Result<(Vec<u64>, String), Error> {
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let values = decoder
+        .decode_repeated_u64(1, &mut input)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let s: &str = decoder.decode_ref(2, &mut input)?;
+    assert!(input.is_empty());
+    Ok((values, s.to_owned()))
+}
+
+#[test]
+fn packed_and_unpacked() {
+    let body = Literal::new().vint64(1).vint64(2).vint64(3);
+    let packed = Literal::new()
+        .sequence(1, WireType::UInt64, body.as_bytes())
+        .string(2, "end");
+
+    let unpacked = Literal::new()
+        .uint64(1, 1)
+        .header(1, false, WireType::UInt64)
+        .vint64(2)
+        .header(1, false, WireType::UInt64)
+        .vint64(3)
+        .string(2, "end");
+
+    let expected = (vec![1, 2, 3], "end".to_owned());
+    assert_eq!(decode_repeated(&packed).unwrap(), expected);
+    assert_eq!(decode_repeated(&unpacked).unwrap(), expected);
+}
+
+#[test]
+fn absent() {
+    let encoded = Literal::new().string(2, "end");
+    assert_eq!(
+        decode_repeated(&encoded).unwrap(),
+        (vec![], "end".to_owned())
+    );
+}
+
+#[test]
+fn unpacked_with_wrong_wire_type() {
+    let encoded = Literal::new()
+        .uint64(1, 1)
+        .header(1, false, WireType::SInt64)
+        .vint64(2)
+        .string(2, "end");
+
+    let err = decode_repeated(&encoded).unwrap_err();
+    assert_eq!(
+        err.kind(),
+        error::Kind::UnexpectedWireType {
+            actual: WireType::SInt64,
+            wanted: WireType::UInt64
+        }
+    );
+}
+
+#[test]
+fn repeated_tag_outside_repeated_field() {
+    // Tags may only repeat within a repeated field
+    let encoded = Literal::new()
+        .uint64(1, 1)
+        .header(1, false, WireType::UInt64)
+        .vint64(2);
+
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let first: u64 = decoder.decode(1, &mut input).unwrap();
+    assert_eq!(first, 1);
+
+    let err = Decode::<u64>::decode(&mut decoder, 1, &mut input).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 1 });
+}
+
+#[test]
+fn unpacked_dropped_early() {
+    let encoded = Literal::new()
+        .uint64(1, 1)
+        .header(1, false, WireType::UInt64)
+        .vint64(2)
+        .header(1, false, WireType::UInt64)
+        .vint64(3)
+        .string(2, "end");
+
+    // Digest of the message when every value is yielded
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let values = decoder
+        .decode_repeated_u64(1, &mut input)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(values, [1, 2, 3]);
+    let _: &str = decoder.decode_ref(2, &mut input).unwrap();
+
+    let mut expected = [0u8; 32];
+    decoder.fill_digest(&mut expected).unwrap();
+
+    // The values which weren't yielded are still decoded and hashed when
+    // the iterator is dropped
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+    let first = decoder.decode_repeated_u64(1, &mut input).unwrap().next();
+    assert_eq!(first.unwrap().unwrap(), 1);
+
+    let s: &str = decoder.decode_ref(2, &mut input).unwrap();
+    assert_eq!(s, "end");
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    assert_eq!(digest, expected);
+}
+
+#[test]
+fn after_unknown_field() {
+    let body = Literal::new().vint64(5).vint64(6);
+    let packed = Literal::new()
+        .uint64(0, 99)
+        .sequence(1, WireType::UInt64, body.as_bytes());
+
+    let unpacked = Literal::new()
+        .uint64(0, 99)
+        .uint64(1, 5)
+        .header(1, false, WireType::UInt64)
+        .vint64(6);
+
+    for encoded in &[packed, unpacked] {
+        // Under `UnknownFieldPolicy::Skip`, the unknown field is skipped
+        // rather than masking the repeated field
+        let mut decoder: Decoder = Decoder::new();
+        decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+        let mut input = encoded.as_bytes();
+
+        let values = decoder
+            .decode_repeated_u64(1, &mut input)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(values, [5, 6]);
+        assert!(decoder.at_end(input));
+
+        // Under the default policy, the unknown field is an error
+        let mut decoder: Decoder = Decoder::new();
+        let mut input = encoded.as_bytes();
+        let err = decoder.decode_repeated_u64(1, &mut input).err().unwrap();
+        assert_eq!(err.kind(), error::Kind::UnknownField { tag: 0 });
+    }
+}
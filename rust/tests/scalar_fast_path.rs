@@ -0,0 +1,134 @@
+//! Integration tests for the fast path for decoding scalar fields, which
+//! must give identical results to the general path
+
+use veriform::{
+    consts::Literal,
+    decoder::{Decode, DecodeOptions, DecodeRef, UnknownFieldPolicy},
+    error,
+    field::{Header, Tag, WireType},
+    Decoder, Error,
+};
+
+/// Number of fields in the all-`uint64` message
+const NUM_FIELDS: Tag = 100;
+
+/// Options for decoding using the general path only
+const SLOW_PATH: DecodeOptions = DecodeOptions::new().scalar_fast_path(false);
+
+/// Encode a message consisting of [`NUM_FIELDS`] `uint64` fields
+fn uint64_message() -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    for tag in 0..NUM_FIELDS {
+        let header = Header::new(tag, tag % 3 == 0, WireType::UInt64).encode();
+        encoded.extend_from_slice(header.as_ref());
+        encoded.extend_from_slice(vint64::encode(tag << (tag % 57)).as_ref());
+    }
+
+    encoded
+}
+
+/// Decode the all-`uint64` message, returning its fields and digest
+fn decode_uint64_message(mut decoder: Decoder, input: &[u8]) -> (Vec<u64>, [u8; 32]) {
+    let mut input = input;
+    let fields = (0..NUM_FIELDS)
+        .map(|tag| decoder.decode(tag, &mut input).unwrap())
+        .collect();
+    assert!(input.is_empty());
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest).unwrap();
+    (fields, digest)
+}
+
+#[test]
+fn uint64_message_matches_slow_path() {
+    let encoded = uint64_message();
+    let fast = decode_uint64_message(Decoder::new(), &encoded);
+    let slow = decode_uint64_message(Decoder::with_options(SLOW_PATH), &encoded);
+    assert_eq!(fast, slow);
+
+    let expected: Vec<u64> = (0..NUM_FIELDS).map(|tag| tag << (tag % 57)).collect();
+    assert_eq!(fast.0, expected);
+}
+
+/// Decoded fields of [`MIXED`]
+type Mixed = (u64, i64, Vec<u8>, i64, [u8; 32]);
+
+/// Message mixing scalar fields with others, including an unknown field
+const MIXED: Literal = Literal::new()
+    .uint64(0, 42)
+    .sint64(1, -42)
+    .bytes(2, b"bytes")
+    .message(3, &Literal::new().uint64(0, 1))
+    .sint64(4, i64::MIN);
+
+/// Decode [`MIXED`], skipping the unknown field with tag 3
+fn decode_mixed(options: DecodeOptions) -> Result<Mixed, Error> {
+    let options = options.unknown_field_policy(UnknownFieldPolicy::Skip);
+    let mut decoder: Decoder = Decoder::with_options(options);
+    let mut input = MIXED.as_bytes();
+
+    let a = decoder.decode(0, &mut input)?;
+    let b = decoder.decode(1, &mut input)?;
+    let c: &[u8] = decoder.decode_ref(2, &mut input)?;
+    let d = decoder.decode(4, &mut input)?;
+
+    let mut digest = [0u8; 32];
+    decoder.fill_digest(&mut digest)?;
+    Ok((a, b, c.to_vec(), d, digest))
+}
+
+#[test]
+fn mixed_message_matches_slow_path() {
+    let fast = decode_mixed(DecodeOptions::new()).unwrap();
+    assert_eq!(fast, decode_mixed(SLOW_PATH).unwrap());
+    assert_eq!((fast.0, fast.1, fast.3), (42, -42, i64::MIN));
+}
+
+/// Decode a `uint64` field with tag 1 followed by one with tag `tag`
+fn decode_two_fields(options: DecodeOptions, input: &[u8], tag: Tag) -> Result<u64, Error> {
+    let mut decoder: Decoder = Decoder::with_options(options);
+    let mut input = input;
+    let a: u64 = decoder.decode(1, &mut input)?;
+    let b: u64 = decoder.decode(tag, &mut input)?;
+    Ok(a + b)
+}
+
+#[test]
+fn errors_match_slow_path() {
+    let out_of_order = Literal::new()
+        .uint64(1, 1)
+        .raw(Literal::new().uint64(0, 2).as_bytes());
+    let wrong_type = Literal::new().uint64(1, 1).sint64(2, 2);
+    let non_canonical = Literal::new()
+        .uint64(1, 1)
+        .header(2, false, WireType::UInt64)
+        .raw(&[0x02, 0x00]);
+    let truncated = Literal::new()
+        .uint64(1, 1)
+        .header(2, false, WireType::UInt64);
+
+    for (input, tag) in &[
+        (out_of_order, 0),
+        (wrong_type, 2),
+        (non_canonical, 2),
+        (truncated, 2),
+    ] {
+        let fast = decode_two_fields(DecodeOptions::new(), input.as_bytes(), *tag).unwrap_err();
+        let slow = decode_two_fields(SLOW_PATH, input.as_bytes(), *tag).unwrap_err();
+        assert_eq!(fast.kind(), slow.kind());
+        assert_eq!(fast.position(), slow.position());
+    }
+}
+
+#[test]
+fn field_budget_matches_slow_path() {
+    let encoded = Literal::new().uint64(1, 1).uint64(2, 2);
+    let options = DecodeOptions::new().max_total_fields(Some(1));
+
+    for options in &[options, options.scalar_fast_path(false)] {
+        let err = decode_two_fields(*options, encoded.as_bytes(), 2).unwrap_err();
+        assert_eq!(err.kind(), error::Kind::LengthLimit);
+    }
+}
@@ -0,0 +1,60 @@
+//! Integration tests for `veriform::Decoder::decode_secret_bytes`
+
+use veriform::{consts::Literal, error, Decoder};
+
+/// Maximum length of the secrets in these tests
+const MAX_LEN: usize = 16;
+
+/// Decode a secret, returning the output buffer and the length of the
+/// secret
+fn decode_secret(secret: &[u8]) -> ([u8; MAX_LEN], usize) {
+    let encoded = Literal::new().bytes(1, secret);
+    let mut decoder: Decoder = Decoder::new();
+    let mut out = [0xAA; MAX_LEN];
+
+    let len = decoder
+        .decode_secret_bytes(1, &mut encoded.as_bytes(), &mut out)
+        .unwrap();
+
+    (out, len)
+}
+
+#[test]
+fn secrets_of_equal_length() {
+    let (a, a_len) = decode_secret(b"hunter2");
+    let (b, b_len) = decode_secret(b"letmein");
+
+    assert_eq!((a_len, b_len), (7, 7));
+    assert_eq!(&a[..7], b"hunter2");
+    assert_eq!(&b[..7], b"letmein");
+
+    // The rest of the buffer is zeroed in both cases
+    assert_eq!(&a[7..], &[0; MAX_LEN - 7]);
+    assert_eq!(&b[7..], &[0; MAX_LEN - 7]);
+}
+
+#[test]
+fn secrets_of_different_lengths() {
+    let (long, long_len) = decode_secret(b"correct horse");
+    assert_eq!(long_len, 13);
+    assert_eq!(&long[..13], b"correct horse");
+    assert_eq!(&long[13..], &[0; MAX_LEN - 13]);
+}
+
+#[test]
+fn empty_secret() {
+    assert_eq!(decode_secret(b""), ([0; MAX_LEN], 0));
+}
+
+#[test]
+fn secret_longer_than_buffer() {
+    let encoded = Literal::new().bytes(1, &[1; MAX_LEN + 1]);
+    let mut decoder: Decoder = Decoder::new();
+    let mut out = [0; MAX_LEN];
+
+    let err = decoder
+        .decode_secret_bytes(1, &mut encoded.as_bytes(), &mut out)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::Capacity);
+}
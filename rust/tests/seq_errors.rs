@@ -0,0 +1,181 @@
+//! Integration tests for errors decoding the elements of sequences
+
+use veriform::{
+    consts::Literal,
+    decoder::{DecodeOptions, DecodeSeq},
+    error::{self, ElementContext, ElementFailure},
+    field::WireType,
+    Decoder, Message,
+};
+
+#[cfg(feature = "alloc")]
+use veriform::decoder::DecodeSeqOwned;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Middle {
+    #[field(tag = 1, wire_type = "message")]
+    pub inner: Inner,
+}
+
+#[test]
+fn element_length_overruns_body() {
+    // The second element claims to be 10 bytes long, but only 3 remain
+    let element = Literal::new().uint64(0, 1);
+    let body = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes())
+        .vint64(10)
+        .raw(element.as_bytes());
+
+    let encoded = Literal::new().sequence(1, WireType::Message, body.as_bytes());
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let mut iter = DecodeSeq::<Inner, _>::decode_seq(&mut decoder, 1, &mut input).unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), Inner { uint64_field: 1 });
+
+    let err = iter.next().unwrap().unwrap_err();
+    assert_eq!(
+        err.sequence_element(),
+        Some(ElementContext {
+            index: 1,
+            offset: 1 + element.len(),
+            failure: ElementFailure::Structural,
+        })
+    );
+}
+
+#[test]
+fn element_exceeds_nesting_depth() {
+    // Each `Middle` element contains an `Inner` message, which is nested 3
+    // deep counting the outermost message
+    let element = Literal::new().message(1, &Literal::new().uint64(0, 1));
+    let body = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes());
+
+    let encoded = Literal::new().sequence(1, WireType::Message, body.as_bytes());
+    let mut decoder = Decoder::with_options(DecodeOptions::new().max_nesting(Some(2)));
+    let mut input = encoded.as_bytes();
+
+    let mut iter = DecodeSeq::<Middle, _>::decode_seq(&mut decoder, 1, &mut input).unwrap();
+    let err = iter.next().unwrap().unwrap_err();
+
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+    assert_eq!(
+        err.sequence_element(),
+        Some(ElementContext {
+            index: 0,
+            offset: 0,
+            failure: ElementFailure::Semantic,
+        })
+    );
+}
+
+#[test]
+fn no_element_outside_sequences() {
+    let encoded = Literal::new().uint64(1, 1);
+    let mut decoder: Decoder = Decoder::new();
+    let err = Middle::decode(&mut decoder, encoded.as_bytes()).unwrap_err();
+    assert_eq!(err.sequence_element(), None);
+}
+
+#[test]
+fn decode_seq_on_scalar_field() {
+    let encoded = Literal::new().uint64(1, 42);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let err = DecodeSeq::<u64, _>::decode_seq(&mut decoder, 1, &mut input)
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::ExpectedSequence {
+            tag: 1,
+            actual: WireType::UInt64
+        }
+    );
+    assert_eq!(err.to_string(), "field 1 is UInt64, not a sequence");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn decode_seq_owned_on_scalar_field() {
+    let encoded = Literal::new().sint64(1, -42);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let err = DecodeSeqOwned::<i64, _>::decode_seq_owned(&mut decoder, 1, &mut input)
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::ExpectedSequence {
+            tag: 1,
+            actual: WireType::SInt64
+        }
+    );
+}
+
+#[test]
+fn field_sequence_on_scalar_field() {
+    let encoded = Literal::new().bool(1, true);
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let field = decoder.decode_field(&mut input).unwrap().unwrap();
+    let err = field.sequence::<bool>(&mut input).err().unwrap();
+
+    assert_eq!(
+        err.kind(),
+        error::Kind::ExpectedSequence {
+            tag: 1,
+            actual: WireType::True
+        }
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn bytes_element_length_overruns_body() {
+    // The second element claims to be 2 bytes long, but only 1 byte of the
+    // sequence body remains. It must not extend into the following field.
+    let body = Literal::new().vint64(1).raw(b"a").vint64(2).raw(b"b");
+
+    let encoded = Literal::new()
+        .sequence(1, WireType::Bytes, body.as_bytes())
+        .uint64(2, 42);
+
+    let mut decoder: Decoder = Decoder::new();
+    let mut input = encoded.as_bytes();
+
+    let mut iter =
+        DecodeSeqOwned::<Vec<u8>, _>::decode_seq_owned(&mut decoder, 1, &mut input).unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), b"a");
+
+    let err = iter.next().unwrap().unwrap_err();
+    assert_eq!(
+        err.kind(),
+        error::Kind::Truncated {
+            remaining: 1,
+            wire_type: WireType::Bytes
+        }
+    );
+    assert_eq!(
+        err.sequence_element(),
+        Some(ElementContext {
+            index: 1,
+            offset: 2,
+            failure: ElementFailure::Structural,
+        })
+    );
+}
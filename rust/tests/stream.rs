@@ -0,0 +1,189 @@
+//! Integration tests for `veriform::decoder::Stream`
+
+use heapless::{consts::U64, Vec};
+use veriform::{
+    decoder::{Event, Stream},
+    field::{Header, WireType},
+    Decoder, Message, Sha256Digest,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: Vec<Inner, heapless::consts::U4>,
+
+    #[field(tag = 3, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[digest(alg = "sha256")]
+    pub digest: Option<Sha256Digest>,
+}
+
+impl Default for Outer {
+    fn default() -> Self {
+        let mut msg_sequence_field = Vec::new();
+
+        for n in 0..3 {
+            let inner = Inner {
+                uint64_field: n * 1000,
+                sint64_field: -(n as i64),
+            };
+
+            msg_sequence_field.push(inner).unwrap();
+        }
+
+        Self {
+            uint64_field: 0x0f0f_f0f0,
+            message_field: Inner {
+                uint64_field: 42,
+                sint64_field: -42,
+            },
+            msg_sequence_field,
+            sint64_field: -0x0f0f_f0f0,
+            digest: None,
+        }
+    }
+}
+
+/// Non-chunk events decoded from a stream along with their depth
+type Events<'a> = Vec<(usize, Event<'a>), U64>;
+
+/// Decode the given chunks of input using a [`Stream`], returning the
+/// decoded events and message digest
+fn decode_chunks<'a>(chunks: &[&'a [u8]]) -> (Events<'a>, Sha256Digest) {
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+    let mut events = Vec::new();
+
+    for chunk in chunks {
+        let mut input = *chunk;
+
+        while let Some(event) = stream.decode(&mut input).unwrap() {
+            match event {
+                Event::ValueChunk { .. } => (),
+                event => events.push((stream.depth(), event)).unwrap(),
+            }
+        }
+
+        assert!(input.is_empty());
+    }
+
+    let mut digest = Sha256Digest::default();
+    stream.fill_digest(&mut digest).unwrap();
+    (events, digest)
+}
+
+/// Decode all events (including value chunks) from the given input
+fn decode_events(mut input: &[u8]) -> Vec<Event<'_>, U64> {
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+    let mut events = Vec::new();
+
+    while let Some(event) = stream.decode(&mut input).unwrap() {
+        events.push(event).unwrap();
+    }
+
+    events
+}
+
+#[test]
+fn split_at_every_byte_boundary() {
+    let mut buffer = [0u8; 256];
+    let encoded = Outer::default().encode(&mut buffer).unwrap();
+
+    let decoded = Outer::decode(&mut Decoder::new(), encoded).unwrap();
+    let (expected_events, digest) = decode_chunks(&[encoded]);
+    assert_eq!(Some(digest), decoded.digest);
+
+    for i in 0..=encoded.len() {
+        let (events, digest) = decode_chunks(&[&encoded[..i], &encoded[i..]]);
+        assert_eq!(events, expected_events, "split at {}", i);
+        assert_eq!(Some(digest), decoded.digest, "split at {}", i);
+    }
+}
+
+#[test]
+fn decode_one_byte_at_a_time() {
+    let mut buffer = [0u8; 256];
+    let encoded = Outer::default().encode(&mut buffer).unwrap();
+    let decoded = Outer::decode(&mut Decoder::new(), encoded).unwrap();
+
+    let mut chunks: Vec<&[u8], heapless::consts::U256> = Vec::new();
+    for i in 0..encoded.len() {
+        chunks.push(&encoded[i..=i]).unwrap();
+    }
+
+    let (events, digest) = decode_chunks(&chunks);
+    assert_eq!(events, decode_chunks(&[encoded]).0);
+    assert_eq!(Some(digest), decoded.digest);
+}
+
+#[test]
+fn event_streams_compare_chunk_contents() {
+    // 1: uint64 = 42, 2: bytes = "hi"
+    let encoded = [0x25, 0x55, 0x49, 0x05, b'h', b'i'];
+    let copy = encoded;
+
+    let events = decode_events(&encoded);
+    assert_eq!(events, decode_events(&copy));
+    assert_eq!(
+        events,
+        [
+            Event::FieldHeader(Header::new(1, false, WireType::UInt64)),
+            Event::UInt64(42),
+            Event::FieldHeader(Header::new(2, false, WireType::Bytes)),
+            Event::LengthDelimiter {
+                wire_type: WireType::Bytes,
+                length: 2
+            },
+            Event::ValueChunk {
+                wire_type: WireType::Bytes,
+                bytes: b"hi",
+                remaining: 0
+            },
+        ]
+    );
+}
+
+#[test]
+fn incomplete_message_has_no_digest() {
+    let mut buffer = [0u8; 256];
+    let encoded = Outer::default().encode(&mut buffer).unwrap();
+
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+    let mut input = &encoded[..encoded.len() / 2];
+    while stream.decode(&mut input).unwrap().is_some() {}
+
+    let mut digest = Sha256Digest::default();
+    assert!(stream.fill_digest(&mut digest).is_err());
+}
+
+#[test]
+fn partial_field_header_has_no_digest() {
+    let mut buffer = [0u8; 256];
+    let len = Outer::default().encode(&mut buffer).unwrap().len();
+
+    // First byte of a two byte field header
+    buffer[len] = 0x02;
+
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+    let mut input = &buffer[..=len];
+    while stream.decode(&mut input).unwrap().is_some() {}
+    assert!(input.is_empty());
+
+    let mut digest = Sha256Digest::default();
+    assert!(stream.fill_digest(&mut digest).is_err());
+}
@@ -0,0 +1,87 @@
+//! Integration tests for `veriform::encoder::StreamEncoder`
+
+#![cfg(feature = "std")]
+
+use veriform::{
+    consts::Literal, decoder::DecodeRef, encoder::StreamEncoder, error, Decoder, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 1, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 2, wire_type = "message")]
+    pub inner: Inner,
+}
+
+#[test]
+fn round_trip() {
+    let mut encoder = StreamEncoder::new(Vec::new());
+    encoder.write_uint64(1, false, 42).unwrap();
+    encoder
+        .write_message(2, false, |inner| {
+            inner.write_uint64(0, false, 7)?;
+            inner.write_sint64(1, false, -7)
+        })
+        .unwrap();
+    let encoded = encoder.finish().unwrap();
+
+    let expected = Literal::new()
+        .uint64(1, 42)
+        .message(2, &Literal::new().uint64(0, 7).sint64(1, -7));
+    assert_eq!(encoded, expected.as_bytes());
+
+    let mut decoder = Decoder::new();
+    assert_eq!(
+        Outer::decode(&mut decoder, &encoded).unwrap(),
+        Outer {
+            uint64_field: 42,
+            inner: Inner {
+                uint64_field: 7,
+                sint64_field: -7,
+            },
+        }
+    );
+}
+
+#[test]
+fn dynamically_sized_values() {
+    let mut encoder = StreamEncoder::new(Vec::new());
+    encoder.write_bytes(1, false, b"bytes").unwrap();
+    encoder.write_string(2, false, "string").unwrap();
+    let encoded = encoder.finish().unwrap();
+
+    let mut decoder = Decoder::new();
+    let mut input = encoded.as_slice();
+    let bytes: &[u8] = decoder.decode_ref(1, &mut input).unwrap();
+    let string: &str = decoder.decode_ref(2, &mut input).unwrap();
+    assert_eq!((bytes, string), (&b"bytes"[..], "string"));
+}
+
+#[test]
+fn out_of_order_fields() {
+    let mut encoder = StreamEncoder::new(Vec::new());
+    encoder.write_uint64(2, false, 1).unwrap();
+
+    let err = encoder.write_uint64(1, false, 1).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 1 });
+
+    // Tags are ordered independently within nested messages
+    let err = encoder
+        .write_message(3, false, |inner| {
+            inner.write_uint64(1, false, 1)?;
+            inner.write_uint64(0, false, 1)
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), error::Kind::Order { tag: 0 });
+}
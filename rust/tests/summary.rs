@@ -0,0 +1,132 @@
+//! Integration tests for summarizing the fields consumed while decoding
+
+use heapless::consts::U4;
+use veriform::{
+    consts::Literal,
+    decoder::{Decode, DecodeOptions, FieldSummary, PathElement, UnknownFieldPolicy},
+    field::WireType,
+    Decoder, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Outer {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 2, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: heapless::Vec<Inner, U4>,
+}
+
+/// Example message, whose fields are 2, 6, and 14 bytes long
+fn example_message() -> Literal {
+    const INNER: Literal = Literal::new().uint64(0, 1).sint64(1, -1);
+    const FIRST: Literal = Literal::new().uint64(0, 0).sint64(1, 0);
+    const SECOND: Literal = Literal::new().uint64(0, 1000).sint64(1, -1);
+
+    let elements = Literal::new()
+        .vint64(FIRST.len() as u64)
+        .raw(FIRST.as_bytes())
+        .vint64(SECOND.len() as u64)
+        .raw(SECOND.as_bytes());
+
+    Literal::new().uint64(0, 42).message(1, &INNER).sequence(
+        2,
+        WireType::Message,
+        elements.as_bytes(),
+    )
+}
+
+/// Decode the given message, returning the summary of its fields
+fn summarize(decoder: &mut Decoder, encoded: &Literal, summary: FieldSummary) -> FieldSummary {
+    decoder.summarize_fields(summary);
+    Outer::decode(decoder, encoded.as_bytes()).unwrap();
+    decoder.take_field_summary().unwrap()
+}
+
+#[test]
+fn top_level_fields() {
+    let encoded = example_message();
+    let summary = summarize(&mut Decoder::new(), &encoded, FieldSummary::new());
+
+    let fields: Vec<_> = summary
+        .iter()
+        .map(|record| (record.tag, record.wire_type, record.encoded_len))
+        .collect();
+
+    assert_eq!(
+        fields,
+        [
+            (0, WireType::UInt64, 2),
+            (1, WireType::Message, 6),
+            (2, WireType::Sequence, 14),
+        ]
+    );
+
+    assert!(summary.iter().all(|record| record.path.is_empty()));
+    assert_eq!(fields.iter().map(|f| f.2).sum::<usize>(), encoded.len());
+    assert_eq!(
+        summary.to_string(),
+        "0: UInt64 (2 bytes)\n1: Message (6 bytes)\n2: Sequence (14 bytes)\n"
+    );
+}
+
+#[test]
+fn nested_fields() {
+    let encoded = example_message();
+    let summary = summarize(
+        &mut Decoder::new(),
+        &encoded,
+        FieldSummary::from(heapless::Vec::new()).max_depth(1),
+    );
+
+    assert_eq!(
+        summary.to_string(),
+        "0: UInt64 (2 bytes)\n\
+         1: Message (6 bytes)\n\
+         1.0: UInt64 (2 bytes)\n\
+         1.1: SInt64 (2 bytes)\n\
+         2: Sequence (14 bytes)\n\
+         2[0].0: UInt64 (2 bytes)\n\
+         2[0].1: SInt64 (2 bytes)\n\
+         2[1].0: UInt64 (3 bytes)\n\
+         2[1].1: SInt64 (2 bytes)\n"
+    );
+
+    assert_eq!(
+        &summary.as_slice()[7].path[..],
+        &[PathElement::seq_element(2, 1)]
+    );
+}
+
+#[test]
+fn skipped_unknown_fields() {
+    let encoded = Literal::new()
+        .uint64(0, 42)
+        .bytes(1, b"unknown")
+        .message(2, &Literal::new().uint64(0, 1).sint64(1, -1));
+
+    let mut decoder =
+        Decoder::with_options(DecodeOptions::new().unknown_field_policy(UnknownFieldPolicy::Skip));
+    decoder.summarize_fields(FieldSummary::new());
+
+    let mut input = encoded.as_bytes();
+    let _: u64 = decoder.decode(0, &mut input).unwrap();
+    let _: Inner = decoder.decode(2, &mut input).unwrap();
+
+    assert_eq!(
+        decoder.take_field_summary().unwrap().to_string(),
+        "0: UInt64 (2 bytes)\n1: Bytes (9 bytes)\n2: Message (6 bytes)\n"
+    );
+}
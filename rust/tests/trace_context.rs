@@ -0,0 +1,65 @@
+//! Integration tests for the `trace-context` feature
+
+#![cfg(feature = "trace-context")]
+
+use veriform::{
+    decoder::{Decode, DecodeRef, Decoder},
+    error,
+    field::WireType,
+    verihash::VerihashDigest,
+    Error, Message,
+};
+
+/// Message containing a string field
+#[derive(Debug)]
+struct Inner;
+
+impl Message for Inner {
+    fn decode<D>(decoder: &mut Decoder<D>, mut input: &[u8]) -> Result<Self, Error>
+    where
+        D: VerihashDigest,
+    {
+        let _: u64 = decoder.decode(2, &mut input)?;
+        let _: &str = decoder.decode_ref(3, &mut input)?;
+        Ok(Inner)
+    }
+
+    fn encode<'a>(&self, _buffer: &'a mut [u8]) -> Result<&'a [u8], Error> {
+        unimplemented!();
+    }
+
+    fn encoded_len(&self) -> usize {
+        unimplemented!();
+    }
+}
+
+#[test]
+fn nested_string_error_context() {
+    // Field 1 is a message containing a `uint64` (field 2) and a string
+    // (field 3) which contains invalid UTF-8
+    let input = [45, 11, 69, 85, 107, 3, 0xff];
+    let mut input_ref = &input[..];
+    let mut decoder = Decoder::<sha2::Sha256>::new();
+
+    let err = Decode::<Inner>::decode(&mut decoder, 1, &mut input_ref)
+        .err()
+        .unwrap();
+
+    assert!(matches!(err.kind(), error::Kind::Utf8 { .. }));
+    assert_eq!(
+        err.context(),
+        &[(1, WireType::Message), (3, WireType::String)]
+    );
+}
+
+#[test]
+fn no_context_outside_fields() {
+    let err = Error::from(error::Kind::Failed);
+    assert!(err.context().is_empty());
+}
+
+#[test]
+fn error_is_copy() {
+    fn assert_copy<T: Copy>() {}
+    assert_copy::<Error>();
+}
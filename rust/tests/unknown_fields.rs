@@ -0,0 +1,189 @@
+//! Integration tests for handling unknown fields
+
+use heapless::{consts::U4, Vec};
+use veriform::{
+    consts::Literal,
+    decoder::{hash_message, UnknownFieldPolicy},
+    error, Decoder, Error, Message,
+};
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct Inner {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+}
+
+/// Newer version of a message with additional fields
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct MessageV2 {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "message")]
+    pub message_field: Inner,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[field(tag = 3, wire_type = "sequence", max = 4)]
+    pub msg_sequence_field: Vec<Inner, U4>,
+}
+
+/// Older version of a message which only knows about some of the fields
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct MessageV1 {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+
+    #[digest(alg = "sha256")]
+    pub digest: Option<veriform::Sha256Digest>,
+}
+
+/// Newer version of a message with an additional critical field
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct CriticalV2 {
+    #[field(tag = 0, wire_type = "uint64")]
+    pub uint64_field: u64,
+
+    #[field(tag = 1, wire_type = "uint64", critical = true)]
+    pub critical_field: u64,
+
+    #[field(tag = 2, wire_type = "sint64")]
+    pub sint64_field: i64,
+}
+
+/// Encode an example message with fields unknown to [`MessageV1`]
+fn encode_v2(buffer: &mut [u8]) -> &[u8] {
+    let mut msg_sequence_field = Vec::new();
+    msg_sequence_field.push(Inner { uint64_field: 1 }).unwrap();
+    msg_sequence_field.push(Inner { uint64_field: 2 }).unwrap();
+
+    MessageV2 {
+        uint64_field: 42,
+        message_field: Inner { uint64_field: 23 },
+        sint64_field: -1,
+        msg_sequence_field,
+    }
+    .encode(buffer)
+    .unwrap()
+}
+
+/// Decode a message under [`UnknownFieldPolicy::Skip`], with a budget of
+/// `max_total_fields` fields
+fn decode_limited<M: Message>(encoded: &[u8], max_total_fields: usize) -> Result<M, Error> {
+    let mut decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+    decoder.set_max_total_fields(Some(max_total_fields));
+    M::decode(&mut decoder, encoded)
+}
+
+#[test]
+fn unknown_field_error() {
+    let mut buffer = [0u8; 64];
+    let encoded = encode_v2(&mut buffer);
+
+    let mut decoder = Decoder::new();
+    let err = MessageV1::decode(&mut decoder, encoded).err().unwrap();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 1 });
+}
+
+#[test]
+fn unknown_field_skip() {
+    let mut buffer = [0u8; 64];
+    let encoded = encode_v2(&mut buffer);
+
+    let mut decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+
+    let msg = MessageV1::decode(&mut decoder, encoded).unwrap();
+    assert_eq!(msg.uint64_field, 42);
+    assert_eq!(msg.sint64_field, -1);
+
+    // Skipped fields are still included in the digest
+    let expected_digest = hash_message::<sha2::Sha256>(encoded).unwrap();
+    assert_eq!(msg.digest.unwrap(), expected_digest.as_slice());
+}
+
+#[test]
+fn max_total_fields_counts_skipped_fields() {
+    // 4 fields, plus 1 in `message_field`, and 2 elements with 1 field each
+    // in `msg_sequence_field`
+    let mut buffer = [0u8; 64];
+    let encoded = encode_v2(&mut buffer);
+
+    // Skipping fields charges the same budget as decoding them
+    assert!(decode_limited::<MessageV2>(encoded, 9).is_ok());
+    assert!(decode_limited::<MessageV1>(encoded, 9).is_ok());
+
+    let err = decode_limited::<MessageV2>(encoded, 8).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+
+    let err = decode_limited::<MessageV1>(encoded, 8).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+}
+
+#[test]
+fn oversized_unknown_message_skip() {
+    let inner = (0..16).fold(Literal::new(), |literal, tag| literal.uint64(tag, tag));
+    let encoded = Literal::new()
+        .uint64(0, 42)
+        .message(1, &inner)
+        .sint64(2, -1);
+
+    assert!(decode_limited::<MessageV1>(encoded.as_bytes(), 19).is_ok());
+
+    let err = decode_limited::<MessageV1>(encoded.as_bytes(), 18).unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+}
+
+#[test]
+fn unknown_critical_field_skip() {
+    let mut buffer = [0u8; 64];
+    let encoded = CriticalV2 {
+        uint64_field: 42,
+        critical_field: 23,
+        sint64_field: -1,
+    }
+    .encode(&mut buffer)
+    .unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Skip);
+
+    let err = MessageV1::decode(&mut decoder, encoded).err().unwrap();
+    assert_eq!(err.kind(), error::Kind::UnknownField { tag: 1 });
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn unknown_field_collect() {
+    let mut buffer = [0u8; 64];
+    let encoded = encode_v2(&mut buffer);
+
+    let mut decoder = Decoder::new();
+    decoder.set_unknown_field_policy(UnknownFieldPolicy::Collect);
+
+    let msg = MessageV1::decode(&mut decoder, encoded).unwrap();
+    assert_eq!(msg.uint64_field, 42);
+    assert_eq!(msg.sint64_field, -1);
+
+    let unknown_fields = decoder.unknown_fields().collect::<std::vec::Vec<_>>();
+    assert_eq!(unknown_fields.len(), 2);
+
+    // Collected fields contain the raw encoded field, including its header
+    let mut inner_buffer = [0u8; 16];
+    let inner = Inner { uint64_field: 23 }
+        .encode(&mut inner_buffer)
+        .unwrap();
+    let (tag, raw_field) = unknown_fields[0];
+    assert_eq!(tag, 1);
+    assert_eq!(raw_field[..2], [45, 5]);
+    assert_eq!(&raw_field[2..], inner);
+
+    let (tag, raw_field) = unknown_fields[1];
+    assert_eq!(tag, 3);
+    assert!(encoded.ends_with(raw_field));
+}
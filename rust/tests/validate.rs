@@ -0,0 +1,233 @@
+//! Integration tests for `veriform::validate`
+
+use veriform::{
+    consts::Literal,
+    decoder::{DecodeOptions, FieldSchema, Schema},
+    error::{self, Kind},
+    field::WireType,
+    validate, validate_against,
+};
+
+/// Message exercising every wire type, including nested messages and a
+/// sequence of messages
+fn valid_message() -> Literal {
+    const INNER: Literal = Literal::new().uint64(1, 42).string(2, "inner");
+    const ELEMENT: Literal = Literal::new().sint64(1, -1);
+
+    let mut elements = Literal::new();
+    for _ in 0..2 {
+        elements = elements
+            .vint64(ELEMENT.len() as u64)
+            .raw(ELEMENT.as_bytes());
+    }
+
+    Literal::new()
+        .bool(1, true)
+        .uint64(2, 2)
+        .sint64(3, -3)
+        .bytes(4, b"four")
+        .string(5, "five")
+        .message(6, &Literal::new().message(1, &INNER))
+        .sequence(7, WireType::Message, elements.as_bytes())
+        .sequence(8, WireType::UInt64, &[0x03, 0x05])
+}
+
+fn validate_err(input: &Literal) -> Kind {
+    validate(input.as_bytes(), DecodeOptions::new())
+        .unwrap_err()
+        .kind()
+}
+
+#[test]
+fn accepts_valid_message() {
+    validate(valid_message().as_bytes(), DecodeOptions::new()).unwrap();
+    validate(&[], DecodeOptions::new()).unwrap();
+}
+
+#[test]
+fn rejects_truncated() {
+    let encoded = valid_message();
+    let truncated = &encoded.as_bytes()[..encoded.len() - 1];
+    let err = validate(truncated, DecodeOptions::new()).unwrap_err();
+    assert!(matches!(err.kind(), Kind::Truncated { .. }));
+
+    let truncated_nested = Literal::new()
+        .header(1, false, WireType::Message)
+        .vint64(3)
+        .raw(&[0x45, 0x55]);
+    assert!(matches!(
+        validate_err(&truncated_nested),
+        Kind::Truncated { .. }
+    ));
+}
+
+#[test]
+fn rejects_out_of_order_fields() {
+    let encoded = Literal::new().uint64(2, 2).uint64(1, 1);
+    assert_eq!(validate_err(&encoded), Kind::Order { tag: 1 });
+
+    let duplicate = Literal::new().uint64(1, 1).uint64(1, 1);
+    assert_eq!(validate_err(&duplicate), Kind::Order { tag: 1 });
+}
+
+#[test]
+fn rejects_malformed_nested_message() {
+    let inner = Literal::new().uint64(2, 2).uint64(1, 1);
+    let encoded = Literal::new().message(1, &Literal::new().message(1, &inner));
+    assert_eq!(validate_err(&encoded), Kind::Order { tag: 1 });
+}
+
+#[test]
+fn rejects_malformed_sequence_element() {
+    let element = Literal::new().uint64(2, 2).uint64(1, 1);
+    let elements = Literal::new()
+        .vint64(element.len() as u64)
+        .raw(element.as_bytes());
+    let encoded = Literal::new().sequence(1, WireType::Message, elements.as_bytes());
+    assert_eq!(validate_err(&encoded), Kind::Order { tag: 1 });
+
+    let nested_sequence = Literal::new().sequence(1, WireType::Sequence, &[]);
+    assert_eq!(validate_err(&nested_sequence), Kind::NestedSequence);
+}
+
+#[test]
+fn rejects_non_canonical_encoding() {
+    // `uint64` value 1 encoded as a two-byte `vint64`
+    let non_minimal = Literal::new()
+        .header(1, false, WireType::UInt64)
+        .raw(&[0x06, 0x00]);
+    assert_eq!(validate_err(&non_minimal), Kind::NonCanonicalVarint);
+
+    let invalid_utf8 = Literal::new()
+        .header(1, false, WireType::String)
+        .vint64(1)
+        .raw(&[0xff]);
+    assert!(matches!(validate_err(&invalid_utf8), Kind::Utf8 { .. }));
+
+    // "e" followed by a combining acute accent, which isn't NFC
+    let unnormalized = Literal::new().string(1, "e\u{301}");
+    assert_eq!(validate_err(&unnormalized), Kind::UnicodeNormalization);
+}
+
+#[test]
+fn enforces_limits() {
+    let encoded = valid_message();
+
+    let err = validate(
+        encoded.as_bytes(),
+        DecodeOptions::new().max_nesting(Some(2)),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), error::Kind::NestingDepth);
+
+    let err = validate(
+        encoded.as_bytes(),
+        DecodeOptions::new().max_total_fields(Some(4)),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), error::Kind::LengthLimit);
+}
+
+/// Schema of the inner message within field 6 of [`valid_message`]
+const INNER_SCHEMA: Schema<'static> = Schema::new(&[
+    FieldSchema::required(1, WireType::UInt64),
+    FieldSchema::required(2, WireType::String),
+    FieldSchema::optional(3, WireType::Bytes),
+]);
+
+/// Schema of a message whose field 1 contains an inner message
+const OUTER_SCHEMA: Schema<'static> =
+    Schema::new(&[FieldSchema::required(1, WireType::Message).with_schema(INNER_SCHEMA)]);
+
+/// Schema of [`valid_message`]
+const SCHEMA: Schema<'static> = Schema::new(&[
+    FieldSchema::required(1, WireType::False),
+    FieldSchema::required(2, WireType::UInt64),
+    FieldSchema::optional(3, WireType::SInt64),
+    FieldSchema::required(4, WireType::Bytes),
+    FieldSchema::optional(5, WireType::String),
+    FieldSchema::required(6, WireType::Message).with_schema(OUTER_SCHEMA),
+    FieldSchema::required(7, WireType::Sequence)
+        .with_schema(Schema::new(&[FieldSchema::required(1, WireType::SInt64)])),
+    FieldSchema::optional(8, WireType::Sequence),
+    FieldSchema::optional(9, WireType::UInt64),
+]);
+
+fn validate_against_err(input: &Literal, schema: &Schema<'_>) -> Kind {
+    validate_against(input.as_bytes(), schema)
+        .unwrap_err()
+        .kind()
+}
+
+#[test]
+fn accepts_conforming_message() {
+    validate_against(valid_message().as_bytes(), &SCHEMA).unwrap();
+
+    const OPTIONAL: Schema<'static> = Schema::new(&[FieldSchema::optional(1, WireType::UInt64)]);
+    validate_against(&[], &OPTIONAL).unwrap();
+}
+
+#[test]
+fn rejects_wrong_wire_type() {
+    let encoded = Literal::new().bool(1, false).sint64(2, 2);
+    assert_eq!(
+        validate_against_err(&encoded, &SCHEMA),
+        Kind::FieldWireType {
+            tag: 2,
+            actual: WireType::SInt64,
+            wanted: WireType::UInt64
+        }
+    );
+
+    let err = validate_against(encoded.as_bytes(), &SCHEMA).unwrap_err();
+    assert_eq!(err.to_string(), "field 2 is SInt64, expected UInt64");
+
+    // The inner message's field 1 must be a `uint64`
+    let nested = Literal::new().message(1, &Literal::new().string(1, "one"));
+    assert_eq!(
+        validate_against_err(&nested, &OUTER_SCHEMA),
+        Kind::FieldWireType {
+            tag: 1,
+            actual: WireType::String,
+            wanted: WireType::UInt64
+        }
+    );
+}
+
+#[test]
+fn rejects_missing_required_field() {
+    // Field 4 is missing between fields 3 and 5
+    let encoded = Literal::new()
+        .bool(1, true)
+        .uint64(2, 2)
+        .sint64(3, -3)
+        .string(5, "five");
+    assert_eq!(
+        validate_against_err(&encoded, &SCHEMA),
+        Kind::MissingField { tag: 4 }
+    );
+
+    // Fields at the end of the message are missing
+    let truncated = Literal::new().bool(1, true).uint64(2, 2);
+    assert_eq!(
+        validate_against_err(&truncated, &SCHEMA),
+        Kind::MissingField { tag: 4 }
+    );
+
+    // The inner message is missing its field 2
+    let nested = Literal::new().message(1, &Literal::new().uint64(1, 1));
+    assert_eq!(
+        validate_against_err(&nested, &OUTER_SCHEMA),
+        Kind::MissingField { tag: 2 }
+    );
+}
+
+#[test]
+fn rejects_field_not_in_schema() {
+    let encoded = Literal::new().uint64(1, 1).uint64(2, 2);
+    const SCHEMA: Schema<'static> = Schema::new(&[FieldSchema::required(1, WireType::UInt64)]);
+    assert_eq!(
+        validate_against_err(&encoded, &SCHEMA),
+        Kind::UnknownField { tag: 2 }
+    );
+}
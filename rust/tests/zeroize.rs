@@ -0,0 +1,52 @@
+//! Integration tests for the `zeroize` feature
+
+#![cfg(feature = "zeroize")]
+
+use veriform::{decoder::Stream, Decoder, Message};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Example session token
+const EXAMPLE_TOKEN: u64 = 0x0f0f_f0f0_0f0f_f0f0;
+
+#[derive(Message, Debug, Default, Eq, PartialEq)]
+pub struct SessionToken {
+    #[field(tag = 0, wire_type = "uint64", critical = true)]
+    pub token: u64,
+}
+
+impl Zeroize for SessionToken {
+    fn zeroize(&mut self) {
+        self.token.zeroize();
+    }
+}
+
+#[test]
+fn decode_into_zeroizing_wrapper() {
+    let mut buffer = Zeroizing::new([0u8; 64]);
+    let token = SessionToken {
+        token: EXAMPLE_TOKEN,
+    };
+    let encoded = token.encode(&mut buffer[..]).unwrap();
+
+    let mut decoded = Zeroizing::new(SessionToken::decode(&mut Decoder::new(), encoded).unwrap());
+    assert_eq!(decoded.token, EXAMPLE_TOKEN);
+
+    decoded.zeroize();
+    assert_eq!(decoded.token, 0);
+}
+
+#[test]
+fn drop_partially_decoded_stream() {
+    let mut buffer = [0u8; 64];
+    let token = SessionToken {
+        token: EXAMPLE_TOKEN,
+    };
+    let encoded = token.encode(&mut buffer).unwrap();
+
+    // Leave a partially decoded `vint64` buffered in the stream
+    let mut stream: Stream<sha2::Sha256> = Stream::new();
+    let mut input = &encoded[..encoded.len() - 1];
+    while stream.decode(&mut input).unwrap().is_some() {}
+
+    drop(stream);
+}
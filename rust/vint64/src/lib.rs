@@ -84,7 +84,7 @@ pub mod signed;
 pub use self::error::Error;
 
 use core::{
-    convert::{TryFrom, TryInto},
+    convert::TryFrom,
     fmt::{self, Debug},
 };
 
@@ -213,15 +213,25 @@ pub fn decode(input: &mut &[u8]) -> Result<u64, Error> {
 
     let result = if length == 9 {
         // 9-byte special case
-        u64::from_le_bytes(bytes[1..9].try_into().unwrap())
+        let mut encoded = [0u8; 8];
+        encoded.copy_from_slice(&bytes[1..9]);
+        u64::from_le_bytes(encoded)
     } else {
         let mut encoded = [0u8; 8];
         encoded[..length].copy_from_slice(&bytes[..length]);
         u64::from_le_bytes(encoded) >> length
     };
 
-    // Ensure there are no superfluous leading (little-endian) zeros
-    if length == 1 || result >= (1 << (7 * (length - 1))) {
+    // Ensure there are no superfluous leading (little-endian) zeros, i.e.
+    // that the value could not have been encoded using fewer bytes
+    let is_minimal = match length {
+        1 => true,
+        // 9-byte values must not fit in the 56-bit precision of 8 bytes
+        9 => result >= 1 << 56,
+        _ => result >= 1 << (7 * (length - 1)),
+    };
+
+    if is_minimal {
         *input = &bytes[length..];
         Ok(result)
     } else {
@@ -231,7 +241,7 @@ pub fn decode(input: &mut &[u8]) -> Result<u64, Error> {
 
 #[cfg(test)]
 mod tests {
-    use super::{decode, encode, signed};
+    use super::{decode, encode, encoded_len, signed, Error, MAX_BYTES};
     use proptest::{num::u64::ANY, prelude::*};
 
     #[test]
@@ -328,6 +338,93 @@ mod tests {
         assert!(decode(&mut slice).is_err());
     }
 
+    /// Values at each length breakpoint, along with their encoded lengths
+    const BREAKPOINTS: &[(u64, usize)] = &[
+        ((1 << 7) - 1, 1),
+        (1 << 7, 2),
+        ((1 << 14) - 1, 2),
+        (1 << 14, 3),
+        ((1 << 21) - 1, 3),
+        (1 << 21, 4),
+        ((1 << 28) - 1, 4),
+        (1 << 28, 5),
+        ((1 << 35) - 1, 5),
+        (1 << 35, 6),
+        ((1 << 42) - 1, 6),
+        (1 << 42, 7),
+        ((1 << 49) - 1, 7),
+        (1 << 49, 8),
+        ((1 << 56) - 1, 8),
+        (1 << 56, 9),
+        (u64::MAX, 9),
+    ];
+
+    /// Encode a value using the given number of bytes, even if it is not the
+    /// minimal encoding for that value
+    fn encode_with_length(value: u64, length: usize) -> ([u8; MAX_BYTES], usize) {
+        let mut bytes = [0u8; MAX_BYTES];
+
+        if length == 9 {
+            bytes[1..].copy_from_slice(&value.to_le_bytes());
+        } else {
+            let encoded = (value << 1 | 1) << (length - 1);
+            bytes[..8].copy_from_slice(&encoded.to_le_bytes());
+        }
+
+        (bytes, length)
+    }
+
+    #[test]
+    fn encode_breakpoints() {
+        for &(value, length) in BREAKPOINTS {
+            assert_eq!(encoded_len(value), length, "value: {}", value);
+            assert_eq!(encode(value).as_ref().len(), length, "value: {}", value);
+        }
+    }
+
+    #[test]
+    fn decode_breakpoints() {
+        for &(value, length) in BREAKPOINTS {
+            let encoded = encode(value);
+            let mut slice = encoded.as_ref();
+            assert_eq!(decode(&mut slice).unwrap(), value);
+            assert!(slice.is_empty());
+
+            // Truncating any byte of the encoded value must fail
+            let mut truncated = &encoded.as_ref()[..length - 1];
+            assert!(matches!(decode(&mut truncated), Err(Error::Truncated)));
+        }
+    }
+
+    #[test]
+    fn decode_nine_byte_first_byte_zero() {
+        let mut slice = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01].as_ref();
+        assert_eq!(decode(&mut slice).unwrap(), 1 << 56);
+        assert!(slice.is_empty());
+    }
+
+    #[test]
+    fn decode_non_minimal_encodings() {
+        for length in 2..=MAX_BYTES {
+            // Largest value which fits in one fewer byte
+            let value = (1u64 << (7 * (length - 1))) - 1;
+            let (bytes, length) = encode_with_length(value, length);
+            let mut slice = &bytes[..length];
+
+            assert!(
+                matches!(decode(&mut slice), Err(Error::LeadingZeroes)),
+                "length: {}",
+                length
+            );
+
+            // Smallest value which requires this many bytes
+            let value = value + 1;
+            let (bytes, length) = encode_with_length(value, length);
+            let mut slice = &bytes[..length];
+            assert_eq!(decode(&mut slice).unwrap(), value);
+        }
+    }
+
     #[test]
     fn decode_signed_values() {
         let mut slice = [0x10, 0x3c, 0xfc, 0xc3, 0x03].as_ref();